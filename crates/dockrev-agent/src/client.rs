@@ -0,0 +1,147 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{
+    config::Config,
+    executor::{self, RunRequest},
+    protocol::{AgentCommandsResponse, AgentOp, AgentOutputRequest, AgentResult},
+};
+
+/// Output lines sent to the server per request.
+const OUTPUT_BATCH_LINES: usize = 200;
+
+struct Client {
+    cfg: Config,
+    http: reqwest::Client,
+}
+
+/// Polls the server for commands until the process is stopped. Every command runs in its own
+/// task so long `compose pull`s do not hold up discovery or cancellation.
+pub async fn run(cfg: Config) -> anyhow::Result<()> {
+    let http = reqwest::Client::builder()
+        .timeout(cfg.poll_wait + Duration::from_secs(15))
+        .build()?;
+    let client = Arc::new(Client { cfg, http });
+    let mut running: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+    loop {
+        running.retain(|_, task| !task.is_finished());
+        let commands = match client.poll().await {
+            Ok(v) => v.commands,
+            Err(e) => {
+                tracing::warn!(error = %e, "poll dockrev server failed");
+                tokio::time::sleep(client.cfg.retry_delay).await;
+                continue;
+            }
+        };
+
+        for command in commands {
+            if let AgentOp::Cancel { command_id } = &command.op {
+                // Aborting the task drops the child process (`kill_on_drop`).
+                if let Some(task) = running.remove(command_id) {
+                    task.abort();
+                    tracing::info!(command = %command_id, "command cancelled");
+                }
+                continue;
+            }
+            let client = client.clone();
+            let id = command.id.clone();
+            running.insert(
+                command.id,
+                tokio::spawn(async move { client.execute(id, command.op).await }),
+            );
+        }
+    }
+}
+
+impl Client {
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.cfg.server_url)
+    }
+
+    async fn poll(&self) -> anyhow::Result<AgentCommandsResponse> {
+        let resp = self
+            .http
+            .get(self.url("/api/agent/commands"))
+            .bearer_auth(&self.cfg.token)
+            .query(&[("waitSeconds", self.cfg.poll_wait.as_secs())])
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(resp.json().await?)
+    }
+
+    async fn post(&self, path: &str, body: &impl serde::Serialize) -> anyhow::Result<()> {
+        self.http
+            .post(self.url(path))
+            .bearer_auth(&self.cfg.token)
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn execute(&self, id: String, op: AgentOp) {
+        let result = match op {
+            AgentOp::Run {
+                program,
+                args,
+                env,
+                timeout_seconds,
+                stream,
+                files,
+                stdin,
+            } => {
+                tracing::info!(command = %id, program = %program, args = ?args, "run");
+                let (tx, rx) = mpsc::unbounded_channel();
+                let req = RunRequest {
+                    program,
+                    args,
+                    env,
+                    timeout: Duration::from_secs(timeout_seconds),
+                    files,
+                    stdin,
+                };
+                let forward = async {
+                    if stream {
+                        self.forward_output(&id, rx).await;
+                    }
+                };
+                tokio::join!(executor::run(&self.cfg.allowed_programs, req, tx), forward).0
+            }
+            AgentOp::ReadFile { path } => executor::read_file(&path).await,
+            AgentOp::Cancel { .. } => return,
+        };
+
+        if let Some(error) = result.error.as_deref() {
+            tracing::warn!(command = %id, error = %error, "command failed");
+        }
+        self.post_result(&id, &result).await;
+    }
+
+    /// Sends output in batches until the command closes its side of the channel.
+    async fn forward_output(&self, id: &str, mut rx: mpsc::UnboundedReceiver<String>) {
+        let path = format!("/api/agent/commands/{id}/output");
+        while let Some(line) = rx.recv().await {
+            let mut lines = vec![line];
+            while lines.len() < OUTPUT_BATCH_LINES {
+                match rx.try_recv() {
+                    Ok(line) => lines.push(line),
+                    Err(_) => break,
+                }
+            }
+            if let Err(e) = self.post(&path, &AgentOutputRequest { lines }).await {
+                tracing::warn!(command = %id, error = %e, "send output failed");
+            }
+        }
+    }
+
+    async fn post_result(&self, id: &str, result: &AgentResult) {
+        let path = format!("/api/agent/commands/{id}/result");
+        if let Err(e) = self.post(&path, result).await {
+            tracing::warn!(command = %id, error = %e, "send result failed");
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Base URL of the Dockrev API, e.g. `https://dockrev.example.com`.
+    pub server_url: String,
+    /// Token returned when the `agent://` host was created.
+    pub token: String,
+    /// Programs the server may run on this machine.
+    pub allowed_programs: Vec<String>,
+    /// How long one poll waits for work on the server.
+    pub poll_wait: Duration,
+    /// Delay before polling again after a failed request.
+    pub retry_delay: Duration,
+}
+
+impl Config {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let server_url = std::env::var("DOCKREV_AGENT_SERVER_URL")
+            .ok()
+            .and_then(non_empty)
+            .ok_or_else(|| anyhow::anyhow!("DOCKREV_AGENT_SERVER_URL is required"))?;
+        let server_url = normalize_server_url(&server_url)?;
+
+        let token = std::env::var("DOCKREV_AGENT_TOKEN")
+            .ok()
+            .and_then(non_empty)
+            .ok_or_else(|| anyhow::anyhow!("DOCKREV_AGENT_TOKEN is required"))?;
+
+        let allowed_programs = parse_csv(
+            &std::env::var("DOCKREV_AGENT_ALLOWED_PROGRAMS")
+                .unwrap_or_else(|_| "docker,docker-compose".to_string()),
+        );
+
+        let poll_wait = std::env::var("DOCKREV_AGENT_POLL_SECONDS")
+            .ok()
+            .and_then(non_empty)
+            .map(|v| v.trim().parse::<u64>())
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("DOCKREV_AGENT_POLL_SECONDS must be a number"))?
+            .unwrap_or(25);
+
+        Ok(Self {
+            server_url,
+            token,
+            allowed_programs,
+            poll_wait: Duration::from_secs(poll_wait.clamp(1, 30)),
+            retry_delay: Duration::from_secs(5),
+        })
+    }
+}
+
+fn non_empty(v: String) -> Option<String> {
+    if v.trim().is_empty() { None } else { Some(v) }
+}
+
+fn parse_csv(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn normalize_server_url(input: &str) -> anyhow::Result<String> {
+    let t = input.trim().trim_end_matches('/');
+    if !(t.starts_with("http://") || t.starts_with("https://")) {
+        return Err(anyhow::anyhow!(
+            "DOCKREV_AGENT_SERVER_URL must start with http:// or https://"
+        ));
+    }
+    Ok(t.to_string())
+}
@@ -0,0 +1,222 @@
+use std::{path::PathBuf, process::Stdio, time::Duration};
+
+use tokio::{
+    io::{AsyncBufReadExt as _, AsyncRead, AsyncWriteExt as _, BufReader},
+    process::Command,
+    sync::mpsc,
+};
+
+use crate::protocol::{AgentFile, AgentResult};
+
+/// Compose files are the only files the server may read.
+const READABLE_EXTENSIONS: &[&str] = &["yml", "yaml"];
+
+pub struct RunRequest {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub timeout: Duration,
+    pub files: Vec<AgentFile>,
+    pub stdin: Option<String>,
+}
+
+/// Runs an allowed program, sending each output line to `lines`. Dropping the future kills the
+/// child (cancellation by the server).
+pub async fn run(
+    allowed_programs: &[String],
+    req: RunRequest,
+    lines: mpsc::UnboundedSender<String>,
+) -> AgentResult {
+    if !allowed_programs.iter().any(|p| p == &req.program) {
+        return AgentResult::failed(format!("program not allowed: {}", req.program));
+    }
+
+    let files = match TempFiles::write(&req.files).await {
+        Ok(v) => v,
+        Err(e) => return AgentResult::failed(format!("write generated files: {e}")),
+    };
+    let args = req
+        .args
+        .iter()
+        .map(|arg| files.resolve(arg).unwrap_or_else(|| arg.clone()))
+        .collect::<Vec<_>>();
+
+    let mut cmd = Command::new(&req.program);
+    cmd.args(&args)
+        .envs(req.env.iter().map(|(k, v)| (k, v)))
+        .stdin(if req.stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = match cmd.spawn() {
+        Ok(v) => v,
+        Err(e) => return AgentResult::failed(format!("spawn {}: {e}", req.program)),
+    };
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return AgentResult::failed("child output not captured");
+    };
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), req.stdin.as_deref())
+        && let Err(e) = stdin.write_all(input.as_bytes()).await
+    {
+        return AgentResult::failed(format!("write stdin of {}: {e}", req.program));
+    }
+
+    let output = async {
+        tokio::try_join!(
+            read_lines(stdout, &lines),
+            read_lines(stderr, &lines),
+            child.wait(),
+        )
+    };
+    match tokio::time::timeout(req.timeout, output).await {
+        Ok(Ok((stdout, stderr, status))) => AgentResult {
+            status: status.code().unwrap_or(-1),
+            stdout,
+            stderr,
+            error: None,
+        },
+        Ok(Err(e)) => AgentResult::failed(format!("run {}: {e}", req.program)),
+        Err(_) => AgentResult::failed(format!("{} timed out", req.program)),
+    }
+}
+
+pub async fn read_file(path: &str) -> AgentResult {
+    let p = std::path::Path::new(path);
+    let readable = p.is_absolute()
+        && p.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| READABLE_EXTENSIONS.contains(&e));
+    if !readable {
+        return AgentResult::failed(format!("file not readable by agent: {path}"));
+    }
+    match tokio::fs::read_to_string(p).await {
+        Ok(contents) => AgentResult {
+            stdout: contents,
+            ..Default::default()
+        },
+        Err(e) => AgentResult::failed(format!("{path}: {e}")),
+    }
+}
+
+async fn read_lines(
+    reader: impl AsyncRead + Unpin,
+    lines: &mpsc::UnboundedSender<String>,
+) -> std::io::Result<String> {
+    let mut reader = BufReader::new(reader);
+    let mut out = String::new();
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf).await? == 0 {
+            return Ok(out);
+        }
+        let text = String::from_utf8_lossy(&buf);
+        out.push_str(&text);
+        let _ = lines.send(text.trim_end_matches(['\r', '\n']).to_string());
+    }
+}
+
+/// Local copies of server-generated files, removed when the command finishes.
+struct TempFiles(Vec<(String, PathBuf)>);
+
+impl TempFiles {
+    async fn write(files: &[AgentFile]) -> std::io::Result<Self> {
+        let mut out = Self(Vec::new());
+        for file in files {
+            let name = std::path::Path::new(&file.path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("file");
+            let local =
+                std::env::temp_dir().join(format!("dockrev-agent-{}-{name}", ulid::Ulid::new()));
+            tokio::fs::write(&local, &file.contents).await?;
+            out.0.push((file.path.clone(), local));
+        }
+        Ok(out)
+    }
+
+    fn resolve(&self, arg: &str) -> Option<String> {
+        self.0
+            .iter()
+            .find(|(remote, _)| remote == arg)
+            .map(|(_, local)| local.to_string_lossy().to_string())
+    }
+}
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for (_, local) in &self.0 {
+            let _ = std::fs::remove_file(local);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> RunRequest {
+        RunRequest {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: vec![("GREETING".to_string(), "hello".to_string())],
+            timeout: Duration::from_secs(10),
+            files: Vec::new(),
+            stdin: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_allowed_programs_and_streams_output() {
+        let allowed = vec!["sh".to_string()];
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let out = run(&allowed, sh("echo $GREETING; echo oops >&2; exit 3"), tx).await;
+        assert_eq!(out.error, None);
+        assert_eq!(out.status, 3);
+        assert_eq!(out.stdout, "hello\n");
+        assert_eq!(out.stderr, "oops\n");
+
+        let mut lines = Vec::new();
+        while let Some(line) = rx.recv().await {
+            lines.push(line);
+        }
+        lines.sort();
+        assert_eq!(lines, ["hello", "oops"]);
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let denied = run(&["docker".to_string()], sh("true"), tx).await;
+        assert_eq!(denied.error.as_deref(), Some("program not allowed: sh"));
+    }
+
+    #[tokio::test]
+    async fn materializes_generated_files() {
+        let mut req = sh("cat \"$0\"");
+        req.args.push("/tmp/dockrev-override-demo.yml".to_string());
+        req.files.push(AgentFile {
+            path: "/tmp/dockrev-override-demo.yml".to_string(),
+            contents: "services: {}\n".to_string(),
+        });
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let out = run(&["sh".to_string()], req, tx).await;
+        assert_eq!(out.error, None);
+        assert_eq!(out.stdout, "services: {}\n");
+    }
+
+    #[tokio::test]
+    async fn only_reads_compose_files() {
+        assert!(read_file("/etc/passwd").await.error.is_some());
+        assert!(read_file("compose.yml").await.error.is_some());
+
+        let path =
+            std::env::temp_dir().join(format!("dockrev-agent-test-{}.yml", ulid::Ulid::new()));
+        tokio::fs::write(&path, "services: {}\n").await.unwrap();
+        let out = read_file(path.to_str().unwrap()).await;
+        let _ = tokio::fs::remove_file(&path).await;
+        assert_eq!(out.error, None);
+        assert_eq!(out.stdout, "services: {}\n");
+    }
+}
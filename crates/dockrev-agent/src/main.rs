@@ -0,0 +1,27 @@
+#![forbid(unsafe_code)]
+
+mod client;
+mod config;
+mod executor;
+mod protocol;
+
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "dockrev_agent=info".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let cfg = config::Config::from_env()?;
+    tracing::info!(server = %cfg.server_url, "dockrev agent started");
+
+    tokio::select! {
+        res = client::run(cfg) => res,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    }
+}
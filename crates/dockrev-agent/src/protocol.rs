@@ -0,0 +1,70 @@
+//! Wire types of `/api/agent/*`; kept in sync with `dockrev-api`'s `agent` module.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentCommand {
+    pub id: String,
+    #[serde(flatten)]
+    pub op: AgentOp,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AgentOp {
+    #[serde(rename_all = "camelCase")]
+    Run {
+        program: String,
+        args: Vec<String>,
+        env: Vec<(String, String)>,
+        timeout_seconds: u64,
+        stream: bool,
+        #[serde(default)]
+        files: Vec<AgentFile>,
+        #[serde(default)]
+        stdin: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    ReadFile { path: String },
+    #[serde(rename_all = "camelCase")]
+    Cancel { command_id: String },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentFile {
+    pub path: String,
+    pub contents: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentCommandsResponse {
+    pub commands: Vec<AgentCommand>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentOutputRequest {
+    pub lines: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentResult {
+    pub status: i32,
+    pub stdout: String,
+    pub stderr: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AgentResult {
+    pub fn failed(error: impl std::fmt::Display) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            ..Default::default()
+        }
+    }
+}
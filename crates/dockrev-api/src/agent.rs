@@ -0,0 +1,500 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context as _;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use sha2::Digest as _;
+use tokio::sync::{Notify, mpsc, oneshot};
+
+use crate::runner::{CommandOutput, CommandRunner, CommandSpec, OutputLine};
+
+/// An agent that has not polled for this long is treated as disconnected.
+const AGENT_OFFLINE_AFTER: Duration = Duration::from_secs(60);
+/// Upper bound for one long-poll request of an agent.
+pub const MAX_POLL_WAIT: Duration = Duration::from_secs(30);
+
+/// Work item handed to an agent by `GET /api/agent/commands`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentCommand {
+    pub id: String,
+    #[serde(flatten)]
+    pub op: AgentOp,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AgentOp {
+    /// Runs a `docker` / compose command; output lines are sent back while it runs when
+    /// `stream` is set.
+    #[serde(rename_all = "camelCase")]
+    Run {
+        program: String,
+        args: Vec<String>,
+        env: Vec<(String, String)>,
+        timeout_seconds: u64,
+        stream: bool,
+        /// Files generated by Dockrev (compose overrides) that `args` refer to.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        files: Vec<AgentFile>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stdin: Option<String>,
+    },
+    /// Reads a compose file on the agent host.
+    #[serde(rename_all = "camelCase")]
+    ReadFile { path: String },
+    /// Aborts a command that is still running.
+    #[serde(rename_all = "camelCase")]
+    Cancel { command_id: String },
+}
+
+/// A file that only exists on the Dockrev side. The agent writes it to its own temp dir and
+/// replaces `path` in the command arguments with that copy.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentFile {
+    pub path: String,
+    pub contents: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentCommandsResponse {
+    pub commands: Vec<AgentCommand>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentOutputRequest {
+    pub lines: Vec<String>,
+}
+
+/// Outcome of a command; `error` is set when it could not run at all (spawn failure, timeout,
+/// program not allowed, unreadable file).
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentResult {
+    #[serde(default)]
+    pub status: i32,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Creates a random agent token; only its hash is stored.
+pub fn new_token() -> anyhow::Result<String> {
+    let mut buf = [0u8; 32];
+    getrandom::fill(&mut buf).map_err(|e| anyhow::anyhow!("generate agent token: {e}"))?;
+    Ok(format!(
+        "dra_{}",
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(buf)
+    ))
+}
+
+pub fn token_hash(token: &str) -> String {
+    sha2::Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+struct PendingCommand {
+    lines: Option<mpsc::UnboundedSender<OutputLine>>,
+    done: oneshot::Sender<AgentResult>,
+}
+
+#[derive(Default)]
+struct Session {
+    last_seen: Option<Instant>,
+    queue: VecDeque<AgentCommand>,
+    pending: HashMap<String, PendingCommand>,
+    notify: Arc<Notify>,
+}
+
+/// Routes commands to agents (which pick them up by long-polling) and their results back to
+/// the waiting callers.
+#[derive(Default)]
+pub struct AgentHub {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl AgentHub {
+    fn with_session<R>(&self, host_id: &str, f: impl FnOnce(&mut Session) -> R) -> R {
+        let mut sessions = self.sessions.lock().expect("agent hub lock poisoned");
+        f(sessions.entry(host_id.to_string()).or_default())
+    }
+
+    pub fn is_connected(&self, host_id: &str) -> bool {
+        self.with_session(host_id, |s| {
+            s.last_seen
+                .is_some_and(|t| t.elapsed() < AGENT_OFFLINE_AFTER)
+        })
+    }
+
+    /// Drops the session of a removed host; callers waiting on it fail.
+    pub fn forget(&self, host_id: &str) {
+        self.sessions
+            .lock()
+            .expect("agent hub lock poisoned")
+            .remove(host_id);
+    }
+
+    /// Long-poll of an agent: returns queued commands, waiting up to `wait` for one to arrive.
+    pub async fn poll(&self, host_id: &str, wait: Duration) -> Vec<AgentCommand> {
+        let deadline = tokio::time::Instant::now() + wait.min(MAX_POLL_WAIT);
+        loop {
+            let (commands, notify) = self.with_session(host_id, |s| {
+                s.last_seen = Some(Instant::now());
+                (s.queue.drain(..).collect::<Vec<_>>(), s.notify.clone())
+            });
+            if !commands.is_empty() {
+                return commands;
+            }
+            let notified = notify.notified();
+            // Re-check after registering, so a command queued in between is not missed.
+            if self.with_session(host_id, |s| !s.queue.is_empty()) {
+                continue;
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                self.with_session(host_id, |s| s.last_seen = Some(Instant::now()));
+                return Vec::new();
+            }
+        }
+    }
+
+    /// Forwards streamed output of a running command; false if nobody waits for it anymore.
+    pub fn push_output(&self, host_id: &str, command_id: &str, lines: Vec<String>) -> bool {
+        self.with_session(host_id, |s| {
+            let Some(pending) = s.pending.get(command_id) else {
+                return false;
+            };
+            if let Some(tx) = pending.lines.as_ref() {
+                for line in lines {
+                    let _ = tx.send(OutputLine { line });
+                }
+            }
+            true
+        })
+    }
+
+    pub fn complete(&self, host_id: &str, command_id: &str, result: AgentResult) -> bool {
+        let pending = self.with_session(host_id, |s| s.pending.remove(command_id));
+        match pending {
+            Some(p) => p.done.send(result).is_ok(),
+            None => false,
+        }
+    }
+
+    fn enqueue(&self, host_id: &str, command: AgentCommand) {
+        self.with_session(host_id, |s| {
+            s.queue.push_back(command);
+            s.notify.notify_one();
+        });
+    }
+
+    /// Sends `op` to the agent of `host_id` and waits for its result. Dropping the future
+    /// (timeout, job cancellation) asks the agent to abort the command.
+    pub async fn execute(
+        &self,
+        host_id: &str,
+        op: AgentOp,
+        lines: Option<mpsc::UnboundedSender<OutputLine>>,
+        timeout: Duration,
+    ) -> anyhow::Result<AgentResult> {
+        if !self.is_connected(host_id) {
+            anyhow::bail!("agent of host {host_id} is not connected");
+        }
+
+        let id = format!("cmd_{}", ulid::Ulid::new());
+        let (done, result) = oneshot::channel();
+        self.with_session(host_id, |s| {
+            s.pending.insert(id.clone(), PendingCommand { lines, done });
+        });
+        let guard = CancelOnDrop {
+            hub: self,
+            host_id,
+            command_id: &id,
+            armed: true,
+        };
+        self.enqueue(host_id, AgentCommand { id: id.clone(), op });
+
+        // The agent enforces the command timeout itself; allow some slack for the round trip.
+        let outcome = tokio::time::timeout(timeout + MAX_POLL_WAIT, result).await;
+        match outcome {
+            Ok(Ok(result)) => {
+                let mut guard = guard;
+                guard.armed = false;
+                match result.error {
+                    Some(e) => Err(anyhow::anyhow!("agent: {e}")),
+                    None => Ok(result),
+                }
+            }
+            Ok(Err(_)) => {
+                anyhow::bail!("agent of host {host_id} went away")
+            }
+            Err(_) => anyhow::bail!("agent command timed out"),
+        }
+    }
+}
+
+struct CancelOnDrop<'a> {
+    hub: &'a AgentHub,
+    host_id: &'a str,
+    command_id: &'a str,
+    armed: bool,
+}
+
+impl Drop for CancelOnDrop<'_> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let removed = self
+            .hub
+            .with_session(self.host_id, |s| s.pending.remove(self.command_id));
+        if removed.is_some() {
+            self.hub.enqueue(
+                self.host_id,
+                AgentCommand {
+                    id: format!("cmd_{}", ulid::Ulid::new()),
+                    op: AgentOp::Cancel {
+                        command_id: self.command_id.to_string(),
+                    },
+                },
+            );
+        }
+    }
+}
+
+/// [`CommandRunner`] that executes commands through the agent of a host.
+pub struct AgentCommandRunner {
+    hub: Arc<AgentHub>,
+    host_id: String,
+}
+
+impl AgentCommandRunner {
+    pub fn new(hub: Arc<AgentHub>, host_id: String) -> Self {
+        Self { hub, host_id }
+    }
+
+    pub async fn read_file(&self, path: &str) -> anyhow::Result<String> {
+        let out = self
+            .hub
+            .execute(
+                &self.host_id,
+                AgentOp::ReadFile {
+                    path: path.to_string(),
+                },
+                None,
+                Duration::from_secs(30),
+            )
+            .await?;
+        Ok(out.stdout)
+    }
+
+    async fn run_op(
+        &self,
+        spec: CommandSpec,
+        timeout: Duration,
+        lines: Option<mpsc::UnboundedSender<OutputLine>>,
+    ) -> anyhow::Result<CommandOutput> {
+        let files = generated_files(&spec.files).await?;
+        let op = AgentOp::Run {
+            program: spec.program,
+            args: spec.args,
+            env: spec.env,
+            timeout_seconds: timeout.as_secs().max(1),
+            stream: lines.is_some(),
+            files,
+            stdin: spec.stdin,
+        };
+        let out = self.hub.execute(&self.host_id, op, lines, timeout).await?;
+        Ok(CommandOutput {
+            status: out.status,
+            stdout: out.stdout,
+            stderr: out.stderr,
+        })
+    }
+}
+
+/// Files generated for the command (see `updater::build_override_file`) have to be shipped to
+/// the agent along with it.
+async fn generated_files(paths: &[String]) -> anyhow::Result<Vec<AgentFile>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("read generated file {path}"))?;
+        files.push(AgentFile {
+            path: path.clone(),
+            contents,
+        });
+    }
+    Ok(files)
+}
+
+#[async_trait::async_trait]
+impl CommandRunner for AgentCommandRunner {
+    async fn run(&self, spec: CommandSpec, timeout: Duration) -> anyhow::Result<CommandOutput> {
+        self.run_op(spec, timeout, None).await
+    }
+
+    async fn run_streaming(
+        &self,
+        spec: CommandSpec,
+        timeout: Duration,
+        lines: mpsc::UnboundedSender<OutputLine>,
+    ) -> anyhow::Result<CommandOutput> {
+        self.run_op(spec, timeout, Some(lines)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(args: &[&str]) -> CommandSpec {
+        CommandSpec {
+            program: "docker".to_string(),
+            args: args.iter().map(|s| s.to_string()).collect(),
+            env: Vec::new(),
+            stdin: None,
+            files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn commands_serialize_with_kind_tag() {
+        let cmd = AgentCommand {
+            id: "cmd_1".to_string(),
+            op: AgentOp::ReadFile {
+                path: "/srv/app/compose.yml".to_string(),
+            },
+        };
+        let json = serde_json::to_value(&cmd).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"id": "cmd_1", "kind": "readFile", "path": "/srv/app/compose.yml"})
+        );
+        assert_eq!(serde_json::from_value::<AgentCommand>(json).unwrap(), cmd);
+        assert_ne!(new_token().unwrap(), new_token().unwrap());
+        assert_eq!(token_hash("abc").len(), 64);
+    }
+
+    #[tokio::test]
+    async fn runner_round_trips_through_a_polling_agent() {
+        let hub = Arc::new(AgentHub::default());
+        let runner = AgentCommandRunner::new(hub.clone(), "hst_1".to_string());
+        assert!(
+            runner
+                .run(spec(&["ps"]), Duration::from_secs(5))
+                .await
+                .is_err(),
+            "agent never polled"
+        );
+
+        hub.poll("hst_1", Duration::ZERO).await;
+        let agent = {
+            let hub = hub.clone();
+            tokio::spawn(async move {
+                let cmds = hub.poll("hst_1", Duration::from_secs(5)).await;
+                let cmd = cmds.into_iter().next().unwrap();
+                let AgentOp::Run { args, stream, .. } = &cmd.op else {
+                    panic!("unexpected op {:?}", cmd.op);
+                };
+                assert_eq!(args, &["compose", "pull"]);
+                assert!(*stream);
+                assert!(hub.push_output("hst_1", &cmd.id, vec!["pulling web".to_string()]));
+                assert!(hub.complete(
+                    "hst_1",
+                    &cmd.id,
+                    AgentResult {
+                        status: 0,
+                        stdout: "pulling web\n".to_string(),
+                        ..Default::default()
+                    },
+                ));
+            })
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let out = runner
+            .run_streaming(spec(&["compose", "pull"]), Duration::from_secs(5), tx)
+            .await
+            .unwrap();
+        agent.await.unwrap();
+        assert_eq!(out.stdout, "pulling web\n");
+        assert_eq!(rx.recv().await.unwrap().line, "pulling web");
+    }
+
+    #[tokio::test]
+    async fn only_generated_files_are_shipped() {
+        let hub = Arc::new(AgentHub::default());
+        let runner = AgentCommandRunner::new(hub.clone(), "hst_1".to_string());
+        hub.poll("hst_1", Duration::ZERO).await;
+
+        let dir = std::env::temp_dir();
+        let generated = dir.join(format!("dockrev-override-{}.yml", ulid::Ulid::new()));
+        let other = dir.join(format!("dockrev-secret-{}.env", ulid::Ulid::new()));
+        std::fs::write(&generated, "services: {}\n").unwrap();
+        std::fs::write(&other, "TOKEN=1\n").unwrap();
+        let generated = generated.to_string_lossy().to_string();
+        let other = other.to_string_lossy().to_string();
+
+        let agent = {
+            let hub = hub.clone();
+            let generated = generated.clone();
+            tokio::spawn(async move {
+                let cmds = hub.poll("hst_1", Duration::from_secs(5)).await;
+                let cmd = cmds.into_iter().next().unwrap();
+                let AgentOp::Run { files, .. } = &cmd.op else {
+                    panic!("unexpected op {:?}", cmd.op);
+                };
+                assert_eq!(
+                    files,
+                    &[AgentFile {
+                        path: generated,
+                        contents: "services: {}\n".to_string(),
+                    }]
+                );
+                assert!(hub.complete("hst_1", &cmd.id, AgentResult::default()));
+            })
+        };
+
+        let mut spec = spec(&["compose", "-f", &generated, "--env-file", &other, "up"]);
+        spec.files = vec![generated.clone()];
+        runner.run(spec, Duration::from_secs(5)).await.unwrap();
+        agent.await.unwrap();
+        let _ = std::fs::remove_file(&generated);
+        let _ = std::fs::remove_file(&other);
+    }
+
+    #[tokio::test]
+    async fn dropped_command_is_cancelled_on_the_agent() {
+        let hub = Arc::new(AgentHub::default());
+        let runner = AgentCommandRunner::new(hub.clone(), "hst_1".to_string());
+        hub.poll("hst_1", Duration::ZERO).await;
+
+        let run = runner.run(spec(&["events"]), Duration::from_secs(60));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), run)
+                .await
+                .is_err()
+        );
+
+        let cmds = hub.poll("hst_1", Duration::ZERO).await;
+        assert_eq!(cmds.len(), 2);
+        assert!(matches!(
+            &cmds[1].op,
+            AgentOp::Cancel { command_id } if command_id == &cmds[0].id
+        ));
+        assert!(!hub.complete("hst_1", &cmds[0].id, AgentResult::default()));
+    }
+}
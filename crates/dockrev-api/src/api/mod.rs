@@ -3,19 +3,36 @@ pub mod types;
 #[cfg(test)]
 mod tests;
 
-use std::sync::Arc;
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
 
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
-    routing::{get, post},
+    response::sse::{self, KeepAlive, Sse},
+    routing::{get, post, put},
 };
+use futures_util::Stream;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 use crate::{
-    backup, candidates, discovery, error::ApiError, ids, ignore, notify, registry, state::AppState,
+    agent::{self, AgentCommandsResponse, AgentOutputRequest, AgentResult},
+    backup, candidates,
+    credentials::normalize_auth_key,
+    db::GLOBAL_CHECK_SCHEDULE_ID,
+    discovery,
+    docker_engine::DockerEngine,
+    error::ApiError,
+    events,
+    hosts::{self, LOCAL_HOST_ID},
+    ids, ignore, maintenance, notify, queue, registry, release_notes,
+    runner::CancelToken,
+    scanner, scheduler,
+    state::AppState,
     ui, updater,
 };
 use types::*;
@@ -24,11 +41,37 @@ pub fn router(state: Arc<AppState>) -> Router {
     Router::<Arc<AppState>>::new()
         .route("/api/health", get(health))
         .route("/api/version", get(version))
+        .route("/api/hosts", get(list_hosts).post(create_host))
+        .route("/api/hosts/{host_id}", axum::routing::delete(delete_host))
+        .route("/api/registries/quota", get(list_registry_quotas))
+        .route(
+            "/api/registries/credentials",
+            get(list_registry_credentials),
+        )
+        .route(
+            "/api/registries/credentials/{registry}",
+            put(put_registry_credential).delete(delete_registry_credential),
+        )
+        .route("/api/agent/commands", get(agent_commands))
+        .route(
+            "/api/agent/commands/{command_id}/output",
+            post(agent_command_output),
+        )
+        .route(
+            "/api/agent/commands/{command_id}/result",
+            post(agent_command_result),
+        )
         .route(
             "/api/stacks",
             get(list_stacks).post(register_stack_disabled),
         )
         .route("/api/stacks/{stack_id}", get(get_stack))
+        .route(
+            "/api/stacks/{stack_id}/schedule",
+            get(get_stack_schedule)
+                .put(put_stack_schedule)
+                .delete(delete_stack_schedule),
+        )
         .route("/api/stacks/{stack_id}/archive", post(archive_stack))
         .route("/api/stacks/{stack_id}/restore", post(restore_stack))
         .route("/api/services/{service_id}/archive", post(archive_service))
@@ -48,9 +91,22 @@ pub fn router(state: Arc<AppState>) -> Router {
             post(restore_discovery_project),
         )
         .route("/api/checks", post(trigger_check))
+        .route("/api/schedules", get(list_schedules))
+        .route("/api/schedules/global", put(put_global_schedule))
+        .route(
+            "/api/maintenance-windows",
+            get(list_maintenance_windows).post(create_maintenance_window),
+        )
+        .route(
+            "/api/maintenance-windows/{window_id}",
+            axum::routing::delete(delete_maintenance_window),
+        )
         .route("/api/updates", post(trigger_update))
         .route("/api/jobs", get(list_jobs))
         .route("/api/jobs/{job_id}", get(get_job))
+        .route("/api/jobs/{job_id}/cancel", post(cancel_job))
+        .route("/api/jobs/{job_id}/events", get(job_events))
+        .route("/api/events", get(global_events))
         .route(
             "/api/ignores",
             get(list_ignores).post(create_ignore).delete(delete_ignore),
@@ -97,7 +153,10 @@ async fn list_stacks(
     let _user = require_user(&state, &headers)?;
     let stacks = state
         .db
-        .list_stacks(parse_archived_filter(q.archived.as_deref())?)
+        .list_stacks(
+            parse_archived_filter(q.archived.as_deref())?,
+            q.host_id.as_deref(),
+        )
         .await
         .map_err(map_internal)?;
     Ok(Json(ListStacksResponse { stacks }))
@@ -107,6 +166,238 @@ async fn list_stacks(
 #[serde(rename_all = "camelCase")]
 struct ListStacksQuery {
     archived: Option<String>,
+    host_id: Option<String>,
+}
+
+async fn list_hosts(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ListHostsResponse>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    let hosts = state
+        .hosts
+        .list()
+        .iter()
+        .map(|h| {
+            let mut record = h.record.clone();
+            if h.is_agent() {
+                record.agent_connected = Some(state.agents.is_connected(h.id()));
+            }
+            record
+        })
+        .collect();
+    Ok(Json(ListHostsResponse { hosts }))
+}
+
+async fn create_host(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<CreateHostRequest>,
+) -> Result<(StatusCode, Json<CreateHostResponse>), ApiError> {
+    let _user = require_user(&state, &headers)?;
+    let now = now_rfc3339().map_err(map_internal)?;
+
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiError::invalid_argument("name is required"));
+    }
+    let endpoint = req.endpoint.trim().to_string();
+    let tls_cert_path = req
+        .tls_cert_path
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty());
+    hosts::validate(&endpoint, tls_cert_path.as_deref())
+        .map_err(|e| ApiError::invalid_argument(format!("invalid host: {e}")))?;
+
+    let host_id = ids::new_host_id();
+    let record = HostRecord {
+        id: host_id.clone(),
+        name,
+        endpoint,
+        platform: req
+            .platform
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty()),
+        tls_cert_path,
+        created_at: Some(now.clone()),
+        agent_connected: None,
+    };
+    let host = hosts::connect(record.clone(), state.runner.clone(), &state.agents)
+        .map_err(|e| ApiError::invalid_argument(format!("invalid host: {e:#}")))?;
+    let agent_token = if host.is_agent() {
+        Some(agent::new_token().map_err(map_internal)?)
+    } else {
+        None
+    };
+    state
+        .db
+        .insert_host(
+            &record,
+            agent_token.as_deref().map(agent::token_hash).as_deref(),
+            &now,
+        )
+        .await
+        .map_err(map_internal)?;
+    state.hosts.insert(Arc::new(host));
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateHostResponse {
+            host_id,
+            agent_token,
+        }),
+    ))
+}
+
+async fn delete_host(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(host_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    if host_id == LOCAL_HOST_ID {
+        return Err(ApiError::invalid_argument(
+            "the local host cannot be removed",
+        ));
+    }
+    let deleted = state.db.delete_host(&host_id).await.map_err(map_internal)?;
+    if !deleted {
+        return Err(ApiError::not_found("host not found"));
+    }
+    state.hosts.remove(&host_id);
+    state.agents.forget(&host_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_registry_quotas(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ListRegistryQuotasResponse>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    Ok(Json(ListRegistryQuotasResponse {
+        registries: state.registry.quotas(),
+    }))
+}
+
+async fn list_registry_credentials(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ListRegistryCredentialsResponse>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    let credentials = state
+        .db
+        .list_registry_credentials()
+        .await
+        .map_err(map_internal)?;
+    Ok(Json(ListRegistryCredentialsResponse {
+        credentials: credentials
+            .into_iter()
+            .map(RegistryCredential::masked)
+            .collect(),
+    }))
+}
+
+async fn put_registry_credential(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(registry): Path<String>,
+    Json(req): Json<PutRegistryCredentialRequest>,
+) -> Result<Json<RegistryCredential>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    let now = now_rfc3339().map_err(map_internal)?;
+    let registry = normalize_auth_key(&registry);
+    if registry.is_empty() || registry.contains('/') {
+        return Err(ApiError::invalid_argument(
+            "registry must be a host like ghcr.io or registry.internal:5000",
+        ));
+    }
+    if req.username.trim().is_empty() {
+        return Err(ApiError::invalid_argument("username is required"));
+    }
+
+    let existing = state
+        .db
+        .get_registry_credential(&registry)
+        .await
+        .map_err(map_internal)?;
+    let mut secret = req.secret;
+    merge_secret(&mut secret, existing.and_then(|c| c.secret));
+    if secret.is_none() {
+        return Err(ApiError::invalid_argument("secret is required"));
+    }
+
+    let credential = RegistryCredential {
+        registry,
+        username: req.username.trim().to_string(),
+        secret,
+        updated_at: Some(now.clone()),
+    };
+    state
+        .db
+        .put_registry_credential(&credential, &now)
+        .await
+        .map_err(map_internal)?;
+    Ok(Json(credential.masked()))
+}
+
+async fn delete_registry_credential(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(registry): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    let deleted = state
+        .db
+        .delete_registry_credential(&normalize_auth_key(&registry))
+        .await
+        .map_err(map_internal)?;
+    if !deleted {
+        return Err(ApiError::not_found("registry credential not found"));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentCommandsQuery {
+    wait_seconds: Option<u64>,
+}
+
+async fn agent_commands(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<AgentCommandsQuery>,
+) -> Result<Json<AgentCommandsResponse>, ApiError> {
+    let host_id = require_agent(&state, &headers).await?;
+    let wait = std::time::Duration::from_secs(query.wait_seconds.unwrap_or(25));
+    let commands = state.agents.poll(&host_id, wait).await;
+    Ok(Json(AgentCommandsResponse { commands }))
+}
+
+async fn agent_command_output(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(command_id): Path<String>,
+    Json(req): Json<AgentOutputRequest>,
+) -> Result<StatusCode, ApiError> {
+    let host_id = require_agent(&state, &headers).await?;
+    if !state.agents.push_output(&host_id, &command_id, req.lines) {
+        return Err(ApiError::not_found("command not found"));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn agent_command_result(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(command_id): Path<String>,
+    Json(req): Json<AgentResult>,
+) -> Result<StatusCode, ApiError> {
+    let host_id = require_agent(&state, &headers).await?;
+    if !state.agents.complete(&host_id, &command_id, req) {
+        return Err(ApiError::not_found("command not found"));
+    }
+    Ok(StatusCode::NO_CONTENT)
 }
 
 fn parse_archived_filter(input: Option<&str>) -> Result<crate::db::ArchivedFilter, ApiError> {
@@ -135,6 +426,7 @@ async fn get_stack(
         stack: StackResponse {
             id: stack.id,
             name: stack.name,
+            host_id: stack.host_id,
             compose: stack.compose,
             services: stack.services,
             archived: Some(stack.archived),
@@ -291,6 +583,14 @@ async fn trigger_discovery_scan(
 #[serde(rename_all = "camelCase")]
 struct ListDiscoveryProjectsQuery {
     archived: Option<String>,
+    host_id: Option<String>,
+}
+
+/// Selects the host of a discovered project; defaults to the local host.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscoveryProjectQuery {
+    host_id: Option<String>,
 }
 
 async fn list_discovery_projects(
@@ -301,7 +601,10 @@ async fn list_discovery_projects(
     let _user = require_user(&state, &headers)?;
     let projects = state
         .db
-        .list_discovered_compose_projects(parse_archived_filter(q.archived.as_deref())?)
+        .list_discovered_compose_projects(
+            parse_archived_filter(q.archived.as_deref())?,
+            q.host_id.as_deref(),
+        )
         .await
         .map_err(map_internal)?;
     Ok(Json(ListDiscoveredProjectsResponse { projects }))
@@ -311,12 +614,19 @@ async fn archive_discovery_project(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Path(project): Path<String>,
+    Query(q): Query<DiscoveryProjectQuery>,
 ) -> Result<StatusCode, ApiError> {
     let _user = require_user(&state, &headers)?;
     let now = now_rfc3339().map_err(map_internal)?;
     let changed = state
         .db
-        .set_discovered_compose_project_archived(&project, true, Some("user_archive"), &now)
+        .set_discovered_compose_project_archived(
+            q.host_id.as_deref().unwrap_or(LOCAL_HOST_ID),
+            &project,
+            true,
+            Some("user_archive"),
+            &now,
+        )
         .await
         .map_err(map_internal)?;
     if !changed {
@@ -329,12 +639,19 @@ async fn restore_discovery_project(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Path(project): Path<String>,
+    Query(q): Query<DiscoveryProjectQuery>,
 ) -> Result<StatusCode, ApiError> {
     let _user = require_user(&state, &headers)?;
     let now = now_rfc3339().map_err(map_internal)?;
     let changed = state
         .db
-        .set_discovered_compose_project_archived(&project, false, None, &now)
+        .set_discovered_compose_project_archived(
+            q.host_id.as_deref().unwrap_or(LOCAL_HOST_ID),
+            &project,
+            false,
+            None,
+            &now,
+        )
         .await
         .map_err(map_internal)?;
     if !changed {
@@ -372,26 +689,25 @@ async fn trigger_check(
     job_db.reason = req.reason.as_str().to_string();
     state.db.insert_job(job_db).await.map_err(map_internal)?;
 
-    let host_platform = registry::host_platform_override(state.config.host_platform.as_deref())
-        .unwrap_or_else(|| "linux/amd64".to_string());
-
+    let cancel = state.queue.register(&check_id);
     let outcome = run_check_for_job(
         &state,
         &check_id,
         &req.scope,
         req.stack_id.as_deref(),
         req.service_id.as_deref(),
-        &host_platform,
         &now,
+        &cancel,
     )
     .await;
+    state.queue.unregister(&check_id);
 
     let finished_at = now_rfc3339().map_err(map_internal)?;
     match outcome {
         Ok(summary) => {
             state
                 .db
-                .finish_job(&check_id, "success", &finished_at, &summary)
+                .finish_job(&check_id, check_status(&summary), &finished_at, &summary)
                 .await
                 .map_err(map_internal)?;
         }
@@ -408,14 +724,88 @@ async fn trigger_check(
     Ok(Json(TriggerCheckResponse { check_id }))
 }
 
+/// Runs a check job over an explicit set of stacks on behalf of a background trigger
+/// (e.g. the scheduler), recording it in `jobs` like a UI-triggered check.
+pub(crate) async fn run_check_job(
+    state: &Arc<AppState>,
+    created_by: &str,
+    reason: CheckReason,
+    scope: JobScope,
+    stack_id: Option<String>,
+    stack_ids: Vec<String>,
+) -> Result<String, ApiError> {
+    let now = now_rfc3339().map_err(map_internal)?;
+    let check_id = ids::new_check_id();
+    let job = JobRecord::new_running(
+        check_id.clone(),
+        JobType::Check,
+        scope.clone(),
+        stack_id,
+        None,
+        &now,
+    );
+
+    let mut job_db = job.to_db();
+    job_db.created_by = created_by.to_string();
+    job_db.reason = reason.as_str().to_string();
+    state.db.insert_job(job_db).await.map_err(map_internal)?;
+
+    state
+        .db
+        .insert_job_log(
+            &check_id,
+            &JobLogLine {
+                ts: now.clone(),
+                level: "info".to_string(),
+                msg: format!("{} check started", reason.as_str()),
+            },
+        )
+        .await
+        .map_err(map_internal)?;
+
+    let cancel = state.queue.register(&check_id);
+    let outcome = run_check_for_stacks(state, &check_id, &scope, &stack_ids, &now, &cancel).await;
+    state.queue.unregister(&check_id);
+
+    let finished_at = now_rfc3339().map_err(map_internal)?;
+    match outcome {
+        Ok(summary) => {
+            state
+                .db
+                .finish_job(&check_id, check_status(&summary), &finished_at, &summary)
+                .await
+                .map_err(map_internal)?;
+            Ok(check_id)
+        }
+        Err(e) => {
+            let summary = json!({"error": format!("{e:?}")});
+            let _ = state
+                .db
+                .finish_job(&check_id, "failed", &finished_at, &summary)
+                .await;
+            Err(e)
+        }
+    }
+}
+
+/// Final job status of a finished check, derived from its summary.
+fn check_status(summary: &serde_json::Value) -> &'static str {
+    if summary.get("cancelled").and_then(|v| v.as_bool()) == Some(true) {
+        "cancelled"
+    } else {
+        "success"
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn run_check_for_job(
     state: &Arc<AppState>,
     job_id: &str,
     scope: &JobScope,
     stack_id: Option<&str>,
     service_id: Option<&str>,
-    host_platform: &str,
     now: &str,
+    cancel: &CancelToken,
 ) -> Result<serde_json::Value, ApiError> {
     let stack_ids = match scope {
         JobScope::All => state.db.list_stack_ids().await.map_err(map_internal)?,
@@ -432,12 +822,61 @@ async fn run_check_for_job(
         }
     };
 
+    run_check_for_stacks(state, job_id, scope, &stack_ids, now, cancel).await
+}
+
+async fn run_check_for_stacks(
+    state: &Arc<AppState>,
+    job_id: &str,
+    scope: &JobScope,
+    stack_ids: &[String],
+    now: &str,
+    cancel: &CancelToken,
+) -> Result<serde_json::Value, ApiError> {
     let mut services_checked = 0u32;
+    let mut host_platforms = std::collections::BTreeMap::<String, String>::new();
     let mut services_with_candidate = 0u32;
+    let mut auto_updates = Vec::<AutoUpdatePlan>::new();
     let mut manifest_digest_cache: std::collections::HashMap<String, Option<String>> =
         std::collections::HashMap::new();
+    // Services skipped because their registry is rate limited, per registry (with the time the
+    // limit lifts); they keep their previous check result and are picked up by the next check.
+    let mut deferred = std::collections::BTreeMap::<String, (u32, Option<String>)>::new();
+    let mut defer = |limited: &registry::RateLimited| {
+        let entry = deferred.entry(limited.registry.clone()).or_default();
+        entry.0 += 1;
+        entry.1 = limited.until.and_then(|t| {
+            t.format(&time::format_description::well_known::Rfc3339)
+                .ok()
+        });
+    };
+
+    let mut cancelled = false;
+    'stacks: for stack_id in stack_ids {
+        let host_id = state
+            .db
+            .get_stack_host_id(stack_id)
+            .await
+            .map_err(map_internal)?
+            .unwrap_or_else(|| LOCAL_HOST_ID.to_string());
+        let Some(host) = state.hosts.get(&host_id) else {
+            state
+                .db
+                .insert_job_log(
+                    job_id,
+                    &JobLogLine {
+                        ts: now.to_string(),
+                        level: "warn".to_string(),
+                        msg: format!("skip stack {stack_id}: host {host_id} is not registered"),
+                    },
+                )
+                .await
+                .map_err(map_internal)?;
+            continue;
+        };
+        let host_platform = host.platform.as_str();
+        host_platforms.insert(host_id.clone(), host.platform.clone());
 
-    for stack_id in &stack_ids {
         let compose_project = state
             .db
             .get_stack_compose_project(stack_id)
@@ -451,6 +890,10 @@ async fn run_check_for_job(
             .map_err(map_internal)?;
 
         for svc in services {
+            if cancel.is_cancelled() {
+                cancelled = true;
+                break 'stacks;
+            }
             services_checked += 1;
             let img = match registry::ImageRef::parse(&svc.image_ref) {
                 Ok(img) => img,
@@ -471,6 +914,11 @@ async fn run_check_for_job(
                 }
             };
 
+            if let Some(limited) = state.registry.rate_limited(&img.registry) {
+                defer(&limited);
+                continue;
+            }
+
             let ignore_rules = state
                 .db
                 .list_ignore_rules_for_service(&svc.id)
@@ -493,6 +941,10 @@ async fn run_check_for_job(
             let tags = match state.registry.list_tags(&img).await {
                 Ok(t) => t,
                 Err(e) => {
+                    if let Some(limited) = e.downcast_ref::<registry::RateLimited>() {
+                        defer(limited);
+                        continue;
+                    }
                     state
                         .db
                         .insert_job_log(
@@ -511,7 +963,7 @@ async fn run_check_for_job(
 
             let runtime_digest = if let Some(project) = compose_project.as_deref() {
                 docker_compose_service_runtime_digest(
-                    state.as_ref(),
+                    host.docker.as_ref(),
                     project,
                     &svc.name,
                     &repo_candidates(&img),
@@ -524,17 +976,21 @@ async fn run_check_for_job(
             };
 
             let is_ignored = |tag: &str| matchers.iter().any(|(_, m)| m.matches(tag));
+            let versioning = versioning(state, &svc.version_strategy, svc.prerelease_policy);
             let candidate_non_ignored =
-                candidates::select_candidate_tag(&svc.image_tag, &tags, is_ignored);
-            let candidate_any = candidates::select_candidate_tag(&svc.image_tag, &tags, |_| false);
+                candidates::select_candidate_tag(&versioning, &svc.image_tag, &tags, is_ignored);
+            let candidate_any =
+                candidates::select_candidate_tag(&versioning, &svc.image_tag, &tags, |_| false);
             let mut candidate_tag = candidate_non_ignored.or(candidate_any);
 
-            let current_digest_registry = state
+            let current_manifest_registry = state
                 .registry
                 .get_manifest(&img, &svc.image_tag, host_platform)
                 .await
-                .ok()
-                .and_then(|m| m.digest);
+                .ok();
+            let current_digest_registry = current_manifest_registry
+                .as_ref()
+                .and_then(|m| m.digest.clone());
             let effective_current_digest =
                 runtime_digest.clone().or(current_digest_registry.clone());
             // Persist the best-known digest so that pinned tags and offline/missing compose projects
@@ -545,6 +1001,7 @@ async fn run_check_for_job(
                 candidate_digest_for_infer,
                 candidate_arch_match_for_infer,
                 candidate_arch_json_for_infer,
+                candidate_content_digest,
             ) = if let Some(tag) = candidate_tag.as_deref() {
                 match state.registry.get_manifest(&img, tag, host_platform).await {
                     Ok(m) => {
@@ -553,12 +1010,13 @@ async fn run_check_for_job(
                             m.digest,
                             Some(arch_match.as_str().to_string()),
                             Some(serde_json::to_string(&m.arch).unwrap_or_default()),
+                            m.content_digest,
                         )
                     }
-                    Err(_) => (None, None, None),
+                    Err(_) => (None, None, None, None),
                 }
             } else {
-                (None, None, None)
+                (None, None, None, None)
             };
 
             let mut candidate_digest = candidate_digest_for_infer;
@@ -616,7 +1074,8 @@ async fn run_check_for_job(
                     {
                         candidate_digest.clone()
                     } else {
-                        let cache_key = format!("{}/{}:{}", img.registry, img.name, tag);
+                        let cache_key =
+                            format!("{}/{}:{}@{host_platform}", img.registry, img.name, tag);
                         if let Some(v) = manifest_digest_cache.get(&cache_key) {
                             v.clone()
                         } else {
@@ -649,17 +1108,122 @@ async fn run_check_for_job(
                 (None, None)
             };
 
+            let candidate_release_notes_json =
+                match (candidate_tag.as_deref(), candidate_digest.as_deref()) {
+                    (Some(tag), Some(digest)) => {
+                        match candidate_release_notes(
+                            state,
+                            &img,
+                            host_platform,
+                            &svc.image_tag,
+                            tag,
+                            digest,
+                        )
+                        .await
+                        {
+                            Ok(notes) => notes.and_then(|n| serde_json::to_string(&n).ok()),
+                            Err(e) => {
+                                state
+                                    .db
+                                    .insert_job_log(
+                                        job_id,
+                                        &JobLogLine {
+                                            ts: now.to_string(),
+                                            level: "warn".to_string(),
+                                            msg: format!(
+                                                "release notes for {} ({tag}) failed: {e}",
+                                                svc.name
+                                            ),
+                                        },
+                                    )
+                                    .await
+                                    .map_err(map_internal)?;
+                                None
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+
+            let candidate_signature_json = match candidate_digest.as_deref() {
+                Some(digest) if state.config.signature_policy.covers(&img) => {
+                    let (digest, verdict) = state
+                        .config
+                        .signature_policy
+                        .verify_resolved(
+                            state.registry.as_ref(),
+                            &img,
+                            candidate_content_digest.as_deref(),
+                            digest,
+                        )
+                        .await;
+                    if verdict.status != SignatureStatus::Verified {
+                        state
+                            .db
+                            .insert_job_log(
+                                job_id,
+                                &JobLogLine {
+                                    ts: now.to_string(),
+                                    level: "warn".to_string(),
+                                    msg: format!(
+                                        "signature of {} ({digest}) not verified: {}",
+                                        svc.name,
+                                        verdict.message.as_deref().unwrap_or("unknown reason")
+                                    ),
+                                },
+                            )
+                            .await
+                            .map_err(map_internal)?;
+                    }
+                    serde_json::to_string(&verdict).ok()
+                }
+                _ => None,
+            };
+
+            // Manifest lookups above may have run into the limit; don't overwrite the previous
+            // result with a partial one.
+            if let Some(limited) = state.registry.rate_limited(&img.registry) {
+                defer(&limited);
+                continue;
+            }
+
+            if state.scanner.is_some()
+                && let Some(candidate) = candidate_digest.as_deref()
+            {
+                for digest in current_digest.as_deref().into_iter().chain([candidate]) {
+                    if let Err(e) = scan_digest(state, &img, digest).await {
+                        state
+                            .db
+                            .insert_job_log(
+                                job_id,
+                                &JobLogLine {
+                                    ts: now.to_string(),
+                                    level: "warn".to_string(),
+                                    msg: format!(
+                                        "vulnerability scan of {} ({digest}) failed: {e}",
+                                        svc.name
+                                    ),
+                                },
+                            )
+                            .await
+                            .map_err(map_internal)?;
+                    }
+                }
+            }
+
             state
                 .db
                 .update_service_check_result(
                     &svc.id,
-                    current_digest,
+                    current_digest.clone(),
                     current_resolved_tag,
                     current_resolved_tags_json,
                     candidate_tag.clone(),
                     candidate_digest,
                     candidate_arch_match,
                     candidate_arch_json,
+                    candidate_release_notes_json,
+                    candidate_signature_json,
                     ignore_match.as_ref().map(|(id, _)| id.clone()),
                     ignore_match.as_ref().map(|(_, r)| r.clone()),
                     now,
@@ -667,37 +1231,326 @@ async fn run_check_for_job(
                 )
                 .await
                 .map_err(map_internal)?;
-        }
 
-        state
-            .db
-            .update_stack_last_check_at(stack_id, now)
-            .await
-            .map_err(map_internal)?;
-    }
+            if svc.archived || svc.auto_update.policy == AutoUpdatePolicy::Never {
+                continue;
+            }
 
-    state
+            let auto_target = match candidates::select_auto_update_tag(
+                &versioning,
+                svc.auto_update.policy,
+                &svc.image_tag,
+                &tags,
+                is_ignored,
+            ) {
+                Some(tag) => state
+                    .registry
+                    .get_manifest(&img, &tag, host_platform)
+                    .await
+                    .ok()
+                    .and_then(|m| m.digest.clone().map(|d| (tag, d, m.arch))),
+                // Same tag, new digest: only meaningful when we know what is actually running.
+                None => match (runtime_digest.as_deref(), current_manifest_registry) {
+                    (Some(running), Some(m))
+                        if m.digest.as_deref().is_some_and(|d| d != running) =>
+                    {
+                        m.digest.map(|d| (svc.image_tag.clone(), d, m.arch))
+                    }
+                    _ => None,
+                },
+            };
+            let Some((tag, digest, arch)) = auto_target else {
+                continue;
+            };
+
+            let security = svc.auto_update.security;
+            let fixes = match security {
+                SecurityPreference::Ignore => Ok(false),
+                _ => fixes_vulnerabilities(state, &img, current_digest.as_deref(), &digest).await,
+            };
+            // Security fixes don't wait for the service's window under `prefer`.
+            let skip_window = security == SecurityPreference::Prefer && matches!(fixes, Ok(true));
+            let skip_reason = if matches!(
+                registry::compute_arch_match(host_platform, &arch),
+                ArchMatch::Mismatch
+            ) {
+                Some("candidate arch mismatch".to_string())
+            } else if security == SecurityPreference::Require && !matches!(fixes, Ok(true)) {
+                Some(match fixes {
+                    Err(e) => format!("security fixes required: {e}"),
+                    _ => "security fixes required: candidate fixes no vulnerabilities".to_string(),
+                })
+            } else {
+                match svc
+                    .auto_update
+                    .window
+                    .as_ref()
+                    .map(maintenance::Window::parse)
+                {
+                    Some(Ok(w)) if !skip_window && !w.is_open(time::OffsetDateTime::now_utc()) => {
+                        Some("outside maintenance window".to_string())
+                    }
+                    Some(Err(e)) => Some(format!("invalid maintenance window: {e}")),
+                    _ => None,
+                }
+            };
+            if let Some(reason) = skip_reason {
+                state
+                    .db
+                    .insert_job_log(
+                        job_id,
+                        &JobLogLine {
+                            ts: now.to_string(),
+                            level: "info".to_string(),
+                            msg: format!("auto-update skipped for {} ({tag}): {reason}", svc.name),
+                        },
+                    )
+                    .await
+                    .map_err(map_internal)?;
+                continue;
+            }
+
+            auto_updates.push(AutoUpdatePlan {
+                stack_id: stack_id.clone(),
+                service_id: svc.id.clone(),
+                service_name: svc.name.clone(),
+                tag,
+                digest,
+            });
+        }
+
+        state
+            .db
+            .update_stack_last_check_at(stack_id, now)
+            .await
+            .map_err(map_internal)?;
+    }
+
+    let mut services_deferred = 0u32;
+    for (registry, (count, until)) in &deferred {
+        services_deferred += count;
+        state
+            .db
+            .insert_job_log(
+                job_id,
+                &JobLogLine {
+                    ts: now.to_string(),
+                    level: "warn".to_string(),
+                    msg: format!(
+                        "deferred {count} service(s) on {registry}: registry rate limited{}",
+                        until
+                            .as_deref()
+                            .map(|u| format!(" until {u}"))
+                            .unwrap_or_default()
+                    ),
+                },
+            )
+            .await
+            .map_err(map_internal)?;
+    }
+
+    state
         .db
         .insert_job_log(
             job_id,
             &JobLogLine {
                 ts: now.to_string(),
                 level: "info".to_string(),
-                msg: "check finished".to_string(),
+                msg: if cancelled {
+                    "check cancelled".to_string()
+                } else {
+                    "check finished".to_string()
+                },
             },
         )
         .await
         .map_err(map_internal)?;
 
+    // A cancelled check has only seen part of the services, so it does not trigger auto-updates.
+    let auto_update_job_ids = if cancelled {
+        Vec::new()
+    } else {
+        enqueue_auto_updates(state, job_id, auto_updates).await?
+    };
+
     Ok(json!({
-        "hostPlatform": host_platform,
+        "hostPlatform": state.local_host().platform,
+        "hostPlatforms": host_platforms,
         "scope": scope.as_str(),
         "stackIds": stack_ids,
         "servicesChecked": services_checked,
         "servicesWithCandidate": services_with_candidate,
+        "servicesDeferred": services_deferred,
+        "deferredRegistries": deferred.keys().collect::<Vec<_>>(),
+        "autoUpdateJobIds": auto_update_job_ids,
+        "cancelled": cancelled,
     }))
 }
 
+struct AutoUpdatePlan {
+    stack_id: String,
+    service_id: String,
+    service_name: String,
+    tag: String,
+    digest: String,
+}
+
+async fn enqueue_auto_updates(
+    state: &Arc<AppState>,
+    check_job_id: &str,
+    plans: Vec<AutoUpdatePlan>,
+) -> Result<Vec<String>, ApiError> {
+    let mut job_ids = Vec::new();
+    for plan in plans {
+        let req = TriggerUpdateRequest {
+            scope: JobScope::Service,
+            stack_id: Some(plan.stack_id),
+            service_id: Some(plan.service_id),
+            target_tag: Some(plan.tag.clone()),
+            target_digest: Some(plan.digest.clone()),
+            mode: UpdateMode::Apply,
+            allow_arch_mismatch: false,
+            backup_mode: BackupMode::Inherit,
+            reason: UpdateReason::Schedule,
+        };
+        let now = now_rfc3339().map_err(map_internal)?;
+        let (level, msg) = match enqueue_update_job(
+            state.clone(),
+            "auto-update".to_string(),
+            UpdateReason::Schedule.as_str().to_string(),
+            req,
+            now.clone(),
+        )
+        .await
+        {
+            Ok(update_job_id) => {
+                let msg = format!(
+                    "auto-update queued for {} -> {}@{} (job {update_job_id})",
+                    plan.service_name, plan.tag, plan.digest
+                );
+                job_ids.push(update_job_id);
+                ("info", msg)
+            }
+            Err(e) => (
+                "warn",
+                format!(
+                    "auto-update for {} failed to start: {}",
+                    plan.service_name,
+                    e.message()
+                ),
+            ),
+        };
+        state
+            .db
+            .insert_job_log(
+                check_job_id,
+                &JobLogLine {
+                    ts: now,
+                    level: level.to_string(),
+                    msg,
+                },
+            )
+            .await
+            .map_err(map_internal)?;
+    }
+    Ok(job_ids)
+}
+
+/// Release notes of a candidate, cached per candidate digest and current tag for
+/// [`release_notes::CACHE_TTL`]. `None` when they are disabled or the image names no source.
+async fn candidate_release_notes(
+    state: &AppState,
+    img: &registry::ImageRef,
+    host_platform: &str,
+    current_tag: &str,
+    candidate_tag: &str,
+    candidate_digest: &str,
+) -> anyhow::Result<Option<ReleaseNotes>> {
+    let Some(client) = state.release_notes.as_ref() else {
+        return Ok(None);
+    };
+    let now = time::OffsetDateTime::now_utc().unix_timestamp();
+    if let Some((notes_json, fetched_at)) = state
+        .db
+        .get_release_notes_cache(&img.registry, &img.name, candidate_digest, current_tag)
+        .await?
+        && now - fetched_at < release_notes::CACHE_TTL.as_secs() as i64
+    {
+        return Ok(notes_json.and_then(|j| serde_json::from_str(&j).ok()));
+    }
+
+    let config = state
+        .registry
+        .get_image_config(img, candidate_digest, host_platform)
+        .await?;
+    let notes = match release_notes::SourceInfo::from_config(&config) {
+        Some(source) => Some(client.fetch(&source, current_tag, candidate_tag).await?),
+        None => None,
+    };
+    let notes_json = notes.as_ref().map(serde_json::to_string).transpose()?;
+    state
+        .db
+        .put_release_notes_cache(
+            &img.registry,
+            &img.name,
+            candidate_digest,
+            current_tag,
+            notes_json,
+            now,
+        )
+        .await?;
+    Ok(notes)
+}
+
+/// Vulnerability scan of `digest`, reused for `DOCKREV_SCAN_TTL_SECONDS`. `None` when no
+/// scanner is configured.
+async fn scan_digest(
+    state: &AppState,
+    img: &registry::ImageRef,
+    digest: &str,
+) -> anyhow::Result<Option<VulnerabilityScan>> {
+    let Some(scanner) = state.scanner.as_ref() else {
+        return Ok(None);
+    };
+    let now = time::OffsetDateTime::now_utc();
+    if let Some((scan, scanned_at)) = state.db.get_vulnerability_scan(digest).await?
+        && now.unix_timestamp() - scanned_at < state.config.scan_ttl_seconds as i64
+    {
+        return Ok(Some(scan));
+    }
+
+    let counts = scanner
+        .scan(&format!("{}/{}@{digest}", img.registry, img.name))
+        .await?;
+    let scan = VulnerabilityScan {
+        scanner: scanner.name().to_string(),
+        counts,
+        scanned_at: now.format(&time::format_description::well_known::Rfc3339)?,
+    };
+    state
+        .db
+        .put_vulnerability_scan(digest, &scan, now.unix_timestamp())
+        .await?;
+    Ok(Some(scan))
+}
+
+/// Whether `target` has fewer findings than `current`, for auto-update security preferences.
+async fn fixes_vulnerabilities(
+    state: &AppState,
+    img: &registry::ImageRef,
+    current: Option<&str>,
+    target: &str,
+) -> anyhow::Result<bool> {
+    let current = current.ok_or_else(|| anyhow::anyhow!("current digest unknown"))?;
+    let (Some(current), Some(target)) = (
+        scan_digest(state, img, current).await?,
+        scan_digest(state, img, target).await?,
+    ) else {
+        return Err(anyhow::anyhow!("no vulnerability scanner configured"));
+    };
+    Ok(scanner::diff(&current.counts, &target.counts).fixes)
+}
+
 fn repo_candidates(img: &registry::ImageRef) -> Vec<String> {
     let mut out = Vec::<String>::new();
     out.push(format!("{}/{}", img.registry, img.name));
@@ -713,99 +1566,31 @@ fn repo_candidates(img: &registry::ImageRef) -> Vec<String> {
 }
 
 async fn docker_compose_service_runtime_digest(
-    state: &AppState,
+    docker: &dyn DockerEngine,
     compose_project: &str,
     compose_service: &str,
     repo_candidates: &[String],
 ) -> anyhow::Result<Option<String>> {
-    use crate::runner::CommandSpec;
-
-    let ps = state
-        .runner
-        .run(
-            CommandSpec {
-                program: "docker".to_string(),
-                args: vec![
-                    "ps".to_string(),
-                    "-q".to_string(),
-                    "--filter".to_string(),
-                    format!("label=com.docker.compose.project={compose_project}"),
-                    "--filter".to_string(),
-                    format!("label=com.docker.compose.service={compose_service}"),
-                ],
-                env: Vec::new(),
-            },
-            std::time::Duration::from_secs(8),
-        )
+    let containers = docker
+        .list_containers(&[
+            format!("com.docker.compose.project={compose_project}"),
+            format!("com.docker.compose.service={compose_service}"),
+        ])
         .await?;
-
-    if ps.status != 0 {
-        return Err(anyhow::anyhow!(
-            "docker ps failed status={} stderr={}",
-            ps.status,
-            ps.stderr
-        ));
-    }
-
-    let container_ids = ps
-        .stdout
-        .lines()
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .collect::<Vec<_>>();
-    if container_ids.is_empty() {
+    if containers.is_empty() {
         return Ok(None);
     }
 
     let mut digests = std::collections::BTreeSet::<String>::new();
-    for id in container_ids {
-        let img_id = state
-            .runner
-            .run(
-                CommandSpec {
-                    program: "docker".to_string(),
-                    args: vec![
-                        "inspect".to_string(),
-                        "--format".to_string(),
-                        "{{.Image}}".to_string(),
-                        id,
-                    ],
-                    env: Vec::new(),
-                },
-                std::time::Duration::from_secs(10),
-            )
-            .await?;
-        if img_id.status != 0 {
-            continue;
-        }
-        let img_id = img_id.stdout.trim().to_string();
-        if img_id.is_empty() {
+    for container in containers {
+        if container.image_id.is_empty() {
             continue;
         }
-
-        let inspect = state
-            .runner
-            .run(
-                CommandSpec {
-                    program: "docker".to_string(),
-                    args: vec![
-                        "image".to_string(),
-                        "inspect".to_string(),
-                        img_id,
-                        "--format".to_string(),
-                        "{{json .RepoDigests}}".to_string(),
-                    ],
-                    env: Vec::new(),
-                },
-                std::time::Duration::from_secs(10),
-            )
-            .await?;
-        if inspect.status != 0 {
+        let Ok(image) = docker.inspect_image(&container.image_id).await else {
             continue;
-        }
+        };
 
-        let parsed = serde_json::from_str::<Vec<String>>(inspect.stdout.trim()).unwrap_or_default();
-        for d in parsed {
+        for d in image.repo_digests {
             for repo in repo_candidates {
                 if let Some(rest) = d.strip_prefix(&format!("{repo}@"))
                     && !rest.trim().is_empty()
@@ -823,6 +1608,241 @@ async fn docker_compose_service_runtime_digest(
     }
 }
 
+async fn list_schedules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ListSchedulesResponse>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    let schedules = state
+        .db
+        .list_check_schedules()
+        .await
+        .map_err(map_internal)?;
+
+    let mut global = None;
+    let mut stacks = Vec::new();
+    for s in schedules {
+        match s.stack_id.clone() {
+            Some(stack_id) => stacks.push(StackCheckSchedule {
+                stack_id,
+                schedule: s.into_api(),
+            }),
+            None if s.id == GLOBAL_CHECK_SCHEDULE_ID => global = Some(s.into_api()),
+            None => {}
+        }
+    }
+
+    let global = global.ok_or_else(|| ApiError::internal("global schedule missing"))?;
+    Ok(Json(ListSchedulesResponse { global, stacks }))
+}
+
+async fn put_global_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<PutScheduleRequest>,
+) -> Result<Json<CheckSchedule>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    let schedule = save_schedule(&state, GLOBAL_CHECK_SCHEDULE_ID, None, req).await?;
+    Ok(Json(schedule))
+}
+
+async fn get_stack_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(stack_id): Path<String>,
+) -> Result<Json<StackScheduleResponse>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    if state
+        .db
+        .is_stack_archived(&stack_id)
+        .await
+        .map_err(map_internal)?
+        .is_none()
+    {
+        return Err(ApiError::not_found("stack not found"));
+    }
+
+    let own = state
+        .db
+        .get_check_schedule(&stack_id)
+        .await
+        .map_err(map_internal)?;
+    let (inherited, schedule) = match own {
+        Some(s) => (false, s),
+        None => (
+            true,
+            state
+                .db
+                .get_check_schedule(GLOBAL_CHECK_SCHEDULE_ID)
+                .await
+                .map_err(map_internal)?
+                .ok_or_else(|| ApiError::internal("global schedule missing"))?,
+        ),
+    };
+
+    Ok(Json(StackScheduleResponse {
+        stack_id,
+        inherited,
+        schedule: schedule.into_api(),
+    }))
+}
+
+async fn put_stack_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(stack_id): Path<String>,
+    Json(req): Json<PutScheduleRequest>,
+) -> Result<Json<StackScheduleResponse>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    if state
+        .db
+        .is_stack_archived(&stack_id)
+        .await
+        .map_err(map_internal)?
+        .is_none()
+    {
+        return Err(ApiError::not_found("stack not found"));
+    }
+
+    let schedule = save_schedule(&state, &stack_id, Some(&stack_id), req).await?;
+    Ok(Json(StackScheduleResponse {
+        stack_id,
+        inherited: false,
+        schedule,
+    }))
+}
+
+async fn delete_stack_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(stack_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    // Schedules share one table; only ever delete a live stack's own row.
+    if stack_id == GLOBAL_CHECK_SCHEDULE_ID
+        || state
+            .db
+            .is_stack_archived(&stack_id)
+            .await
+            .map_err(map_internal)?
+            != Some(false)
+    {
+        return Err(ApiError::not_found("stack not found"));
+    }
+    let deleted = state
+        .db
+        .delete_check_schedule(&stack_id)
+        .await
+        .map_err(map_internal)?;
+    if !deleted {
+        return Err(ApiError::not_found("stack schedule not found"));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_maintenance_windows(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ListMaintenanceWindowsResponse>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    let windows = state
+        .db
+        .list_maintenance_windows()
+        .await
+        .map_err(map_internal)?;
+    Ok(Json(ListMaintenanceWindowsResponse { windows }))
+}
+
+async fn create_maintenance_window(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<CreateMaintenanceWindowRequest>,
+) -> Result<(StatusCode, Json<CreateMaintenanceWindowResponse>), ApiError> {
+    let _user = require_user(&state, &headers)?;
+    let now = now_rfc3339().map_err(map_internal)?;
+
+    maintenance::Window::parse(&req.window)
+        .map_err(|e| ApiError::invalid_argument(format!("invalid maintenance window: {e}")))?;
+    if let Some(stack_id) = req.stack_id.as_deref()
+        && state
+            .db
+            .is_stack_archived(stack_id)
+            .await
+            .map_err(map_internal)?
+            .is_none()
+    {
+        return Err(ApiError::not_found("stack not found"));
+    }
+
+    let window_id = ids::new_maintenance_window_id();
+    let record = MaintenanceWindowRecord {
+        id: window_id.clone(),
+        stack_id: req.stack_id,
+        window: req.window,
+    };
+    state
+        .db
+        .insert_maintenance_window(&record, &now)
+        .await
+        .map_err(map_internal)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateMaintenanceWindowResponse { window_id }),
+    ))
+}
+
+async fn delete_maintenance_window(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(window_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    let deleted = state
+        .db
+        .delete_maintenance_window(&window_id)
+        .await
+        .map_err(map_internal)?;
+    if !deleted {
+        return Err(ApiError::not_found("maintenance window not found"));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn save_schedule(
+    state: &AppState,
+    id: &str,
+    stack_id: Option<&str>,
+    req: PutScheduleRequest,
+) -> Result<CheckSchedule, ApiError> {
+    let cron = req.cron.trim().to_string();
+    let next_run_at = scheduler::next_run_at(&cron, time::OffsetDateTime::now_utc())
+        .map_err(|e| ApiError::invalid_argument(format!("invalid cron expression: {e}")))?;
+    let next_run_at = if req.enabled { next_run_at } else { None };
+
+    let now = now_rfc3339().map_err(map_internal)?;
+    state
+        .db
+        .upsert_check_schedule(
+            id,
+            stack_id,
+            &cron,
+            req.enabled,
+            next_run_at.as_deref(),
+            &now,
+        )
+        .await
+        .map_err(map_internal)?;
+
+    let saved = state
+        .db
+        .get_check_schedule(id)
+        .await
+        .map_err(map_internal)?
+        .ok_or_else(|| ApiError::internal("schedule missing after save"))?;
+    Ok(saved.into_api())
+}
+
 async fn trigger_update(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -859,6 +1879,19 @@ async fn enqueue_update_job(
         .await
         .map_err(map_internal)?;
     validate_arch_mismatch_for_update(&state, &req, &stack_ids).await?;
+    if matches!(req.mode, UpdateMode::Apply) {
+        validate_signatures_for_update(&state, &req, &stack_ids).await?;
+    }
+
+    // Dry-runs never touch containers, so they are not held back by maintenance windows.
+    let deferral = if matches!(req.mode, UpdateMode::Apply) {
+        let windows = maintenance::effective_windows(&state, &req.scope, &stack_ids)
+            .await
+            .map_err(map_internal)?;
+        maintenance::deferral(&windows, time::OffsetDateTime::now_utc())
+    } else {
+        None
+    };
 
     let job_id = ids::new_job_id();
     let mut job = JobRecord::new_running(
@@ -872,12 +1905,33 @@ async fn enqueue_update_job(
     job.allow_arch_mismatch = req.allow_arch_mismatch;
     job.backup_mode = req.backup_mode.as_str().to_string();
     job.summary_json = json!({ "mode": req.mode.as_str() });
+    job.status = if deferral.is_some() {
+        "pending".to_string()
+    } else {
+        "queued".to_string()
+    };
+    job.started_at = None;
 
     let mut job_db = job.to_db();
     job_db.created_by = created_by;
     job_db.reason = reason;
     state.db.insert_job(job_db).await.map_err(map_internal)?;
+    state
+        .db
+        .set_job_request(&job_id, &json!(req))
+        .await
+        .map_err(map_internal)?;
 
+    let msg = match deferral {
+        None => "update queued".to_string(),
+        Some(Some(opens_at)) => format!(
+            "update deferred: outside maintenance window (next window opens at {})",
+            opens_at
+                .format(&time::format_description::well_known::Rfc3339)
+                .map_err(|e| map_internal(e.into()))?
+        ),
+        Some(None) => "update deferred: outside maintenance window".to_string(),
+    };
     state
         .db
         .insert_job_log(
@@ -885,23 +1939,49 @@ async fn enqueue_update_job(
             &JobLogLine {
                 ts: now.clone(),
                 level: "info".to_string(),
-                msg: "update started".to_string(),
+                msg,
             },
         )
         .await
         .map_err(map_internal)?;
 
-    let run_state = state.clone();
-    let run_job_id = job_id.clone();
-    let run_req = req.clone();
-    tokio::spawn(async move {
-        let _ = run_update_job(run_state, run_job_id, run_req).await;
-    });
+    if deferral.is_none() {
+        queue::dispatch(&state).await.map_err(map_internal)?;
+    }
 
     Ok(job_id)
 }
 
-async fn resolve_stack_ids_for_update(
+/// Queues a job previously deferred by a maintenance window; returns false if it is no longer pending.
+pub(crate) async fn release_pending_update_job(
+    state: &Arc<AppState>,
+    job_id: &str,
+) -> anyhow::Result<bool> {
+    if !state
+        .db
+        .claim_job(job_id, "pending", "queued", None)
+        .await?
+    {
+        return Ok(false);
+    }
+
+    state
+        .db
+        .insert_job_log(
+            job_id,
+            &JobLogLine {
+                ts: now_rfc3339()?,
+                level: "info".to_string(),
+                msg: "maintenance window open; update queued".to_string(),
+            },
+        )
+        .await?;
+
+    queue::dispatch(state).await?;
+    Ok(true)
+}
+
+pub(crate) async fn resolve_stack_ids_for_update(
     state: &AppState,
     req: &TriggerUpdateRequest,
 ) -> anyhow::Result<Vec<String>> {
@@ -926,9 +2006,6 @@ async fn validate_arch_mismatch_for_update(
     req: &TriggerUpdateRequest,
     stack_ids: &[String],
 ) -> Result<(), ApiError> {
-    let host_platform = registry::host_platform_override(state.config.host_platform.as_deref())
-        .unwrap_or_else(|| "linux/amd64".to_string());
-
     if req.allow_arch_mismatch {
         return Ok(());
     }
@@ -943,6 +2020,7 @@ async fn validate_arch_mismatch_for_update(
         let Some(stack) = state.db.get_stack(stack_id).await.map_err(map_internal)? else {
             continue;
         };
+        let host_platform = state.host_platform(&stack.host_id);
 
         for svc in &stack.services {
             if req.service_id.as_deref().is_some_and(|id| id != svc.id) {
@@ -951,7 +2029,9 @@ async fn validate_arch_mismatch_for_update(
 
             if let Some(tag) = req.target_tag.as_deref() {
                 let img = registry::ImageRef::parse(&svc.image.reference).map_err(|_| {
-                    ApiError::invalid_argument("invalid image ref (expected repo/name:tag)")
+                    ApiError::invalid_argument(
+                        "invalid image ref (expected [registry/]name[:tag][@digest])",
+                    )
                 })?;
 
                 let reference = req.target_digest.as_deref().unwrap_or(tag);
@@ -972,7 +2052,9 @@ async fn validate_arch_mismatch_for_update(
 
             if req.target_digest.is_some() {
                 let img = registry::ImageRef::parse(&svc.image.reference).map_err(|_| {
-                    ApiError::invalid_argument("invalid image ref (expected repo/name:tag)")
+                    ApiError::invalid_argument(
+                        "invalid image ref (expected [registry/]name[:tag][@digest])",
+                    )
                 })?;
                 let reference = req.target_digest.as_deref().unwrap();
                 let manifest = state
@@ -1003,14 +2085,102 @@ async fn validate_arch_mismatch_for_update(
     Ok(())
 }
 
+/// Returns the verified digest of every service under `DOCKREV_SIGNATURE_REQUIRED`, by service
+/// id. The update pins exactly these digests, as a tag may have moved since it was verified.
+async fn validate_signatures_for_update(
+    state: &AppState,
+    req: &TriggerUpdateRequest,
+    stack_ids: &[String],
+) -> Result<std::collections::BTreeMap<String, String>, ApiError> {
+    let policy = &state.config.signature_policy;
+    let mut verified = std::collections::BTreeMap::new();
+    for stack_id in stack_ids {
+        let Some(stack) = state.db.get_stack(stack_id).await.map_err(map_internal)? else {
+            continue;
+        };
+        let host_platform = state.host_platform(&stack.host_id);
+        for svc in updater::services_to_update(
+            &stack,
+            &req.scope,
+            req.service_id.as_deref(),
+            req.allow_arch_mismatch,
+        ) {
+            let img = registry::ImageRef::parse(&svc.image.reference).map_err(|_| {
+                ApiError::invalid_argument(
+                    "invalid image ref (expected [registry/]name[:tag][@digest])",
+                )
+            })?;
+            if !policy.requires(&img) {
+                continue;
+            }
+            let (content_digest, digest) =
+                match (req.target_digest.as_deref(), req.target_tag.as_deref()) {
+                    (Some(digest), _) => (None, digest.to_string()),
+                    (None, Some(tag)) => {
+                        let manifest = state
+                            .registry
+                            .get_manifest(&img, tag, &host_platform)
+                            .await
+                            .map_err(map_registry_error)?;
+                        let digest = manifest.digest.ok_or_else(|| {
+                            ApiError::failed_precondition(format!(
+                                "signature required for {}: no digest for tag {tag}",
+                                svc.name
+                            ))
+                        })?;
+                        (manifest.content_digest, digest)
+                    }
+                    (None, None) => match svc.candidate.as_ref() {
+                        // The index of the tag counts only while it still holds the candidate.
+                        Some(candidate) => (
+                            state
+                                .registry
+                                .get_manifest(&img, &candidate.tag, &host_platform)
+                                .await
+                                .ok()
+                                .filter(|m| m.digest.as_deref() == Some(candidate.digest.as_str()))
+                                .and_then(|m| m.content_digest),
+                            candidate.digest.clone(),
+                        ),
+                        // Nothing is pinned; compose pulls the tag it already runs.
+                        None => continue,
+                    },
+                };
+            let (digest, verdict) = policy
+                .verify_resolved(
+                    state.registry.as_ref(),
+                    &img,
+                    content_digest.as_deref(),
+                    &digest,
+                )
+                .await;
+            if verdict.status != SignatureStatus::Verified {
+                return Err(ApiError::failed_precondition(format!(
+                    "signature required for {} ({digest}): {}",
+                    svc.name,
+                    verdict.message.as_deref().unwrap_or("not verified")
+                ))
+                .with_details(json!({
+                    "serviceId": svc.id,
+                    "digest": digest,
+                    "signature": verdict,
+                })));
+            }
+            verified.insert(svc.id.clone(), digest);
+        }
+    }
+    Ok(verified)
+}
+
 type UpdateStackSummaries = Vec<serde_json::Value>;
 type UpdateBackupsToCleanup = Vec<(String, u32)>;
 type UpdateJobOutcome = (String, UpdateStackSummaries, UpdateBackupsToCleanup);
 
-async fn run_update_job(
+pub(crate) async fn run_update_job(
     state: Arc<AppState>,
     job_id: String,
     req: TriggerUpdateRequest,
+    cancel: CancelToken,
 ) -> anyhow::Result<()> {
     fn extract_changed_service_ids(update: &serde_json::Value) -> Option<Vec<String>> {
         let ids = update
@@ -1028,22 +2198,68 @@ async fn run_update_job(
         let mut stack_summaries = Vec::new();
         let mut backups_to_cleanup: Vec<(String, u32)> = Vec::new();
 
+        let output_bytes = Arc::new(AtomicUsize::new(0));
+
         for stack_id in &stack_ids {
+            if cancel.is_cancelled() {
+                final_status = "cancelled".to_string();
+                break;
+            }
             let Some(stack) = state.db.get_stack(stack_id).await? else {
                 continue;
             };
-
-            let logging_runner = DbLoggingRunner {
+            let host = state.host(&stack.host_id)?;
+            let stack_runner = DbLoggingRunner {
                 db: state.db.clone(),
-                inner: state.runner.clone(),
+                inner: host.runner.clone(),
                 job_id: job_id.clone(),
+                cancel: cancel.clone(),
+                output_bytes: output_bytes.clone(),
+                max_output_bytes: state.config.job_log_max_bytes,
             };
 
             let mut stack_summary = serde_json::Map::new();
             stack_summary.insert("stackId".to_string(), json!(stack_id));
+            let release_notes = release_notes_summary(&stack, &req);
+            if !release_notes.is_empty() {
+                stack_summary.insert("releaseNotes".to_string(), json!(release_notes));
+            }
+
+            // Checked again at apply time: the job may have waited for a maintenance window.
+            let verified_digests = if req.mode.as_str() == "apply" {
+                validate_signatures_for_update(&state, &req, std::slice::from_ref(stack_id)).await
+            } else {
+                Ok(std::collections::BTreeMap::new())
+            };
+            let verified_digests = match verified_digests {
+                Ok(digests) => digests,
+                Err(e) => {
+                    state
+                        .db
+                        .insert_job_log(
+                            &job_id,
+                            &JobLogLine {
+                                ts: now_rfc3339()?,
+                                level: "error".to_string(),
+                                msg: format!("update blocked: {}", e.message()),
+                            },
+                        )
+                        .await?;
+                    final_status = "failed".to_string();
+                    stack_summary.insert("update".to_string(), json!({"error": e.message()}));
+                    stack_summaries.push(serde_json::Value::Object(stack_summary));
+                    break;
+                }
+            };
 
             let mut backup_id_for_cleanup: Option<(String, u32)> = None;
-            if req.mode.as_str() == "apply"
+            // Backup helpers read volumes and write artifacts on the local daemon only.
+            if req.mode.as_str() == "apply" && !host.is_local() {
+                stack_summary.insert(
+                    "backup".to_string(),
+                    json!({"status":"skipped","reason":"remote_host"}),
+                );
+            } else if req.mode.as_str() == "apply"
                 && backup::should_run_backup(&backup_settings, req.backup_mode.as_str())
             {
                 let backup_id = ids::new_backup_id();
@@ -1065,7 +2281,7 @@ async fn run_update_job(
                     .await?;
 
                 match backup::run_pre_update_backup(
-                    &logging_runner,
+                    &stack_runner,
                     &backup_settings,
                     &stack,
                     &req.scope,
@@ -1149,8 +2365,19 @@ async fn run_update_job(
                 );
             }
 
+            if cancel.is_cancelled() {
+                final_status = "cancelled".to_string();
+                stack_summaries.push(serde_json::Value::Object(stack_summary));
+                break;
+            }
+
+            let stack_docker = DbLoggingDocker {
+                runner: &stack_runner,
+                inner: host.docker.as_ref(),
+            };
             let update_outcome = updater::run_update_job(
-                &logging_runner,
+                &stack_runner,
+                &stack_docker,
                 &state.config.compose_bin,
                 &stack,
                 &req.scope,
@@ -1158,7 +2385,9 @@ async fn run_update_job(
                 req.mode.as_str(),
                 req.target_tag.as_deref(),
                 req.target_digest.as_deref(),
+                &verified_digests,
                 req.allow_arch_mismatch,
+                &cancel,
             )
             .await;
             match update_outcome {
@@ -1231,6 +2460,20 @@ async fn run_update_job(
             }
         };
 
+    if final_status == "cancelled" {
+        let _ = state
+            .db
+            .insert_job_log(
+                &job_id,
+                &JobLogLine {
+                    ts: finished_at.clone(),
+                    level: "warn".to_string(),
+                    msg: "update cancelled".to_string(),
+                },
+            )
+            .await;
+    }
+
     let force_notify = final_status != "success";
     let mut should_notify = true;
     let mut notify_summary = final_summary.clone();
@@ -1357,56 +2600,256 @@ struct DbLoggingRunner {
     db: crate::db::Db,
     inner: Arc<dyn crate::runner::CommandRunner>,
     job_id: String,
+    cancel: CancelToken,
+    /// Streamed output bytes logged so far, shared by every command of the job.
+    output_bytes: Arc<AtomicUsize>,
+    max_output_bytes: usize,
 }
 
-#[async_trait::async_trait]
-impl crate::runner::CommandRunner for DbLoggingRunner {
-    async fn run(
-        &self,
-        spec: crate::runner::CommandSpec,
-        timeout: std::time::Duration,
-    ) -> anyhow::Result<crate::runner::CommandOutput> {
-        let start = time::OffsetDateTime::now_utc()
-            .format(&time::format_description::well_known::Rfc3339)?;
-        let msg = format!("$ {} {}", spec.program, spec.args.join(" "));
+impl DbLoggingRunner {
+    async fn log(&self, level: &str, msg: String) {
+        let Ok(ts) = now_rfc3339() else {
+            return;
+        };
         let _ = self
             .db
             .insert_job_log(
                 &self.job_id,
                 &JobLogLine {
-                    ts: start,
-                    level: "info".to_string(),
+                    ts,
+                    level: level.to_string(),
                     msg,
                 },
             )
             .await;
+    }
+
+    async fn start(&self, spec: &crate::runner::CommandSpec) -> anyhow::Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(anyhow::anyhow!("job cancelled"));
+        }
+        self.log(
+            "info",
+            format!("$ {} {}", spec.program, spec.args.join(" ")),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Drives `command` until it finishes or the job is cancelled; dropping the command future
+    /// aborts the child process (see `TokioCommandRunner`).
+    async fn until_cancelled<T>(
+        &self,
+        command: impl std::future::Future<Output = anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        tokio::select! {
+            biased;
+            _ = self.cancel.cancelled() => {
+                self.log("warn", "command aborted: job cancelled".to_string()).await;
+                Err(anyhow::anyhow!("job cancelled"))
+            }
+            out = command => out,
+        }
+    }
+
+    async fn log_output_line(&self, line: &crate::runner::OutputLine) {
+        let len = line.line.len() + 1;
+        let before = self.output_bytes.fetch_add(len, Ordering::Relaxed);
+        if before + len <= self.max_output_bytes {
+            self.log("info", line.line.clone()).await;
+        } else if before <= self.max_output_bytes {
+            self.log(
+                "warn",
+                format!(
+                    "output truncated: job log limit of {} bytes reached",
+                    self.max_output_bytes
+                ),
+            )
+            .await;
+        }
+    }
+}
 
-        let out = self.inner.run(spec, timeout).await?;
-        let ts = time::OffsetDateTime::now_utc()
-            .format(&time::format_description::well_known::Rfc3339)?;
+#[async_trait::async_trait]
+impl crate::runner::CommandRunner for DbLoggingRunner {
+    async fn run(
+        &self,
+        spec: crate::runner::CommandSpec,
+        timeout: std::time::Duration,
+    ) -> anyhow::Result<crate::runner::CommandOutput> {
+        self.start(&spec).await?;
+        let out = self.until_cancelled(self.inner.run(spec, timeout)).await?;
         let msg = format!(
             "status={} stdout={} stderr={}",
             out.status,
             truncate(&out.stdout, 2000),
             truncate(&out.stderr, 2000)
         );
-        let _ = self
-            .db
-            .insert_job_log(
-                &self.job_id,
-                &JobLogLine {
-                    ts,
-                    level: if out.status == 0 {
-                        "info".to_string()
-                    } else {
-                        "warn".to_string()
-                    },
-                    msg,
-                },
-            )
+        self.log(if out.status == 0 { "info" } else { "warn" }, msg)
             .await;
         Ok(out)
     }
+
+    async fn run_streaming(
+        &self,
+        spec: crate::runner::CommandSpec,
+        timeout: std::time::Duration,
+        lines: tokio::sync::mpsc::UnboundedSender<crate::runner::OutputLine>,
+    ) -> anyhow::Result<crate::runner::CommandOutput> {
+        self.start(&spec).await?;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let command = self.inner.run_streaming(spec, timeout, tx);
+        let forward = async {
+            while let Some(line) = rx.recv().await {
+                self.log_output_line(&line).await;
+                let _ = lines.send(line);
+            }
+        };
+        let out = self
+            .until_cancelled(async { tokio::join!(command, forward).0 })
+            .await?;
+        // The output itself has already been logged line by line.
+        self.log(
+            if out.status == 0 { "info" } else { "warn" },
+            format!("status={}", out.status),
+        )
+        .await;
+        Ok(out)
+    }
+}
+
+/// Engine calls of an update job (inspects, health polling, rollback tags), logged to the job
+/// like the commands of its [`DbLoggingRunner`] and aborted when the job is cancelled.
+struct DbLoggingDocker<'a> {
+    runner: &'a DbLoggingRunner,
+    inner: &'a dyn DockerEngine,
+}
+
+impl DbLoggingDocker<'_> {
+    async fn logged<T>(
+        &self,
+        call: String,
+        command: impl std::future::Future<Output = anyhow::Result<T>>,
+        describe: impl FnOnce(&T) -> String,
+    ) -> anyhow::Result<T> {
+        if self.runner.cancel.is_cancelled() {
+            return Err(anyhow::anyhow!("job cancelled"));
+        }
+        match self.runner.until_cancelled(command).await {
+            Ok(out) => {
+                self.runner
+                    .log("info", format!("{call}: {}", describe(&out)))
+                    .await;
+                Ok(out)
+            }
+            Err(e) => {
+                self.runner.log("warn", format!("{call} failed: {e}")).await;
+                Err(e)
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DockerEngine for DbLoggingDocker<'_> {
+    async fn list_containers(
+        &self,
+        label_filters: &[String],
+    ) -> anyhow::Result<Vec<crate::docker_engine::ContainerSummary>> {
+        self.inner.list_containers(label_filters).await
+    }
+
+    async fn inspect_container(
+        &self,
+        container_id: &str,
+    ) -> anyhow::Result<crate::docker_engine::ContainerDetails> {
+        self.logged(
+            format!("docker inspect {container_id}"),
+            self.inner.inspect_container(container_id),
+            |c| {
+                format!(
+                    "image={} health={}",
+                    c.image_id,
+                    c.health.as_deref().unwrap_or("none")
+                )
+            },
+        )
+        .await
+    }
+
+    async fn inspect_image(
+        &self,
+        image_id: &str,
+    ) -> anyhow::Result<crate::docker_engine::ImageDetails> {
+        self.logged(
+            format!("docker image inspect {image_id}"),
+            self.inner.inspect_image(image_id),
+            |i| format!("id={}", i.id),
+        )
+        .await
+    }
+
+    async fn tag_image(&self, image_id: &str, image_ref: &str) -> anyhow::Result<()> {
+        // The image the reference points to before it is moved (the update being rolled back).
+        let previous = match self.inner.inspect_image(image_ref).await {
+            Ok(image) => image.id,
+            Err(_) => "unknown".to_string(),
+        };
+        self.logged(
+            format!("docker tag {image_id} {image_ref}"),
+            self.inner.tag_image(image_id, image_ref),
+            |_| format!("replaced {previous}"),
+        )
+        .await
+    }
+
+    async fn container_events(
+        &self,
+        label_filters: &[String],
+    ) -> anyhow::Result<tokio::sync::mpsc::Receiver<crate::docker_engine::ContainerEvent>> {
+        self.inner.container_events(label_filters).await
+    }
+}
+
+/// Releases (without their bodies) of the candidates an update applies, by service name; part
+/// of the job summary and thus of notifications.
+fn release_notes_summary(
+    stack: &StackRecord,
+    req: &TriggerUpdateRequest,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut out = serde_json::Map::new();
+    for svc in &stack.services {
+        if req.scope == JobScope::Service && req.service_id.as_deref() != Some(svc.id.as_str()) {
+            continue;
+        }
+        let Some(candidate) = svc.candidate.as_ref() else {
+            continue;
+        };
+        let Some(notes) = candidate.release_notes.as_ref() else {
+            continue;
+        };
+        if req
+            .target_tag
+            .as_deref()
+            .is_some_and(|t| t != candidate.tag)
+            || req
+                .target_digest
+                .as_deref()
+                .is_some_and(|d| d != candidate.digest)
+        {
+            continue;
+        }
+        let releases = notes
+            .releases
+            .iter()
+            .map(|r| json!({"tag": r.tag, "name": r.name, "url": r.url}))
+            .collect::<Vec<_>>();
+        out.insert(
+            svc.name.clone(),
+            json!({"source": notes.source, "tag": candidate.tag, "releases": releases}),
+        );
+    }
+    out
 }
 
 fn truncate(input: &str, max: usize) -> String {
@@ -1416,12 +2859,23 @@ fn truncate(input: &str, max: usize) -> String {
     format!("{}...(truncated)", &input[..max])
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListJobsQuery {
+    host_id: Option<String>,
+}
+
 async fn list_jobs(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
+    Query(q): Query<ListJobsQuery>,
 ) -> Result<Json<ListJobsResponse>, ApiError> {
     let _user = require_user(&state, &headers)?;
-    let jobs = state.db.list_jobs().await.map_err(map_internal)?;
+    let jobs = state
+        .db
+        .list_jobs(q.host_id.as_deref())
+        .await
+        .map_err(map_internal)?;
     Ok(Json(ListJobsResponse {
         jobs: jobs.into_iter().map(|j| j.into_api()).collect(),
     }))
@@ -1452,6 +2906,7 @@ async fn get_job(
             scope: job.scope.as_str().to_string(),
             stack_id: job.stack_id,
             service_id: job.service_id,
+            host_id: job.host_id,
             status: job.status,
             created_by: job.created_by,
             reason: job.reason,
@@ -1466,6 +2921,144 @@ async fn get_job(
     }))
 }
 
+async fn cancel_job(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(job_id): Path<String>,
+) -> Result<Json<CancelJobResponse>, ApiError> {
+    let user = require_user(&state, &headers)?;
+    let now = now_rfc3339().map_err(map_internal)?;
+
+    let Some(job) = state.db.get_job(&job_id).await.map_err(map_internal)? else {
+        return Err(ApiError::not_found("job not found"));
+    };
+
+    // Jobs that have not started yet are cancelled right away.
+    if matches!(job.status.as_str(), "queued" | "pending")
+        && state
+            .db
+            .claim_job(&job_id, &job.status, "cancelled", None)
+            .await
+            .map_err(map_internal)?
+    {
+        state
+            .db
+            .insert_job_log(
+                &job_id,
+                &JobLogLine {
+                    ts: now.clone(),
+                    level: "warn".to_string(),
+                    msg: format!("job cancelled by {user} before it started"),
+                },
+            )
+            .await
+            .map_err(map_internal)?;
+        state
+            .db
+            .finish_job(&job_id, "cancelled", &now, &job.summary_json)
+            .await
+            .map_err(map_internal)?;
+        return Ok(Json(CancelJobResponse {
+            job_id,
+            status: "cancelled".to_string(),
+        }));
+    }
+
+    // Running jobs stop cooperatively; the job itself records the final `cancelled` status.
+    if !state.queue.cancel(&job_id) {
+        return Err(ApiError::invalid_argument(format!(
+            "job is not running (status={})",
+            job.status
+        )));
+    }
+    state
+        .db
+        .insert_job_log(
+            &job_id,
+            &JobLogLine {
+                ts: now,
+                level: "warn".to_string(),
+                msg: format!("cancel requested by {user}"),
+            },
+        )
+        .await
+        .map_err(map_internal)?;
+
+    Ok(Json(CancelJobResponse {
+        job_id,
+        status: "cancelling".to_string(),
+    }))
+}
+
+/// SSE stream of a job's log lines and status transitions; ends once the job has finished.
+///
+/// Log events carry the log line id as SSE `id`, so reconnecting clients resume via `Last-Event-ID`.
+async fn job_events(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(job_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, axum::Error>>>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+    if state
+        .db
+        .get_job(&job_id)
+        .await
+        .map_err(map_internal)?
+        .is_none()
+    {
+        return Err(ApiError::not_found("job not found"));
+    }
+
+    let after_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(0);
+    let rx = events::follow_job(state.db.clone(), job_id, after_id);
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        let event = rx.recv().await?;
+        Some((sse_event(&event), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// SSE stream of job status, stack and service changes for dashboards.
+///
+/// A `lagged` event means the client fell behind and should refetch its state.
+async fn global_events(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, axum::Error>>>, ApiError> {
+    let _user = require_user(&state, &headers)?;
+
+    let rx = state.db.subscribe();
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        use tokio::sync::broadcast::error::RecvError;
+        loop {
+            match rx.recv().await {
+                Ok(events::Event::JobLog { .. }) => continue,
+                Ok(event) => return Some((sse_event(&event), rx)),
+                Err(RecvError::Lagged(skipped)) => {
+                    let event = sse::Event::default()
+                        .event("lagged")
+                        .data(skipped.to_string());
+                    return Some((Ok(event), rx));
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+fn sse_event(event: &events::Event) -> Result<sse::Event, axum::Error> {
+    let out = sse::Event::default().event(event.name()).json_data(event)?;
+    Ok(match event {
+        events::Event::JobLog { id, .. } => out.id(id.to_string()),
+        _ => out,
+    })
+}
+
 async fn list_ignores(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -1549,6 +3142,9 @@ async fn get_service_settings(
     Ok(Json(ServiceSettingsResponse {
         auto_rollback: settings.auto_rollback,
         backup_targets: settings.backup_targets,
+        auto_update: settings.auto_update,
+        version_strategy: settings.version_strategy,
+        prerelease_policy: settings.prerelease_policy,
     }))
 }
 
@@ -1580,11 +3176,11 @@ async fn list_service_candidates(
         .cloned()
         .ok_or_else(|| ApiError::not_found("service not found"))?;
 
-    let host_platform = registry::host_platform_override(state.config.host_platform.as_deref())
-        .unwrap_or_else(|| "linux/amd64".to_string());
+    let host_platform = state.host_platform(&stack.host_id);
 
-    let img = registry::ImageRef::parse(&svc.image.reference)
-        .map_err(|_| ApiError::invalid_argument("invalid image ref (expected repo/name:tag)"))?;
+    let img = registry::ImageRef::parse(&svc.image.reference).map_err(|_| {
+        ApiError::invalid_argument("invalid image ref (expected [registry/]name[:tag][@digest])")
+    })?;
 
     let ignore_rules = state
         .db
@@ -1605,39 +3201,19 @@ async fn list_service_candidates(
         })
         .collect::<Vec<_>>();
 
-    let tags = state.registry.list_tags(&img).await.map_err(map_internal)?;
+    let tags = state
+        .registry
+        .list_tags(&img)
+        .await
+        .map_err(map_registry_error)?;
 
     let current_tag = svc.image.tag.clone();
-    let current_semver = ignore::parse_version(&current_tag);
-
-    let mut semver_tags: Vec<(semver::Version, String)> = Vec::new();
-    let mut other_tags: Vec<String> = Vec::new();
-
-    for tag in tags {
-        if tag == current_tag {
-            continue;
-        }
-        if let Some(current) = current_semver.as_ref() {
-            if let Some(v) = ignore::parse_version(&tag)
-                && v > *current
-            {
-                semver_tags.push((v, tag));
-            }
-            continue;
-        }
-        other_tags.push(tag);
-    }
-
-    semver_tags.sort_by(|a, b| b.0.cmp(&a.0));
-    other_tags.sort_by(|a, b| b.cmp(a));
-
-    let mut picked: Vec<String> = Vec::new();
-    for (_, tag) in semver_tags {
-        picked.push(tag);
-    }
-    for tag in other_tags {
-        picked.push(tag);
-    }
+    let mut picked = versioning(
+        &state,
+        &svc.settings.version_strategy,
+        svc.settings.prerelease_policy,
+    )
+    .rank_candidate_tags(&current_tag, &tags);
 
     // Avoid expensive manifest fan-out.
     if picked.len() > 30 {
@@ -1649,6 +3225,7 @@ async fn list_service_candidates(
     let mut out: Vec<ServiceCandidateOption> = Vec::new();
     for tag in picked {
         let ignored = is_ignored(&tag);
+        let prerelease = ignore::is_prerelease(&tag);
         match state
             .registry
             .get_manifest(&img, &tag, &host_platform)
@@ -1656,12 +3233,20 @@ async fn list_service_candidates(
         {
             Ok(m) => {
                 let arch_match = registry::compute_arch_match(&host_platform, &m.arch);
+                let image = match m.digest.as_deref() {
+                    Some(digest) => image_details(&state, &img, digest, &host_platform).await,
+                    None => None,
+                };
                 out.push(ServiceCandidateOption {
                     tag,
                     digest: m.digest,
                     arch_match,
                     arch: m.arch,
                     ignored,
+                    prerelease,
+                    image,
+                    vulnerabilities: None,
+                    vulnerability_diff: None,
                 });
             }
             Err(_) => {
@@ -1671,12 +3256,94 @@ async fn list_service_candidates(
                     arch_match: ArchMatch::Unknown,
                     arch: Vec::new(),
                     ignored,
+                    prerelease,
+                    image: None,
+                    vulnerabilities: None,
+                    vulnerability_diff: None,
                 });
             }
         }
     }
 
-    Ok(Json(ServiceCandidatesResponse { candidates: out }))
+    let current_vulnerabilities = match svc.image.digest.as_deref() {
+        Some(digest) => stored_scan(&state, digest).await?,
+        None => None,
+    };
+    for candidate in &mut out {
+        let Some(digest) = candidate.digest.as_deref() else {
+            continue;
+        };
+        candidate.vulnerabilities = stored_scan(&state, digest).await?;
+        candidate.vulnerability_diff = current_vulnerabilities
+            .as_ref()
+            .zip(candidate.vulnerabilities.as_ref())
+            .map(|(current, scan)| scanner::diff(&current.counts, &scan.counts));
+    }
+
+    let current_ref = svc.image.digest.clone().unwrap_or(current_tag);
+    let current = image_details(&state, &img, &current_ref, &host_platform).await;
+
+    Ok(Json(ServiceCandidatesResponse {
+        current,
+        current_vulnerabilities,
+        candidates: out,
+    }))
+}
+
+/// Strategies are validated when saved; one that no longer compiles falls back to the default.
+fn versioning(
+    state: &AppState,
+    strategy: &VersionStrategy,
+    prerelease: Option<PrereleasePolicy>,
+) -> candidates::Versioning {
+    let prerelease = prerelease.unwrap_or(state.config.prerelease_policy);
+    candidates::Versioning::new(strategy, prerelease).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "invalid version strategy, using the default");
+        candidates::Versioning::new(&VersionStrategy::default(), prerelease)
+            .expect("the default strategy has no pattern")
+    })
+}
+
+/// Scans run during checks; listing candidates only reads what they stored.
+async fn stored_scan(
+    state: &AppState,
+    digest: &str,
+) -> Result<Option<VulnerabilityScan>, ApiError> {
+    Ok(state
+        .db
+        .get_vulnerability_scan(digest)
+        .await
+        .map_err(map_internal)?
+        .map(|(scan, _)| scan))
+}
+
+/// Best effort: a missing config only hides the details of one tag.
+async fn image_details(
+    state: &AppState,
+    img: &registry::ImageRef,
+    reference: &str,
+    host_platform: &str,
+) -> Option<ImageDetails> {
+    let config = match state
+        .registry
+        .get_image_config(img, reference, host_platform)
+        .await
+    {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::debug!(error = %e, image = %img, reference, "image config lookup failed");
+            return None;
+        }
+    };
+    Some(ImageDetails {
+        created: config.created,
+        labels: config.labels,
+        exposed_ports: config.exposed_ports,
+        env: config.env,
+        entrypoint: config.entrypoint,
+        cmd: config.cmd,
+        compressed_size: config.compressed_size,
+    })
 }
 
 async fn put_service_settings(
@@ -1688,9 +3355,19 @@ async fn put_service_settings(
     let _user = require_user(&state, &headers)?;
     let now = now_rfc3339().map_err(map_internal)?;
 
+    if let Some(window) = req.auto_update.window.as_ref() {
+        maintenance::Window::parse(window)
+            .map_err(|e| ApiError::invalid_argument(format!("invalid maintenance window: {e}")))?;
+    }
+    candidates::Versioning::new(&req.version_strategy, PrereleasePolicy::default())
+        .map_err(|e| ApiError::invalid_argument(e.to_string()))?;
+
     let settings = ServiceSettings {
         auto_rollback: req.auto_rollback,
         backup_targets: req.backup_targets,
+        auto_update: req.auto_update,
+        version_strategy: req.version_strategy,
+        prerelease_policy: req.prerelease_policy,
     };
 
     let updated = state
@@ -1863,26 +3540,25 @@ async fn webhook_trigger(
                 .await
                 .map_err(map_internal)?;
 
-            let host_platform =
-                registry::host_platform_override(state.config.host_platform.as_deref())
-                    .unwrap_or_else(|| "linux/amd64".to_string());
+            let cancel = state.queue.register(&job_id);
             let outcome = run_check_for_job(
                 &state,
                 &job_id,
                 &scope,
                 stack_id.as_deref(),
                 service_id.as_deref(),
-                &host_platform,
                 &now,
+                &cancel,
             )
             .await;
+            state.queue.unregister(&job_id);
 
             let finished_at = now_rfc3339().map_err(map_internal)?;
             match outcome {
                 Ok(summary) => {
                     state
                         .db
-                        .finish_job(&job_id, "success", &finished_at, &summary)
+                        .finish_job(&job_id, check_status(&summary), &finished_at, &summary)
                         .await
                         .map_err(map_internal)?;
                     Ok(Json(WebhookTriggerResponse { job_id }))
@@ -1969,6 +3645,24 @@ fn require_user(state: &AppState, headers: &HeaderMap) -> Result<String, ApiErro
     Err(ApiError::auth_required())
 }
 
+/// Authenticates a `dockrev-agent` by its bearer token and returns the id of its host.
+async fn require_agent(state: &AppState, headers: &HeaderMap) -> Result<String, ApiError> {
+    let token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .ok_or_else(ApiError::unauthorized)?;
+    state
+        .db
+        .get_host_id_by_agent_token(&agent::token_hash(token))
+        .await
+        .map_err(map_internal)?
+        .filter(|host_id| state.hosts.get(host_id).is_some())
+        .ok_or_else(ApiError::unauthorized)
+}
+
 fn validate_scope(
     scope: &JobScope,
     stack_id: Option<&str>,
@@ -2004,6 +3698,17 @@ fn map_internal(err: anyhow::Error) -> ApiError {
     ApiError::internal("internal error").with_details(json!({"cause": err.to_string()}))
 }
 
+/// Like [`map_internal`], but a registry rate limit becomes a 429 the UI can show as such.
+fn map_registry_error(err: anyhow::Error) -> ApiError {
+    match err.downcast_ref::<registry::RateLimited>() {
+        Some(limited) => ApiError::rate_limited(limited.to_string()).with_details(json!({
+            "registry": limited.registry,
+            "until": limited.until.and_then(|t| t.format(&time::format_description::well_known::Rfc3339).ok()),
+        })),
+        None => map_internal(err),
+    }
+}
+
 fn merge_secret(target: &mut Option<String>, existing: Option<String>) {
     let keep = match target.as_deref() {
         None => true,
@@ -8,6 +8,7 @@ use crate::{
     api, compose,
     config::Config,
     db::Db,
+    docker_engine::{CliDockerEngine, ContainerEvent, ContainerSummary, DockerEngine},
     ids,
     registry::{ImageRef, ManifestInfo, RegistryClient},
     runner::{CommandOutput, CommandRunner, CommandSpec},
@@ -34,15 +35,165 @@ impl RegistryClient for FakeRegistry {
         reference: &str,
         _host_platform: &str,
     ) -> anyhow::Result<ManifestInfo> {
-        let digest = match reference {
-            "5.2" => "sha256:old",
-            "5.3" => "sha256:new",
-            _ => "sha256:unknown",
-        };
-        Ok(ManifestInfo {
-            digest: Some(digest.to_string()),
-            arch: vec!["linux/amd64".to_string()],
-        })
+        fake_manifest(reference)
+    }
+}
+
+fn fake_manifest(reference: &str) -> anyhow::Result<ManifestInfo> {
+    let digest = match reference {
+        "5.2" => "sha256:old",
+        "5.3" => "sha256:new",
+        _ => "sha256:unknown",
+    };
+    Ok(amd64_manifest(digest))
+}
+
+/// `sha256:<reference>`, a digest of its own for every tag.
+fn manifest_per_tag(reference: &str) -> anyhow::Result<ManifestInfo> {
+    Ok(amd64_manifest(&format!("sha256:{reference}")))
+}
+
+fn amd64_manifest(digest: &str) -> ManifestInfo {
+    ManifestInfo {
+        digest: Some(digest.to_string()),
+        arch: vec!["linux/amd64".to_string()],
+        content_digest: None,
+    }
+}
+
+type ManifestFn = Box<dyn Fn(&str) -> anyhow::Result<ManifestInfo> + Send + Sync>;
+type ImageConfigFn =
+    Box<dyn Fn(&str) -> anyhow::Result<crate::registry::ImageConfig> + Send + Sync>;
+type SignaturesFn = Box<dyn Fn(&str) -> Vec<crate::registry::SignatureArtifact> + Send + Sync>;
+
+/// Registry listing `tags` and resolving references with `manifest`; image configs (by
+/// reference), signatures (by digest) and a rate limit are opt-in.
+struct ScriptedRegistry {
+    tags: Vec<String>,
+    manifest: ManifestFn,
+    image_config: Option<ImageConfigFn>,
+    signatures: Option<SignaturesFn>,
+    limited: Option<Arc<std::sync::atomic::AtomicBool>>,
+}
+
+impl ScriptedRegistry {
+    fn new(
+        tags: &[&str],
+        manifest: impl Fn(&str) -> anyhow::Result<ManifestInfo> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            manifest: Box::new(manifest),
+            image_config: None,
+            signatures: None,
+            limited: None,
+        }
+    }
+
+    /// The tags and digests of [`FakeRegistry`].
+    fn fake() -> Self {
+        Self::new(&["5.2", "5.3"], fake_manifest)
+    }
+
+    fn with_image_config(
+        mut self,
+        image_config: impl Fn(&str) -> anyhow::Result<crate::registry::ImageConfig>
+        + Send
+        + Sync
+        + 'static,
+    ) -> Self {
+        self.image_config = Some(Box::new(image_config));
+        self
+    }
+
+    fn with_signatures(
+        mut self,
+        signatures: impl Fn(&str) -> Vec<crate::registry::SignatureArtifact> + Send + Sync + 'static,
+    ) -> Self {
+        self.signatures = Some(Box::new(signatures));
+        self
+    }
+
+    /// `ghcr.io` is rate limited, with an exhausted quota, while `limited` is set.
+    fn with_rate_limit(mut self, limited: Arc<std::sync::atomic::AtomicBool>) -> Self {
+        self.limited = Some(limited);
+        self
+    }
+
+    fn limit(&self) -> Option<crate::registry::RateLimited> {
+        self.limited
+            .as_ref()
+            .is_some_and(|l| l.load(std::sync::atomic::Ordering::SeqCst))
+            .then(|| crate::registry::RateLimited {
+                registry: "ghcr.io".to_string(),
+                until: Some(time::OffsetDateTime::UNIX_EPOCH + Duration::from_secs(4_000_000_000)),
+            })
+    }
+}
+
+#[async_trait::async_trait]
+impl RegistryClient for ScriptedRegistry {
+    async fn list_tags(&self, _image: &ImageRef) -> anyhow::Result<Vec<String>> {
+        match self.limit() {
+            Some(limited) => Err(limited.into()),
+            None => Ok(self.tags.clone()),
+        }
+    }
+
+    async fn get_manifest(
+        &self,
+        _image: &ImageRef,
+        reference: &str,
+        _host_platform: &str,
+    ) -> anyhow::Result<ManifestInfo> {
+        match self.limit() {
+            Some(limited) => Err(limited.into()),
+            None => (self.manifest)(reference),
+        }
+    }
+
+    async fn get_image_config(
+        &self,
+        _image: &ImageRef,
+        reference: &str,
+        _host_platform: &str,
+    ) -> anyhow::Result<crate::registry::ImageConfig> {
+        match &self.image_config {
+            Some(image_config) => image_config(reference),
+            None => Ok(Default::default()),
+        }
+    }
+
+    async fn get_signatures(
+        &self,
+        _image: &ImageRef,
+        digest: &str,
+    ) -> anyhow::Result<Vec<crate::registry::SignatureArtifact>> {
+        Ok(self
+            .signatures
+            .as_ref()
+            .map(|signatures| signatures(digest))
+            .unwrap_or_default())
+    }
+
+    fn rate_limited(&self, _registry: &str) -> Option<crate::registry::RateLimited> {
+        self.limit()
+    }
+
+    fn quotas(&self) -> Vec<api::types::RegistryQuota> {
+        if self.limited.is_none() {
+            return Vec::new();
+        }
+        vec![api::types::RegistryQuota {
+            registry: "ghcr.io".to_string(),
+            limit: Some(100),
+            remaining: Some(0),
+            window_seconds: Some(21600),
+            reset_at: None,
+            blocked_until: None,
+            deferred: self.limit().is_some(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+        }]
     }
 }
 
@@ -100,10 +251,7 @@ impl RegistryClient for StatefulRegistry {
             // For floating tags (e.g. latest), we don't rely on this value in the test.
             _ => "sha256:unknown",
         };
-        Ok(ManifestInfo {
-            digest: Some(digest.to_string()),
-            arch: vec!["linux/amd64".to_string()],
-        })
+        Ok(amd64_manifest(digest))
     }
 }
 
@@ -129,20 +277,18 @@ impl CommandRunner for ScriptedRunner {
             && args.get(1).map(|s| s.as_str()) == Some("-q")
         {
             (0, "cid1\n".to_string())
-        } else if args.first().map(|s| s.as_str()) == Some("inspect")
-            && args.get(1).map(|s| s.as_str()) == Some("--format")
-            && args.get(2).map(|s| s.as_str()) == Some("{{.Image}}")
-        {
-            (0, "img1\n".to_string())
+        } else if args.first().map(|s| s.as_str()) == Some("inspect") {
+            (
+                0,
+                r#"[{"Id":"cid1","Image":"img1","Config":{"Labels":{}},"State":{}}]"#.to_string(),
+            )
         } else if args.first().map(|s| s.as_str()) == Some("image")
             && args.get(1).map(|s| s.as_str()) == Some("inspect")
-            && args.get(3).map(|s| s.as_str()) == Some("--format")
-            && args
-                .get(4)
-                .map(|s| s.as_str())
-                .is_some_and(|s| s.contains("RepoDigests"))
         {
-            (0, "[\"ghcr.io/acme/web@sha256:match\"]".to_string())
+            (
+                0,
+                r#"[{"Id":"img1","RepoDigests":["ghcr.io/acme/web@sha256:match"]}]"#.to_string(),
+            )
         } else {
             (0, String::new())
         };
@@ -154,38 +300,45 @@ impl CommandRunner for ScriptedRunner {
     }
 }
 
-async fn test_state_with(
-    db_path: &str,
-    registry: Arc<dyn RegistryClient>,
-    runner: Arc<dyn CommandRunner>,
-) -> Arc<AppState> {
-    let config = Config {
-        app_effective_version: "0.1.0".to_string(),
-        http_addr: "127.0.0.1:0".to_string(),
-        db_path: PathBuf::from(db_path),
-        docker_config_path: None,
-        compose_bin: "docker-compose".to_string(),
-        auth_forward_header_name: "X-Forwarded-User".parse().unwrap(),
-        auth_allow_anonymous_in_dev: true,
-        self_upgrade_url: "/supervisor/".to_string(),
-        dockrev_image_repo: "ghcr.io/ivanli-cn/dockrev".to_string(),
-        webhook_secret: Some("secret".to_string()),
-        host_platform: Some("linux/amd64".to_string()),
-        discovery_interval_seconds: 60,
-        discovery_max_actions: 200,
-    };
+/// Blocks every command until the gate is opened, so tests can observe jobs while they run.
+#[derive(Clone)]
+struct GatedRunner {
+    open: tokio::sync::watch::Receiver<bool>,
+}
 
-    let db = Db::open(&config.db_path).await.unwrap();
-    AppState::new(config, db, registry, runner)
+#[async_trait::async_trait]
+impl CommandRunner for GatedRunner {
+    async fn run(&self, _spec: CommandSpec, _timeout: Duration) -> anyhow::Result<CommandOutput> {
+        let mut open = self.open.clone();
+        let _ = open.wait_for(|o| *o).await;
+        Ok(CommandOutput {
+            status: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+        })
+    }
 }
 
-async fn test_state(db_path: &str) -> Arc<AppState> {
-    let config = Config {
+async fn wait_for_job_status(state: &Arc<AppState>, job_id: &str, done: &[&str]) -> String {
+    for _ in 0..200 {
+        let job = state.db.get_job(job_id).await.unwrap().unwrap();
+        if done.contains(&job.status.as_str()) {
+            return job.status;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("job {job_id} did not reach {done:?} in time");
+}
+
+fn test_config(db_path: &str) -> Config {
+    Config {
         app_effective_version: "0.1.0".to_string(),
         http_addr: "127.0.0.1:0".to_string(),
         db_path: PathBuf::from(db_path),
         docker_config_path: None,
         compose_bin: "docker-compose".to_string(),
+        docker_engine: "cli".to_string(),
+        docker_host: None,
         auth_forward_header_name: "X-Forwarded-User".parse().unwrap(),
         auth_allow_anonymous_in_dev: true,
         self_upgrade_url: "/supervisor/".to_string(),
@@ -194,13 +347,62 @@ async fn test_state(db_path: &str) -> Arc<AppState> {
         host_platform: Some("linux/amd64".to_string()),
         discovery_interval_seconds: 60,
         discovery_max_actions: 200,
-    };
+        job_concurrency: 2,
+        job_log_max_bytes: 1024 * 1024,
+        registry_tags_page_size: 1000,
+        registry_max_tags: 10_000,
+        registry_cache_ttl_seconds: 900,
+        registry_rate_limit_reserve: 10,
+        registry_mirrors: Default::default(),
+        registry_endpoints: Default::default(),
+        registry_insecure: Vec::new(),
+        registry_ca_bundle: None,
+        registry_certs_dir: None,
+        registry_token_providers: Vec::new(),
+        release_notes_enabled: true,
+        release_notes_forges: Default::default(),
+        release_notes_tokens: Default::default(),
+        signature_policy: Default::default(),
+        scanner: None,
+        scanner_bin: None,
+        scanner_timeout_seconds: 600,
+        scan_ttl_seconds: 86_400,
+        prerelease_policy: Default::default(),
+    }
+}
+
+async fn test_state_with(
+    db_path: &str,
+    registry: Arc<dyn RegistryClient>,
+    runner: Arc<dyn CommandRunner>,
+) -> Arc<AppState> {
+    let docker = Arc::new(CliDockerEngine::new(runner.clone()));
+    test_state_with_docker(db_path, registry, runner, docker).await
+}
+
+async fn test_state_with_config(
+    config: Config,
+    registry: Arc<dyn RegistryClient>,
+    runner: Arc<dyn CommandRunner>,
+) -> Arc<AppState> {
+    let db = Db::open(&config.db_path).await.unwrap();
+    let docker = Arc::new(CliDockerEngine::new(runner.clone()));
+    AppState::new(config, db, registry, runner, docker)
+}
 
+async fn test_state_with_docker(
+    db_path: &str,
+    registry: Arc<dyn RegistryClient>,
+    runner: Arc<dyn CommandRunner>,
+    docker: Arc<dyn DockerEngine>,
+) -> Arc<AppState> {
+    let config = test_config(db_path);
     let db = Db::open(&config.db_path).await.unwrap();
+    AppState::new(config, db, registry, runner, docker)
+}
 
-    let registry = Arc::new(FakeRegistry);
-    let runner = Arc::new(FakeRunner);
-    AppState::new(config, db, registry, runner)
+async fn test_state(db_path: &str) -> Arc<AppState> {
+    test_state_with(db_path, Arc::new(FakeRegistry), Arc::new(FakeRunner)).await
 }
 
 async fn seed_stack_from_compose(state: &Arc<AppState>, name: &str, compose_file: &str) -> String {
@@ -217,6 +419,7 @@ async fn seed_stack_from_compose(state: &Arc<AppState>, name: &str, compose_file
     let stack = crate::api::types::StackRecord {
         id: stack_id.clone(),
         name: name.to_string(),
+        host_id: crate::hosts::LOCAL_HOST_ID.to_string(),
         archived: false,
         compose: crate::api::types::ComposeConfig {
             kind: "path".to_string(),
@@ -1153,6 +1356,7 @@ services:
     state
         .db
         .upsert_discovered_compose_project(crate::db::DiscoveredComposeProjectUpsert {
+            host_id: crate::hosts::LOCAL_HOST_ID.to_string(),
             project: "demo".to_string(),
             stack_id: Some(stack_id.clone()),
             status: "active".to_string(),
@@ -1169,7 +1373,7 @@ services:
 
     let img = crate::registry::ImageRef::parse("ghcr.io/acme/web:latest").unwrap();
     let runtime = super::docker_compose_service_runtime_digest(
-        &state,
+        state.local_host().docker.as_ref(),
         "demo",
         "web",
         &super::repo_candidates(&img),
@@ -1289,6 +1493,7 @@ services:
     state
         .db
         .upsert_discovered_compose_project(crate::db::DiscoveredComposeProjectUpsert {
+            host_id: crate::hosts::LOCAL_HOST_ID.to_string(),
             project: "demo".to_string(),
             stack_id: Some(stack_id.clone()),
             status: "active".to_string(),
@@ -1547,3 +1752,2826 @@ async fn settings_and_notifications_roundtrip() {
     assert!(conf["webhook"]["enabled"].as_bool().unwrap());
     assert_eq!(conf["webhook"]["url"].as_str().unwrap(), "******");
 }
+
+#[tokio::test]
+async fn schedules_roundtrip_and_stack_override() {
+    let state = test_state(":memory:").await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/schedules")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let list = response_json(resp).await;
+    assert!(!list["global"]["enabled"].as_bool().unwrap());
+    assert!(list["global"]["nextRunAt"].is_null());
+    assert_eq!(list["stacks"].as_array().unwrap().len(), 0);
+
+    let put = serde_json::json!({ "cron": "0 3 * * *", "enabled": true });
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/api/schedules/global")
+                .header("content-type", "application/json")
+                .body(Body::from(put.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let global = response_json(resp).await;
+    assert!(global["nextRunAt"].as_str().unwrap().contains("T03:00:00"));
+
+    let put = serde_json::json!({ "cron": "0 25 * * *" });
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/api/schedules/global")
+                .header("content-type", "application/json")
+                .body(Body::from(put.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/stacks/{stack_id}/schedule"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let stack_schedule = response_json(resp).await;
+    assert!(stack_schedule["inherited"].as_bool().unwrap());
+    assert_eq!(
+        stack_schedule["schedule"]["cron"].as_str().unwrap(),
+        "0 3 * * *"
+    );
+
+    let put = serde_json::json!({ "cron": "*/30 * * * *" });
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/stacks/{stack_id}/schedule"))
+                .header("content-type", "application/json")
+                .body(Body::from(put.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let stack_schedule = response_json(resp).await;
+    assert!(!stack_schedule["inherited"].as_bool().unwrap());
+    assert!(stack_schedule["schedule"]["enabled"].as_bool().unwrap());
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/stacks/stk_missing/schedule")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/stacks/{stack_id}/schedule"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 204);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/stacks/{stack_id}/schedule"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let stack_schedule = response_json(resp).await;
+    assert!(stack_schedule["inherited"].as_bool().unwrap());
+
+    // The global row shares the table but is not a stack schedule.
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/api/stacks/global/schedule")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/schedules")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    state
+        .db
+        .set_stack_archived(&stack_id, true, None, "2026-01-01T00:00:00Z")
+        .await
+        .unwrap();
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/stacks/{stack_id}/schedule"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn scheduler_fires_due_schedule_once() {
+    let state = test_state(":memory:").await;
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    seed_stack_from_compose(&state, "demo", &compose_path).await;
+
+    // Simulate a run that came due while the process was down.
+    state
+        .db
+        .upsert_check_schedule(
+            crate::db::GLOBAL_CHECK_SCHEDULE_ID,
+            None,
+            "0 * * * *",
+            true,
+            Some("2026-01-10T05:00:00Z"),
+            "2026-01-10T04:00:00Z",
+        )
+        .await
+        .unwrap();
+
+    let now = time::OffsetDateTime::parse(
+        "2026-01-10T07:30:00Z",
+        &time::format_description::well_known::Rfc3339,
+    )
+    .unwrap();
+    let fired = crate::scheduler::run_due(&state, now).await.unwrap();
+    assert_eq!(fired.len(), 1);
+
+    // A second tick (or a restarted instance) at the same instant must not fire again.
+    let fired_again = crate::scheduler::run_due(&state, now).await.unwrap();
+    assert!(fired_again.is_empty());
+
+    let schedule = state
+        .db
+        .get_check_schedule(crate::db::GLOBAL_CHECK_SCHEDULE_ID)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        schedule.next_run_at.as_deref(),
+        Some("2026-01-10T08:00:00Z")
+    );
+    assert_eq!(schedule.last_job_id.as_deref(), Some(fired[0].as_str()));
+
+    let job = state.db.get_job(&fired[0]).await.unwrap().unwrap();
+    assert_eq!(job.created_by, "scheduler");
+    assert_eq!(job.reason, "schedule");
+    assert_eq!(job.status, "success");
+}
+
+#[tokio::test]
+async fn scheduler_prunes_stale_registry_cache_rows() {
+    let state = test_state(":memory:").await;
+    let now = time::OffsetDateTime::now_utc();
+    let at = |age: time::Duration| (now - age).unix_timestamp();
+
+    let tags = |fetched_at_unix| crate::db::RegistryTagsCacheEntry {
+        tags: vec!["5.2".to_string()],
+        etag: Some("\"v1\"".to_string()),
+        fetched_at_unix,
+    };
+    let manifest = |fetched_at_unix| crate::db::RegistryManifestCacheEntry {
+        content_digest: Some("sha256:abc".to_string()),
+        digest: Some("sha256:abc".to_string()),
+        arch: vec!["linux/amd64".to_string()],
+        fetched_at_unix,
+    };
+    // The test TTL is 15 minutes: rows are kept for a day (more than ten TTLs).
+    let fresh = at(time::Duration::hours(3));
+    let stale = at(time::Duration::days(2));
+    state
+        .db
+        .put_registry_tags_cache("ghcr.io", "acme/web", &tags(fresh))
+        .await
+        .unwrap();
+    state
+        .db
+        .put_registry_tags_cache("ghcr.io", "acme/old", &tags(stale))
+        .await
+        .unwrap();
+    state
+        .db
+        .put_registry_manifest_cache(
+            "ghcr.io",
+            "acme/web",
+            "5.2",
+            "linux/amd64",
+            &manifest(fresh),
+        )
+        .await
+        .unwrap();
+    state
+        .db
+        .put_registry_manifest_cache(
+            "ghcr.io",
+            "acme/old",
+            "1.0",
+            "linux/amd64",
+            &manifest(stale),
+        )
+        .await
+        .unwrap();
+
+    let pruned = crate::scheduler::prune_registry_cache(&state, now)
+        .await
+        .unwrap();
+    assert_eq!(pruned, 2);
+
+    let db = &state.db;
+    assert!(
+        db.get_registry_tags_cache("ghcr.io", "acme/web")
+            .await
+            .unwrap()
+            .is_some()
+    );
+    assert!(
+        db.get_registry_tags_cache("ghcr.io", "acme/old")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        db.get_registry_manifest_cache("ghcr.io", "acme/web", "5.2", "linux/amd64")
+            .await
+            .unwrap()
+            .is_some()
+    );
+    assert!(
+        db.get_registry_manifest_cache("ghcr.io", "acme/old", "1.0", "linux/amd64")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn auto_update_policy_enqueues_update_after_check() {
+    let state = test_state(":memory:").await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    let stack = state.db.get_stack(&stack_id).await.unwrap().unwrap();
+    let service_id = stack.services[0].id.clone();
+
+    let put_policy = |policy: &str, window: serde_json::Value| {
+        let body = serde_json::json!({
+            "autoRollback": true,
+            "backupTargets": { "bindPaths": {}, "volumeNames": {} },
+            "autoUpdate": { "policy": policy, "window": window }
+        });
+        Request::builder()
+            .method("PUT")
+            .uri(format!("/api/services/{service_id}/settings"))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let check = || async {
+        let job_id = api::run_check_job(
+            &state,
+            "scheduler",
+            api::types::CheckReason::Schedule,
+            api::types::JobScope::Stack,
+            Some(stack_id.clone()),
+            vec![stack_id.clone()],
+        )
+        .await
+        .unwrap();
+        state.db.get_job(&job_id).await.unwrap().unwrap()
+    };
+
+    // 5.2 -> 5.3 is a minor bump, so a patch-only policy must not apply it.
+    let resp = app
+        .clone()
+        .oneshot(put_policy("patch", serde_json::Value::Null))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let job = check().await;
+    assert!(
+        job.summary_json["autoUpdateJobIds"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+
+    // Minor policy, but the window is closed today.
+    let today = time::OffsetDateTime::now_utc().weekday();
+    let other_days = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"]
+        .into_iter()
+        .filter(|d| !today.to_string().to_ascii_lowercase().starts_with(d))
+        .collect::<Vec<_>>();
+    let closed = serde_json::json!({ "weekdays": other_days, "start": "00:00", "end": "00:00" });
+    let resp = app
+        .clone()
+        .oneshot(put_policy("minor", closed))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let job = check().await;
+    assert!(
+        job.summary_json["autoUpdateJobIds"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+    let logs = state.db.list_job_logs(&job.id).await.unwrap();
+    assert!(
+        logs.iter()
+            .any(|l| l.msg.contains("outside maintenance window"))
+    );
+
+    let resp = app
+        .clone()
+        .oneshot(put_policy("minor", serde_json::Value::Null))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let job = check().await;
+    let update_ids = job.summary_json["autoUpdateJobIds"].as_array().unwrap();
+    assert_eq!(update_ids.len(), 1);
+
+    let update = state
+        .db
+        .get_job(update_ids[0].as_str().unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(update.created_by, "auto-update");
+    assert_eq!(update.reason, "schedule");
+    assert_eq!(update.service_id.as_deref(), Some(service_id.as_str()));
+
+    let bad_window = serde_json::json!({ "start": "25:00", "end": "02:00" });
+    let resp = app
+        .clone()
+        .oneshot(put_policy("minor", bad_window))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn update_outside_maintenance_window_is_deferred_until_open() {
+    let state = test_state(":memory:").await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+
+    let today = time::OffsetDateTime::now_utc().weekday();
+    let other_days = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"]
+        .into_iter()
+        .filter(|d| !today.to_string().to_ascii_lowercase().starts_with(d))
+        .collect::<Vec<_>>();
+    let window = serde_json::json!({
+        "stackId": stack_id,
+        "weekdays": other_days,
+        "start": "00:00",
+        "end": "00:00",
+        "timezone": "Etc/UTC"
+    });
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/maintenance-windows")
+                .header("content-type", "application/json")
+                .body(Body::from(window.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let window_id = response_json(resp).await["windowId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let update = |mode: &str| {
+        let body = serde_json::json!({
+            "scope": "stack",
+            "stackId": stack_id,
+            "mode": mode,
+            "allowArchMismatch": false,
+            "backupMode": "inherit",
+            "reason": "webhook"
+        });
+        Request::builder()
+            .method("POST")
+            .uri("/api/updates")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let resp = app.clone().oneshot(update("dry-run")).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let dry_run_id = response_json(resp).await["jobId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let dry_run = state.db.get_job(&dry_run_id).await.unwrap().unwrap();
+    assert_ne!(dry_run.status, "pending");
+
+    let resp = app.clone().oneshot(update("apply")).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let job_id = response_json(resp).await["jobId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let job = state.db.get_job(&job_id).await.unwrap().unwrap();
+    assert_eq!(job.status, "pending");
+    assert!(job.started_at.is_none());
+    let logs = state.db.list_job_logs(&job_id).await.unwrap();
+    assert!(logs[0].msg.contains("outside maintenance window"));
+
+    let now = time::OffsetDateTime::now_utc();
+    let started = crate::maintenance::start_due_pending(&state, now)
+        .await
+        .unwrap();
+    assert!(started.is_empty());
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/maintenance-windows/{window_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 204);
+
+    let started = crate::maintenance::start_due_pending(&state, now)
+        .await
+        .unwrap();
+    assert_eq!(started, vec![job_id.clone()]);
+    let job = state.db.get_job(&job_id).await.unwrap().unwrap();
+    assert_ne!(job.status, "pending");
+    assert!(job.started_at.is_some());
+
+    // Already started: a second pass must not start it again.
+    let started = crate::maintenance::start_due_pending(&state, now)
+        .await
+        .unwrap();
+    assert!(started.is_empty());
+
+    let bad = serde_json::json!({ "start": "02:00", "end": "03:00", "timezone": "Nowhere/Land" });
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/maintenance-windows")
+                .header("content-type", "application/json")
+                .body(Body::from(bad.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn pending_update_scan_fails_bad_jobs_and_releases_the_rest() {
+    let state = test_state(":memory:").await;
+
+    let now = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    let pending = |request: serde_json::Value| {
+        let state = state.clone();
+        let now = now.clone();
+        async move {
+            let mut job = api::types::JobRecord::new_running(
+                ids::new_job_id(),
+                api::types::JobType::Update,
+                api::types::JobScope::All,
+                None,
+                None,
+                &now,
+            );
+            job.status = "pending".to_string();
+            job.started_at = None;
+            state.db.insert_job(job.to_db()).await.unwrap();
+            state.db.set_job_request(&job.id, &request).await.unwrap();
+            job.id
+        }
+    };
+    let bad = pending(serde_json::json!({ "scope": "galaxy" })).await;
+    let good = pending(serde_json::json!({
+        "scope": "all",
+        "mode": "apply",
+        "allowArchMismatch": false,
+        "backupMode": "inherit",
+        "reason": "ui"
+    }))
+    .await;
+
+    let started = crate::maintenance::start_due_pending(&state, time::OffsetDateTime::now_utc())
+        .await
+        .unwrap();
+    assert_eq!(started, vec![good.clone()]);
+
+    let bad_job = state.db.get_job(&bad).await.unwrap().unwrap();
+    assert_eq!(bad_job.status, "failed");
+    assert!(bad_job.finished_at.is_some());
+    let logs = state.db.list_job_logs(&bad).await.unwrap();
+    assert!(logs.iter().any(|l| l.level == "error"));
+    let good_job = state.db.get_job(&good).await.unwrap().unwrap();
+    assert_ne!(good_job.status, "pending");
+}
+
+struct PanicRunner;
+
+#[async_trait::async_trait]
+impl CommandRunner for PanicRunner {
+    async fn run(&self, _spec: CommandSpec, _timeout: Duration) -> anyhow::Result<CommandOutput> {
+        panic!("runner exploded");
+    }
+}
+
+#[tokio::test]
+async fn update_queue_releases_stacks_when_a_job_panics() {
+    let state = test_state_with(":memory:", Arc::new(FakeRegistry), Arc::new(PanicRunner)).await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    let service_id = state
+        .db
+        .get_stack(&stack_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .services[0]
+        .id
+        .clone();
+
+    let enqueue = || {
+        let app = app.clone();
+        let service_id = service_id.clone();
+        async move {
+            let body = serde_json::json!({
+                "scope": "service",
+                "serviceId": service_id,
+                "mode": "apply",
+                "allowArchMismatch": false,
+                "backupMode": "skip",
+                "reason": "ui"
+            });
+            let resp = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/updates")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+            response_json(resp).await["jobId"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        }
+    };
+    let first = enqueue().await;
+    let second = enqueue().await;
+
+    // The second job waits for the stack lock the first one held when it panicked.
+    for job_id in [&first, &second] {
+        assert_eq!(
+            wait_for_job_status(&state, job_id, &["failed"]).await,
+            "failed"
+        );
+        let job = state.db.get_job(job_id).await.unwrap().unwrap();
+        assert!(job.finished_at.is_some());
+        let logs = state.db.list_job_logs(job_id).await.unwrap();
+        assert!(logs.iter().any(|l| l.msg.contains("update job aborted")));
+    }
+}
+
+#[tokio::test]
+async fn update_queue_serializes_jobs_per_stack() {
+    let (open_tx, open_rx) = tokio::sync::watch::channel(false);
+    let state = test_state_with(
+        ":memory:",
+        Arc::new(FakeRegistry),
+        Arc::new(GatedRunner { open: open_rx }),
+    )
+    .await;
+    let app = api::router(state.clone());
+
+    let compose_a = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_a,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+  worker:
+    image: ghcr.io/acme/worker:5.2
+"#,
+    )
+    .unwrap();
+    let compose_b = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_b,
+        r#"
+services:
+  api:
+    image: ghcr.io/acme/api:5.2
+"#,
+    )
+    .unwrap();
+    let stack_a = seed_stack_from_compose(&state, "alpha", &compose_a).await;
+    let stack_b = seed_stack_from_compose(&state, "beta", &compose_b).await;
+    let services_a = state
+        .db
+        .get_stack(&stack_a)
+        .await
+        .unwrap()
+        .unwrap()
+        .services;
+    let services_b = state
+        .db
+        .get_stack(&stack_b)
+        .await
+        .unwrap()
+        .unwrap()
+        .services;
+
+    let enqueue = |service_id: String| {
+        let app = app.clone();
+        async move {
+            let body = serde_json::json!({
+                "scope": "service",
+                "serviceId": service_id,
+                "mode": "apply",
+                "allowArchMismatch": false,
+                "backupMode": "skip",
+                "reason": "ui"
+            });
+            let resp = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/updates")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+            response_json(resp).await["jobId"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        }
+    };
+
+    let first = enqueue(services_a[0].id.clone()).await;
+    let second = enqueue(services_a[1].id.clone()).await;
+    let other_stack = enqueue(services_b[0].id.clone()).await;
+
+    let status = |id: String| {
+        let state = state.clone();
+        async move { state.db.get_job(&id).await.unwrap().unwrap().status }
+    };
+    assert_eq!(status(first.clone()).await, "running");
+    assert_eq!(status(second.clone()).await, "queued");
+    assert_eq!(status(other_stack.clone()).await, "running");
+
+    open_tx.send(true).unwrap();
+    for id in [&first, &second, &other_stack] {
+        wait_for_job_status(&state, id, &["success", "failed"]).await;
+    }
+
+    let logs = state.db.list_job_logs(&second).await.unwrap();
+    let queued_at = logs.iter().position(|l| l.msg == "update queued").unwrap();
+    let started_at = logs.iter().position(|l| l.msg == "update started").unwrap();
+    assert!(queued_at < started_at);
+
+    let first_job = state.db.get_job(&first).await.unwrap().unwrap();
+    let second_job = state.db.get_job(&second).await.unwrap().unwrap();
+    assert!(second_job.started_at.unwrap() >= first_job.finished_at.unwrap());
+}
+
+#[tokio::test]
+async fn cancel_job_stops_queued_and_running_updates() {
+    let (_open_tx, open_rx) = tokio::sync::watch::channel(false);
+    let state = test_state_with(
+        ":memory:",
+        Arc::new(FakeRegistry),
+        Arc::new(GatedRunner { open: open_rx }),
+    )
+    .await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "alpha", &compose_path).await;
+    let service_id = state
+        .db
+        .get_stack(&stack_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .services[0]
+        .id
+        .clone();
+
+    let enqueue = || {
+        let app = app.clone();
+        let service_id = service_id.clone();
+        async move {
+            let body = serde_json::json!({
+                "scope": "service",
+                "serviceId": service_id,
+                "mode": "apply",
+                "allowArchMismatch": false,
+                "backupMode": "skip",
+                "reason": "ui"
+            });
+            let resp = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/updates")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+            response_json(resp).await["jobId"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        }
+    };
+    let cancel = |job_id: String| {
+        let app = app.clone();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/jobs/{job_id}/cancel"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    let running = enqueue().await;
+    let queued = enqueue().await;
+    assert_eq!(
+        state.db.get_job(&running).await.unwrap().unwrap().status,
+        "running"
+    );
+    assert_eq!(
+        state.db.get_job(&queued).await.unwrap().unwrap().status,
+        "queued"
+    );
+
+    let resp = cancel(queued.clone()).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(response_json(resp).await["status"], "cancelled");
+    let job = state.db.get_job(&queued).await.unwrap().unwrap();
+    assert_eq!(job.status, "cancelled");
+    assert!(job.finished_at.is_some());
+
+    // The running job is blocked inside a command; cancelling aborts it.
+    let resp = cancel(running.clone()).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(response_json(resp).await["status"], "cancelling");
+    assert_eq!(
+        wait_for_job_status(&state, &running, &["cancelled", "success", "failed"]).await,
+        "cancelled"
+    );
+
+    let job = state.db.get_job(&running).await.unwrap().unwrap();
+    assert_eq!(
+        job.summary_json["stacks"][0]["update"]["changedServices"],
+        0
+    );
+    let logs = state.db.list_job_logs(&running).await.unwrap();
+    assert!(
+        logs.iter()
+            .any(|l| l.msg == "cancel requested by anonymous")
+    );
+    assert!(
+        logs.iter()
+            .any(|l| l.msg == "command aborted: job cancelled")
+    );
+    assert!(logs.iter().any(|l| l.msg == "update cancelled"));
+
+    let resp = cancel(running).await;
+    assert_eq!(resp.status(), 400);
+    let resp = cancel("job_missing".to_string()).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn queue_recovery_marks_running_jobs_interrupted() {
+    let state = test_state(":memory:").await;
+
+    let now = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    let job = api::types::JobRecord::new_running(
+        ids::new_job_id(),
+        api::types::JobType::Update,
+        api::types::JobScope::All,
+        None,
+        None,
+        &now,
+    );
+    state.db.insert_job(job.to_db()).await.unwrap();
+
+    crate::queue::recover(&state).await.unwrap();
+
+    let recovered = state.db.get_job(&job.id).await.unwrap().unwrap();
+    assert_eq!(recovered.status, "interrupted");
+    assert!(recovered.finished_at.is_some());
+    let logs = state.db.list_job_logs(&job.id).await.unwrap();
+    assert!(logs.iter().any(|l| l.msg.contains("interrupted")));
+}
+
+#[tokio::test]
+async fn job_events_stream_logs_and_status_until_finished() {
+    let state = test_state(":memory:").await;
+    let app = api::router(state.clone());
+
+    let now = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    let job = api::types::JobRecord::new_running(
+        ids::new_job_id(),
+        api::types::JobType::Update,
+        api::types::JobScope::All,
+        None,
+        None,
+        &now,
+    );
+    state.db.insert_job(job.to_db()).await.unwrap();
+    let line = |msg: &str| api::types::JobLogLine {
+        ts: now.clone(),
+        level: "info".to_string(),
+        msg: msg.to_string(),
+    };
+    state
+        .db
+        .insert_job_log(&job.id, &line("pulling web"))
+        .await
+        .unwrap();
+
+    let open = |last_event_id: Option<i64>| {
+        let app = app.clone();
+        let uri = format!("/api/jobs/{}/events", job.id);
+        async move {
+            let mut req = Request::builder().uri(uri);
+            if let Some(id) = last_event_id {
+                req = req.header("last-event-id", id.to_string());
+            }
+            app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap()
+        }
+    };
+
+    let resp = open(None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+
+    state
+        .db
+        .insert_job_log(&job.id, &line("web updated"))
+        .await
+        .unwrap();
+    state
+        .db
+        .finish_job(&job.id, "success", &now, &serde_json::json!({}))
+        .await
+        .unwrap();
+
+    let body = tokio::time::timeout(Duration::from_secs(5), resp.into_body().collect())
+        .await
+        .expect("stream ends after the job finishes")
+        .unwrap()
+        .to_bytes();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    let pulling = body.find("pulling web").unwrap();
+    let updated = body.find("web updated").unwrap();
+    let finished = body.find(r#""status":"success""#).unwrap();
+    assert!(pulling < updated && updated < finished);
+    assert!(body.contains("event: log"));
+    assert!(body.contains("event: status"));
+
+    // Resuming after the first line only replays what came later.
+    let first_id = state.db.list_job_logs_after(&job.id, 0).await.unwrap()[0].0;
+    let resp = open(Some(first_id)).await;
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(!body.contains("pulling web"));
+    assert!(body.contains("web updated"));
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/jobs/job_missing/events")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn global_events_stream_stack_and_service_changes() {
+    let state = test_state(":memory:").await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "alpha", &compose_path).await;
+    let service_id = state
+        .db
+        .get_stack(&stack_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .services[0]
+        .id
+        .clone();
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/events")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let mut body = resp.into_body();
+
+    let now = "2026-01-10T00:00:00Z";
+    state
+        .db
+        .set_service_archived(&service_id, true, Some("test"), now)
+        .await
+        .unwrap();
+    state
+        .db
+        .set_stack_archived(&stack_id, true, Some("test"), now)
+        .await
+        .unwrap();
+
+    let mut received = String::new();
+    while !received.contains("event: stack") {
+        let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+            .await
+            .expect("event delivered")
+            .unwrap()
+            .unwrap();
+        if let Ok(data) = frame.into_data() {
+            received.push_str(std::str::from_utf8(&data).unwrap());
+        }
+    }
+    let service = received.find(&format!(r#""serviceId":"{service_id}""#));
+    let stack = received.find(&format!(r#""stackId":"{stack_id}""#));
+    assert!(service.unwrap() < stack.unwrap());
+}
+
+#[tokio::test]
+async fn streamed_command_output_is_logged_up_to_job_cap() {
+    struct LinesRunner;
+
+    #[async_trait::async_trait]
+    impl CommandRunner for LinesRunner {
+        async fn run(
+            &self,
+            _spec: CommandSpec,
+            _timeout: Duration,
+        ) -> anyhow::Result<CommandOutput> {
+            Ok(CommandOutput {
+                status: 0,
+                stdout: (1..=5).map(|i| format!("layer-{i}\n")).collect(),
+                stderr: String::new(),
+            })
+        }
+    }
+
+    let state = test_state(":memory:").await;
+    let now = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    let job = api::types::JobRecord::new_running(
+        ids::new_job_id(),
+        api::types::JobType::Update,
+        api::types::JobScope::All,
+        None,
+        None,
+        &now,
+    );
+    state.db.insert_job(job.to_db()).await.unwrap();
+
+    let runner = super::DbLoggingRunner {
+        db: state.db.clone(),
+        inner: Arc::new(LinesRunner),
+        job_id: job.id.clone(),
+        cancel: Default::default(),
+        output_bytes: Default::default(),
+        // Room for two 8-byte lines ("layer-N\n").
+        max_output_bytes: 20,
+    };
+    let spec = CommandSpec {
+        program: "docker-compose".to_string(),
+        args: vec!["pull".to_string(), "web".to_string()],
+        env: Vec::new(),
+        stdin: None,
+        files: Vec::new(),
+    };
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let out = runner
+        .run_streaming(spec.clone(), Duration::from_secs(10), tx)
+        .await
+        .unwrap();
+    assert_eq!(out.status, 0);
+    let mut forwarded = 0;
+    while rx.recv().await.is_some() {
+        forwarded += 1;
+    }
+    assert_eq!(forwarded, 5);
+
+    let (tx, _) = tokio::sync::mpsc::unbounded_channel();
+    runner
+        .run_streaming(spec, Duration::from_secs(10), tx)
+        .await
+        .unwrap();
+
+    let logs = state
+        .db
+        .list_job_logs(&job.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|l| l.msg)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        logs,
+        [
+            "$ docker-compose pull web",
+            "layer-1",
+            "layer-2",
+            "output truncated: job log limit of 20 bytes reached",
+            "status=0",
+            "$ docker-compose pull web",
+            "status=0",
+        ]
+    );
+}
+
+/// Engine with a single container, whose image reference points at `sha256:new`.
+#[derive(Default)]
+struct RollbackDocker {
+    tags: std::sync::Mutex<Vec<(String, String)>>,
+}
+
+#[async_trait::async_trait]
+impl DockerEngine for RollbackDocker {
+    async fn list_containers(
+        &self,
+        _label_filters: &[String],
+    ) -> anyhow::Result<Vec<ContainerSummary>> {
+        Ok(Vec::new())
+    }
+
+    async fn inspect_container(
+        &self,
+        container_id: &str,
+    ) -> anyhow::Result<crate::docker_engine::ContainerDetails> {
+        Ok(crate::docker_engine::ContainerDetails {
+            id: container_id.to_string(),
+            image_id: "sha256:new".to_string(),
+            labels: BTreeMap::new(),
+            health: Some("unhealthy".to_string()),
+        })
+    }
+
+    async fn inspect_image(
+        &self,
+        _image_id: &str,
+    ) -> anyhow::Result<crate::docker_engine::ImageDetails> {
+        Ok(crate::docker_engine::ImageDetails {
+            id: "sha256:new".to_string(),
+            repo_digests: Vec::new(),
+        })
+    }
+
+    async fn tag_image(&self, image_id: &str, image_ref: &str) -> anyhow::Result<()> {
+        self.tags
+            .lock()
+            .unwrap()
+            .push((image_id.to_string(), image_ref.to_string()));
+        Ok(())
+    }
+
+    async fn container_events(
+        &self,
+        _label_filters: &[String],
+    ) -> anyhow::Result<tokio::sync::mpsc::Receiver<ContainerEvent>> {
+        Err(anyhow::anyhow!("no events"))
+    }
+}
+
+#[tokio::test]
+async fn update_job_engine_calls_are_logged_and_cancellable() {
+    let state = test_state(":memory:").await;
+    let now = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    let job = api::types::JobRecord::new_running(
+        ids::new_job_id(),
+        api::types::JobType::Update,
+        api::types::JobScope::All,
+        None,
+        None,
+        &now,
+    );
+    state.db.insert_job(job.to_db()).await.unwrap();
+
+    let engine = RollbackDocker::default();
+    let runner = super::DbLoggingRunner {
+        db: state.db.clone(),
+        inner: Arc::new(FakeRunner),
+        job_id: job.id.clone(),
+        cancel: Default::default(),
+        output_bytes: Default::default(),
+        max_output_bytes: 1024,
+    };
+    let docker = super::DbLoggingDocker {
+        runner: &runner,
+        inner: &engine,
+    };
+
+    docker.inspect_container("c1").await.unwrap();
+    docker
+        .tag_image("sha256:old", "ghcr.io/acme/web:5.3")
+        .await
+        .unwrap();
+    runner.cancel.cancel();
+    assert!(
+        docker
+            .tag_image("sha256:old", "ghcr.io/acme/web:5.3")
+            .await
+            .is_err()
+    );
+    assert!(docker.inspect_container("c1").await.is_err());
+    assert_eq!(engine.tags.lock().unwrap().len(), 1);
+
+    let logs = state
+        .db
+        .list_job_logs(&job.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|l| l.msg)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        logs,
+        [
+            "docker inspect c1: image=sha256:new health=unhealthy",
+            "docker tag sha256:old ghcr.io/acme/web:5.3: replaced sha256:new",
+        ]
+    );
+}
+
+/// Engine whose running containers and event stream are driven by the test.
+struct EventedDocker {
+    containers: std::sync::Mutex<Vec<ContainerSummary>>,
+    events: std::sync::Mutex<Option<tokio::sync::mpsc::Receiver<ContainerEvent>>>,
+}
+
+#[async_trait::async_trait]
+impl DockerEngine for EventedDocker {
+    async fn list_containers(
+        &self,
+        label_filters: &[String],
+    ) -> anyhow::Result<Vec<ContainerSummary>> {
+        let containers = self.containers.lock().unwrap();
+        Ok(containers
+            .iter()
+            .filter(|c| {
+                label_filters.iter().all(|f| match f.split_once('=') {
+                    Some((k, v)) => c.labels.get(k).is_some_and(|l| l == v),
+                    None => c.labels.contains_key(f),
+                })
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn inspect_container(
+        &self,
+        container_id: &str,
+    ) -> anyhow::Result<crate::docker_engine::ContainerDetails> {
+        Err(anyhow::anyhow!("no such container: {container_id}"))
+    }
+
+    async fn inspect_image(
+        &self,
+        image_id: &str,
+    ) -> anyhow::Result<crate::docker_engine::ImageDetails> {
+        Err(anyhow::anyhow!("no such image: {image_id}"))
+    }
+
+    async fn tag_image(&self, _image_id: &str, _image_ref: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn container_events(
+        &self,
+        _label_filters: &[String],
+    ) -> anyhow::Result<tokio::sync::mpsc::Receiver<ContainerEvent>> {
+        self.events
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("event stream already taken"))
+    }
+}
+
+#[tokio::test]
+async fn discovery_follows_container_events_incrementally() {
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+
+    let labels = BTreeMap::from([
+        ("com.docker.compose.project".to_string(), "demo".to_string()),
+        (
+            "com.docker.compose.project.config_files".to_string(),
+            compose_path.clone(),
+        ),
+    ]);
+    let (events_tx, events_rx) = tokio::sync::mpsc::channel(8);
+    let docker = Arc::new(EventedDocker {
+        containers: std::sync::Mutex::new(vec![ContainerSummary {
+            id: "c1".to_string(),
+            image_id: "sha256:i1".to_string(),
+            labels: labels.clone(),
+        }]),
+        events: std::sync::Mutex::new(Some(events_rx)),
+    });
+    let state = test_state_with_docker(
+        ":memory:",
+        Arc::new(FakeRegistry),
+        Arc::new(FakeRunner),
+        docker.clone(),
+    )
+    .await;
+
+    let follower = {
+        let state = state.clone();
+        tokio::spawn(async move {
+            crate::discovery::follow_events(&state, &state.local_host(), false).await
+        })
+    };
+
+    let project_status = |state: Arc<AppState>| async move {
+        state
+            .db
+            .list_discovered_compose_projects(crate::db::ArchivedFilter::Include, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|p| p.project == "demo")
+            .map(|p| (p.status, p.stack_id))
+    };
+
+    let event = |action: &str| ContainerEvent {
+        action: action.to_string(),
+        container_id: "c1".to_string(),
+        attributes: labels.clone(),
+    };
+    events_tx.send(event("start")).await.unwrap();
+
+    let mut stack_id = None;
+    for _ in 0..1000 {
+        if let Some((api::types::DiscoveredProjectStatus::Active, Some(id))) =
+            project_status(state.clone()).await
+        {
+            stack_id = Some(id);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let stack_id = stack_id.expect("project discovered from start event");
+    assert!(state.db.get_stack(&stack_id).await.unwrap().is_some());
+
+    docker.containers.lock().unwrap().clear();
+    events_tx.send(event("die")).await.unwrap();
+    events_tx.send(event("destroy")).await.unwrap();
+
+    let mut missing = false;
+    for _ in 0..1000 {
+        if let Some((api::types::DiscoveredProjectStatus::Missing, _)) =
+            project_status(state.clone()).await
+        {
+            missing = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(missing, "project marked missing after destroy event");
+
+    drop(events_tx);
+    follower.await.unwrap().unwrap();
+    let _ = std::fs::remove_file(&compose_path);
+}
+
+#[tokio::test]
+async fn hosts_scope_discovery_stacks_and_jobs() {
+    let compose_path = |label: &str| {
+        let path = format!("/tmp/dockrev-test-{label}-{}.yml", ulid::Ulid::new());
+        std::fs::write(
+            &path,
+            r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+        )
+        .unwrap();
+        path
+    };
+    let engine = |config_files: &str| {
+        Arc::new(EventedDocker {
+            containers: std::sync::Mutex::new(vec![ContainerSummary {
+                id: "c1".to_string(),
+                image_id: "sha256:i1".to_string(),
+                labels: BTreeMap::from([
+                    ("com.docker.compose.project".to_string(), "demo".to_string()),
+                    (
+                        "com.docker.compose.project.config_files".to_string(),
+                        config_files.to_string(),
+                    ),
+                ]),
+            }]),
+            events: std::sync::Mutex::new(None),
+        })
+    };
+    let local_compose = compose_path("local");
+    let edge_compose = compose_path("edge");
+
+    let state = test_state_with_docker(
+        ":memory:",
+        Arc::new(FakeRegistry),
+        Arc::new(FakeRunner),
+        engine(&local_compose),
+    )
+    .await;
+    let app = api::router(state.clone());
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/hosts")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "name": "edge",
+                        "endpoint": "unix:///var/run/docker.sock"
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/hosts")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "name": "edge",
+                        "endpoint": "tcp://10.0.0.2:2375",
+                        "platform": "linux/arm64"
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let host_id = response_json(resp).await["hostId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Swap the engine of the registered host for one driven by the test.
+    let mut edge = crate::hosts::connect(
+        state.db.list_hosts().await.unwrap().remove(0),
+        state.runner.clone(),
+        &state.agents,
+    )
+    .unwrap();
+    edge.docker = engine(&edge_compose);
+    state.hosts.insert(Arc::new(edge));
+    assert_eq!(state.host_platform(&host_id), "linux/arm64");
+
+    let scan = crate::discovery::run_scan(&state).await.unwrap();
+    assert_eq!(scan.summary.stacks_created, 2);
+
+    let get = |uri: String| {
+        let app = app.clone();
+        async move {
+            let resp = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+            response_json(resp).await
+        }
+    };
+
+    let hosts = get("/api/hosts".to_string()).await;
+    assert_eq!(hosts["hosts"][0]["id"], "local");
+    assert_eq!(hosts["hosts"][1]["id"], host_id.as_str());
+
+    let stacks = get("/api/stacks".to_string()).await;
+    assert_eq!(stacks["stacks"].as_array().unwrap().len(), 2);
+    let stacks = get(format!("/api/stacks?hostId={host_id}")).await;
+    let edge_stacks = stacks["stacks"].as_array().unwrap();
+    assert_eq!(edge_stacks.len(), 1);
+    assert_eq!(edge_stacks[0]["hostId"], host_id.as_str());
+    let edge_stack_id = edge_stacks[0]["id"].as_str().unwrap().to_string();
+
+    let stack = state.db.get_stack(&edge_stack_id).await.unwrap().unwrap();
+    assert_eq!(stack.compose.compose_files, vec![edge_compose.clone()]);
+
+    let projects = get(format!("/api/discovery/projects?hostId={host_id}")).await;
+    assert_eq!(projects["projects"].as_array().unwrap().len(), 1);
+    assert_eq!(projects["projects"][0]["stackId"], edge_stack_id.as_str());
+
+    let job = api::types::JobRecord::new_running(
+        ids::new_check_id(),
+        api::types::JobType::Check,
+        api::types::JobScope::Stack,
+        Some(edge_stack_id.clone()),
+        None,
+        "2026-01-01T00:00:00Z",
+    );
+    state.db.insert_job(job.to_db()).await.unwrap();
+    let jobs = get(format!("/api/jobs?hostId={host_id}")).await;
+    assert_eq!(jobs["jobs"].as_array().unwrap().len(), 1);
+    assert_eq!(jobs["jobs"][0]["hostId"], host_id.as_str());
+    let jobs = get("/api/jobs?hostId=local".to_string()).await;
+    assert!(jobs["jobs"].as_array().unwrap().is_empty());
+
+    let delete = |uri: String| {
+        let app = app.clone();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+        }
+    };
+    assert_eq!(delete("/api/hosts/local".to_string()).await, 400);
+    assert_eq!(delete(format!("/api/hosts/{host_id}")).await, 204);
+    assert!(state.hosts.get(&host_id).is_none());
+    assert!(state.db.get_stack(&edge_stack_id).await.unwrap().is_none());
+
+    let _ = std::fs::remove_file(&local_compose);
+    let _ = std::fs::remove_file(&edge_compose);
+}
+
+#[tokio::test]
+async fn agent_hosts_run_commands_through_the_polling_agent() {
+    let state = test_state(":memory:").await;
+    let app = api::router(state.clone());
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/hosts")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"name": "edge", "endpoint": "agent://edge"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let created = response_json(resp).await;
+    let host_id = created["hostId"].as_str().unwrap().to_string();
+    let token = created["agentToken"].as_str().unwrap().to_string();
+
+    let agent_request = |method: &str, uri: &str, token: &str, body: serde_json::Value| {
+        let app = app.clone();
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("authorization", format!("Bearer {token}"))
+            .header("content-type", "application/json")
+            .body(if body.is_null() {
+                Body::empty()
+            } else {
+                Body::from(body.to_string())
+            })
+            .unwrap();
+        async move { app.oneshot(req).await.unwrap() }
+    };
+    let poll = "/api/agent/commands?waitSeconds=0";
+
+    let resp = agent_request("GET", poll, "wrong", serde_json::Value::Null).await;
+    assert_eq!(resp.status(), 401);
+    let resp = agent_request("GET", poll, &token, serde_json::Value::Null).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(response_json(resp).await["commands"], serde_json::json!([]));
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/hosts")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let hosts = response_json(resp).await;
+    assert_eq!(hosts["hosts"][1]["agentConnected"], true);
+    assert!(hosts["hosts"][0].get("agentConnected").is_none());
+
+    let host = state.host(&host_id).unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let run = tokio::spawn(async move {
+        let out = host
+            .runner
+            .run_streaming(
+                CommandSpec {
+                    program: "docker-compose".to_string(),
+                    args: vec!["pull".to_string()],
+                    env: Vec::new(),
+                    stdin: None,
+                    files: Vec::new(),
+                },
+                Duration::from_secs(10),
+                tx,
+            )
+            .await?;
+        let compose = host.read_file("/srv/edge/compose.yml").await?;
+        anyhow::Ok((out, compose))
+    });
+
+    let mut served = 0;
+    while served < 2 {
+        let resp = agent_request(
+            "GET",
+            "/api/agent/commands?waitSeconds=5",
+            &token,
+            serde_json::Value::Null,
+        )
+        .await;
+        for cmd in response_json(resp).await["commands"].as_array().unwrap() {
+            let id = cmd["id"].as_str().unwrap();
+            let result = match cmd["kind"].as_str().unwrap() {
+                "run" => {
+                    assert_eq!(cmd["program"], "docker-compose");
+                    assert_eq!(cmd["stream"], true);
+                    let resp = agent_request(
+                        "POST",
+                        &format!("/api/agent/commands/{id}/output"),
+                        &token,
+                        serde_json::json!({"lines": ["pulling web"]}),
+                    )
+                    .await;
+                    assert_eq!(resp.status(), 204);
+                    serde_json::json!({"status": 0, "stdout": "pulling web\n", "stderr": ""})
+                }
+                "readFile" => {
+                    assert_eq!(cmd["path"], "/srv/edge/compose.yml");
+                    serde_json::json!({"status": 0, "stdout": "services: {}\n", "stderr": ""})
+                }
+                other => panic!("unexpected command kind {other}"),
+            };
+            let resp = agent_request(
+                "POST",
+                &format!("/api/agent/commands/{id}/result"),
+                &token,
+                result,
+            )
+            .await;
+            assert_eq!(resp.status(), 204);
+            served += 1;
+        }
+    }
+
+    let (out, compose) = run.await.unwrap().unwrap();
+    assert_eq!(out.status, 0);
+    assert_eq!(out.stdout, "pulling web\n");
+    assert_eq!(compose, "services: {}\n");
+    assert_eq!(rx.recv().await.unwrap().line, "pulling web");
+
+    let resp = agent_request(
+        "POST",
+        "/api/agent/commands/cmd_unknown/result",
+        &token,
+        serde_json::json!({"status": 0}),
+    )
+    .await;
+    assert_eq!(resp.status(), 404);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/hosts/{host_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 204);
+    let resp = agent_request("GET", poll, &token, serde_json::Value::Null).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn rate_limited_registries_defer_checks() {
+    let limited = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let registry = Arc::new(ScriptedRegistry::fake().with_rate_limit(limited.clone()));
+    let state = test_state_with(":memory:", registry, Arc::new(FakeRunner)).await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    let check = || async {
+        let job_id = api::run_check_job(
+            &state,
+            "scheduler",
+            api::types::CheckReason::Schedule,
+            api::types::JobScope::Stack,
+            Some(stack_id.clone()),
+            vec![stack_id.clone()],
+        )
+        .await
+        .unwrap();
+        state.db.get_job(&job_id).await.unwrap().unwrap()
+    };
+    let candidate_tag = || async {
+        let stack = state.db.get_stack(&stack_id).await.unwrap().unwrap();
+        stack.services[0].candidate.as_ref().map(|c| c.tag.clone())
+    };
+
+    let job = check().await;
+    assert_eq!(job.summary_json["servicesDeferred"], 0);
+    assert_eq!(candidate_tag().await.as_deref(), Some("5.3"));
+
+    limited.store(true, std::sync::atomic::Ordering::SeqCst);
+    let job = check().await;
+    assert_eq!(job.status, "success");
+    assert_eq!(job.summary_json["servicesDeferred"], 1);
+    assert_eq!(
+        job.summary_json["deferredRegistries"],
+        serde_json::json!(["ghcr.io"])
+    );
+    assert_eq!(
+        candidate_tag().await.as_deref(),
+        Some("5.3"),
+        "previous result is kept"
+    );
+    let logs = state.db.list_job_logs(&job.id).await.unwrap();
+    assert!(logs.iter().any(|l| l.level == "warn"
+        && l.msg
+            == "deferred 1 service(s) on ghcr.io: registry rate limited until 2096-10-02T07:06:40Z"));
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/registries/quota")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let quota = response_json(resp).await;
+    assert_eq!(quota["registries"][0]["registry"], "ghcr.io");
+    assert_eq!(quota["registries"][0]["deferred"], true);
+
+    let service_id = state
+        .db
+        .get_stack(&stack_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .services[0]
+        .id
+        .clone();
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/services/{service_id}/candidates"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 429);
+    assert_eq!(response_json(resp).await["error"]["code"], "rate_limited");
+
+    let _ = std::fs::remove_file(&compose_path);
+}
+
+#[tokio::test]
+async fn registry_credentials_roundtrip_with_masked_secrets() {
+    let state = test_state(":memory:").await;
+    let app = api::router(state.clone());
+    let put = |secret: serde_json::Value| {
+        Request::builder()
+            .method("PUT")
+            .uri("/api/registries/credentials/https%3A%2F%2Fghcr.io")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "username": "bot", "secret": secret }).to_string(),
+            ))
+            .unwrap()
+    };
+
+    let resp = app
+        .clone()
+        .oneshot(put(serde_json::Value::Null))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400, "a new credential needs a secret");
+
+    let resp = app
+        .clone()
+        .oneshot(put(serde_json::json!("ghp_secret")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body = response_json(resp).await;
+    assert_eq!(body["registry"], "ghcr.io");
+    assert_eq!(body["secret"], "******");
+
+    // The masked value keeps the stored secret.
+    let resp = app
+        .clone()
+        .oneshot(put(serde_json::json!("******")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let stored = state
+        .db
+        .get_registry_credential("ghcr.io")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.secret.as_deref(), Some("ghp_secret"));
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/registries/credentials")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let list = response_json(resp).await;
+    assert_eq!(list["credentials"][0]["username"], "bot");
+    assert_eq!(list["credentials"][0]["secret"], "******");
+
+    let delete = || {
+        Request::builder()
+            .method("DELETE")
+            .uri("/api/registries/credentials/ghcr.io")
+            .body(Body::empty())
+            .unwrap()
+    };
+    assert_eq!(app.clone().oneshot(delete()).await.unwrap().status(), 204);
+    assert_eq!(app.clone().oneshot(delete()).await.unwrap().status(), 404);
+}
+
+#[tokio::test]
+async fn checks_attach_release_notes_to_candidates() {
+    async fn releases() -> axum::Json<serde_json::Value> {
+        axum::Json(serde_json::json!([
+            {"tag_name": "v5.4.0", "body": "too new"},
+            {"tag_name": "v5.3.0", "name": "Five three", "body": "new things",
+             "html_url": "https://forge.test/acme/web/releases/v5.3.0"},
+            {"tag_name": "v5.2.1", "body": "fixes"},
+            {"tag_name": "v5.2.0", "body": "current"}
+        ]))
+    }
+    let app = axum::Router::new().route(
+        "/api/v1/repos/acme/web/releases",
+        axum::routing::get(releases),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let forge = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    // The image points at a Gitea-style forge.
+    let source = format!("http://{forge}/acme/web.git");
+    let config_calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let registry = ScriptedRegistry::fake().with_image_config({
+        let config_calls = config_calls.clone();
+        move |reference| {
+            assert_eq!(reference, "sha256:new", "config of the candidate digest");
+            config_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(crate::registry::ImageConfig {
+                annotations: BTreeMap::from([(
+                    "org.opencontainers.image.source".to_string(),
+                    source.clone(),
+                )]),
+                labels: BTreeMap::from([(
+                    "org.opencontainers.image.version".to_string(),
+                    "5.3.0".to_string(),
+                )]),
+                ..Default::default()
+            })
+        }
+    });
+    let mut config = test_config(":memory:");
+    config.release_notes_forges = [(forge.clone(), crate::release_notes::ForgeKind::Gitea)].into();
+    let state = test_state_with_config(config, Arc::new(registry), Arc::new(FakeRunner)).await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    for _ in 0..2 {
+        api::run_check_job(
+            &state,
+            "scheduler",
+            api::types::CheckReason::Schedule,
+            api::types::JobScope::Stack,
+            Some(stack_id.clone()),
+            vec![stack_id.clone()],
+        )
+        .await
+        .unwrap();
+    }
+    assert_eq!(
+        config_calls.load(std::sync::atomic::Ordering::SeqCst),
+        1,
+        "release notes are cached per candidate digest"
+    );
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/stacks/{stack_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let detail = response_json(resp).await;
+    let notes = &detail["stack"]["services"][0]["candidate"]["releaseNotes"];
+    assert_eq!(notes["source"], format!("http://{forge}/acme/web"));
+    assert_eq!(notes["version"], "5.3.0");
+    let tags = notes["releases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["tag"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(tags, ["v5.3.0", "v5.2.1"]);
+    assert_eq!(notes["releases"][0]["name"], "Five three");
+    assert_eq!(notes["releases"][0]["body"], "new things");
+
+    let stack = state.db.get_stack(&stack_id).await.unwrap().unwrap();
+    let req = api::types::TriggerUpdateRequest {
+        scope: api::types::JobScope::Stack,
+        stack_id: Some(stack_id.clone()),
+        service_id: None,
+        target_tag: None,
+        target_digest: None,
+        mode: api::types::UpdateMode::DryRun,
+        allow_arch_mismatch: false,
+        backup_mode: api::types::BackupMode::Inherit,
+        reason: api::types::UpdateReason::Ui,
+    };
+    let summary = api::release_notes_summary(&stack, &req);
+    assert_eq!(summary["web"]["tag"], "5.3");
+    assert_eq!(
+        summary["web"]["releases"][0],
+        serde_json::json!({
+            "tag": "v5.3.0",
+            "name": "Five three",
+            "url": "https://forge.test/acme/web/releases/v5.3.0"
+        })
+    );
+    let other_target = api::types::TriggerUpdateRequest {
+        target_tag: Some("5.4".to_string()),
+        ..req
+    };
+    assert!(api::release_notes_summary(&stack, &other_target).is_empty());
+}
+
+#[tokio::test]
+async fn service_candidates_include_image_details() {
+    let registry = ScriptedRegistry::fake().with_image_config(|reference| {
+        let (entrypoint, port) = match reference {
+            "5.2" => ("/old-entrypoint", "80/tcp"),
+            "sha256:new" => ("/docker-entrypoint.sh", "8080/tcp"),
+            _ => anyhow::bail!("unexpected reference {reference}"),
+        };
+        Ok(crate::registry::ImageConfig {
+            created: Some("2026-01-01T00:00:00Z".to_string()),
+            exposed_ports: vec![port.to_string()],
+            env: vec!["PATH".to_string()],
+            entrypoint: Some(vec![entrypoint.to_string()]),
+            compressed_size: Some(1234),
+            ..Default::default()
+        })
+    });
+    let state = test_state_with(":memory:", Arc::new(registry), Arc::new(FakeRunner)).await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    let service_id = state
+        .db
+        .get_stack(&stack_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .services[0]
+        .id
+        .clone();
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/services/{service_id}/candidates"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body = response_json(resp).await;
+    assert_eq!(
+        body["current"]["entrypoint"],
+        serde_json::json!(["/old-entrypoint"])
+    );
+    let candidate = &body["candidates"][0];
+    assert_eq!(candidate["tag"], "5.3");
+    assert_eq!(
+        candidate["image"],
+        serde_json::json!({
+            "created": "2026-01-01T00:00:00Z",
+            "labels": {},
+            "exposedPorts": ["8080/tcp"],
+            "env": ["PATH"],
+            "entrypoint": ["/docker-entrypoint.sh"],
+            "compressedSize": 1234
+        })
+    );
+
+    let _ = std::fs::remove_file(&compose_path);
+}
+
+/// Signatures by `signer` are required for `ghcr.io/acme/*`.
+fn signature_policy(
+    signer: &crate::signatures::tests::TestSigner,
+) -> crate::signatures::SignaturePolicy {
+    crate::signatures::SignaturePolicy::default()
+        .with_key("ghcr.io/acme/*", signer.public_key("acme"))
+        .unwrap()
+        .with_required("ghcr.io/acme/*")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn required_signatures_gate_apply() {
+    let signer = Arc::new(crate::signatures::tests::TestSigner::new());
+    let signed = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let registry = ScriptedRegistry::fake().with_signatures({
+        let (signer, signed) = (signer.clone(), signed.clone());
+        move |digest| {
+            if signed.load(std::sync::atomic::Ordering::SeqCst) {
+                vec![signer.cosign(digest)]
+            } else {
+                Vec::new()
+            }
+        }
+    });
+    let mut config = test_config(":memory:");
+    config.signature_policy = signature_policy(&signer);
+    let state = test_state_with_config(config, Arc::new(registry), Arc::new(FakeRunner)).await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    let check = || async {
+        api::run_check_job(
+            &state,
+            "scheduler",
+            api::types::CheckReason::Schedule,
+            api::types::JobScope::Stack,
+            Some(stack_id.clone()),
+            vec![stack_id.clone()],
+        )
+        .await
+        .unwrap();
+        let stack = state.db.get_stack(&stack_id).await.unwrap().unwrap();
+        stack.services[0].candidate.clone().unwrap()
+    };
+    let update = |mode: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/updates")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({
+                    "scope": "stack",
+                    "stackId": stack_id,
+                    "mode": mode,
+                    "allowArchMismatch": false,
+                    "backupMode": "skip",
+                    "reason": "ui"
+                })
+                .to_string(),
+            ))
+            .unwrap()
+    };
+
+    let candidate = check().await;
+    let signature = candidate.signature.unwrap();
+    assert_eq!(signature.status, api::types::SignatureStatus::Unsigned);
+    assert!(signature.required);
+
+    let resp = app.clone().oneshot(update("apply")).await.unwrap();
+    assert_eq!(resp.status(), 412);
+    let body = response_json(resp).await;
+    assert_eq!(body["error"]["code"], "failed_precondition");
+    assert_eq!(body["error"]["details"]["digest"], "sha256:new");
+    assert_eq!(body["error"]["details"]["signature"]["status"], "unsigned");
+
+    let resp = app.clone().oneshot(update("dry-run")).await.unwrap();
+    assert_eq!(resp.status(), 200, "dry-runs pin nothing");
+
+    signed.store(true, std::sync::atomic::Ordering::SeqCst);
+    let signature = check().await.signature.unwrap();
+    assert_eq!(signature.status, api::types::SignatureStatus::Verified);
+    assert_eq!(signature.format, Some(api::types::SignatureFormat::Cosign));
+    assert_eq!(signature.key.as_deref(), Some("acme"));
+
+    let resp = app.clone().oneshot(update("apply")).await.unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let _ = std::fs::remove_file(&compose_path);
+}
+
+/// Records the compose override files the update passes to compose.
+#[derive(Default)]
+struct OverrideRunner {
+    overrides: std::sync::Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl CommandRunner for OverrideRunner {
+    async fn run(&self, spec: CommandSpec, _timeout: Duration) -> anyhow::Result<CommandOutput> {
+        for file in &spec.files {
+            self.overrides
+                .lock()
+                .unwrap()
+                .push(std::fs::read_to_string(file).unwrap());
+        }
+        Ok(CommandOutput {
+            status: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn required_signatures_pin_the_verified_digest() {
+    // `5.3` moves to a new image index on every lookup. Only the indexes handed out so far are
+    // signed, as `cosign sign` does; their platform manifests are not.
+    let signer = Arc::new(crate::signatures::tests::TestSigner::new());
+    let served = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+    let registry = ScriptedRegistry::new(&["5.2", "5.3"], {
+        let served = served.clone();
+        move |reference| {
+            if reference != "5.3" {
+                return fake_manifest(reference);
+            }
+            let mut served = served.lock().unwrap();
+            let index = format!("sha256:index{}", served.len());
+            served.push(index.clone());
+            Ok(ManifestInfo {
+                content_digest: Some(index),
+                ..amd64_manifest(&format!("sha256:moved{}", served.len()))
+            })
+        }
+    })
+    .with_signatures({
+        let (signer, served) = (signer.clone(), served.clone());
+        move |digest| {
+            if served.lock().unwrap().iter().any(|d| d == digest) {
+                vec![signer.cosign(digest)]
+            } else {
+                Vec::new()
+            }
+        }
+    });
+    let mut config = test_config(":memory:");
+    config.signature_policy = signature_policy(&signer);
+    let runner = Arc::new(OverrideRunner::default());
+    let state = test_state_with_config(config, Arc::new(registry), runner.clone()).await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    let service_id = state
+        .db
+        .get_stack(&stack_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .services[0]
+        .id
+        .clone();
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/updates")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "scope": "service",
+                        "serviceId": service_id,
+                        "mode": "apply",
+                        "targetTag": "5.3",
+                        "allowArchMismatch": false,
+                        "backupMode": "skip",
+                        "reason": "ui"
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let job_id = response_json(resp).await["jobId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        wait_for_job_status(&state, &job_id, &["success", "failed"]).await,
+        "success"
+    );
+
+    // The apply-time gate is the last lookup; the tag has moved since the enqueue checks, and
+    // would move again if compose pulled it. The signed index is pinned, not its platform image.
+    let served = served.lock().unwrap().clone();
+    assert!(served.len() >= 2, "{served:?}");
+    let verified = served.last().unwrap();
+    let overrides = runner.overrides.lock().unwrap().clone();
+    assert!(!overrides.is_empty());
+    for contents in overrides {
+        assert!(
+            contents.contains(&format!("image: ghcr.io/acme/web@{verified}")),
+            "{contents}"
+        );
+    }
+
+    let _ = std::fs::remove_file(&compose_path);
+}
+
+#[tokio::test]
+async fn vulnerability_scans_diff_candidates_and_steer_auto_updates() {
+    // sha256:old has one critical finding; sha256:new has two until it is patched, then none.
+    let patched = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let scans = axum::Router::new().route(
+        "/scan",
+        axum::routing::post({
+            let patched = patched.clone();
+            move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                let image = body["image"].as_str().unwrap().to_string();
+                assert!(image.starts_with("ghcr.io/acme/web@sha256:"), "{image}");
+                axum::Json(if image.ends_with("sha256:old") {
+                    serde_json::json!({"critical": 1, "high": 2})
+                } else if patched.load(std::sync::atomic::Ordering::SeqCst) {
+                    serde_json::json!({"Results": [{"Vulnerabilities": [{"Severity": "HIGH"}]}]})
+                } else {
+                    serde_json::json!({"Results": [{"Vulnerabilities": [
+                        {"Severity": "CRITICAL"}, {"Severity": "CRITICAL"}
+                    ]}]})
+                })
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, scans).await });
+
+    let mut config = test_config(":memory:");
+    config.scanner = Some(crate::scanner::ScannerKind::Http(format!(
+        "http://{addr}/scan"
+    )));
+    config.scan_ttl_seconds = 0;
+    let state = test_state_with_config(config, Arc::new(FakeRegistry), Arc::new(FakeRunner)).await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    let stack = state.db.get_stack(&stack_id).await.unwrap().unwrap();
+    let service_id = stack.services[0].id.clone();
+
+    let put_policy = |window: serde_json::Value, security: &str| {
+        let body = serde_json::json!({
+            "autoRollback": true,
+            "backupTargets": { "bindPaths": {}, "volumeNames": {} },
+            "autoUpdate": { "policy": "minor", "window": window, "security": security }
+        });
+        Request::builder()
+            .method("PUT")
+            .uri(format!("/api/services/{service_id}/settings"))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let check = || async {
+        let job_id = api::run_check_job(
+            &state,
+            "scheduler",
+            api::types::CheckReason::Schedule,
+            api::types::JobScope::Stack,
+            Some(stack_id.clone()),
+            vec![stack_id.clone()],
+        )
+        .await
+        .unwrap();
+        state.db.get_job(&job_id).await.unwrap().unwrap()
+    };
+
+    let resp = app
+        .clone()
+        .oneshot(put_policy(serde_json::Value::Null, "require"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let job = check().await;
+    assert!(
+        job.summary_json["autoUpdateJobIds"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+    let logs = state.db.list_job_logs(&job.id).await.unwrap();
+    assert!(
+        logs.iter()
+            .any(|l| l.msg.contains("candidate fixes no vulnerabilities")),
+        "{logs:?}"
+    );
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/services/{service_id}/candidates"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body = response_json(resp).await;
+    assert_eq!(body["currentVulnerabilities"]["scanner"], "http");
+    assert_eq!(body["currentVulnerabilities"]["counts"]["critical"], 1);
+    let candidate = body["candidates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["tag"] == "5.3")
+        .unwrap();
+    assert_eq!(candidate["vulnerabilities"]["counts"]["critical"], 2);
+    assert_eq!(candidate["vulnerabilityDiff"]["critical"], 1);
+    assert_eq!(candidate["vulnerabilityDiff"]["high"], -2);
+    assert_eq!(candidate["vulnerabilityDiff"]["fixes"], false);
+
+    // Once the candidate fixes findings, `prefer` applies it even though the window is closed.
+    patched.store(true, std::sync::atomic::Ordering::SeqCst);
+    let today = time::OffsetDateTime::now_utc().weekday();
+    let other_days = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"]
+        .into_iter()
+        .filter(|d| !today.to_string().to_ascii_lowercase().starts_with(d))
+        .collect::<Vec<_>>();
+    let closed = serde_json::json!({ "weekdays": other_days, "start": "00:00", "end": "00:00" });
+    let resp = app
+        .clone()
+        .oneshot(put_policy(closed, "prefer"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let job = check().await;
+    assert_eq!(
+        job.summary_json["autoUpdateJobIds"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let _ = std::fs::remove_file(&compose_path);
+}
+
+#[tokio::test]
+async fn version_strategy_bounds_checks_and_candidates() {
+    let state = test_state(":memory:").await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    let stack = state.db.get_stack(&stack_id).await.unwrap().unwrap();
+    let service_id = stack.services[0].id.clone();
+
+    let put_strategy = |strategy: serde_json::Value| {
+        let body = serde_json::json!({
+            "autoRollback": true,
+            "backupTargets": { "bindPaths": {}, "volumeNames": {} },
+            "versionStrategy": strategy
+        });
+        Request::builder()
+            .method("PUT")
+            .uri(format!("/api/services/{service_id}/settings"))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let get = |uri: String| Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let check = || async {
+        api::run_check_job(
+            &state,
+            "scheduler",
+            api::types::CheckReason::Schedule,
+            api::types::JobScope::Stack,
+            Some(stack_id.clone()),
+            vec![stack_id.clone()],
+        )
+        .await
+        .unwrap();
+        let stack = state.db.get_stack(&stack_id).await.unwrap().unwrap();
+        stack.services[0].candidate.clone()
+    };
+
+    let resp = app
+        .clone()
+        .oneshot(put_strategy(
+            serde_json::json!({ "kind": "regex", "pattern": "(" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+
+    // 5.2 -> 5.3 leaves the minor version.
+    let resp = app
+        .clone()
+        .oneshot(put_strategy(serde_json::json!({ "kind": "same_minor" })))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let resp = app
+        .clone()
+        .oneshot(get(format!("/api/services/{service_id}/settings")))
+        .await
+        .unwrap();
+    let body = response_json(resp).await;
+    assert_eq!(body["versionStrategy"]["kind"], "same_minor");
+    assert!(check().await.is_none());
+    let resp = app
+        .clone()
+        .oneshot(get(format!("/api/services/{service_id}/candidates")))
+        .await
+        .unwrap();
+    let body = response_json(resp).await;
+    assert_eq!(body["candidates"], serde_json::json!([]));
+
+    let resp = app
+        .clone()
+        .oneshot(put_strategy(serde_json::json!({ "kind": "same_major" })))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(check().await.unwrap().tag, "5.3");
+    let resp = app
+        .clone()
+        .oneshot(get(format!("/api/services/{service_id}/candidates")))
+        .await
+        .unwrap();
+    let body = response_json(resp).await;
+    assert_eq!(body["candidates"][0]["tag"], "5.3");
+
+    let _ = std::fs::remove_file(&compose_path);
+}
+
+#[tokio::test]
+async fn variant_tags_keep_their_variant() {
+    let registry = ScriptedRegistry::new(
+        &[
+            "16.2-alpine",
+            "16.3-alpine",
+            "17.0",
+            "17.1-bookworm",
+            "latest",
+        ],
+        manifest_per_tag,
+    );
+    let state = test_state_with(":memory:", Arc::new(registry), Arc::new(FakeRunner)).await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  db:
+    image: postgres:16.2-alpine
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    api::run_check_job(
+        &state,
+        "scheduler",
+        api::types::CheckReason::Schedule,
+        api::types::JobScope::Stack,
+        Some(stack_id.clone()),
+        vec![stack_id.clone()],
+    )
+    .await
+    .unwrap();
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/stacks/{stack_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body = response_json(resp).await;
+    let service = &body["stack"]["services"][0];
+    assert_eq!(service["image"]["variant"], "alpine");
+    assert_eq!(service["candidate"]["tag"], "16.3-alpine");
+
+    let service_id = service["id"].as_str().unwrap();
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/services/{service_id}/candidates"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response_json(resp).await;
+    let tags = body["candidates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["tag"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(tags, ["16.3-alpine"]);
+
+    let _ = std::fs::remove_file(&compose_path);
+}
+
+#[tokio::test]
+async fn prerelease_policy_defaults_globally_and_per_service() {
+    let mut config = test_config(":memory:");
+    config.prerelease_policy = api::types::PrereleasePolicy::Always;
+    let registry = ScriptedRegistry::new(&["5.2", "5.3", "6.0.0-rc.1"], manifest_per_tag);
+    let state = test_state_with_config(config, Arc::new(registry), Arc::new(FakeRunner)).await;
+    let app = api::router(state.clone());
+
+    let compose_path = format!("/tmp/dockrev-test-{}.yml", ulid::Ulid::new());
+    std::fs::write(
+        &compose_path,
+        r#"
+services:
+  web:
+    image: ghcr.io/acme/web:5.2
+"#,
+    )
+    .unwrap();
+    let stack_id = seed_stack_from_compose(&state, "demo", &compose_path).await;
+    let stack = state.db.get_stack(&stack_id).await.unwrap().unwrap();
+    let service_id = stack.services[0].id.clone();
+
+    let check = || async {
+        api::run_check_job(
+            &state,
+            "scheduler",
+            api::types::CheckReason::Schedule,
+            api::types::JobScope::Stack,
+            Some(stack_id.clone()),
+            vec![stack_id.clone()],
+        )
+        .await
+        .unwrap();
+        let stack = state.db.get_stack(&stack_id).await.unwrap().unwrap();
+        stack.services[0].candidate.clone().unwrap().tag
+    };
+    let list = || async {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/services/{service_id}/candidates"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        response_json(resp).await["candidates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| {
+                (
+                    c["tag"].as_str().unwrap().to_string(),
+                    c["prerelease"].as_bool().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(check().await, "6.0.0-rc.1");
+    assert_eq!(
+        list().await,
+        [("6.0.0-rc.1".to_string(), true), ("5.3".to_string(), false)]
+    );
+
+    let body = serde_json::json!({
+        "autoRollback": true,
+        "backupTargets": { "bindPaths": {}, "volumeNames": {} },
+        "prereleasePolicy": "exclude"
+    });
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/services/{service_id}/settings"))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(check().await, "5.3");
+    assert_eq!(list().await, [("5.3".to_string(), false)]);
+
+    let _ = std::fs::remove_file(&compose_path);
+}
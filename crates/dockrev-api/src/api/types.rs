@@ -14,6 +14,7 @@ pub struct ListStacksResponse {
 pub struct StackListItem {
     pub id: String,
     pub name: String,
+    pub host_id: String,
     pub status: StackStatus,
     pub services: u32,
     pub updates: u32,
@@ -43,6 +44,7 @@ pub struct GetStackResponse {
 pub struct StackResponse {
     pub id: String,
     pub name: String,
+    pub host_id: String,
     pub compose: ComposeConfig,
     pub services: Vec<Service>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -53,6 +55,7 @@ pub struct StackResponse {
 pub struct StackRecord {
     pub id: String,
     pub name: String,
+    pub host_id: String,
     pub archived: bool,
     pub compose: ComposeConfig,
     pub backup: StackBackupConfig,
@@ -80,6 +83,9 @@ pub struct ComposeRef {
     #[serde(rename = "ref")]
     pub reference: String,
     pub tag: String,
+    /// Variant suffix of `tag` (`alpine` for `16.2-alpine`); candidates keep it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub digest: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -95,6 +101,75 @@ pub struct Candidate {
     pub digest: String,
     pub arch_match: ArchMatch,
     pub arch: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_notes: Option<ReleaseNotes>,
+    /// Set when signature verification covers the image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureVerdict>,
+}
+
+/// Outcome of verifying the signatures of a digest against the configured public keys.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureVerdict {
+    pub status: SignatureStatus,
+    /// Apply is blocked unless the status is `verified`.
+    pub required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<SignatureFormat>,
+    /// Name (file stem) of the key that verified the signature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub checked_at: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    Verified,
+    /// No signature was found.
+    Unsigned,
+    /// Signatures exist, but none verifies with a configured key.
+    Invalid,
+    /// Signatures could not be fetched, or no key is configured for the image.
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureFormat {
+    Cosign,
+    Notation,
+}
+
+/// Releases of the candidate's source repository between the current and the candidate version.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNotes {
+    /// `org.opencontainers.image.source` of the candidate.
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Newest first.
+    pub releases: Vec<Release>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    pub tag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<String>,
+    /// Markdown as published, truncated.
+    pub body: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -136,6 +211,168 @@ pub struct IgnoreMatch {
 pub struct ServiceSettings {
     pub auto_rollback: bool,
     pub backup_targets: BackupTargetOverrides,
+    #[serde(default)]
+    pub auto_update: AutoUpdateSettings,
+    #[serde(default)]
+    pub version_strategy: VersionStrategy,
+    /// `None` follows `DOCKREV_PRERELEASE_POLICY`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prerelease_policy: Option<PrereleasePolicy>,
+}
+
+/// Whether prerelease versions (`2.0.0-rc.1`) count as candidates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrereleasePolicy {
+    Exclude,
+    /// Only while the current tag is a prerelease itself.
+    #[default]
+    IfCurrent,
+    Always,
+}
+
+impl PrereleasePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exclude => "exclude",
+            Self::IfCurrent => "if_current",
+            Self::Always => "always",
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "exclude" => Some(Self::Exclude),
+            "if_current" => Some(Self::IfCurrent),
+            "always" => Some(Self::Always),
+            _ => None,
+        }
+    }
+}
+
+/// How tags of a service are ordered when picking candidates.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VersionStrategy {
+    /// Highest semver tag above the current one; non-semver tags fall back to the
+    /// lexicographic maximum.
+    #[default]
+    Semver,
+    /// Semver, within the current major version.
+    SameMajor,
+    /// Semver, within the current `major.minor`.
+    SameMinor,
+    /// Tags matching `pattern` as a whole, ordered by its `version` capture group (or the whole
+    /// tag), e.g. `(?P<version>\d+\.\d+\.\d+)-alpine`.
+    Regex {
+        pattern: String,
+        #[serde(default)]
+        compare: VersionCompare,
+    },
+    /// Date tags: `2024.01.05`, `2024-01-05` or `20240105`, optionally followed by more numbers.
+    Date,
+    /// Build numbers: `1234`, or a fixed prefix followed by digits such as `build-1234`.
+    Build,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionCompare {
+    #[default]
+    Semver,
+    /// Runs of digits compared as numbers, left to right.
+    Numeric,
+    Lexical,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoUpdateSettings {
+    pub policy: AutoUpdatePolicy,
+    /// Only auto-apply while this window is open; `None` means any time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<MaintenanceWindow>,
+    /// How vulnerability scans of the target weigh in; needs `DOCKREV_SCANNER`.
+    #[serde(default)]
+    pub security: SecurityPreference,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityPreference {
+    /// Scans do not affect auto-updates.
+    #[default]
+    Ignore,
+    /// Updates that fix vulnerabilities are applied even while `window` is closed.
+    Prefer,
+    /// Only updates that fix vulnerabilities are applied.
+    Require,
+}
+
+impl SecurityPreference {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ignore => "ignore",
+            Self::Prefer => "prefer",
+            Self::Require => "require",
+        }
+    }
+
+    pub fn from_str(input: &str) -> Self {
+        match input {
+            "prefer" => Self::Prefer,
+            "require" => Self::Require,
+            _ => Self::Ignore,
+        }
+    }
+}
+
+/// Ordered from most to least conservative: each policy also allows everything the previous ones do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoUpdatePolicy {
+    #[default]
+    Never,
+    Digest,
+    Patch,
+    Minor,
+    Major,
+}
+
+impl AutoUpdatePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Never => "never",
+            Self::Digest => "digest",
+            Self::Patch => "patch",
+            Self::Minor => "minor",
+            Self::Major => "major",
+        }
+    }
+
+    pub fn from_str(input: &str) -> Self {
+        match input {
+            "digest" => Self::Digest,
+            "patch" => Self::Patch,
+            "minor" => Self::Minor,
+            "major" => Self::Major,
+            _ => Self::Never,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceWindow {
+    /// `mon`..`sun`; empty means every day.
+    #[serde(default)]
+    pub weekdays: Vec<String>,
+    /// `HH:MM`; a window with `end <= start` wraps past midnight.
+    pub start: String,
+    pub end: String,
+    /// `UTC`, a fixed offset such as `+08:00`, or an IANA name such as `Asia/Shanghai`.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -241,6 +478,190 @@ pub struct TriggerCheckResponse {
     pub check_id: String,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckSchedule {
+    pub cron: String,
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_run_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_job_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackCheckSchedule {
+    pub stack_id: String,
+    #[serde(flatten)]
+    pub schedule: CheckSchedule,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListSchedulesResponse {
+    pub global: CheckSchedule,
+    pub stacks: Vec<StackCheckSchedule>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PutScheduleRequest {
+    pub cron: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackScheduleResponse {
+    pub stack_id: String,
+    pub inherited: bool,
+    pub schedule: CheckSchedule,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostRecord {
+    pub id: String,
+    pub name: String,
+    /// `tcp://`, `ssh://` or `agent://` endpoint (`unix://` for the local host).
+    pub endpoint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    /// Directory with `ca.pem` / `cert.pem` / `key.pem` for TLS-protected `tcp://` endpoints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_cert_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    /// Whether the `dockrev-agent` of an `agent://` host is currently polling.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_connected: Option<bool>,
+}
+
+/// Pull quota of a registry as last reported by its `RateLimit-*` headers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryQuota {
+    pub registry: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_at: Option<String>,
+    /// Set after a 429 until its `Retry-After` has passed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_until: Option<String>,
+    /// Whether checks currently defer services of this registry.
+    pub deferred: bool,
+    pub updated_at: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListRegistryQuotasResponse {
+    pub registries: Vec<RegistryQuota>,
+}
+
+/// Registry login stored in Dockrev; used before the Docker config's credentials.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryCredential {
+    /// Registry host as in image refs (`docker.io`, `ghcr.io`, `registry.internal:5000`).
+    pub registry: String,
+    pub username: String,
+    /// Password or access token; `******` in responses.
+    pub secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+impl RegistryCredential {
+    pub fn masked(self) -> Self {
+        Self {
+            secret: mask_if_some(self.secret),
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListRegistryCredentialsResponse {
+    pub credentials: Vec<RegistryCredential>,
+}
+
+/// `secret` may be omitted or `******` to keep the stored one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PutRegistryCredentialRequest {
+    pub username: String,
+    pub secret: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListHostsResponse {
+    pub hosts: Vec<HostRecord>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateHostRequest {
+    pub name: String,
+    pub endpoint: String,
+    #[serde(default)]
+    pub platform: Option<String>,
+    #[serde(default)]
+    pub tls_cert_path: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateHostResponse {
+    pub host_id: String,
+    /// Token for `DOCKREV_AGENT_TOKEN`; only returned once, when an `agent://` host is created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_token: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceWindowRecord {
+    pub id: String,
+    /// `None` for global windows.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack_id: Option<String>,
+    #[serde(flatten)]
+    pub window: MaintenanceWindow,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListMaintenanceWindowsResponse {
+    pub windows: Vec<MaintenanceWindowRecord>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMaintenanceWindowRequest {
+    #[serde(default)]
+    pub stack_id: Option<String>,
+    #[serde(flatten)]
+    pub window: MaintenanceWindow,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMaintenanceWindowResponse {
+    pub window_id: String,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TriggerUpdateRequest {
@@ -317,6 +738,14 @@ pub struct TriggerUpdateResponse {
     pub job_id: String,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelJobResponse {
+    pub job_id: String,
+    /// `cancelled` when the job had not started yet, `cancelling` while a running job winds down.
+    pub status: String,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum JobScope {
@@ -387,6 +816,8 @@ pub struct JobListItem {
     pub allow_arch_mismatch: bool,
     pub backup_mode: String,
     pub summary_json: Value,
+    /// Derived from the stack (or service) on insert; `None` for jobs spanning hosts.
+    pub host_id: Option<String>,
 }
 
 impl JobListItem {
@@ -397,6 +828,7 @@ impl JobListItem {
             scope: self.scope.as_str().to_string(),
             stack_id: self.stack_id,
             service_id: self.service_id,
+            host_id: self.host_id,
             status: self.status,
             created_by: self.created_by,
             reason: self.reason,
@@ -426,6 +858,8 @@ pub struct JobApiListItem {
     pub stack_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub service_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_id: Option<String>,
     pub status: String,
     pub created_by: String,
     pub reason: String,
@@ -455,6 +889,8 @@ pub struct JobDetail {
     pub stack_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub service_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_id: Option<String>,
     pub status: String,
     pub created_by: String,
     pub reason: String,
@@ -534,6 +970,7 @@ impl JobRecord {
             allow_arch_mismatch: self.allow_arch_mismatch,
             backup_mode: self.backup_mode.clone(),
             summary_json: self.summary_json.clone(),
+            host_id: None,
         }
     }
 }
@@ -606,6 +1043,9 @@ pub struct DeleteIgnoreResponse {
 pub struct ServiceSettingsResponse {
     pub auto_rollback: bool,
     pub backup_targets: BackupTargetOverrides,
+    pub auto_update: AutoUpdateSettings,
+    pub version_strategy: VersionStrategy,
+    pub prerelease_policy: Option<PrereleasePolicy>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -613,6 +1053,12 @@ pub struct ServiceSettingsResponse {
 pub struct ServiceSettingsRequest {
     pub auto_rollback: bool,
     pub backup_targets: BackupTargetOverrides,
+    #[serde(default)]
+    pub auto_update: AutoUpdateSettings,
+    #[serde(default)]
+    pub version_strategy: VersionStrategy,
+    #[serde(default)]
+    pub prerelease_policy: Option<PrereleasePolicy>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -840,6 +1286,10 @@ fn default_true() -> bool {
     true
 }
 
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum DiscoveredProjectStatus {
@@ -861,6 +1311,7 @@ impl DiscoveredProjectStatus {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DiscoveredProject {
+    pub host_id: String,
     pub project: String,
     pub status: DiscoveredProjectStatus,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -906,6 +1357,7 @@ pub enum DiscoveryActionKind {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DiscoveryAction {
+    pub host_id: String,
     pub project: String,
     pub action: DiscoveryActionKind,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -934,6 +1386,12 @@ pub struct TriggerDiscoveryScanJobResponse {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceCandidatesResponse {
+    /// Image details of the running tag, to compare candidates against.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<ImageDetails>,
+    /// Last scan of the current digest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_vulnerabilities: Option<VulnerabilityScan>,
     pub candidates: Vec<ServiceCandidateOption>,
 }
 
@@ -947,4 +1405,72 @@ pub struct ServiceCandidateOption {
     #[serde(default)]
     pub arch: Vec<String>,
     pub ignored: bool,
+    /// A prerelease version such as `2.0.0-rc.1`.
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<ImageDetails>,
+    /// Last scan of `digest`; only digests a check picked as candidate are scanned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vulnerabilities: Option<VulnerabilityScan>,
+    /// Against `currentVulnerabilities`, when both are scanned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vulnerability_diff: Option<VulnerabilityDiff>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VulnerabilityCounts {
+    #[serde(default)]
+    pub critical: u32,
+    #[serde(default)]
+    pub high: u32,
+    #[serde(default)]
+    pub medium: u32,
+    #[serde(default)]
+    pub low: u32,
+    #[serde(default)]
+    pub unknown: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VulnerabilityScan {
+    pub scanner: String,
+    pub counts: VulnerabilityCounts,
+    pub scanned_at: String,
+}
+
+/// Candidate minus current findings per severity.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VulnerabilityDiff {
+    pub critical: i64,
+    pub high: i64,
+    pub medium: i64,
+    pub low: i64,
+    pub unknown: i64,
+    /// Fewer findings, compared from the most severe level down.
+    pub fixes: bool,
+}
+
+/// Host-platform image config of a tag.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageDetails {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub exposed_ports: Vec<String>,
+    /// Names only.
+    #[serde(default)]
+    pub env: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cmd: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed_size: Option<u64>,
 }
@@ -4,7 +4,7 @@ use serde_json::json;
 
 use crate::api::types::{BackupSettings, BackupTarget, JobScope, StackRecord, TernaryChoice};
 use crate::compose_runner::{ComposeRunnerConfig, ComposeStack};
-use crate::docker_runner;
+use crate::docker_engine::DockerEngine;
 use crate::runner::{CommandRunner, CommandSpec};
 
 #[derive(Clone, Debug)]
@@ -164,9 +164,17 @@ async fn cleanup_once(state: &crate::state::AppState) -> anyhow::Result<()> {
             }
         }
 
-        let healthy = stack_is_healthy_now(&*state.runner, &state.config.compose_bin, &stack)
-            .await
-            .unwrap_or(false);
+        let Some(host) = state.hosts.get(&stack.host_id) else {
+            continue;
+        };
+        let healthy = stack_is_healthy_now(
+            host.runner.as_ref(),
+            host.docker.as_ref(),
+            &state.config.compose_bin,
+            &stack,
+        )
+        .await
+        .unwrap_or(false);
         if !healthy {
             continue;
         }
@@ -191,6 +199,7 @@ async fn cleanup_once(state: &crate::state::AppState) -> anyhow::Result<()> {
 
 async fn stack_is_healthy_now(
     runner: &dyn CommandRunner,
+    docker: &dyn DockerEngine,
     compose_bin: &str,
     stack: &StackRecord,
 ) -> anyhow::Result<bool> {
@@ -200,10 +209,9 @@ async fn stack_is_healthy_now(
     let compose_stack = ComposeStack {
         project_name: sanitize_project_name(&stack.name),
         compose: stack.compose.clone(),
+        override_file: None,
     };
 
-    let docker_cfg = docker_runner::DockerRunnerConfig::default();
-
     for svc in &stack.services {
         let container_id = run_to_string(
             runner,
@@ -216,25 +224,14 @@ async fn stack_is_healthy_now(
             return Ok(false);
         }
 
-        let has_health = run_to_string(
-            runner,
-            docker_runner::inspect_has_healthcheck(&docker_cfg, &container_id),
-            Duration::from_secs(10),
-        )
-        .await?;
-        let has_health = has_health.trim() == "1";
-        if !has_health {
-            continue;
-        }
-
-        let status = run_to_string(
-            runner,
-            docker_runner::inspect_health_status(&docker_cfg, &container_id),
-            Duration::from_secs(10),
-        )
-        .await?;
-        if status.trim() != "healthy" {
-            return Ok(false);
+        match docker
+            .inspect_container(&container_id)
+            .await?
+            .health
+            .as_deref()
+        {
+            None | Some("healthy") => {}
+            Some(_) => return Ok(false),
         }
     }
 
@@ -356,6 +353,8 @@ async fn probe_size_bytes(
             "du -sb /data | cut -f1".to_string(),
         ],
         env: Vec::new(),
+        stdin: None,
+        files: Vec::new(),
     };
 
     let out = runner.run(spec, Duration::from_secs(30)).await?;
@@ -415,6 +414,8 @@ async fn run_backup_container(
         program: "docker".to_string(),
         args,
         env: Vec::new(),
+        stdin: None,
+        files: Vec::new(),
     };
 
     let out = runner.run(spec, Duration::from_secs(600)).await?;
@@ -497,6 +498,7 @@ mod tests {
         StackRecord {
             id: "stk_test".to_string(),
             name: "demo".to_string(),
+            host_id: crate::hosts::LOCAL_HOST_ID.to_string(),
             archived: false,
             compose: crate::api::types::ComposeConfig {
                 kind: "path".to_string(),
@@ -513,6 +515,7 @@ mod tests {
                 image: crate::api::types::ComposeRef {
                     reference: "ghcr.io/acme/web:5.2".to_string(),
                     tag: "5.2".to_string(),
+                    variant: None,
                     digest: None,
                     resolved_tag: None,
                     resolved_tags: None,
@@ -525,6 +528,9 @@ mod tests {
                         bind_paths: BTreeMap::new(),
                         volume_names: BTreeMap::new(),
                     },
+                    auto_update: Default::default(),
+                    version_strategy: Default::default(),
+                    prerelease_policy: None,
                 },
                 archived: None,
             }],
@@ -1,40 +1,214 @@
+use regex::Regex;
 use semver::Version;
 
-use crate::ignore::parse_version;
+use crate::{
+    api::types::{AutoUpdatePolicy, PrereleasePolicy, VersionCompare, VersionStrategy},
+    ignore::{is_prerelease, parse_version, split_variant},
+};
 
-pub fn select_candidate_tag(
-    current_tag: &str,
-    tags: &[String],
-    is_ignored: impl Fn(&str) -> bool,
-) -> Option<String> {
-    let current_semver = parse_version(current_tag);
-    if let Some(current) = current_semver {
-        let mut best: Option<Version> = None;
-        let mut best_tag: Option<String> = None;
-        for tag in tags {
-            if tag == current_tag || is_ignored(tag) {
-                continue;
+/// A [`VersionStrategy`] and [`PrereleasePolicy`] ready to order tags.
+#[derive(Clone, Debug, Default)]
+pub struct Versioning {
+    strategy: VersionStrategy,
+    pattern: Option<Regex>,
+    prerelease: PrereleasePolicy,
+}
+
+/// Position of a tag under one strategy; keys of different variants are never compared.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum VersionKey {
+    Semver(Version),
+    Numeric(Vec<u64>),
+    Lexical(String),
+}
+
+impl Versioning {
+    /// Fails on an invalid `regex` pattern.
+    pub fn new(strategy: &VersionStrategy, prerelease: PrereleasePolicy) -> anyhow::Result<Self> {
+        let pattern = match strategy {
+            VersionStrategy::Regex { pattern, .. } => Some(
+                Regex::new(&format!("^(?:{pattern})$"))
+                    .map_err(|e| anyhow::anyhow!("invalid version pattern: {e}"))?,
+            ),
+            _ => None,
+        };
+        Ok(Self {
+            strategy: strategy.clone(),
+            pattern,
+            prerelease,
+        })
+    }
+
+    fn allows_prereleases(&self, current_tag: &str) -> bool {
+        match self.prerelease {
+            PrereleasePolicy::Exclude => false,
+            PrereleasePolicy::IfCurrent => is_prerelease(current_tag),
+            PrereleasePolicy::Always => true,
+        }
+    }
+
+    /// Tags are only compared within a family: semver tags with the same variant suffix
+    /// (`16.2-alpine` with `16.3-alpine`), build numbers with the same prefix.
+    fn key(&self, tag: &str) -> Option<(String, VersionKey)> {
+        match &self.strategy {
+            VersionStrategy::Semver | VersionStrategy::SameMajor | VersionStrategy::SameMinor => {
+                let (version, variant) = split_variant(tag);
+                parse_version(version).map(|v| {
+                    (
+                        variant.unwrap_or_default().to_string(),
+                        VersionKey::Semver(v),
+                    )
+                })
             }
-            let Some(v) = parse_version(tag) else {
-                continue;
-            };
-            if v <= current {
-                continue;
+            VersionStrategy::Regex { compare, .. } => {
+                let caps = self.pattern.as_ref()?.captures(tag)?;
+                let version = caps.name("version").or_else(|| caps.get(0))?.as_str();
+                let key = match compare {
+                    VersionCompare::Semver => VersionKey::Semver(parse_version(version)?),
+                    VersionCompare::Numeric => VersionKey::Numeric(numbers(version)?),
+                    VersionCompare::Lexical => VersionKey::Lexical(version.to_string()),
+                };
+                Some((String::new(), key))
             }
-            if best.as_ref().is_none_or(|b| &v > b) {
-                best = Some(v);
-                best_tag = Some(tag.clone());
+            VersionStrategy::Date => date_key(tag).map(|k| (String::new(), VersionKey::Numeric(k))),
+            VersionStrategy::Build => {
+                let prefix_len =
+                    tag.len() - tag.trim_start_matches(|c: char| !c.is_ascii_digit()).len();
+                let (prefix, number) = tag.split_at(prefix_len);
+                if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                Some((
+                    prefix.to_string(),
+                    VersionKey::Numeric(vec![number.parse().ok()?]),
+                ))
             }
         }
-        if best_tag.is_some() {
-            return best_tag;
+    }
+
+    /// Whether the strategy lets `current` move to `candidate` at all.
+    fn allows(&self, current: &VersionKey, candidate: &VersionKey) -> bool {
+        match (&self.strategy, current, candidate) {
+            (VersionStrategy::SameMajor, VersionKey::Semver(c), VersionKey::Semver(v)) => {
+                v.major == c.major
+            }
+            (VersionStrategy::SameMinor, VersionKey::Semver(c), VersionKey::Semver(v)) => {
+                v.major == c.major && v.minor == c.minor
+            }
+            _ => true,
         }
     }
 
+    /// Tags newer than `current_tag`, best first; `None` when `current_tag` has no version
+    /// under this strategy.
+    fn newer_tags<'a>(
+        &self,
+        current_tag: &str,
+        tags: &'a [String],
+    ) -> Option<Vec<(VersionKey, &'a String)>> {
+        let (family, current) = self.key(current_tag)?;
+        let prereleases = self.allows_prereleases(current_tag);
+        let mut out = tags
+            .iter()
+            .filter(|tag| tag.as_str() != current_tag)
+            .filter_map(|tag| {
+                let (f, key) = self.key(tag)?;
+                let prerelease = matches!(&key, VersionKey::Semver(v) if !v.pre.is_empty());
+                (f == family
+                    && key > current
+                    && self.allows(&current, &key)
+                    && (prereleases || !prerelease))
+                    .then_some((key, tag))
+            })
+            .collect::<Vec<_>>();
+        out.sort_by(|a, b| b.0.cmp(&a.0));
+        Some(out)
+    }
+
+    /// Candidate tags of `current_tag` to offer, best first.
+    ///
+    /// Under the default strategy a non-semver current tag lists every other tag,
+    /// lexicographically descending; other strategies only list newer versions.
+    pub fn rank_candidate_tags(&self, current_tag: &str, tags: &[String]) -> Vec<String> {
+        if let Some(newer) = self.newer_tags(current_tag, tags) {
+            return newer.into_iter().map(|(_, tag)| tag.clone()).collect();
+        }
+        if self.strategy != VersionStrategy::Semver {
+            return Vec::new();
+        }
+        let prereleases = self.allows_prereleases(current_tag);
+        let mut other = tags
+            .iter()
+            .filter(|tag| tag.as_str() != current_tag && (prereleases || !is_prerelease(tag)))
+            .cloned()
+            .collect::<Vec<_>>();
+        other.sort_by(|a, b| b.cmp(a));
+        other
+    }
+}
+
+/// Runs of digits in `input`, e.g. `1.2_3` is `[1, 2, 3]`.
+fn numbers(input: &str) -> Option<Vec<u64>> {
+    let out = input
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().ok())
+        .collect::<Option<Vec<_>>>()?;
+    (!out.is_empty()).then_some(out)
+}
+
+/// `YYYY.MM.DD`, `YYYY-MM-DD` or `YYYYMMDD`, optionally followed by further numeric parts.
+fn date_key(tag: &str) -> Option<Vec<u64>> {
+    let parts = tag.split(['.', '-', '_']).collect::<Vec<_>>();
+    if parts
+        .iter()
+        .any(|p| p.is_empty() || !p.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return None;
+    }
+    let (date, rest) = match parts.as_slice() {
+        [compact, rest @ ..] if compact.len() == 8 => {
+            let (y, md) = compact.split_at(4);
+            let (m, d) = md.split_at(2);
+            (vec![y, m, d], rest)
+        }
+        [y, m, d, rest @ ..] if y.len() == 4 && m.len() <= 2 && d.len() <= 2 => {
+            (vec![*y, *m, *d], rest)
+        }
+        _ => return None,
+    };
+    let key = date
+        .into_iter()
+        .chain(rest.iter().copied())
+        .map(|p| p.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    ((1..=12).contains(&key[1]) && (1..=31).contains(&key[2])).then_some(key)
+}
+
+pub fn select_candidate_tag(
+    versioning: &Versioning,
+    current_tag: &str,
+    tags: &[String],
+    is_ignored: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let best = versioning
+        .newer_tags(current_tag, tags)
+        .into_iter()
+        .flatten()
+        .find(|(_, tag)| !is_ignored(tag));
+    if let Some((_, tag)) = best {
+        return Some(tag.clone());
+    }
+    // A variant only ever moves to the same variant.
+    if versioning.strategy != VersionStrategy::Semver || split_variant(current_tag).1.is_some() {
+        return None;
+    }
+
     // Fallback: lexicographic maximum (still ignoring current and ignored tags).
+    let prereleases = versioning.allows_prereleases(current_tag);
     let mut best: Option<&str> = None;
     for tag in tags {
-        if tag == current_tag || is_ignored(tag) {
+        if tag == current_tag || is_ignored(tag) || (!prereleases && is_prerelease(tag)) {
             continue;
         }
         if best.is_none_or(|b| tag.as_str() > b) {
@@ -44,6 +218,40 @@ pub fn select_candidate_tag(
     best.map(|s| s.to_string())
 }
 
+/// Picks the best tag above `current_tag` under `versioning` that `policy` allows applying
+/// automatically.
+///
+/// Digest-only policies never move to another tag, and tags without a version under the strategy
+/// are never auto-selected. Patch and minor levels apply to semver versions; date and build
+/// numbers follow any tag policy.
+pub fn select_auto_update_tag(
+    versioning: &Versioning,
+    policy: AutoUpdatePolicy,
+    current_tag: &str,
+    tags: &[String],
+    is_ignored: impl Fn(&str) -> bool,
+) -> Option<String> {
+    if policy < AutoUpdatePolicy::Patch {
+        return None;
+    }
+    let (_, current) = versioning.key(current_tag)?;
+    versioning
+        .newer_tags(current_tag, tags)?
+        .into_iter()
+        .find(|(key, tag)| {
+            let allowed = match (&current, key) {
+                (VersionKey::Semver(c), VersionKey::Semver(v)) => match policy {
+                    AutoUpdatePolicy::Patch => v.major == c.major && v.minor == c.minor,
+                    AutoUpdatePolicy::Minor => v.major == c.major,
+                    _ => true,
+                },
+                _ => true,
+            };
+            allowed && !is_ignored(tag)
+        })
+        .map(|(_, tag)| tag.clone())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,21 +259,271 @@ mod tests {
     #[test]
     fn semver_picks_higher() {
         let tags = vec!["5.2".to_string(), "5.3".to_string(), "5.10".to_string()];
-        let picked = select_candidate_tag("5.2", &tags, |_| false).unwrap();
+        let picked = select_candidate_tag(&Versioning::default(), "5.2", &tags, |_| false).unwrap();
         assert_eq!(picked, "5.10");
     }
 
     #[test]
     fn semver_respects_ignore() {
         let tags = vec!["5.2".to_string(), "5.3".to_string(), "5.4".to_string()];
-        let picked = select_candidate_tag("5.2", &tags, |t| t == "5.4").unwrap();
+        let picked =
+            select_candidate_tag(&Versioning::default(), "5.2", &tags, |t| t == "5.4").unwrap();
         assert_eq!(picked, "5.3");
     }
 
     #[test]
     fn fallback_lexicographic() {
         let tags = vec!["alpha".to_string(), "beta".to_string()];
-        let picked = select_candidate_tag("alpha", &tags, |_| false).unwrap();
+        let picked =
+            select_candidate_tag(&Versioning::default(), "alpha", &tags, |_| false).unwrap();
         assert_eq!(picked, "beta");
     }
+
+    #[test]
+    fn auto_update_respects_policy_level() {
+        let tags = ["1.2.3", "1.2.5", "1.3.0", "2.0.0", "latest"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>();
+        let pick = |policy| {
+            select_auto_update_tag(&Versioning::default(), policy, "1.2.3", &tags, |_| false)
+        };
+        assert_eq!(pick(AutoUpdatePolicy::Never), None);
+        assert_eq!(pick(AutoUpdatePolicy::Digest), None);
+        assert_eq!(pick(AutoUpdatePolicy::Patch).as_deref(), Some("1.2.5"));
+        assert_eq!(pick(AutoUpdatePolicy::Minor).as_deref(), Some("1.3.0"));
+        assert_eq!(pick(AutoUpdatePolicy::Major).as_deref(), Some("2.0.0"));
+    }
+
+    #[test]
+    fn auto_update_skips_non_semver_current_tag() {
+        let tags = vec!["latest".to_string(), "1.0.0".to_string()];
+        assert_eq!(
+            select_auto_update_tag(
+                &Versioning::default(),
+                AutoUpdatePolicy::Major,
+                "latest",
+                &tags,
+                |_| false
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn strategies_bound_and_order_candidates() {
+        type Case<'a> = (VersionStrategy, &'a str, &'a [&'a str], Option<&'a str>);
+        let regex = |pattern: &str, compare| VersionStrategy::Regex {
+            pattern: pattern.to_string(),
+            compare,
+        };
+        let cases: &[Case] = &[
+            (
+                VersionStrategy::Semver,
+                "16",
+                &["15", "16", "16.2", "17"],
+                Some("17"),
+            ),
+            (
+                VersionStrategy::SameMajor,
+                "16",
+                &["15", "16", "16.2", "17"],
+                Some("16.2"),
+            ),
+            (
+                VersionStrategy::SameMinor,
+                "1.2.3",
+                &["1.2.9", "1.3.0", "2.0.0"],
+                Some("1.2.9"),
+            ),
+            // No lexicographic fallback outside the default strategy.
+            (
+                VersionStrategy::SameMajor,
+                "alpine",
+                &["edge", "3.19"],
+                None,
+            ),
+            (
+                regex(r"(?P<version>\d+\.\d+\.\d+)-alpine", VersionCompare::Semver),
+                "1.2.3-alpine",
+                &["1.2.4", "1.2.4-alpine", "1.10.0-alpine", "2.0.0-bookworm"],
+                Some("1.10.0-alpine"),
+            ),
+            (
+                regex(r"r\d+\.\d+", VersionCompare::Numeric),
+                "r9.1",
+                &["r10.0", "r9.2", "r8.9", "latest"],
+                Some("r10.0"),
+            ),
+            (
+                VersionStrategy::Date,
+                "2024.01.05",
+                &[
+                    "2024.01.12",
+                    "2023.12.31",
+                    "2024.1.20",
+                    "latest",
+                    "2024.13.01",
+                ],
+                Some("2024.1.20"),
+            ),
+            (
+                VersionStrategy::Date,
+                "20240105",
+                &["20240112", "2024-02-01", "99999999"],
+                Some("2024-02-01"),
+            ),
+            (
+                VersionStrategy::Build,
+                "build-98",
+                &["build-99", "build-100", "1000", "nightly-200"],
+                Some("build-100"),
+            ),
+            (
+                VersionStrategy::Build,
+                "98",
+                &["99", "build-100"],
+                Some("99"),
+            ),
+        ];
+        for (strategy, current, tags, expected) in cases {
+            let versioning = Versioning::new(strategy, PrereleasePolicy::Always).unwrap();
+            let tags = tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+            assert_eq!(
+                select_candidate_tag(&versioning, current, &tags, |_| false).as_deref(),
+                *expected,
+                "{strategy:?} from {current}"
+            );
+        }
+    }
+
+    #[test]
+    fn strategies_rank_and_auto_update() {
+        let tags = ["2024.01.05", "2024.02.01", "2024.01.20", "latest"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>();
+        let date = Versioning::new(&VersionStrategy::Date, Default::default()).unwrap();
+        assert_eq!(
+            date.rank_candidate_tags("2024.01.05", &tags),
+            ["2024.02.01", "2024.01.20"]
+        );
+        assert_eq!(
+            select_auto_update_tag(&date, AutoUpdatePolicy::Patch, "2024.01.05", &tags, |t| t
+                == "2024.02.01")
+            .as_deref(),
+            Some("2024.01.20")
+        );
+        assert_eq!(
+            Versioning::default().rank_candidate_tags("latest", &tags),
+            ["2024.02.01", "2024.01.20", "2024.01.05"]
+        );
+
+        let same_major = Versioning::new(&VersionStrategy::SameMajor, Default::default()).unwrap();
+        let tags = vec![
+            "1.2.4".to_string(),
+            "1.3.0".to_string(),
+            "2.0.0".to_string(),
+        ];
+        assert_eq!(
+            select_auto_update_tag(&same_major, AutoUpdatePolicy::Major, "1.2.3", &tags, |_| {
+                false
+            })
+            .as_deref(),
+            Some("1.3.0")
+        );
+        assert!(
+            Versioning::new(
+                &VersionStrategy::Regex {
+                    pattern: "(".to_string(),
+                    compare: VersionCompare::Semver,
+                },
+                Default::default()
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn variants_only_move_to_the_same_variant() {
+        let tags = [
+            "16.2-alpine",
+            "16.3-alpine",
+            "17.0-alpine",
+            "17.1",
+            "17.2-bookworm",
+            "latest",
+            "alpine",
+        ]
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>();
+        let semver = Versioning::default();
+        let pick = |current| select_candidate_tag(&semver, current, &tags, |_| false);
+        assert_eq!(pick("16.2-alpine").as_deref(), Some("17.0-alpine"));
+        assert_eq!(pick("17.0-alpine"), None);
+        assert_eq!(pick("16.2").as_deref(), Some("17.1"));
+        assert_eq!(pick("8.0-bookworm").as_deref(), Some("17.2-bookworm"));
+        assert_eq!(
+            semver.rank_candidate_tags("16.2-alpine", &tags),
+            ["17.0-alpine", "16.3-alpine"]
+        );
+
+        let same_major = Versioning::new(&VersionStrategy::SameMajor, Default::default()).unwrap();
+        assert_eq!(
+            select_candidate_tag(&same_major, "16.2-alpine", &tags, |_| false).as_deref(),
+            Some("16.3-alpine")
+        );
+        assert_eq!(
+            select_auto_update_tag(
+                &semver,
+                AutoUpdatePolicy::Patch,
+                "16.2-alpine",
+                &["16.2.1-alpine".to_string(), "16.2.2".to_string()],
+                |_| false
+            )
+            .as_deref(),
+            Some("16.2.1-alpine")
+        );
+    }
+
+    #[test]
+    fn prerelease_policy_filters_candidates() {
+        let tags = [
+            "1.2.0",
+            "1.3.0-rc.1",
+            "1.2.1",
+            "2.0.0-beta.2-alpine",
+            "nightly",
+        ]
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>();
+        let pick = |policy, current| {
+            let versioning = Versioning::new(&VersionStrategy::Semver, policy).unwrap();
+            select_candidate_tag(&versioning, current, &tags, |_| false)
+        };
+        assert_eq!(
+            pick(PrereleasePolicy::Exclude, "1.2.0").as_deref(),
+            Some("1.2.1")
+        );
+        assert_eq!(
+            pick(PrereleasePolicy::IfCurrent, "1.2.0").as_deref(),
+            Some("1.2.1")
+        );
+        assert_eq!(
+            pick(PrereleasePolicy::IfCurrent, "1.3.0-alpha").as_deref(),
+            Some("1.3.0-rc.1")
+        );
+        assert_eq!(
+            pick(PrereleasePolicy::Always, "1.2.0").as_deref(),
+            Some("1.3.0-rc.1")
+        );
+        assert_eq!(
+            Versioning::new(&VersionStrategy::Semver, PrereleasePolicy::Exclude)
+                .unwrap()
+                .rank_candidate_tags("latest", &tags),
+            ["nightly", "1.2.1", "1.2.0"]
+        );
+    }
 }
@@ -2,6 +2,8 @@ use std::collections::BTreeMap;
 
 use anyhow::Context as _;
 
+use crate::registry::{DEFAULT_TAG, ImageRef};
+
 #[derive(Clone, Debug)]
 pub struct ServiceFromCompose {
     pub name: String,
@@ -32,7 +34,7 @@ pub fn parse_services(compose_yaml: &str) -> anyhow::Result<Vec<ServiceFromCompo
             continue;
         }
 
-        let image_tag = extract_tag(&image_ref).unwrap_or_else(|| "latest".to_string());
+        let image_tag = extract_tag(&image_ref).unwrap_or_else(|| DEFAULT_TAG.to_string());
 
         out.push(ServiceFromCompose {
             name: name.to_string(),
@@ -54,22 +56,9 @@ pub fn merge_services(
     base
 }
 
+/// Explicit tag of the reference; `None` for untagged and digest-only refs.
 fn extract_tag(image_ref: &str) -> Option<String> {
-    if image_ref.contains('@') {
-        return None;
-    }
-    let (left, right) = image_ref.rsplit_once(':')?;
-    if right.is_empty() {
-        return None;
-    }
-    // If the part after the last ':' contains a '/', it is likely "registry:port/path", not a tag.
-    if right.contains('/') {
-        return None;
-    }
-    if left.is_empty() {
-        return None;
-    }
-    Some(right.to_string())
+    ImageRef::parse(image_ref).ok()?.tag
 }
 
 #[cfg(test)]
@@ -114,5 +103,18 @@ services:
             extract_tag("ghcr.io/acme/web@sha256:deadbeef").as_deref(),
             None
         );
+        assert_eq!(
+            extract_tag("ghcr.io/acme/web:5.2@sha256:deadbeef").as_deref(),
+            Some("5.2")
+        );
+    }
+
+    #[test]
+    fn extract_tag_ipv6_registry() {
+        assert_eq!(
+            extract_tag("[::1]:5000/app:1.2.3").as_deref(),
+            Some("1.2.3")
+        );
+        assert_eq!(extract_tag("[::1]:5000/app").as_deref(), None);
     }
 }
@@ -9,6 +9,9 @@ pub struct ComposeRunnerConfig {
 pub struct ComposeStack {
     pub project_name: String,
     pub compose: ComposeConfig,
+    /// Generated by dockrev (see `updater::build_override_file`); applied after the stack's own
+    /// files and listed in [`CommandSpec::files`].
+    pub override_file: Option<String>,
 }
 
 impl ComposeStack {
@@ -19,7 +22,12 @@ impl ComposeStack {
             args.push("compose".to_string());
         }
 
-        for f in &self.compose.compose_files {
+        for f in self
+            .compose
+            .compose_files
+            .iter()
+            .chain(self.override_file.as_ref())
+        {
             args.push("-f".to_string());
             args.push(f.clone());
         }
@@ -36,6 +44,8 @@ impl ComposeStack {
             program: cfg.compose_bin.clone(),
             args,
             env: Vec::new(),
+            stdin: None,
+            files: self.override_file.iter().cloned().collect(),
         }
     }
 
@@ -90,6 +100,7 @@ mod tests {
                 compose_files: vec!["/srv/app/docker-compose.yml".to_string()],
                 env_file: Some("/srv/app/.env".to_string()),
             },
+            override_file: None,
         };
         let cfg = ComposeRunnerConfig {
             compose_bin: "docker".to_string(),
@@ -109,6 +120,7 @@ mod tests {
                 compose_files: vec!["/srv/app/docker-compose.yml".to_string()],
                 env_file: None,
             },
+            override_file: None,
         };
         let cfg = ComposeRunnerConfig {
             compose_bin: "docker-compose".to_string(),
@@ -1,7 +1,16 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
+use anyhow::Context as _;
 use axum::http::HeaderName;
 
+use crate::{
+    api::types::PrereleasePolicy,
+    cloud_auth::{ProviderKind, parse_provider_entries},
+    release_notes::ForgeKind,
+    scanner::ScannerKind,
+    signatures::SignaturePolicy,
+};
+
 #[derive(Clone)]
 pub struct Config {
     pub app_effective_version: String,
@@ -9,6 +18,8 @@ pub struct Config {
     pub db_path: PathBuf,
     pub docker_config_path: Option<PathBuf>,
     pub compose_bin: String,
+    pub docker_engine: String,
+    pub docker_host: Option<String>,
     pub auth_forward_header_name: HeaderName,
     pub auth_allow_anonymous_in_dev: bool,
     pub self_upgrade_url: String,
@@ -17,6 +28,35 @@ pub struct Config {
     pub host_platform: Option<String>,
     pub discovery_interval_seconds: u64,
     pub discovery_max_actions: u32,
+    pub job_concurrency: usize,
+    pub job_log_max_bytes: usize,
+    pub registry_tags_page_size: u32,
+    pub registry_max_tags: usize,
+    pub registry_cache_ttl_seconds: u64,
+    pub registry_rate_limit_reserve: u64,
+    pub registry_mirrors: HashMap<String, Vec<String>>,
+    pub registry_endpoints: HashMap<String, String>,
+    pub registry_insecure: Vec<String>,
+    pub registry_ca_bundle: Option<PathBuf>,
+    pub registry_certs_dir: Option<PathBuf>,
+    /// Registry host patterns served by cloud token providers.
+    pub registry_token_providers: Vec<(String, ProviderKind)>,
+    pub release_notes_enabled: bool,
+    /// Release API kind per source host, on top of github.com, gitlab.com and codeberg.org.
+    pub release_notes_forges: HashMap<String, ForgeKind>,
+    /// Release API token per source host.
+    pub release_notes_tokens: HashMap<String, String>,
+    /// Public keys per image pattern and the patterns that require a verified signature.
+    pub signature_policy: SignaturePolicy,
+    /// Vulnerability scanner; `None` disables scans.
+    pub scanner: Option<ScannerKind>,
+    /// Overrides the `trivy` / `grype` executable.
+    pub scanner_bin: Option<String>,
+    pub scanner_timeout_seconds: u64,
+    /// How long a scan of a digest is reused before it is rescanned.
+    pub scan_ttl_seconds: u64,
+    /// Prerelease policy of services that don't set their own.
+    pub prerelease_policy: PrereleasePolicy,
 }
 
 impl Config {
@@ -40,6 +80,21 @@ impl Config {
         let compose_bin =
             std::env::var("DOCKREV_COMPOSE_BIN").unwrap_or_else(|_| "docker-compose".to_string());
 
+        let docker_engine = std::env::var("DOCKREV_DOCKER_ENGINE")
+            .map(|v| v.trim().to_ascii_lowercase())
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "auto".to_string());
+        if !matches!(docker_engine.as_str(), "auto" | "api" | "cli") {
+            return Err(anyhow::anyhow!(
+                "DOCKREV_DOCKER_ENGINE must be one of auto, api, cli"
+            ));
+        }
+
+        let docker_host = std::env::var("DOCKER_HOST")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+
         let auth_forward_header_name = std::env::var("DOCKREV_AUTH_FORWARD_HEADER_NAME")
             .unwrap_or_else(|_| "X-Forwarded-User".to_string())
             .parse::<HeaderName>()?;
@@ -77,12 +132,142 @@ impl Config {
             .and_then(|v| v.trim().parse::<u32>().ok())
             .unwrap_or(200);
 
+        let job_concurrency = std::env::var("DOCKREV_JOB_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(2);
+        if job_concurrency == 0 {
+            return Err(anyhow::anyhow!("DOCKREV_JOB_CONCURRENCY must be >= 1"));
+        }
+
+        let job_log_max_bytes = std::env::var("DOCKREV_JOB_LOG_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(1024 * 1024);
+
+        let registry_tags_page_size = std::env::var("DOCKREV_REGISTRY_TAGS_PAGE_SIZE")
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .unwrap_or(1000);
+        if registry_tags_page_size == 0 {
+            return Err(anyhow::anyhow!(
+                "DOCKREV_REGISTRY_TAGS_PAGE_SIZE must be >= 1"
+            ));
+        }
+
+        let registry_max_tags = std::env::var("DOCKREV_REGISTRY_MAX_TAGS")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(10_000);
+        if registry_max_tags == 0 {
+            return Err(anyhow::anyhow!("DOCKREV_REGISTRY_MAX_TAGS must be >= 1"));
+        }
+
+        let registry_cache_ttl_seconds = std::env::var("DOCKREV_REGISTRY_CACHE_TTL_SECONDS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(900);
+
+        let registry_rate_limit_reserve = std::env::var("DOCKREV_REGISTRY_RATE_LIMIT_RESERVE")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(10);
+
+        let mut registry_mirrors = HashMap::<String, Vec<String>>::new();
+        for (registry, url) in parse_pairs("DOCKREV_REGISTRY_MIRRORS", "registry=url")? {
+            registry_mirrors.entry(registry).or_default().push(url);
+        }
+        let registry_endpoints = parse_pairs("DOCKREV_REGISTRY_ENDPOINTS", "registry=url")?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let registry_insecure = std::env::var("DOCKREV_REGISTRY_INSECURE")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        let registry_ca_bundle = std::env::var("DOCKREV_REGISTRY_CA_BUNDLE")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(PathBuf::from);
+        let registry_certs_dir = std::env::var("DOCKREV_REGISTRY_CERTS_DIR")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(PathBuf::from);
+        let registry_token_providers = parse_provider_entries(
+            &std::env::var("DOCKREV_REGISTRY_TOKEN_PROVIDERS").unwrap_or_default(),
+        )
+        .context("parse DOCKREV_REGISTRY_TOKEN_PROVIDERS")?;
+
+        let release_notes_enabled = std::env::var("DOCKREV_RELEASE_NOTES")
+            .ok()
+            .and_then(|v| parse_bool(&v))
+            .unwrap_or(true);
+        let release_notes_forges = parse_pairs("DOCKREV_RELEASE_NOTES_FORGES", "host=kind")?
+            .into_iter()
+            .map(|(host, kind)| match ForgeKind::parse(&kind) {
+                Some(kind) => Ok((host, kind)),
+                None => Err(anyhow::anyhow!(
+                    "DOCKREV_RELEASE_NOTES_FORGES: unknown kind {kind:?} (expected github, gitlab or gitea)"
+                )),
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let release_notes_tokens = parse_pairs("DOCKREV_RELEASE_NOTES_TOKENS", "host=token")?
+            .into_iter()
+            .collect();
+
+        let signature_keys = parse_pairs("DOCKREV_SIGNATURE_KEYS", "pattern=path")?
+            .into_iter()
+            .map(|(pattern, path)| (pattern, PathBuf::from(path)))
+            .collect::<Vec<_>>();
+        let signature_required = std::env::var("DOCKREV_SIGNATURE_REQUIRED")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        let signature_policy = SignaturePolicy::load(&signature_keys, &signature_required)
+            .context("load DOCKREV_SIGNATURE_KEYS / DOCKREV_SIGNATURE_REQUIRED")?;
+
+        let scanner = match std::env::var("DOCKREV_SCANNER") {
+            Ok(v) if !v.trim().is_empty() => Some(ScannerKind::parse(&v).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "DOCKREV_SCANNER: unknown scanner {v:?} (expected trivy, grype or an http(s) URL)"
+                )
+            })?),
+            _ => None,
+        };
+        let scanner_bin = std::env::var("DOCKREV_SCANNER_BIN")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+        let scanner_timeout_seconds = std::env::var("DOCKREV_SCANNER_TIMEOUT_SECONDS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(600);
+        let scan_ttl_seconds = std::env::var("DOCKREV_SCAN_TTL_SECONDS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(86_400);
+
+        let prerelease_policy = match std::env::var("DOCKREV_PRERELEASE_POLICY") {
+            Ok(v) if !v.trim().is_empty() => {
+                PrereleasePolicy::parse(v.trim()).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "DOCKREV_PRERELEASE_POLICY: unknown policy {v:?} (expected exclude, if_current or always)"
+                    )
+                })?
+            }
+            _ => PrereleasePolicy::default(),
+        };
+
         Ok(Self {
             app_effective_version,
             http_addr,
             db_path,
             docker_config_path,
             compose_bin,
+            docker_engine,
+            docker_host,
             auth_forward_header_name,
             auth_allow_anonymous_in_dev,
             self_upgrade_url,
@@ -91,6 +276,27 @@ impl Config {
             host_platform,
             discovery_interval_seconds,
             discovery_max_actions,
+            job_concurrency,
+            job_log_max_bytes,
+            registry_tags_page_size,
+            registry_max_tags,
+            registry_cache_ttl_seconds,
+            registry_rate_limit_reserve,
+            registry_mirrors,
+            registry_endpoints,
+            registry_insecure,
+            registry_ca_bundle,
+            registry_certs_dir,
+            registry_token_providers,
+            release_notes_enabled,
+            release_notes_forges,
+            release_notes_tokens,
+            signature_policy,
+            scanner,
+            scanner_bin,
+            scanner_timeout_seconds,
+            scan_ttl_seconds,
+            prerelease_policy,
         })
     }
 }
@@ -102,3 +308,21 @@ fn parse_bool(input: &str) -> Option<bool> {
         _ => None,
     }
 }
+
+/// Comma-separated `key=value` pairs (`shape` names them in errors); a key may repeat.
+fn parse_pairs(var: &str, shape: &str) -> anyhow::Result<Vec<(String, String)>> {
+    std::env::var(var)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() && !value.trim().is_empty() => {
+                Ok((key.trim().to_string(), value.trim().to_string()))
+            }
+            _ => Err(anyhow::anyhow!(
+                "{var} entries must look like {shape}, got {pair:?}"
+            )),
+        })
+        .collect()
+}
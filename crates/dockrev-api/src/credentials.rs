@@ -0,0 +1,435 @@
+//! Registry credentials. Per registry, credentials stored in Dockrev's database win over minted
+//! cloud credentials and the Docker config, where `credHelpers` / `credsStore` helpers are asked
+//! before static `auths`.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context as _;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::Deserialize;
+
+use crate::{
+    cloud_auth::TokenProviders,
+    db::Db,
+    runner::{CommandRunner, CommandSpec},
+};
+
+/// Helper answers (including "not found") are reused this long.
+const HELPER_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+const HELPER_TIMEOUT: Duration = Duration::from_secs(30);
+/// Username credential helpers return for identity tokens.
+const IDENTITY_TOKEN_USERNAME: &str = "<token>";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Clone, Default)]
+pub struct CredentialStore {
+    docker: Option<DockerConfig>,
+    db: Option<Db>,
+    /// Runs `docker-credential-<helper>`; helpers are skipped without one.
+    runner: Option<Arc<dyn CommandRunner>>,
+    providers: Option<Arc<TokenProviders>>,
+    helper_cache: Arc<Mutex<HashMap<String, HelperAnswer>>>,
+}
+
+/// When a helper was asked and what it returned.
+type HelperAnswer = (Instant, Option<Credentials>);
+
+impl CredentialStore {
+    pub fn load(docker_config_path: Option<&Path>) -> Self {
+        let docker = docker_config_path.and_then(|p| match DockerConfig::load(p) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                tracing::warn!(error = %e, "docker config not loaded");
+                None
+            }
+        });
+        Self {
+            docker,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_db(mut self, db: Db) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = Some(runner);
+        self
+    }
+
+    pub fn with_token_providers(mut self, providers: Arc<TokenProviders>) -> Self {
+        self.providers = Some(providers);
+        self
+    }
+
+    pub async fn get(&self, registry_host: &str) -> Option<Credentials> {
+        let key = normalize_auth_key(registry_host);
+        if let Some(db) = self.db.as_ref() {
+            match db.get_registry_credential(&key).await {
+                Ok(Some(c)) => {
+                    return Some(Credentials {
+                        username: c.username,
+                        password: c.secret.unwrap_or_default(),
+                    });
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(registry = %key, error = %e, "read registry credential failed")
+                }
+            }
+        }
+
+        if let Some(providers) = self.providers.as_ref()
+            && let Some(c) = providers.get(&key).await
+        {
+            return Some(c);
+        }
+
+        let docker = self.docker.as_ref()?;
+        if let Some(helper) = docker.helper_for(&key)
+            && let Some(c) = self.ask_helper(helper, &key).await
+        {
+            return Some(c);
+        }
+        docker.static_auth(&key)
+    }
+
+    async fn ask_helper(&self, helper: &str, key: &str) -> Option<Credentials> {
+        let cache_key = format!("{helper}|{key}");
+        if let Ok(cache) = self.helper_cache.lock()
+            && let Some((at, cached)) = cache.get(&cache_key)
+            && at.elapsed() < HELPER_CACHE_TTL
+        {
+            return cached.clone();
+        }
+
+        let runner = self.runner.as_ref()?;
+        let program = format!("docker-credential-{helper}");
+        let spec = CommandSpec {
+            program: program.clone(),
+            args: vec!["get".to_string()],
+            env: Vec::new(),
+            stdin: Some(helper_server_url(key)),
+            files: Vec::new(),
+        };
+        let found = match runner.run(spec, HELPER_TIMEOUT).await {
+            Ok(out) if out.status == 0 => match parse_helper_output(&out.stdout) {
+                Ok(c) => Some(c),
+                Err(e) => {
+                    tracing::warn!(helper = %program, registry = %key, error = %e, "credential helper output not understood");
+                    None
+                }
+            },
+            // Helpers exit non-zero with "credentials not found in native keychain".
+            Ok(out) => {
+                tracing::debug!(helper = %program, registry = %key, output = %out.stdout.trim(), "credential helper has no credentials");
+                None
+            }
+            Err(e) => {
+                tracing::warn!(helper = %program, registry = %key, error = %e, "credential helper failed");
+                return None;
+            }
+        };
+        if let Ok(mut cache) = self.helper_cache.lock() {
+            cache.insert(cache_key, (Instant::now(), found.clone()));
+        }
+        found
+    }
+}
+
+/// Server URL a helper stores a registry under; `docker login` uses the legacy index URL for
+/// Docker Hub.
+fn helper_server_url(key: &str) -> String {
+    if key == "docker.io" {
+        "https://index.docker.io/v1/".to_string()
+    } else {
+        key.to_string()
+    }
+}
+
+fn parse_helper_output(stdout: &str) -> anyhow::Result<Credentials> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct HelperOutput {
+        username: String,
+        secret: String,
+    }
+    let out: HelperOutput = serde_json::from_str(stdout).context("parse helper json")?;
+    Ok(identity_token_aware(out.username, out.secret))
+}
+
+/// Identity tokens are sent as password of the `oauth2` user, like registries expect them in the
+/// token request.
+fn identity_token_aware(username: String, secret: String) -> Credentials {
+    if username == IDENTITY_TOKEN_USERNAME {
+        Credentials {
+            username: "oauth2".to_string(),
+            password: secret,
+        }
+    } else {
+        Credentials {
+            username,
+            password: secret,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct DockerConfig {
+    auths: HashMap<String, DockerAuthEntry>,
+    creds_store: Option<String>,
+    cred_helpers: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct DockerAuthEntry {
+    auth: Option<String>,
+    #[serde(rename = "identitytoken")]
+    identity_token: Option<String>,
+}
+
+impl DockerConfig {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("read docker config {path:?}"))?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Root {
+            auths: Option<HashMap<String, DockerAuthEntry>>,
+            creds_store: Option<String>,
+            cred_helpers: Option<HashMap<String, String>>,
+        }
+        let root: Root = serde_json::from_str(text).context("parse docker config json")?;
+        let auths = root
+            .auths
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(k, v)| (normalize_auth_key(&k), v))
+            .collect();
+        let cred_helpers = root
+            .cred_helpers
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(k, v)| (normalize_auth_key(&k), v))
+            .collect();
+        Ok(Self {
+            auths,
+            creds_store: root.creds_store.filter(|s| !s.trim().is_empty()),
+            cred_helpers,
+        })
+    }
+
+    /// `credHelpers` entry of the registry, else the default `credsStore`.
+    fn helper_for(&self, key: &str) -> Option<&str> {
+        self.cred_helpers
+            .get(key)
+            .or(self.creds_store.as_ref())
+            .map(|s| s.as_str())
+    }
+
+    fn static_auth(&self, key: &str) -> Option<Credentials> {
+        let entry = self.auths.get(key)?;
+
+        if let Some(token) = entry.identity_token.as_deref() {
+            return Some(Credentials {
+                username: "oauth2".to_string(),
+                password: token.to_string(),
+            });
+        }
+
+        let auth = entry.auth.as_deref()?;
+        let decoded = BASE64.decode(auth).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (user, pass) = decoded.split_once(':')?;
+        Some(Credentials {
+            username: user.to_string(),
+            password: pass.to_string(),
+        })
+    }
+}
+
+/// `https://index.docker.io/v1/`, `registry-1.docker.io` and `docker.io` all name Docker Hub.
+pub fn normalize_auth_key(input: &str) -> String {
+    if let Ok(url) = reqwest::Url::parse(input)
+        && let Some(host) = url.host_str()
+    {
+        return match url.port() {
+            Some(port) => normalize_auth_key(&format!("{host}:{port}")),
+            None => normalize_auth_key(host),
+        };
+    }
+
+    let host = input
+        .trim()
+        .trim_end_matches('/')
+        .trim_end_matches("/v1/")
+        .trim_end_matches("/v2/")
+        .trim_end_matches("/v1")
+        .trim_end_matches("/v2")
+        .to_string();
+
+    match host.as_str() {
+        "index.docker.io" | "registry-1.docker.io" => "docker.io".to_string(),
+        _ => host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{api::types::RegistryCredential, runner::CommandOutput};
+
+    /// Fake `docker-credential-*` binaries answering from a fixed table.
+    #[derive(Default)]
+    struct HelperRunner {
+        calls: Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait]
+    impl CommandRunner for HelperRunner {
+        async fn run(
+            &self,
+            spec: CommandSpec,
+            _timeout: Duration,
+        ) -> anyhow::Result<CommandOutput> {
+            let server = spec.stdin.unwrap_or_default();
+            self.calls
+                .lock()
+                .unwrap()
+                .push((spec.program.clone(), server.clone()));
+            let (status, stdout) = match (spec.program.as_str(), server.as_str()) {
+                ("docker-credential-ecr-login", "123.dkr.ecr.eu-west-1.amazonaws.com") => (
+                    0,
+                    r#"{"ServerURL":"123.dkr.ecr.eu-west-1.amazonaws.com","Username":"AWS","Secret":"ecr-token"}"#,
+                ),
+                ("docker-credential-pass", "https://index.docker.io/v1/") => (
+                    0,
+                    r#"{"ServerURL":"https://index.docker.io/v1/","Username":"<token>","Secret":"refresh"}"#,
+                ),
+                _ => (1, "credentials not found in native keychain\n"),
+            };
+            Ok(CommandOutput {
+                status,
+                stdout: stdout.to_string(),
+                stderr: String::new(),
+            })
+        }
+    }
+
+    fn store(config: &str, runner: Arc<HelperRunner>) -> CredentialStore {
+        CredentialStore {
+            docker: Some(DockerConfig::parse(config).unwrap()),
+            ..Default::default()
+        }
+        .with_runner(runner)
+    }
+
+    fn creds(username: &str, password: &str) -> Option<Credentials> {
+        Some(Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn asks_cred_helpers_before_static_auths() {
+        let runner = Arc::new(HelperRunner::default());
+        let store = store(
+            r#"{
+              "auths": {
+                "https://index.docker.io/v1/": {},
+                "ghcr.io": { "auth": "dXNlcjpwYXNz" }
+              },
+              "credsStore": "pass",
+              "credHelpers": { "123.dkr.ecr.eu-west-1.amazonaws.com": "ecr-login" }
+            }"#,
+            runner.clone(),
+        );
+
+        assert_eq!(
+            store.get("123.dkr.ecr.eu-west-1.amazonaws.com").await,
+            creds("AWS", "ecr-token")
+        );
+        assert_eq!(store.get("docker.io").await, creds("oauth2", "refresh"));
+        // credsStore has nothing for ghcr.io; the static entry is used.
+        assert_eq!(store.get("ghcr.io").await, creds("user", "pass"));
+        assert_eq!(store.get("quay.io").await, None);
+
+        // Answers, including misses, are cached.
+        assert_eq!(store.get("ghcr.io").await, creds("user", "pass"));
+        assert_eq!(
+            *runner.calls.lock().unwrap(),
+            [
+                (
+                    "docker-credential-ecr-login".to_string(),
+                    "123.dkr.ecr.eu-west-1.amazonaws.com".to_string()
+                ),
+                (
+                    "docker-credential-pass".to_string(),
+                    "https://index.docker.io/v1/".to_string()
+                ),
+                ("docker-credential-pass".to_string(), "ghcr.io".to_string()),
+                ("docker-credential-pass".to_string(), "quay.io".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn database_credentials_take_precedence() {
+        let db = Db::open(Path::new(":memory:")).await.unwrap();
+        db.put_registry_credential(
+            &RegistryCredential {
+                registry: "ghcr.io".to_string(),
+                username: "bot".to_string(),
+                secret: Some("ghp_x".to_string()),
+                updated_at: None,
+            },
+            "2026-01-01T00:00:00Z",
+        )
+        .await
+        .unwrap();
+        let store = store(
+            r#"{ "auths": { "ghcr.io": { "auth": "dXNlcjpwYXNz" } } }"#,
+            Arc::new(HelperRunner::default()),
+        )
+        .with_db(db);
+
+        assert_eq!(store.get("ghcr.io").await, creds("bot", "ghp_x"));
+        assert_eq!(
+            store.get("https://ghcr.io/v2/").await,
+            creds("bot", "ghp_x")
+        );
+    }
+
+    #[test]
+    fn normalizes_registry_keys() {
+        assert_eq!(
+            normalize_auth_key("https://index.docker.io/v1/"),
+            "docker.io"
+        );
+        assert_eq!(normalize_auth_key("registry-1.docker.io"), "docker.io");
+        assert_eq!(
+            normalize_auth_key("http://localhost:5000/v2/"),
+            "localhost:5000"
+        );
+        assert_eq!(normalize_auth_key("ghcr.io"), "ghcr.io");
+    }
+}
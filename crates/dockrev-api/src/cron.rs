@@ -0,0 +1,242 @@
+use time::{Duration, OffsetDateTime, Time};
+
+/// Standard 5-field cron expression (`minute hour day-of-month month day-of-week`), evaluated in UTC.
+///
+/// Supports `*`, lists (`1,2`), ranges (`1-5`), steps (`*/15`, `0-30/10`) and the `@hourly`,
+/// `@daily`, `@weekly`, `@monthly` shorthands. As in Vixie cron, when both day-of-month and
+/// day-of-week are restricted a day matches if either field matches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    days_of_week: u8,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> anyhow::Result<Self> {
+        let expr = expr.trim();
+        let expanded = match expr {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+
+        let fields = expanded.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(anyhow::anyhow!(
+                "cron expression must have 5 fields (minute hour day month weekday)"
+            ));
+        }
+
+        let minutes = parse_field(fields[0], 0, 59, "minute")?;
+        let hours = parse_field(fields[1], 0, 23, "hour")?;
+        let days_of_month = parse_field(fields[2], 1, 31, "day of month")?;
+        let months = parse_field(fields[3], 1, 12, "month")?;
+        // Accept both 0 and 7 for Sunday.
+        let mut days_of_week = parse_field(fields[4], 0, 7, "day of week")?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+            days_of_week &= !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes,
+            hours: hours as u32,
+            days_of_month: days_of_month as u32,
+            months: months as u16,
+            days_of_week: days_of_week as u8,
+            dom_restricted: fields[2] != "*",
+            dow_restricted: fields[4] != "*",
+        })
+    }
+
+    /// Returns the first matching minute strictly after `after`.
+    pub fn next_after(&self, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        let after = after.to_offset(time::UtcOffset::UTC);
+        let mut t = after.replace_time(Time::from_hms(after.hour(), after.minute(), 0).ok()?)
+            + Duration::minutes(1);
+
+        // Bounded search: any valid expression matches within a few years (Feb 29 included).
+        let limit = after + Duration::days(366 * 5);
+        while t <= limit {
+            if !self.month_matches(t) {
+                let (year, month) = if u8::from(t.month()) == 12 {
+                    (t.year() + 1, time::Month::January)
+                } else {
+                    (t.year(), t.month().next())
+                };
+                let date = time::Date::from_calendar_date(year, month, 1).ok()?;
+                t = date.midnight().assume_utc();
+                continue;
+            }
+            if !self.day_matches(t) {
+                t = (t.date() + Duration::days(1)).midnight().assume_utc();
+                continue;
+            }
+            if self.hours & (1 << t.hour()) == 0 {
+                t = t.replace_time(Time::from_hms(t.hour(), 0, 0).ok()?) + Duration::hours(1);
+                continue;
+            }
+            if self.minutes & (1 << t.minute()) == 0 {
+                t += Duration::minutes(1);
+                continue;
+            }
+            return Some(t);
+        }
+        None
+    }
+
+    fn month_matches(&self, t: OffsetDateTime) -> bool {
+        self.months & (1 << u8::from(t.month())) != 0
+    }
+
+    fn day_matches(&self, t: OffsetDateTime) -> bool {
+        let dom = self.days_of_month & (1 << t.day()) != 0;
+        let dow = self.days_of_week & (1 << t.weekday().number_days_from_sunday()) != 0;
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> anyhow::Result<u64> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step =
+                    step.parse::<u32>().ok().filter(|s| *s > 0).ok_or_else(|| {
+                        anyhow::anyhow!("invalid step in cron {name} field: {part}")
+                    })?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (
+                parse_value(a, min, max, name)?,
+                parse_value(b, min, max, name)?,
+            )
+        } else {
+            let v = parse_value(range, min, max, name)?;
+            // `5/10` means "from 5 to max every 10", like most cron implementations.
+            if part.contains('/') { (v, max) } else { (v, v) }
+        };
+
+        if start > end {
+            return Err(anyhow::anyhow!(
+                "invalid range in cron {name} field: {part}"
+            ));
+        }
+
+        let mut v = start;
+        while v <= end {
+            mask |= 1 << v;
+            v += step;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(input: &str, min: u32, max: u32, name: &str) -> anyhow::Result<u32> {
+    let v = input
+        .parse::<u32>()
+        .map_err(|_| anyhow::anyhow!("invalid value in cron {name} field: {input}"))?;
+    if v < min || v > max {
+        return Err(anyhow::anyhow!(
+            "cron {name} value out of range ({min}-{max}): {v}"
+        ));
+    }
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::format_description::well_known::Rfc3339;
+
+    fn at(s: &str) -> OffsetDateTime {
+        OffsetDateTime::parse(s, &Rfc3339).unwrap()
+    }
+
+    fn next(expr: &str, after: &str) -> String {
+        CronSchedule::parse(expr)
+            .unwrap()
+            .next_after(at(after))
+            .unwrap()
+            .format(&Rfc3339)
+            .unwrap()
+    }
+
+    #[test]
+    fn every_six_hours() {
+        assert_eq!(
+            next("0 */6 * * *", "2026-01-10T05:59:30Z"),
+            "2026-01-10T06:00:00Z"
+        );
+        assert_eq!(
+            next("0 */6 * * *", "2026-01-10T06:00:00Z"),
+            "2026-01-10T12:00:00Z"
+        );
+    }
+
+    #[test]
+    fn rolls_over_month_and_year() {
+        assert_eq!(
+            next("30 2 1 * *", "2026-12-15T00:00:00Z"),
+            "2027-01-01T02:30:00Z"
+        );
+    }
+
+    #[test]
+    fn weekday_and_sunday_aliases() {
+        // 2026-01-10 is a Saturday.
+        assert_eq!(
+            next("0 3 * * 1-5", "2026-01-10T00:00:00Z"),
+            "2026-01-12T03:00:00Z"
+        );
+        assert_eq!(
+            next("0 0 * * 7", "2026-01-10T00:00:00Z"),
+            "2026-01-11T00:00:00Z"
+        );
+        assert_eq!(
+            next("@weekly", "2026-01-10T00:00:00Z"),
+            "2026-01-11T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn dom_and_dow_are_ored_when_both_restricted() {
+        assert_eq!(
+            next("0 0 13 * 5", "2026-01-10T00:00:00Z"),
+            "2026-01-13T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("a * * * *").is_err());
+    }
+
+    #[test]
+    fn impossible_date_has_no_next_run() {
+        let s = CronSchedule::parse("0 0 31 2 *").unwrap();
+        assert!(s.next_after(at("2026-01-01T00:00:00Z")).is_none());
+    }
+}
@@ -8,12 +8,20 @@ use anyhow::Context as _;
 use rusqlite::{OptionalExtension as _, TransactionBehavior, params};
 use tokio_rusqlite::Connection;
 
-use crate::api::types::{
-    BackupSettings, ComposeConfig, ComposeRef, IgnoreRule, IgnoreRuleMatch, IgnoreRuleScope,
-    JobListItem, JobLogLine, JobScope, JobType, NotificationSettings, ServiceSettings,
-    StackListItem, StackRecord, StackStatus,
+use crate::{
+    api::types::{
+        AutoUpdatePolicy, AutoUpdateSettings, BackupSettings, CheckSchedule, ComposeConfig,
+        ComposeRef, HostRecord, IgnoreRule, IgnoreRuleMatch, IgnoreRuleScope, JobListItem,
+        JobLogLine, JobScope, JobType, MaintenanceWindow, MaintenanceWindowRecord,
+        NotificationSettings, PrereleasePolicy, RegistryCredential, SecurityPreference,
+        ServiceSettings, StackListItem, StackRecord, StackStatus, VersionStrategy,
+        VulnerabilityCounts, VulnerabilityScan,
+    },
+    events::{Event, EventBus},
 };
 
+pub const GLOBAL_CHECK_SCHEDULE_ID: &str = "global";
+
 #[derive(Clone, Debug)]
 pub struct BackupCleanupItem {
     pub id: String,
@@ -22,6 +30,35 @@ pub struct BackupCleanupItem {
     pub artifact_path: String,
 }
 
+#[derive(Clone, Debug)]
+pub struct CheckScheduleRecord {
+    pub id: String,
+    pub stack_id: Option<String>,
+    pub cron: String,
+    pub enabled: bool,
+    pub next_run_at: Option<String>,
+    pub last_run_at: Option<String>,
+    pub last_job_id: Option<String>,
+}
+
+impl CheckScheduleRecord {
+    pub fn into_api(self) -> CheckSchedule {
+        CheckSchedule {
+            cron: self.cron,
+            enabled: self.enabled,
+            next_run_at: self.next_run_at,
+            last_run_at: self.last_run_at,
+            last_job_id: self.last_job_id,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct QueuedJob {
+    pub id: String,
+    pub request_json: serde_json::Value,
+}
+
 #[derive(Clone, Debug)]
 pub struct ComposeServiceSpec {
     pub name: String,
@@ -35,6 +72,10 @@ pub struct ServiceForCheck {
     pub name: String,
     pub image_ref: String,
     pub image_tag: String,
+    pub archived: bool,
+    pub auto_update: AutoUpdateSettings,
+    pub version_strategy: VersionStrategy,
+    pub prerelease_policy: Option<PrereleasePolicy>,
 }
 
 #[derive(Clone, Debug)]
@@ -44,6 +85,7 @@ pub struct DiscoveredComposeProjectRecord {
 
 #[derive(Clone, Debug)]
 pub struct DiscoveredComposeProjectUpsert {
+    pub host_id: String,
     pub project: String,
     pub stack_id: Option<String>,
     pub status: String,
@@ -54,9 +96,28 @@ pub struct DiscoveredComposeProjectUpsert {
     pub unarchive_if_active: bool,
 }
 
+/// Cached tag list of a repository; `etag` is only kept for single-page listings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegistryTagsCacheEntry {
+    pub tags: Vec<String>,
+    pub etag: Option<String>,
+    pub fetched_at_unix: i64,
+}
+
+/// Cached manifest lookup for one platform. `content_digest` is the digest of the manifest
+/// document itself and is compared against `HEAD` responses to revalidate the entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegistryManifestCacheEntry {
+    pub content_digest: Option<String>,
+    pub digest: Option<String>,
+    pub arch: Vec<String>,
+    pub fetched_at_unix: i64,
+}
+
 #[derive(Clone)]
 pub struct Db {
     conn: Connection,
+    events: EventBus,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -81,7 +142,10 @@ impl Db {
         let path = ensure_parent_dir(path)?;
         let conn = Connection::open(path).await?;
 
-        let db = Self { conn };
+        let db = Self {
+            conn,
+            events: EventBus::default(),
+        };
         db.init().await?;
         db.ensure_defaults().await?;
         Ok(db)
@@ -114,10 +178,14 @@ impl Db {
             ensure_service_columns(conn)?;
             ensure_notification_columns(conn)?;
             ensure_stack_archive_columns(conn)?;
+            ensure_stack_host_columns(conn)?;
+            ensure_host_columns(conn)?;
             ensure_service_archive_columns(conn)?;
+            ensure_job_columns(conn)?;
             ensure_discovery_schema(conn)?;
             ensure_schema_migrations_table(conn)?;
             apply_migration_0007_remove_manual_stacks(conn)?;
+            apply_migration_0010_discovery_host_id(conn)?;
             auto_archive_missing_discovery_projects_on_startup(conn)?;
             Ok(())
         })
@@ -175,6 +243,14 @@ INSERT OR IGNORE INTO notification_settings (
                 ],
             )?;
 
+            tx.execute(
+                r#"
+INSERT OR IGNORE INTO check_schedules (id, stack_id, cron, enabled, updated_at)
+VALUES (?1, NULL, ?2, 0, ?3)
+"#,
+                params![GLOBAL_CHECK_SCHEDULE_ID, "0 */6 * * *", now_rfc3339()?],
+            )?;
+
             tx.commit()?;
             Ok(())
         })
@@ -182,10 +258,17 @@ INSERT OR IGNORE INTO notification_settings (
         Ok(())
     }
 
+    /// Subscribes to change events published by writes through this handle (and its clones).
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
     pub async fn list_stacks(
         &self,
         archived: ArchivedFilter,
+        host_id: Option<&str>,
     ) -> anyhow::Result<Vec<StackListItem>> {
+        let host_id = host_id.map(|s| s.to_string());
         self.call(move |conn| {
             let filter_clause = archived.where_clause("s.archived");
             let sql = format!(
@@ -195,6 +278,7 @@ SELECT
   s.name,
   s.last_check_at,
   s.archived,
+  s.host_id,
   (SELECT COUNT(1) FROM services sv WHERE sv.stack_id = s.id) AS services,
   (SELECT COUNT(1) FROM services sv WHERE sv.stack_id = s.id AND sv.archived = 1) AS archived_services,
   (
@@ -207,23 +291,24 @@ SELECT
       AND sv.candidate_arch_match = 'match'
   ) AS updates
 FROM stacks s
-WHERE 1=1
+WHERE (?1 IS NULL OR s.host_id = ?1)
 {filter_clause}
 ORDER BY s.created_at DESC
 "#,
             );
             let mut stmt = conn.prepare(&sql)?;
 
-            let rows = stmt.query_map([], |row| {
+            let rows = stmt.query_map(params![host_id], |row| {
                 Ok(StackListItem {
                     id: row.get(0)?,
                     name: row.get(1)?,
+                    host_id: row.get(4)?,
                     status: StackStatus::Unknown,
                     last_check_at: row.get(2)?,
                     archived: Some(row.get::<_, i64>(3)? != 0),
-                    services: row.get::<_, i64>(4)? as u32,
-                    archived_services: Some(row.get::<_, i64>(5)? as u32),
-                    updates: row.get::<_, i64>(6)? as u32,
+                    services: row.get::<_, i64>(5)? as u32,
+                    archived_services: Some(row.get::<_, i64>(6)? as u32),
+                    updates: row.get::<_, i64>(7)? as u32,
                 })
             })?;
 
@@ -248,7 +333,8 @@ SELECT
   backup_targets_json,
   backup_retention_keep_last,
   backup_retention_delete_after_stable_seconds,
-  archived
+  archived,
+  host_id
 FROM stacks
 WHERE id = ?1
 "#,
@@ -278,6 +364,7 @@ WHERE id = ?1
                         Ok(StackRecord {
                             id: row.get(0)?,
                             name: row.get(1)?,
+                            host_id: row.get(9)?,
                             archived: row.get::<_, i64>(8)? != 0,
                             compose: ComposeConfig {
                                 kind: row.get(2)?,
@@ -320,7 +407,14 @@ WHERE id = ?1
 	  auto_rollback,
 	  archived,
 	  backup_targets_bind_paths_json,
-	  backup_targets_volume_names_json
+	  backup_targets_volume_names_json,
+	  auto_update_policy,
+	  auto_update_window_json,
+	  candidate_release_notes_json,
+	  candidate_signature_json,
+	  auto_update_security,
+	  version_strategy_json,
+	  prerelease_policy
 	FROM services
 	WHERE stack_id = ?1
 	ORDER BY name ASC
@@ -363,6 +457,13 @@ WHERE id = ?1
                     .and_then(|s| serde_json::from_str::<Vec<String>>(s).ok())
                     .and_then(|v| if v.is_empty() { None } else { Some(v) });
 
+                let candidate_release_notes = row
+                    .get::<_, Option<String>>(19)?
+                    .and_then(|s| serde_json::from_str(&s).ok());
+                let candidate_signature = row
+                    .get::<_, Option<String>>(20)?
+                    .and_then(|s| serde_json::from_str(&s).ok());
+
                 let candidate_arch: Vec<String> = candidate_arch_json
                     .as_deref()
                     .and_then(|s| serde_json::from_str::<Vec<String>>(s).ok())
@@ -376,6 +477,8 @@ WHERE id = ?1
                             candidate_arch_match.as_deref().unwrap_or("unknown"),
                         ),
                         arch: candidate_arch,
+                        release_notes: candidate_release_notes,
+                        signature: candidate_signature,
                     }),
                     _ => None,
                 };
@@ -389,12 +492,14 @@ WHERE id = ?1
                     _ => None,
                 };
 
+                let tag: String = row.get(3)?;
                 stack.services.push(crate::api::types::Service {
                     id: row.get(0)?,
                     name: row.get(1)?,
                     image: ComposeRef {
                         reference: row.get(2)?,
-                        tag: row.get(3)?,
+                        variant: crate::ignore::split_variant(&tag).1.map(str::to_string),
+                        tag,
                         digest: row.get(4)?,
                         resolved_tag: current_resolved_tag,
                         resolved_tags: current_resolved_tags,
@@ -407,6 +512,12 @@ WHERE id = ?1
                             bind_paths,
                             volume_names,
                         },
+                        auto_update: auto_update_from_row(row, 17, 18, 21)?,
+                        version_strategy: version_strategy_from_row(row, 22)?,
+                        prerelease_policy: row
+                            .get::<_, Option<String>>(23)?
+                            .as_deref()
+                            .and_then(PrereleasePolicy::parse),
                     },
                     archived: Some(row.get::<_, i64>(14)? != 0),
                 });
@@ -425,6 +536,7 @@ WHERE id = ?1
         now: &str,
     ) -> anyhow::Result<()> {
         let stack = stack.clone();
+        let stack_id = stack.id.clone();
         let services = services.to_vec();
         let now = now.to_string();
         self.call(move |conn| {
@@ -443,8 +555,9 @@ INSERT INTO stacks (
   backup_retention_delete_after_stable_seconds,
   created_at,
   updated_at,
-  last_check_at
-) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+  last_check_at,
+  host_id
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
 "#,
                 params![
                     stack.id,
@@ -457,7 +570,8 @@ INSERT INTO stacks (
                     stack.backup.retention.delete_after_stable_seconds as i64,
                     now,
                     now,
-                    now
+                    now,
+                    stack.host_id
                 ],
             )?;
 
@@ -496,7 +610,9 @@ INSERT INTO services (
             Ok(())
         })
         .await
-        .context("insert stack")
+        .context("insert stack")?;
+        self.events.publish(Event::Stack { stack_id });
+        Ok(())
     }
 
     pub async fn update_stack_last_check_at(
@@ -506,6 +622,9 @@ INSERT INTO services (
     ) -> anyhow::Result<()> {
         let stack_id = stack_id.to_string();
         let now = now.to_string();
+        let event = Event::Stack {
+            stack_id: stack_id.clone(),
+        };
         self.call(move |conn| {
             conn.execute(
                 "UPDATE stacks SET last_check_at = ?2, updated_at = ?2 WHERE id = ?1",
@@ -514,6 +633,7 @@ INSERT INTO services (
             Ok(())
         })
         .await?;
+        self.events.publish(event);
         Ok(())
     }
 
@@ -527,30 +647,38 @@ INSERT INTO services (
         let stack_id = stack_id.to_string();
         let now = now.to_string();
         let reason = reason.map(|s| s.to_string());
-        self.call(move |conn| {
-            let changed = if archived {
-                conn.execute(
-                    r#"
+        let event = Event::Stack {
+            stack_id: stack_id.clone(),
+        };
+        let changed = self
+            .call(move |conn| {
+                let changed = if archived {
+                    conn.execute(
+                        r#"
 UPDATE stacks
 SET archived = 1, archived_at = ?2, archived_reason = ?3, updated_at = ?2
 WHERE id = ?1
 "#,
-                    params![stack_id, now, reason],
-                )?
-            } else {
-                conn.execute(
-                    r#"
+                        params![stack_id, now, reason],
+                    )?
+                } else {
+                    conn.execute(
+                        r#"
 UPDATE stacks
 SET archived = 0, archived_at = NULL, archived_reason = NULL, updated_at = ?2
 WHERE id = ?1
 "#,
-                    params![stack_id, now],
-                )?
-            };
-            Ok(changed > 0)
-        })
-        .await
-        .context("set stack archived")
+                        params![stack_id, now],
+                    )?
+                };
+                Ok(changed > 0)
+            })
+            .await
+            .context("set stack archived")?;
+        if changed {
+            self.events.publish(event);
+        }
+        Ok(changed)
     }
 
     pub async fn set_service_archived(
@@ -563,30 +691,38 @@ WHERE id = ?1
         let service_id = service_id.to_string();
         let now = now.to_string();
         let reason = reason.map(|s| s.to_string());
-        self.call(move |conn| {
-            let changed = if archived {
-                conn.execute(
-                    r#"
+        let event = Event::Service {
+            service_id: service_id.clone(),
+        };
+        let changed = self
+            .call(move |conn| {
+                let changed = if archived {
+                    conn.execute(
+                        r#"
 UPDATE services
 SET archived = 1, archived_at = ?2, archived_reason = ?3, updated_at = ?2
 WHERE id = ?1
 "#,
-                    params![service_id, now, reason],
-                )?
-            } else {
-                conn.execute(
-                    r#"
+                        params![service_id, now, reason],
+                    )?
+                } else {
+                    conn.execute(
+                        r#"
 UPDATE services
 SET archived = 0, archived_at = NULL, archived_reason = NULL, updated_at = ?2
 WHERE id = ?1
 "#,
-                    params![service_id, now],
-                )?
-            };
-            Ok(changed > 0)
-        })
-        .await
-        .context("set service archived")
+                        params![service_id, now],
+                    )?
+                };
+                Ok(changed > 0)
+            })
+            .await
+            .context("set service archived")?;
+        if changed {
+            self.events.publish(event);
+        }
+        Ok(changed)
     }
 
     pub async fn sync_stack_from_compose(
@@ -600,6 +736,9 @@ WHERE id = ?1
         let compose_files = compose_files.to_vec();
         let services = services.to_vec();
         let now = now.to_string();
+        let event = Event::Stack {
+            stack_id: stack_id.clone(),
+        };
         self.call(move |conn| {
             let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
 
@@ -642,6 +781,8 @@ SET
   candidate_digest = NULL,
   candidate_arch_match = NULL,
   candidate_arch_json = NULL,
+  candidate_release_notes_json = NULL,
+  candidate_signature_json = NULL,
   ignore_rule_id = NULL,
   ignore_reason = NULL,
   checked_at = NULL,
@@ -707,7 +848,9 @@ INSERT INTO services (
             Ok(())
         })
         .await
-        .context("sync stack from compose")
+        .context("sync stack from compose")?;
+        self.events.publish(event);
+        Ok(())
     }
 
     pub async fn list_services_for_check(
@@ -718,7 +861,17 @@ INSERT INTO services (
         self.call(move |conn| {
             let mut stmt = conn.prepare(
                 r#"
-SELECT id, name, image_ref, image_tag
+SELECT
+  id,
+  name,
+  image_ref,
+  image_tag,
+  archived,
+  auto_update_policy,
+  auto_update_window_json,
+  auto_update_security,
+  version_strategy_json,
+  prerelease_policy
 FROM services
 WHERE stack_id = ?1
 ORDER BY name ASC
@@ -730,6 +883,13 @@ ORDER BY name ASC
                     name: row.get(1)?,
                     image_ref: row.get(2)?,
                     image_tag: row.get(3)?,
+                    archived: row.get::<_, i64>(4)? != 0,
+                    auto_update: auto_update_from_row(row, 5, 6, 7)?,
+                    version_strategy: version_strategy_from_row(row, 8)?,
+                    prerelease_policy: row
+                        .get::<_, Option<String>>(9)?
+                        .as_deref()
+                        .and_then(PrereleasePolicy::parse),
                 })
             })?;
             Ok(rows.collect::<Result<Vec<_>, _>>()?)
@@ -765,6 +925,21 @@ LIMIT 1
         .context("get stack compose project")
     }
 
+    pub async fn get_stack_host_id(&self, stack_id: &str) -> anyhow::Result<Option<String>> {
+        let stack_id = stack_id.to_string();
+        self.call(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT host_id FROM stacks WHERE id = ?1",
+                    params![stack_id],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?)
+        })
+        .await
+        .context("get stack host id")
+    }
+
     pub async fn get_service_stack_id(&self, service_id: &str) -> anyhow::Result<Option<String>> {
         let service_id = service_id.to_string();
         self.call(move |conn| {
@@ -869,8 +1044,10 @@ WHERE id = ?1
 
     pub async fn get_discovered_compose_project(
         &self,
+        host_id: &str,
         project: &str,
     ) -> anyhow::Result<Option<DiscoveredComposeProjectRecord>> {
+        let host_id = host_id.to_string();
         let project = project.to_string();
         self.call(move |conn| {
             Ok(conn
@@ -878,9 +1055,9 @@ WHERE id = ?1
                     r#"
 SELECT stack_id
 FROM discovered_compose_projects
-WHERE project = ?1
+WHERE host_id = ?1 AND project = ?2
 "#,
-                    params![project],
+                    params![host_id, project],
                     |row| {
                         Ok(DiscoveredComposeProjectRecord {
                             stack_id: row.get(0)?,
@@ -917,9 +1094,10 @@ INSERT INTO discovered_compose_projects (
   last_config_files_json,
   archived,
   archived_at,
-  archived_reason
-) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
-ON CONFLICT(project) DO UPDATE SET
+  archived_reason,
+  host_id
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+ON CONFLICT(host_id, project) DO UPDATE SET
   stack_id = COALESCE(excluded.stack_id, discovered_compose_projects.stack_id),
   status = excluded.status,
   last_seen_at = COALESCE(excluded.last_seen_at, discovered_compose_projects.last_seen_at),
@@ -937,7 +1115,8 @@ ON CONFLICT(project) DO UPDATE SET
                     last_config_files_json,
                     0i64,
                     Option::<String>::None,
-                    Option::<String>::None
+                    Option::<String>::None,
+                    input.host_id
                 ],
             )?;
 
@@ -946,9 +1125,9 @@ ON CONFLICT(project) DO UPDATE SET
                     r#"
 UPDATE discovered_compose_projects
 SET archived = 0, archived_at = NULL, archived_reason = NULL
-WHERE project = ?1
+WHERE host_id = ?1 AND project = ?2
 "#,
-                    params![input.project],
+                    params![input.host_id, input.project],
                 )?;
             }
 
@@ -961,59 +1140,47 @@ WHERE project = ?1
 
     pub async fn mark_discovered_compose_projects_missing_except(
         &self,
+        host_id: &str,
         seen_projects: &[String],
         now: &str,
     ) -> anyhow::Result<Vec<String>> {
+        let host_id = host_id.to_string();
         let seen_projects = seen_projects.to_vec();
         let now = now.to_string();
         self.call(move |conn| {
             let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
 
-            let newly_missing = if seen_projects.is_empty() {
-                let mut stmt = tx.prepare(
-                    r#"
-	SELECT project
-	FROM discovered_compose_projects
-	WHERE status != 'missing'
-	"#,
-                )?;
-                let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
-                let newly_missing = rows.collect::<Result<Vec<_>, _>>()?;
-                tx.execute(
-                    r#"
-	UPDATE discovered_compose_projects
-	SET status = 'missing', last_scan_at = ?1
-	WHERE status != 'missing'
-	"#,
-                    params![now],
-                )?;
-                newly_missing
-            } else {
-                let placeholders = seen_projects.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-                let sql_select = format!(
-                    "SELECT project FROM discovered_compose_projects WHERE status != 'missing' AND project NOT IN ({placeholders})"
-                );
-                let mut params: Vec<&dyn rusqlite::ToSql> =
-                    Vec::with_capacity(seen_projects.len());
-                for p in &seen_projects {
-                    params.push(p);
-                }
-                let mut stmt = tx.prepare(&sql_select)?;
-                let rows = stmt.query_map(params.as_slice(), |row| row.get::<_, String>(0))?;
-                let newly_missing = rows.collect::<Result<Vec<_>, _>>()?;
+            let placeholders = seen_projects
+                .iter()
+                .map(|_| ", ?")
+                .collect::<String>();
+            // `''` keeps `NOT IN` valid when nothing was seen; project names are never empty.
+            let not_seen = format!("project NOT IN (''{placeholders})");
 
-                let sql_update = format!(
-                    "UPDATE discovered_compose_projects SET status = 'missing', last_scan_at = ? WHERE status != 'missing' AND project NOT IN ({placeholders})"
-                );
-                let mut params2: Vec<&dyn rusqlite::ToSql> =
-                    Vec::with_capacity(1 + seen_projects.len());
-                params2.push(&now);
-                for p in &seen_projects {
-                    params2.push(p);
-                }
-                tx.execute(&sql_update, params2.as_slice())?;
-                newly_missing
-            };
+            let sql_select = format!(
+                "SELECT project FROM discovered_compose_projects WHERE host_id = ? AND status != 'missing' AND {not_seen}"
+            );
+            let mut params: Vec<&dyn rusqlite::ToSql> = Vec::with_capacity(1 + seen_projects.len());
+            params.push(&host_id);
+            for p in &seen_projects {
+                params.push(p);
+            }
+            let mut stmt = tx.prepare(&sql_select)?;
+            let rows = stmt.query_map(params.as_slice(), |row| row.get::<_, String>(0))?;
+            let newly_missing = rows.collect::<Result<Vec<_>, _>>()?;
+            drop(stmt);
+
+            let sql_update = format!(
+                "UPDATE discovered_compose_projects SET status = 'missing', last_scan_at = ? WHERE host_id = ? AND status != 'missing' AND {not_seen}"
+            );
+            let mut params2: Vec<&dyn rusqlite::ToSql> =
+                Vec::with_capacity(2 + seen_projects.len());
+            params2.push(&now);
+            params2.push(&host_id);
+            for p in &seen_projects {
+                params2.push(p);
+            }
+            tx.execute(&sql_update, params2.as_slice())?;
 
             tx.commit()?;
             Ok(newly_missing)
@@ -1022,10 +1189,37 @@ WHERE project = ?1
         .context("mark discovered compose projects missing")
     }
 
+    /// Marks one project missing; returns false if it is unknown or already missing.
+    pub async fn mark_discovered_compose_project_missing(
+        &self,
+        host_id: &str,
+        project: &str,
+        now: &str,
+    ) -> anyhow::Result<bool> {
+        let host_id = host_id.to_string();
+        let project = project.to_string();
+        let now = now.to_string();
+        self.call(move |conn| {
+            let changed = conn.execute(
+                r#"
+UPDATE discovered_compose_projects
+SET status = 'missing', last_scan_at = ?3
+WHERE host_id = ?1 AND project = ?2 AND status != 'missing'
+"#,
+                params![host_id, project, now],
+            )?;
+            Ok(changed > 0)
+        })
+        .await
+        .context("mark discovered compose project missing")
+    }
+
     pub async fn list_discovered_compose_projects(
         &self,
         archived: ArchivedFilter,
+        host_id: Option<&str>,
     ) -> anyhow::Result<Vec<crate::api::types::DiscoveredProject>> {
+        let host_id = host_id.map(|s| s.to_string());
         self.call(move |conn| {
             let filter_clause = archived.where_clause("d.archived");
             let sql = format!(
@@ -1038,21 +1232,23 @@ SELECT
   d.last_seen_at,
   d.last_scan_at,
   d.last_error,
-  d.archived
+  d.archived,
+  d.host_id
 FROM discovered_compose_projects d
-WHERE 1=1
+WHERE (?1 IS NULL OR d.host_id = ?1)
 {filter_clause}
-ORDER BY d.project ASC
+ORDER BY d.host_id ASC, d.project ASC
 "#
             );
             let mut stmt = conn.prepare(&sql)?;
-            let rows = stmt.query_map([], |row| {
+            let rows = stmt.query_map(params![host_id], |row| {
                 let config_files_json: Option<String> = row.get(3)?;
                 let config_files = config_files_json
                     .as_deref()
                     .and_then(|s| serde_json::from_str::<Vec<String>>(s).ok());
 
                 Ok(crate::api::types::DiscoveredProject {
+                    host_id: row.get(8)?,
                     project: row.get(0)?,
                     status: crate::api::types::DiscoveredProjectStatus::from_str(
                         row.get::<_, String>(1)?.as_str(),
@@ -1073,11 +1269,13 @@ ORDER BY d.project ASC
 
     pub async fn set_discovered_compose_project_archived(
         &self,
+        host_id: &str,
         project: &str,
         archived: bool,
         reason: Option<&str>,
         now: &str,
     ) -> anyhow::Result<bool> {
+        let host_id = host_id.to_string();
         let project = project.to_string();
         let now = now.to_string();
         let reason = reason.map(|s| s.to_string());
@@ -1086,19 +1284,19 @@ ORDER BY d.project ASC
                 conn.execute(
                     r#"
 UPDATE discovered_compose_projects
-SET archived = 1, archived_at = ?2, archived_reason = ?3
-WHERE project = ?1
+SET archived = 1, archived_at = ?3, archived_reason = ?4
+WHERE host_id = ?1 AND project = ?2
 "#,
-                    params![project, now, reason],
+                    params![host_id, project, now, reason],
                 )?
             } else {
                 conn.execute(
                     r#"
 UPDATE discovered_compose_projects
 SET archived = 0, archived_at = NULL, archived_reason = NULL
-WHERE project = ?1
+WHERE host_id = ?1 AND project = ?2
 "#,
-                    params![project],
+                    params![host_id, project],
                 )?
             };
             Ok(changed > 0)
@@ -1153,6 +1351,8 @@ ORDER BY created_at DESC
         candidate_digest: Option<String>,
         candidate_arch_match: Option<String>,
         candidate_arch_json: Option<String>,
+        candidate_release_notes_json: Option<String>,
+        candidate_signature_json: Option<String>,
         ignore_rule_id: Option<String>,
         ignore_reason: Option<String>,
         checked_at: &str,
@@ -1161,9 +1361,13 @@ ORDER BY created_at DESC
         let service_id = service_id.to_string();
         let checked_at = checked_at.to_string();
         let now = now.to_string();
-        self.call(move |conn| {
-            let changed = conn.execute(
-                r#"
+        let event = Event::Service {
+            service_id: service_id.clone(),
+        };
+        let changed = self
+            .call(move |conn| {
+                let changed = conn.execute(
+                    r#"
 UPDATE services
 SET
   current_digest = ?2,
@@ -1173,31 +1377,39 @@ SET
   candidate_digest = ?6,
   candidate_arch_match = ?7,
   candidate_arch_json = ?8,
-  ignore_rule_id = ?9,
-  ignore_reason = ?10,
-  checked_at = ?11,
-  updated_at = ?12
+  candidate_release_notes_json = ?9,
+  candidate_signature_json = ?10,
+  ignore_rule_id = ?11,
+  ignore_reason = ?12,
+  checked_at = ?13,
+  updated_at = ?14
 WHERE id = ?1
 "#,
-                params![
-                    service_id,
-                    current_digest,
-                    current_resolved_tag,
-                    current_resolved_tags_json,
-                    candidate_tag,
-                    candidate_digest,
-                    candidate_arch_match,
-                    candidate_arch_json,
-                    ignore_rule_id,
-                    ignore_reason,
-                    checked_at,
-                    now
-                ],
-            )?;
-            Ok(changed > 0)
-        })
-        .await
-        .context("update service check result")
+                    params![
+                        service_id,
+                        current_digest,
+                        current_resolved_tag,
+                        current_resolved_tags_json,
+                        candidate_tag,
+                        candidate_digest,
+                        candidate_arch_match,
+                        candidate_arch_json,
+                        candidate_release_notes_json,
+                        candidate_signature_json,
+                        ignore_rule_id,
+                        ignore_reason,
+                        checked_at,
+                        now
+                    ],
+                )?;
+                Ok(changed > 0)
+            })
+            .await
+            .context("update service check result")?;
+        if changed {
+            self.events.publish(event);
+        }
+        Ok(changed)
     }
 
     pub async fn get_service_settings(
@@ -1212,7 +1424,12 @@ WHERE id = ?1
 SELECT
   auto_rollback,
   backup_targets_bind_paths_json,
-  backup_targets_volume_names_json
+  backup_targets_volume_names_json,
+  auto_update_policy,
+  auto_update_window_json,
+  auto_update_security,
+  version_strategy_json,
+  prerelease_policy
 FROM services
 WHERE id = ?1
 "#,
@@ -1241,6 +1458,12 @@ WHERE id = ?1
                                 bind_paths,
                                 volume_names,
                             },
+                            auto_update: auto_update_from_row(row, 3, 4, 5)?,
+                            version_strategy: version_strategy_from_row(row, 6)?,
+                            prerelease_policy: row
+                                .get::<_, Option<String>>(7)?
+                                .as_deref()
+                                .and_then(PrereleasePolicy::parse),
                         })
                     },
                 )
@@ -1259,29 +1482,54 @@ WHERE id = ?1
         let service_id = service_id.to_string();
         let settings = settings.clone();
         let now = now.to_string();
-        self.call(move |conn| {
-            let changed = conn.execute(
-                r#"
+        let event = Event::Service {
+            service_id: service_id.clone(),
+        };
+        let changed = self
+            .call(move |conn| {
+                let changed = conn.execute(
+                    r#"
 UPDATE services
 SET
   auto_rollback = ?2,
   backup_targets_bind_paths_json = ?3,
   backup_targets_volume_names_json = ?4,
-  updated_at = ?5
+  auto_update_policy = ?5,
+  auto_update_window_json = ?6,
+  auto_update_security = ?7,
+  version_strategy_json = ?8,
+  prerelease_policy = ?9,
+  updated_at = ?10
 WHERE id = ?1
 "#,
-                params![
-                    service_id,
-                    settings.auto_rollback as i64,
-                    serde_json::to_string(&settings.backup_targets.bind_paths)?,
-                    serde_json::to_string(&settings.backup_targets.volume_names)?,
-                    now
-                ],
-            )?;
-            Ok(changed > 0)
-        })
-        .await
-        .context("put service settings")
+                    params![
+                        service_id,
+                        settings.auto_rollback as i64,
+                        serde_json::to_string(&settings.backup_targets.bind_paths)?,
+                        serde_json::to_string(&settings.backup_targets.volume_names)?,
+                        settings.auto_update.policy.as_str(),
+                        settings
+                            .auto_update
+                            .window
+                            .as_ref()
+                            .map(serde_json::to_string)
+                            .transpose()?,
+                        settings.auto_update.security.as_str(),
+                        (settings.version_strategy != VersionStrategy::Semver)
+                            .then(|| serde_json::to_string(&settings.version_strategy))
+                            .transpose()?,
+                        settings.prerelease_policy.as_ref().map(|p| p.as_str()),
+                        now
+                    ],
+                )?;
+                Ok(changed > 0)
+            })
+            .await
+            .context("put service settings")?;
+        if changed {
+            self.events.publish(event);
+        }
+        Ok(changed)
     }
 
     pub async fn list_ignore_rules(&self) -> anyhow::Result<Vec<IgnoreRule>> {
@@ -1563,6 +1811,10 @@ WHERE id = 'default'
     }
 
     pub async fn insert_job(&self, job: JobListItem) -> anyhow::Result<()> {
+        let event = Event::JobStatus {
+            job_id: job.id.clone(),
+            status: job.status.clone(),
+        };
         self.call(move |conn| {
             conn.execute(
                 r#"
@@ -1580,8 +1832,15 @@ INSERT INTO jobs (
   created_at,
   started_at,
   finished_at,
-  summary_json
-) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+  summary_json,
+  host_id
+) VALUES (
+  ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14,
+  COALESCE(
+    (SELECT host_id FROM stacks WHERE id = ?4),
+    (SELECT st.host_id FROM services sv JOIN stacks st ON st.id = sv.stack_id WHERE sv.id = ?5)
+  )
+)
 "#,
                 params![
                     job.id,
@@ -1603,7 +1862,9 @@ INSERT INTO jobs (
             Ok(())
         })
         .await
-        .context("insert job")
+        .context("insert job")?;
+        self.events.publish(event);
+        Ok(())
     }
 
     pub async fn finish_job(
@@ -1617,6 +1878,10 @@ INSERT INTO jobs (
         let status = status.to_string();
         let finished_at = finished_at.to_string();
         let summary_json = serde_json::to_string(summary_json)?;
+        let event = Event::JobStatus {
+            job_id: job_id.clone(),
+            status: status.clone(),
+        };
         self.call(move |conn| {
             conn.execute(
                 r#"
@@ -1629,11 +1894,140 @@ WHERE id = ?1
             Ok(())
         })
         .await
-        .context("finish job")
+        .context("finish job")?;
+        self.events.publish(event);
+        Ok(())
     }
 
-    pub async fn list_jobs(&self) -> anyhow::Result<Vec<JobListItem>> {
-        self.call(|conn| {
+    /// Stores the original request so that a deferred job can be started later.
+    pub async fn set_job_request(
+        &self,
+        job_id: &str,
+        request_json: &serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let job_id = job_id.to_string();
+        let request_json = serde_json::to_string(request_json)?;
+        self.call(move |conn| {
+            conn.execute(
+                "UPDATE jobs SET request_json = ?2 WHERE id = ?1",
+                params![job_id, request_json],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("set job request")
+    }
+
+    /// Jobs in `status` that carry a stored request, oldest first.
+    pub async fn list_jobs_by_status(&self, status: &str) -> anyhow::Result<Vec<QueuedJob>> {
+        let status = status.to_string();
+        self.call(move |conn| {
+            let mut stmt = conn.prepare(
+                r#"
+SELECT id, request_json
+FROM jobs
+WHERE status = ?1 AND request_json IS NOT NULL
+ORDER BY created_at ASC, id ASC
+"#,
+            )?;
+            let rows = stmt.query_map(params![status], |row| {
+                let request_json: String = row.get(1)?;
+                Ok(QueuedJob {
+                    id: row.get(0)?,
+                    request_json: serde_json::from_str(&request_json).map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            1,
+                            rusqlite::types::Type::Text,
+                            Box::new(e),
+                        )
+                    })?,
+                })
+            })?;
+            Ok(rows.collect::<Result<Vec<_>, _>>()?)
+        })
+        .await
+        .context("list jobs by status")
+    }
+
+    /// Moves a job from `from` to `to`; returns false if it was no longer in `from`.
+    pub async fn claim_job(
+        &self,
+        job_id: &str,
+        from: &str,
+        to: &str,
+        started_at: Option<&str>,
+    ) -> anyhow::Result<bool> {
+        let job_id = job_id.to_string();
+        let from = from.to_string();
+        let to = to.to_string();
+        let started_at = started_at.map(|s| s.to_string());
+        let event = Event::JobStatus {
+            job_id: job_id.clone(),
+            status: to.clone(),
+        };
+        let claimed = self
+            .call(move |conn| {
+                let changed = conn.execute(
+                    r#"
+UPDATE jobs
+SET status = ?3, started_at = COALESCE(?4, started_at)
+WHERE id = ?1 AND status = ?2
+"#,
+                    params![job_id, from, to, started_at],
+                )?;
+                Ok(changed > 0)
+            })
+            .await
+            .context("claim job")?;
+        if claimed {
+            self.events.publish(event);
+        }
+        Ok(claimed)
+    }
+
+    /// Marks every `running` job as `interrupted`: nothing survives a restart of this process.
+    pub async fn interrupt_running_jobs(&self, now: &str) -> anyhow::Result<Vec<String>> {
+        let now = now.to_string();
+        let ids = self
+            .call(move |conn| {
+                let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                let ids = {
+                    let mut stmt = tx.prepare("SELECT id FROM jobs WHERE status = 'running'")?;
+                    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+                    rows.collect::<Result<Vec<_>, _>>()?
+                };
+                for id in &ids {
+                    tx.execute(
+                        "UPDATE jobs SET status = 'interrupted', finished_at = ?2 WHERE id = ?1",
+                        params![id, now],
+                    )?;
+                    tx.execute(
+                        "INSERT INTO job_logs (job_id, ts, level, msg) VALUES (?1, ?2, ?3, ?4)",
+                        params![
+                            id,
+                            now,
+                            "error",
+                            "job interrupted: dockrev restarted while the job was running"
+                        ],
+                    )?;
+                }
+                tx.commit()?;
+                Ok(ids)
+            })
+            .await
+            .context("interrupt running jobs")?;
+        for id in &ids {
+            self.events.publish(Event::JobStatus {
+                job_id: id.clone(),
+                status: "interrupted".to_string(),
+            });
+        }
+        Ok(ids)
+    }
+
+    pub async fn list_jobs(&self, host_id: Option<&str>) -> anyhow::Result<Vec<JobListItem>> {
+        let host_id = host_id.map(|s| s.to_string());
+        self.call(move |conn| {
             let mut stmt = conn.prepare(
                 r#"
 SELECT
@@ -1650,14 +2044,16 @@ SELECT
   finished_at,
   allow_arch_mismatch,
   backup_mode,
-  summary_json
+  summary_json,
+  host_id
 FROM jobs
+WHERE (?1 IS NULL OR host_id = ?1)
 ORDER BY created_at DESC
 LIMIT 200
 "#,
             )?;
 
-            let rows = stmt.query_map([], |row| {
+            let rows = stmt.query_map(params![host_id], |row| {
                 let summary_json: String = row.get(13)?;
                 let summary: serde_json::Value =
                     serde_json::from_str(&summary_json).map_err(|e| {
@@ -1682,6 +2078,7 @@ LIMIT 200
                     allow_arch_mismatch: row.get::<_, i64>(11)? != 0,
                     backup_mode: row.get(12)?,
                     summary_json: summary,
+                    host_id: row.get(14)?,
                 })
             })?;
 
@@ -1711,7 +2108,8 @@ SELECT
   finished_at,
   allow_arch_mismatch,
   backup_mode,
-  summary_json
+  summary_json,
+  host_id
 FROM jobs
 WHERE id = ?1
 "#,
@@ -1741,6 +2139,7 @@ WHERE id = ?1
                             allow_arch_mismatch: row.get::<_, i64>(11)? != 0,
                             backup_mode: row.get(12)?,
                             summary_json: summary,
+                            host_id: row.get(14)?,
                         })
                     },
                 )
@@ -1776,18 +2175,54 @@ LIMIT 500
         .context("list job logs")
     }
 
-    pub async fn insert_job_log(&self, job_id: &str, line: &JobLogLine) -> anyhow::Result<()> {
+    /// Log lines of `job_id` with an id above `after_id`, with their ids (used for SSE resume).
+    pub async fn list_job_logs_after(
+        &self,
+        job_id: &str,
+        after_id: i64,
+    ) -> anyhow::Result<Vec<(i64, JobLogLine)>> {
         let job_id = job_id.to_string();
-        let line = line.clone();
         self.call(move |conn| {
-            conn.execute(
-                "INSERT INTO job_logs (job_id, ts, level, msg) VALUES (?1, ?2, ?3, ?4)",
-                params![job_id, line.ts, line.level, line.msg],
+            let mut stmt = conn.prepare(
+                r#"
+SELECT id, ts, level, msg
+FROM job_logs
+WHERE job_id = ?1 AND id > ?2
+ORDER BY id ASC
+"#,
             )?;
-            Ok(())
+
+            let rows = stmt.query_map(params![job_id, after_id], |row| {
+                Ok((
+                    row.get(0)?,
+                    JobLogLine {
+                        ts: row.get(1)?,
+                        level: row.get(2)?,
+                        msg: row.get(3)?,
+                    },
+                ))
+            })?;
+            Ok(rows.collect::<Result<Vec<_>, _>>()?)
         })
         .await
-        .context("insert job log")
+        .context("list job logs after")
+    }
+
+    pub async fn insert_job_log(&self, job_id: &str, line: &JobLogLine) -> anyhow::Result<()> {
+        let job_id = job_id.to_string();
+        let line = line.clone();
+        let (job_id, id, line) = self
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO job_logs (job_id, ts, level, msg) VALUES (?1, ?2, ?3, ?4)",
+                    params![job_id, line.ts, line.level, line.msg],
+                )?;
+                Ok((job_id, conn.last_insert_rowid(), line))
+            })
+            .await
+            .context("insert job log")?;
+        self.events.publish(Event::JobLog { job_id, id, line });
+        Ok(())
     }
 
     pub async fn insert_backup(
@@ -1947,8 +2382,805 @@ ORDER BY created_at DESC
         .await
         .context("list success backups for stack")
     }
-}
-
+    pub async fn list_check_schedules(&self) -> anyhow::Result<Vec<CheckScheduleRecord>> {
+        self.call(|conn| {
+            let mut stmt = conn.prepare(
+                r#"
+SELECT id, stack_id, cron, enabled, next_run_at, last_run_at, last_job_id
+FROM check_schedules
+ORDER BY id ASC
+"#,
+            )?;
+            let rows = stmt.query_map([], check_schedule_from_row)?;
+            Ok(rows.collect::<Result<Vec<_>, _>>()?)
+        })
+        .await
+        .context("list check schedules")
+    }
+
+    pub async fn get_check_schedule(
+        &self,
+        id: &str,
+    ) -> anyhow::Result<Option<CheckScheduleRecord>> {
+        let id = id.to_string();
+        self.call(move |conn| {
+            Ok(conn
+                .query_row(
+                    r#"
+SELECT id, stack_id, cron, enabled, next_run_at, last_run_at, last_job_id
+FROM check_schedules
+WHERE id = ?1
+"#,
+                    params![id],
+                    check_schedule_from_row,
+                )
+                .optional()?)
+        })
+        .await
+        .context("get check schedule")
+    }
+
+    pub async fn upsert_check_schedule(
+        &self,
+        id: &str,
+        stack_id: Option<&str>,
+        cron: &str,
+        enabled: bool,
+        next_run_at: Option<&str>,
+        now: &str,
+    ) -> anyhow::Result<()> {
+        let id = id.to_string();
+        let stack_id = stack_id.map(|s| s.to_string());
+        let cron = cron.to_string();
+        let next_run_at = next_run_at.map(|s| s.to_string());
+        let now = now.to_string();
+        self.call(move |conn| {
+            conn.execute(
+                r#"
+INSERT INTO check_schedules (id, stack_id, cron, enabled, next_run_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+ON CONFLICT(id) DO UPDATE SET
+  cron = excluded.cron,
+  enabled = excluded.enabled,
+  next_run_at = excluded.next_run_at,
+  updated_at = excluded.updated_at
+"#,
+                params![id, stack_id, cron, enabled as i64, next_run_at, now],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("upsert check schedule")
+    }
+
+    pub async fn delete_check_schedule(&self, id: &str) -> anyhow::Result<bool> {
+        let id = id.to_string();
+        self.call(move |conn| {
+            Ok(conn.execute("DELETE FROM check_schedules WHERE id = ?1", params![id])? > 0)
+        })
+        .await
+        .context("delete check schedule")
+    }
+
+    /// Advances `next_run_at` only if it still equals `expected_next_run_at`, so that a run is
+    /// claimed by exactly one tick (and never replayed after a restart).
+    pub async fn claim_check_schedule(
+        &self,
+        id: &str,
+        expected_next_run_at: Option<&str>,
+        next_run_at: Option<&str>,
+        last_run_at: Option<&str>,
+    ) -> anyhow::Result<bool> {
+        let id = id.to_string();
+        let expected_next_run_at = expected_next_run_at.map(|s| s.to_string());
+        let next_run_at = next_run_at.map(|s| s.to_string());
+        let last_run_at = last_run_at.map(|s| s.to_string());
+        self.call(move |conn| {
+            let changed = conn.execute(
+                r#"
+UPDATE check_schedules
+SET next_run_at = ?3, last_run_at = COALESCE(?4, last_run_at)
+WHERE id = ?1 AND next_run_at IS ?2
+"#,
+                params![id, expected_next_run_at, next_run_at, last_run_at],
+            )?;
+            Ok(changed > 0)
+        })
+        .await
+        .context("claim check schedule")
+    }
+
+    pub async fn set_check_schedule_last_job(&self, id: &str, job_id: &str) -> anyhow::Result<()> {
+        let id = id.to_string();
+        let job_id = job_id.to_string();
+        self.call(move |conn| {
+            conn.execute(
+                "UPDATE check_schedules SET last_job_id = ?2 WHERE id = ?1",
+                params![id, job_id],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("set check schedule last job")
+    }
+
+    pub async fn list_hosts(&self) -> anyhow::Result<Vec<HostRecord>> {
+        self.call(|conn| {
+            let mut stmt = conn.prepare(
+                r#"
+SELECT id, name, endpoint, platform, tls_cert_path, created_at
+FROM hosts
+ORDER BY name ASC
+"#,
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(HostRecord {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    endpoint: row.get(2)?,
+                    platform: row.get(3)?,
+                    tls_cert_path: row.get(4)?,
+                    created_at: row.get(5)?,
+                    agent_connected: None,
+                })
+            })?;
+            Ok(rows.collect::<Result<Vec<_>, _>>()?)
+        })
+        .await
+        .context("list hosts")
+    }
+
+    pub async fn insert_host(
+        &self,
+        host: &HostRecord,
+        agent_token_sha256: Option<&str>,
+        now: &str,
+    ) -> anyhow::Result<()> {
+        let host = host.clone();
+        let agent_token_sha256 = agent_token_sha256.map(|s| s.to_string());
+        let now = now.to_string();
+        self.call(move |conn| {
+            conn.execute(
+                r#"
+INSERT INTO hosts (id, name, endpoint, platform, tls_cert_path, agent_token_sha256, created_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+"#,
+                params![
+                    host.id,
+                    host.name,
+                    host.endpoint,
+                    host.platform,
+                    host.tls_cert_path,
+                    agent_token_sha256,
+                    now
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("insert host")
+    }
+
+    /// Host an agent token belongs to.
+    pub async fn get_host_id_by_agent_token(
+        &self,
+        agent_token_sha256: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let agent_token_sha256 = agent_token_sha256.to_string();
+        self.call(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT id FROM hosts WHERE agent_token_sha256 = ?1",
+                    params![agent_token_sha256],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        })
+        .await
+        .context("get host by agent token")
+    }
+
+    /// Deletes a host together with its discovery records and stacks (services, schedules and
+    /// windows cascade). Job history is kept.
+    pub async fn delete_host(&self, host_id: &str) -> anyhow::Result<bool> {
+        let host_id = host_id.to_string();
+        self.call(move |conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            tx.execute(
+                "DELETE FROM discovered_compose_projects WHERE host_id = ?1",
+                params![host_id],
+            )?;
+            tx.execute("DELETE FROM stacks WHERE host_id = ?1", params![host_id])?;
+            let changed = tx.execute("DELETE FROM hosts WHERE id = ?1", params![host_id])?;
+            tx.commit()?;
+            Ok(changed > 0)
+        })
+        .await
+        .context("delete host")
+    }
+
+    pub async fn get_registry_tags_cache(
+        &self,
+        registry: &str,
+        name: &str,
+    ) -> anyhow::Result<Option<RegistryTagsCacheEntry>> {
+        let registry = registry.to_string();
+        let name = name.to_string();
+        self.call(move |conn| {
+            let row = conn
+                .query_row(
+                    r#"
+SELECT tags_json, etag, fetched_at_unix
+FROM registry_tags_cache
+WHERE registry = ?1 AND name = ?2
+"#,
+                    params![registry, name],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, Option<String>>(1)?,
+                            row.get::<_, i64>(2)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            let Some((tags_json, etag, fetched_at_unix)) = row else {
+                return Ok(None);
+            };
+            Ok(Some(RegistryTagsCacheEntry {
+                tags: serde_json::from_str(&tags_json)?,
+                etag,
+                fetched_at_unix,
+            }))
+        })
+        .await
+        .context("get registry tags cache")
+    }
+
+    pub async fn put_registry_tags_cache(
+        &self,
+        registry: &str,
+        name: &str,
+        entry: &RegistryTagsCacheEntry,
+    ) -> anyhow::Result<()> {
+        let registry = registry.to_string();
+        let name = name.to_string();
+        let tags_json = serde_json::to_string(&entry.tags)?;
+        let etag = entry.etag.clone();
+        let fetched_at_unix = entry.fetched_at_unix;
+        self.call(move |conn| {
+            conn.execute(
+                r#"
+INSERT INTO registry_tags_cache (registry, name, tags_json, etag, fetched_at_unix)
+VALUES (?1, ?2, ?3, ?4, ?5)
+ON CONFLICT(registry, name) DO UPDATE SET
+  tags_json = excluded.tags_json,
+  etag = excluded.etag,
+  fetched_at_unix = excluded.fetched_at_unix
+"#,
+                params![registry, name, tags_json, etag, fetched_at_unix],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("put registry tags cache")
+    }
+
+    pub async fn get_registry_manifest_cache(
+        &self,
+        registry: &str,
+        name: &str,
+        reference: &str,
+        platform: &str,
+    ) -> anyhow::Result<Option<RegistryManifestCacheEntry>> {
+        let key = [registry, name, reference, platform].map(|s| s.to_string());
+        self.call(move |conn| {
+            let row = conn
+                .query_row(
+                    r#"
+SELECT content_digest, digest, arch_json, fetched_at_unix
+FROM registry_manifest_cache
+WHERE registry = ?1 AND name = ?2 AND reference = ?3 AND platform = ?4
+"#,
+                    params![key[0], key[1], key[2], key[3]],
+                    |row| {
+                        Ok((
+                            row.get::<_, Option<String>>(0)?,
+                            row.get::<_, Option<String>>(1)?,
+                            row.get::<_, String>(2)?,
+                            row.get::<_, i64>(3)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            let Some((content_digest, digest, arch_json, fetched_at_unix)) = row else {
+                return Ok(None);
+            };
+            Ok(Some(RegistryManifestCacheEntry {
+                content_digest,
+                digest,
+                arch: serde_json::from_str(&arch_json)?,
+                fetched_at_unix,
+            }))
+        })
+        .await
+        .context("get registry manifest cache")
+    }
+
+    pub async fn put_registry_manifest_cache(
+        &self,
+        registry: &str,
+        name: &str,
+        reference: &str,
+        platform: &str,
+        entry: &RegistryManifestCacheEntry,
+    ) -> anyhow::Result<()> {
+        let key = [registry, name, reference, platform].map(|s| s.to_string());
+        let arch_json = serde_json::to_string(&entry.arch)?;
+        let entry = entry.clone();
+        self.call(move |conn| {
+            conn.execute(
+                r#"
+INSERT INTO registry_manifest_cache
+  (registry, name, reference, platform, content_digest, digest, arch_json, fetched_at_unix)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+ON CONFLICT(registry, name, reference, platform) DO UPDATE SET
+  content_digest = excluded.content_digest,
+  digest = excluded.digest,
+  arch_json = excluded.arch_json,
+  fetched_at_unix = excluded.fetched_at_unix
+"#,
+                params![
+                    key[0],
+                    key[1],
+                    key[2],
+                    key[3],
+                    entry.content_digest,
+                    entry.digest,
+                    arch_json,
+                    entry.fetched_at_unix
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("put registry manifest cache")
+    }
+
+    /// Deletes tag list and manifest lookups fetched before `before_unix`; returns the number of
+    /// rows removed.
+    pub async fn prune_registry_cache(&self, before_unix: i64) -> anyhow::Result<usize> {
+        self.call(move |conn| {
+            let tags = conn.execute(
+                "DELETE FROM registry_tags_cache WHERE fetched_at_unix < ?1",
+                params![before_unix],
+            )?;
+            let manifests = conn.execute(
+                "DELETE FROM registry_manifest_cache WHERE fetched_at_unix < ?1",
+                params![before_unix],
+            )?;
+            Ok(tags + manifests)
+        })
+        .await
+        .context("prune registry cache")
+    }
+
+    /// Cached image config JSON of a digest as resolved for `platform`. Digests are immutable,
+    /// so entries never expire.
+    pub async fn get_registry_image_config_cache(
+        &self,
+        registry: &str,
+        name: &str,
+        digest: &str,
+        platform: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let key = [registry, name, digest, platform].map(|s| s.to_string());
+        self.call(move |conn| {
+            Ok(conn
+                .query_row(
+                    r#"
+SELECT config_json
+FROM registry_image_config_cache
+WHERE registry = ?1 AND name = ?2 AND digest = ?3 AND platform = ?4
+"#,
+                    params![key[0], key[1], key[2], key[3]],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?)
+        })
+        .await
+        .context("get registry image config cache")
+    }
+
+    pub async fn put_registry_image_config_cache(
+        &self,
+        registry: &str,
+        name: &str,
+        digest: &str,
+        platform: &str,
+        config_json: &str,
+    ) -> anyhow::Result<()> {
+        let key = [registry, name, digest, platform, config_json].map(|s| s.to_string());
+        self.call(move |conn| {
+            conn.execute(
+                r#"
+INSERT INTO registry_image_config_cache (registry, name, digest, platform, config_json)
+VALUES (?1, ?2, ?3, ?4, ?5)
+ON CONFLICT(registry, name, digest, platform) DO UPDATE SET
+  config_json = excluded.config_json
+"#,
+                params![key[0], key[1], key[2], key[3], key[4]],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("put registry image config cache")
+    }
+
+    /// Cached release notes of a candidate digest relative to `current_tag`: the notes JSON
+    /// (`None` when the image names no source) and when they were fetched.
+    pub async fn get_release_notes_cache(
+        &self,
+        registry: &str,
+        name: &str,
+        digest: &str,
+        current_tag: &str,
+    ) -> anyhow::Result<Option<(Option<String>, i64)>> {
+        let key = [registry, name, digest, current_tag].map(|s| s.to_string());
+        self.call(move |conn| {
+            Ok(conn
+                .query_row(
+                    r#"
+SELECT notes_json, fetched_at_unix
+FROM release_notes_cache
+WHERE registry = ?1 AND name = ?2 AND digest = ?3 AND current_tag = ?4
+"#,
+                    params![key[0], key[1], key[2], key[3]],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?)
+        })
+        .await
+        .context("get release notes cache")
+    }
+
+    pub async fn put_release_notes_cache(
+        &self,
+        registry: &str,
+        name: &str,
+        digest: &str,
+        current_tag: &str,
+        notes_json: Option<String>,
+        fetched_at_unix: i64,
+    ) -> anyhow::Result<()> {
+        let key = [registry, name, digest, current_tag].map(|s| s.to_string());
+        self.call(move |conn| {
+            conn.execute(
+                r#"
+INSERT INTO release_notes_cache
+  (registry, name, digest, current_tag, notes_json, fetched_at_unix)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+ON CONFLICT(registry, name, digest, current_tag) DO UPDATE SET
+  notes_json = excluded.notes_json,
+  fetched_at_unix = excluded.fetched_at_unix
+"#,
+                params![key[0], key[1], key[2], key[3], notes_json, fetched_at_unix],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("put release notes cache")
+    }
+
+    /// Last vulnerability scan of a digest and when it ran.
+    pub async fn get_vulnerability_scan(
+        &self,
+        digest: &str,
+    ) -> anyhow::Result<Option<(VulnerabilityScan, i64)>> {
+        let digest = digest.to_string();
+        self.call(move |conn| {
+            Ok(conn
+                .query_row(
+                    r#"
+SELECT scanner, critical, high, medium, low, unknown, scanned_at, scanned_at_unix
+FROM vulnerability_scans
+WHERE digest = ?1
+"#,
+                    params![digest],
+                    |row| {
+                        Ok((
+                            VulnerabilityScan {
+                                scanner: row.get(0)?,
+                                counts: VulnerabilityCounts {
+                                    critical: row.get(1)?,
+                                    high: row.get(2)?,
+                                    medium: row.get(3)?,
+                                    low: row.get(4)?,
+                                    unknown: row.get(5)?,
+                                },
+                                scanned_at: row.get(6)?,
+                            },
+                            row.get(7)?,
+                        ))
+                    },
+                )
+                .optional()?)
+        })
+        .await
+        .context("get vulnerability scan")
+    }
+
+    pub async fn put_vulnerability_scan(
+        &self,
+        digest: &str,
+        scan: &VulnerabilityScan,
+        scanned_at_unix: i64,
+    ) -> anyhow::Result<()> {
+        let digest = digest.to_string();
+        let scan = scan.clone();
+        self.call(move |conn| {
+            conn.execute(
+                r#"
+INSERT INTO vulnerability_scans
+  (digest, scanner, critical, high, medium, low, unknown, scanned_at, scanned_at_unix)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+ON CONFLICT(digest) DO UPDATE SET
+  scanner = excluded.scanner,
+  critical = excluded.critical,
+  high = excluded.high,
+  medium = excluded.medium,
+  low = excluded.low,
+  unknown = excluded.unknown,
+  scanned_at = excluded.scanned_at,
+  scanned_at_unix = excluded.scanned_at_unix
+"#,
+                params![
+                    digest,
+                    scan.scanner,
+                    scan.counts.critical,
+                    scan.counts.high,
+                    scan.counts.medium,
+                    scan.counts.low,
+                    scan.counts.unknown,
+                    scan.scanned_at,
+                    scanned_at_unix
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("put vulnerability scan")
+    }
+
+    pub async fn list_registry_credentials(&self) -> anyhow::Result<Vec<RegistryCredential>> {
+        self.call(|conn| {
+            let mut stmt = conn.prepare(
+                r#"
+SELECT registry, username, secret, updated_at
+FROM registry_credentials
+ORDER BY registry ASC
+"#,
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(RegistryCredential {
+                    registry: row.get(0)?,
+                    username: row.get(1)?,
+                    secret: Some(row.get(2)?),
+                    updated_at: Some(row.get(3)?),
+                })
+            })?;
+            Ok(rows.collect::<Result<Vec<_>, _>>()?)
+        })
+        .await
+        .context("list registry credentials")
+    }
+
+    pub async fn get_registry_credential(
+        &self,
+        registry: &str,
+    ) -> anyhow::Result<Option<RegistryCredential>> {
+        let registry = registry.to_string();
+        self.call(move |conn| {
+            Ok(conn
+                .query_row(
+                    r#"
+SELECT registry, username, secret, updated_at
+FROM registry_credentials
+WHERE registry = ?1
+"#,
+                    params![registry],
+                    |row| {
+                        Ok(RegistryCredential {
+                            registry: row.get(0)?,
+                            username: row.get(1)?,
+                            secret: Some(row.get(2)?),
+                            updated_at: Some(row.get(3)?),
+                        })
+                    },
+                )
+                .optional()?)
+        })
+        .await
+        .context("get registry credential")
+    }
+
+    pub async fn put_registry_credential(
+        &self,
+        credential: &RegistryCredential,
+        now: &str,
+    ) -> anyhow::Result<()> {
+        let credential = credential.clone();
+        let now = now.to_string();
+        self.call(move |conn| {
+            conn.execute(
+                r#"
+INSERT INTO registry_credentials (registry, username, secret, updated_at)
+VALUES (?1, ?2, ?3, ?4)
+ON CONFLICT(registry) DO UPDATE SET
+  username = excluded.username,
+  secret = excluded.secret,
+  updated_at = excluded.updated_at
+"#,
+                params![
+                    credential.registry,
+                    credential.username,
+                    credential.secret.unwrap_or_default(),
+                    now
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("put registry credential")
+    }
+
+    pub async fn delete_registry_credential(&self, registry: &str) -> anyhow::Result<bool> {
+        let registry = registry.to_string();
+        self.call(move |conn| {
+            let changed = conn.execute(
+                "DELETE FROM registry_credentials WHERE registry = ?1",
+                params![registry],
+            )?;
+            Ok(changed > 0)
+        })
+        .await
+        .context("delete registry credential")
+    }
+
+    pub async fn list_maintenance_windows(&self) -> anyhow::Result<Vec<MaintenanceWindowRecord>> {
+        self.call(|conn| {
+            let mut stmt = conn.prepare(
+                r#"
+SELECT id, stack_id, weekdays_json, start_time, end_time, timezone
+FROM maintenance_windows
+ORDER BY created_at ASC
+"#,
+            )?;
+            let rows = stmt.query_map([], |row| {
+                let weekdays_json: String = row.get(2)?;
+                let weekdays = serde_json::from_str(&weekdays_json).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        2,
+                        rusqlite::types::Type::Text,
+                        Box::new(e),
+                    )
+                })?;
+                Ok(MaintenanceWindowRecord {
+                    id: row.get(0)?,
+                    stack_id: row.get(1)?,
+                    window: MaintenanceWindow {
+                        weekdays,
+                        start: row.get(3)?,
+                        end: row.get(4)?,
+                        timezone: row.get(5)?,
+                    },
+                })
+            })?;
+            Ok(rows.collect::<Result<Vec<_>, _>>()?)
+        })
+        .await
+        .context("list maintenance windows")
+    }
+
+    pub async fn insert_maintenance_window(
+        &self,
+        record: &MaintenanceWindowRecord,
+        now: &str,
+    ) -> anyhow::Result<()> {
+        let record = record.clone();
+        let now = now.to_string();
+        self.call(move |conn| {
+            conn.execute(
+                r#"
+INSERT INTO maintenance_windows (
+  id,
+  stack_id,
+  weekdays_json,
+  start_time,
+  end_time,
+  timezone,
+  created_at
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+"#,
+                params![
+                    record.id,
+                    record.stack_id,
+                    serde_json::to_string(&record.window.weekdays)?,
+                    record.window.start,
+                    record.window.end,
+                    record.window.timezone,
+                    now
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+        .context("insert maintenance window")
+    }
+
+    pub async fn delete_maintenance_window(&self, id: &str) -> anyhow::Result<bool> {
+        let id = id.to_string();
+        self.call(move |conn| {
+            Ok(conn.execute("DELETE FROM maintenance_windows WHERE id = ?1", params![id])? > 0)
+        })
+        .await
+        .context("delete maintenance window")
+    }
+}
+
+/// `NULL` is the default strategy.
+fn version_strategy_from_row(
+    row: &rusqlite::Row<'_>,
+    idx: usize,
+) -> rusqlite::Result<VersionStrategy> {
+    let json: Option<String> = row.get(idx)?;
+    json.as_deref()
+        .map(serde_json::from_str)
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))
+        })
+}
+
+fn auto_update_from_row(
+    row: &rusqlite::Row<'_>,
+    policy_idx: usize,
+    window_idx: usize,
+    security_idx: usize,
+) -> rusqlite::Result<AutoUpdateSettings> {
+    let policy: String = row.get(policy_idx)?;
+    let window_json: Option<String> = row.get(window_idx)?;
+    let window = window_json
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(
+                window_idx,
+                rusqlite::types::Type::Text,
+                Box::new(e),
+            )
+        })?;
+    Ok(AutoUpdateSettings {
+        policy: AutoUpdatePolicy::from_str(&policy),
+        window,
+        security: SecurityPreference::from_str(&row.get::<_, String>(security_idx)?),
+    })
+}
+
+fn check_schedule_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<CheckScheduleRecord> {
+    Ok(CheckScheduleRecord {
+        id: row.get(0)?,
+        stack_id: row.get(1)?,
+        cron: row.get(2)?,
+        enabled: row.get::<_, i64>(3)? != 0,
+        next_run_at: row.get(4)?,
+        last_run_at: row.get(5)?,
+        last_job_id: row.get(6)?,
+    })
+}
+
 fn ensure_parent_dir(path: &Path) -> anyhow::Result<PathBuf> {
     let path = path.to_path_buf();
     if let Some(parent) = path.parent()
@@ -2007,6 +3239,34 @@ fn ensure_service_columns(conn: &rusqlite::Connection) -> anyhow::Result<()> {
             name: "checked_at",
             ddl: "ALTER TABLE services ADD COLUMN checked_at TEXT",
         },
+        Col {
+            name: "auto_update_policy",
+            ddl: "ALTER TABLE services ADD COLUMN auto_update_policy TEXT NOT NULL DEFAULT 'never'",
+        },
+        Col {
+            name: "auto_update_window_json",
+            ddl: "ALTER TABLE services ADD COLUMN auto_update_window_json TEXT",
+        },
+        Col {
+            name: "candidate_release_notes_json",
+            ddl: "ALTER TABLE services ADD COLUMN candidate_release_notes_json TEXT",
+        },
+        Col {
+            name: "candidate_signature_json",
+            ddl: "ALTER TABLE services ADD COLUMN candidate_signature_json TEXT",
+        },
+        Col {
+            name: "auto_update_security",
+            ddl: "ALTER TABLE services ADD COLUMN auto_update_security TEXT NOT NULL DEFAULT 'ignore'",
+        },
+        Col {
+            name: "version_strategy_json",
+            ddl: "ALTER TABLE services ADD COLUMN version_strategy_json TEXT",
+        },
+        Col {
+            name: "prerelease_policy",
+            ddl: "ALTER TABLE services ADD COLUMN prerelease_policy TEXT",
+        },
     ];
 
     let mut stmt = conn.prepare("PRAGMA table_info(services)")?;
@@ -2091,6 +3351,58 @@ fn ensure_stack_archive_columns(conn: &rusqlite::Connection) -> anyhow::Result<(
     Ok(())
 }
 
+fn ensure_host_columns(conn: &rusqlite::Connection) -> anyhow::Result<()> {
+    #[derive(Clone)]
+    struct Col<'a> {
+        name: &'a str,
+        ddl: &'a str,
+    }
+
+    let desired = [Col {
+        name: "agent_token_sha256",
+        ddl: "ALTER TABLE hosts ADD COLUMN agent_token_sha256 TEXT",
+    }];
+
+    let mut stmt = conn.prepare("PRAGMA table_info(hosts)")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    let existing = rows.collect::<Result<Vec<_>, _>>()?;
+
+    for col in desired {
+        if existing.iter().any(|c| c == col.name) {
+            continue;
+        }
+        conn.execute_batch(col.ddl)?;
+    }
+
+    Ok(())
+}
+
+fn ensure_stack_host_columns(conn: &rusqlite::Connection) -> anyhow::Result<()> {
+    #[derive(Clone)]
+    struct Col<'a> {
+        name: &'a str,
+        ddl: &'a str,
+    }
+
+    let desired = [Col {
+        name: "host_id",
+        ddl: "ALTER TABLE stacks ADD COLUMN host_id TEXT NOT NULL DEFAULT 'local'",
+    }];
+
+    let mut stmt = conn.prepare("PRAGMA table_info(stacks)")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    let existing = rows.collect::<Result<Vec<_>, _>>()?;
+
+    for col in desired {
+        if existing.iter().any(|c| c == col.name) {
+            continue;
+        }
+        conn.execute_batch(col.ddl)?;
+    }
+
+    Ok(())
+}
+
 fn ensure_service_archive_columns(conn: &rusqlite::Connection) -> anyhow::Result<()> {
     #[derive(Clone)]
     struct Col<'a> {
@@ -2127,11 +3439,44 @@ fn ensure_service_archive_columns(conn: &rusqlite::Connection) -> anyhow::Result
     Ok(())
 }
 
+fn ensure_job_columns(conn: &rusqlite::Connection) -> anyhow::Result<()> {
+    #[derive(Clone)]
+    struct Col<'a> {
+        name: &'a str,
+        ddl: &'a str,
+    }
+
+    let desired = [
+        Col {
+            name: "request_json",
+            ddl: "ALTER TABLE jobs ADD COLUMN request_json TEXT",
+        },
+        Col {
+            name: "host_id",
+            ddl: "ALTER TABLE jobs ADD COLUMN host_id TEXT",
+        },
+    ];
+
+    let mut stmt = conn.prepare("PRAGMA table_info(jobs)")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    let existing = rows.collect::<Result<Vec<_>, _>>()?;
+
+    for col in desired {
+        if existing.iter().any(|c| c == col.name) {
+            continue;
+        }
+        conn.execute_batch(col.ddl)?;
+    }
+
+    Ok(())
+}
+
 fn ensure_discovery_schema(conn: &rusqlite::Connection) -> anyhow::Result<()> {
     conn.execute_batch(
         r#"
 CREATE TABLE IF NOT EXISTS discovered_compose_projects (
-  project TEXT PRIMARY KEY NOT NULL,
+  host_id TEXT NOT NULL DEFAULT 'local',
+  project TEXT NOT NULL,
   stack_id TEXT,
   status TEXT NOT NULL,
   last_seen_at TEXT,
@@ -2140,7 +3485,8 @@ CREATE TABLE IF NOT EXISTS discovered_compose_projects (
   last_config_files_json TEXT,
   archived INTEGER NOT NULL DEFAULT 0,
   archived_at TEXT,
-  archived_reason TEXT
+  archived_reason TEXT,
+  PRIMARY KEY (host_id, project)
 );
 CREATE INDEX IF NOT EXISTS idx_discovered_compose_projects_stack_id ON discovered_compose_projects(stack_id);
 "#,
@@ -2204,6 +3550,60 @@ fn apply_migration_0007_remove_manual_stacks(
     Ok(())
 }
 
+/// Re-keys `discovered_compose_projects` by `(host_id, project)`; rows from before multi-host
+/// support belong to the local host.
+fn apply_migration_0010_discovery_host_id(conn: &mut rusqlite::Connection) -> anyhow::Result<()> {
+    let id = "0010_discovery_host_id";
+    if migration_applied(conn, id)? {
+        return Ok(());
+    }
+
+    let has_host_id = {
+        let mut stmt = conn.prepare("PRAGMA table_info(discovered_compose_projects)")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+        rows.collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .any(|c| c == "host_id")
+    };
+
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    if !has_host_id {
+        tx.execute_batch(
+            r#"
+ALTER TABLE discovered_compose_projects RENAME TO discovered_compose_projects_old;
+DROP INDEX IF EXISTS idx_discovered_compose_projects_stack_id;
+CREATE TABLE discovered_compose_projects (
+  host_id TEXT NOT NULL DEFAULT 'local',
+  project TEXT NOT NULL,
+  stack_id TEXT,
+  status TEXT NOT NULL,
+  last_seen_at TEXT,
+  last_scan_at TEXT,
+  last_error TEXT,
+  last_config_files_json TEXT,
+  archived INTEGER NOT NULL DEFAULT 0,
+  archived_at TEXT,
+  archived_reason TEXT,
+  PRIMARY KEY (host_id, project)
+);
+INSERT INTO discovered_compose_projects (
+  host_id, project, stack_id, status, last_seen_at, last_scan_at, last_error,
+  last_config_files_json, archived, archived_at, archived_reason
+)
+SELECT
+  'local', project, stack_id, status, last_seen_at, last_scan_at, last_error,
+  last_config_files_json, archived, archived_at, archived_reason
+FROM discovered_compose_projects_old;
+DROP TABLE discovered_compose_projects_old;
+CREATE INDEX IF NOT EXISTS idx_discovered_compose_projects_stack_id ON discovered_compose_projects(stack_id);
+"#,
+        )?;
+    }
+    record_migration_tx(&tx, id)?;
+    tx.commit()?;
+    Ok(())
+}
+
 fn auto_archive_missing_discovery_projects_on_startup(
     conn: &rusqlite::Connection,
 ) -> anyhow::Result<()> {
@@ -2232,6 +3632,7 @@ CREATE TABLE IF NOT EXISTS stacks (
   archived INTEGER NOT NULL DEFAULT 0,
   archived_at TEXT,
   archived_reason TEXT,
+  host_id TEXT NOT NULL DEFAULT 'local',
   created_at TEXT NOT NULL,
   updated_at TEXT NOT NULL,
   last_check_at TEXT NOT NULL
@@ -2250,10 +3651,17 @@ CREATE TABLE IF NOT EXISTS services (
   candidate_digest TEXT,
   candidate_arch_match TEXT,
   candidate_arch_json TEXT,
+  candidate_release_notes_json TEXT,
+  candidate_signature_json TEXT,
   ignore_rule_id TEXT,
   ignore_reason TEXT,
   checked_at TEXT,
   auto_rollback INTEGER NOT NULL,
+  auto_update_policy TEXT NOT NULL DEFAULT 'never',
+  auto_update_window_json TEXT,
+  auto_update_security TEXT NOT NULL DEFAULT 'ignore',
+  version_strategy_json TEXT,
+  prerelease_policy TEXT,
   archived INTEGER NOT NULL DEFAULT 0,
   archived_at TEXT,
   archived_reason TEXT,
@@ -2265,7 +3673,8 @@ CREATE TABLE IF NOT EXISTS services (
 CREATE INDEX IF NOT EXISTS idx_services_stack_id ON services(stack_id);
 
 CREATE TABLE IF NOT EXISTS discovered_compose_projects (
-  project TEXT PRIMARY KEY NOT NULL,
+  host_id TEXT NOT NULL DEFAULT 'local',
+  project TEXT NOT NULL,
   stack_id TEXT,
   status TEXT NOT NULL,
   last_seen_at TEXT,
@@ -2274,7 +3683,8 @@ CREATE TABLE IF NOT EXISTS discovered_compose_projects (
   last_config_files_json TEXT,
   archived INTEGER NOT NULL DEFAULT 0,
   archived_at TEXT,
-  archived_reason TEXT
+  archived_reason TEXT,
+  PRIMARY KEY (host_id, project)
 );
 CREATE INDEX IF NOT EXISTS idx_discovered_compose_projects_stack_id ON discovered_compose_projects(stack_id);
 
@@ -2341,7 +3751,9 @@ CREATE TABLE IF NOT EXISTS jobs (
   created_at TEXT NOT NULL,
   started_at TEXT,
   finished_at TEXT,
-  summary_json TEXT NOT NULL
+  summary_json TEXT NOT NULL,
+  request_json TEXT,
+  host_id TEXT
 );
 CREATE INDEX IF NOT EXISTS idx_jobs_created_at ON jobs(created_at);
 CREATE INDEX IF NOT EXISTS idx_jobs_stack_id ON jobs(stack_id);
@@ -2370,4 +3782,94 @@ CREATE TABLE IF NOT EXISTS backups (
 );
 CREATE INDEX IF NOT EXISTS idx_backups_stack_id ON backups(stack_id);
 CREATE INDEX IF NOT EXISTS idx_backups_cleanup_after ON backups(cleanup_after);
+
+CREATE TABLE IF NOT EXISTS check_schedules (
+  id TEXT PRIMARY KEY NOT NULL,
+  stack_id TEXT REFERENCES stacks(id) ON DELETE CASCADE,
+  cron TEXT NOT NULL,
+  enabled INTEGER NOT NULL,
+  next_run_at TEXT,
+  last_run_at TEXT,
+  last_job_id TEXT,
+  updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS hosts (
+  id TEXT PRIMARY KEY NOT NULL,
+  name TEXT NOT NULL,
+  endpoint TEXT NOT NULL,
+  platform TEXT,
+  tls_cert_path TEXT,
+  agent_token_sha256 TEXT,
+  created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS registry_tags_cache (
+  registry TEXT NOT NULL,
+  name TEXT NOT NULL,
+  tags_json TEXT NOT NULL,
+  etag TEXT,
+  fetched_at_unix INTEGER NOT NULL,
+  PRIMARY KEY (registry, name)
+);
+
+CREATE TABLE IF NOT EXISTS registry_manifest_cache (
+  registry TEXT NOT NULL,
+  name TEXT NOT NULL,
+  reference TEXT NOT NULL,
+  platform TEXT NOT NULL,
+  content_digest TEXT,
+  digest TEXT,
+  arch_json TEXT NOT NULL,
+  fetched_at_unix INTEGER NOT NULL,
+  PRIMARY KEY (registry, name, reference, platform)
+);
+
+CREATE TABLE IF NOT EXISTS registry_image_config_cache (
+  registry TEXT NOT NULL,
+  name TEXT NOT NULL,
+  digest TEXT NOT NULL,
+  platform TEXT NOT NULL,
+  config_json TEXT NOT NULL,
+  PRIMARY KEY (registry, name, digest, platform)
+);
+
+CREATE TABLE IF NOT EXISTS release_notes_cache (
+  registry TEXT NOT NULL,
+  name TEXT NOT NULL,
+  digest TEXT NOT NULL,
+  current_tag TEXT NOT NULL,
+  notes_json TEXT,
+  fetched_at_unix INTEGER NOT NULL,
+  PRIMARY KEY (registry, name, digest, current_tag)
+);
+
+CREATE TABLE IF NOT EXISTS vulnerability_scans (
+  digest TEXT PRIMARY KEY NOT NULL,
+  scanner TEXT NOT NULL,
+  critical INTEGER NOT NULL,
+  high INTEGER NOT NULL,
+  medium INTEGER NOT NULL,
+  low INTEGER NOT NULL,
+  unknown INTEGER NOT NULL,
+  scanned_at TEXT NOT NULL,
+  scanned_at_unix INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS registry_credentials (
+  registry TEXT PRIMARY KEY NOT NULL,
+  username TEXT NOT NULL,
+  secret TEXT NOT NULL,
+  updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS maintenance_windows (
+  id TEXT PRIMARY KEY NOT NULL,
+  stack_id TEXT REFERENCES stacks(id) ON DELETE CASCADE,
+  weekdays_json TEXT NOT NULL,
+  start_time TEXT NOT NULL,
+  end_time TEXT NOT NULL,
+  timezone TEXT NOT NULL,
+  created_at TEXT NOT NULL
+);
 "#;
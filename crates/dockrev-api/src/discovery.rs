@@ -1,6 +1,6 @@
 use std::{
     collections::{BTreeMap, BTreeSet},
-    sync::LazyLock,
+    sync::{Arc, LazyLock},
     time::Duration,
 };
 
@@ -12,11 +12,17 @@ use crate::{
     },
     compose,
     db::{ComposeServiceSpec, DiscoveredComposeProjectUpsert},
+    docker_engine::DockerEngine,
+    hosts::Host,
     ids,
-    runner::CommandSpec,
     state::AppState,
 };
 
+const PROJECT_LABEL: &str = "com.docker.compose.project";
+/// Events arriving within this window are handled as one batch (compose recreates emit several).
+const EVENT_DEBOUNCE: Duration = Duration::from_secs(2);
+const EVENT_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
 static DISCOVERY_SCAN_LOCK: LazyLock<tokio::sync::Mutex<()>> =
     LazyLock::new(|| tokio::sync::Mutex::new(()));
 
@@ -52,21 +58,6 @@ fn stack_services_match_specs(
     existing == expected
 }
 
-fn parse_labels_json_line(line: &str) -> anyhow::Result<BTreeMap<String, String>> {
-    let v: serde_json::Value = serde_json::from_str(line).context("parse docker labels json")?;
-    let Some(obj) = v.as_object() else {
-        return Ok(BTreeMap::new());
-    };
-
-    let mut out = BTreeMap::<String, String>::new();
-    for (k, v) in obj {
-        if let Some(s) = v.as_str() {
-            out.insert(k.clone(), s.to_string());
-        }
-    }
-    Ok(out)
-}
-
 #[derive(Clone, Debug)]
 pub enum NormalizeConfigFilesError {
     RelativePathRejected,
@@ -104,134 +95,228 @@ struct ProjectLabels {
 }
 
 async fn list_compose_projects_from_docker(
-    state: &AppState,
+    docker: &dyn DockerEngine,
+    project_filter: &str,
 ) -> anyhow::Result<BTreeMap<String, ProjectLabels>> {
-    let ps = state
-        .runner
-        .run(
-            CommandSpec {
-                program: "docker".to_string(),
-                args: vec![
-                    "ps".to_string(),
-                    "--filter".to_string(),
-                    "label=com.docker.compose.project".to_string(),
-                    "-q".to_string(),
-                ],
-                env: Vec::new(),
-            },
-            Duration::from_secs(8),
-        )
+    let containers = docker
+        .list_containers(&[project_filter.to_string()])
         .await
-        .context("docker ps")?;
-
-    if ps.status != 0 {
-        return Err(anyhow::anyhow!(
-            "docker ps failed status={} stderr={}",
-            ps.status,
-            ps.stderr
-        ));
-    }
-
-    let ids: Vec<String> = ps
-        .stdout
-        .lines()
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .collect();
-
-    if ids.is_empty() {
-        return Ok(BTreeMap::new());
-    }
+        .context("list compose containers")?;
 
     let mut by_project = BTreeMap::<String, ProjectLabels>::new();
 
-    for chunk in ids.chunks(64) {
-        let mut args = vec![
-            "inspect".to_string(),
-            "--format".to_string(),
-            "{{json .Config.Labels}}".to_string(),
-        ];
-        args.extend(chunk.iter().cloned());
-
-        let out = state
-            .runner
-            .run(
-                CommandSpec {
-                    program: "docker".to_string(),
-                    args,
-                    env: Vec::new(),
-                },
-                Duration::from_secs(12),
-            )
-            .await
-            .context("docker inspect")?;
-
-        if out.status != 0 {
-            return Err(anyhow::anyhow!(
-                "docker inspect failed status={} stderr={}",
-                out.status,
-                out.stderr
-            ));
-        }
-
-        for line in out.stdout.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            let labels = parse_labels_json_line(line)?;
+    for container in containers {
+        let labels = container.labels;
+        let Some(project) = labels.get(PROJECT_LABEL).cloned() else {
+            continue;
+        };
 
-            let Some(project) = labels.get("com.docker.compose.project").cloned() else {
-                continue;
-            };
-
-            let config_files_raw = labels
-                .get("com.docker.compose.project.config_files")
-                .map(|s| s.to_string())
-                .filter(|s| !s.trim().is_empty());
-            let working_dir_raw = labels
-                .get("com.docker.compose.project.working_dir")
-                .map(|s| s.to_string())
-                .filter(|s| !s.trim().is_empty());
-
-            let entry = by_project.entry(project).or_insert(ProjectLabels {
-                config_files_raw: None,
-                working_dir_raw: None,
-            });
+        let config_files_raw = labels
+            .get("com.docker.compose.project.config_files")
+            .map(|s| s.to_string())
+            .filter(|s| !s.trim().is_empty());
+        let working_dir_raw = labels
+            .get("com.docker.compose.project.working_dir")
+            .map(|s| s.to_string())
+            .filter(|s| !s.trim().is_empty());
+
+        let entry = by_project.entry(project).or_insert(ProjectLabels {
+            config_files_raw: None,
+            working_dir_raw: None,
+        });
 
-            if let Some(v) = config_files_raw {
-                match &entry.config_files_raw {
-                    None => entry.config_files_raw = Some(v),
-                    Some(prev) if prev == &v => {}
-                    Some(_) => {
-                        // conflict marker: keep a sentinel distinct value to signal conflict later
-                        entry.config_files_raw = Some("__CONFLICT__".to_string());
-                    }
+        if let Some(v) = config_files_raw {
+            match &entry.config_files_raw {
+                None => entry.config_files_raw = Some(v),
+                Some(prev) if prev == &v => {}
+                Some(_) => {
+                    // conflict marker: keep a sentinel distinct value to signal conflict later
+                    entry.config_files_raw = Some("__CONFLICT__".to_string());
                 }
             }
+        }
 
-            if let Some(v) = working_dir_raw
-                && entry.working_dir_raw.is_none()
-            {
-                entry.working_dir_raw = Some(v);
-            }
+        if let Some(v) = working_dir_raw
+            && entry.working_dir_raw.is_none()
+        {
+            entry.working_dir_raw = Some(v);
         }
     }
 
     Ok(by_project)
 }
 
-pub fn spawn_task(state: std::sync::Arc<AppState>) {
+/// Runs discovery from the Docker events stream of every host, with periodic full scans for
+/// reconciliation.
+pub fn spawn_task(state: Arc<AppState>) {
     let interval = state.config.discovery_interval_seconds;
+    let scan_state = state.clone();
     tokio::spawn(async move {
         let mut ticker = tokio::time::interval(Duration::from_secs(interval));
         loop {
             ticker.tick().await;
-            if let Err(e) = run_scan(state.as_ref()).await {
+            if let Err(e) = run_scan(scan_state.as_ref()).await {
                 tracing::warn!(error = %e, "discovery scan failed");
             }
         }
     });
+
+    // Hosts can be added or removed at runtime; keep one event follower per registered host.
+    tokio::spawn(async move {
+        let mut followers = BTreeMap::<String, tokio::task::JoinHandle<()>>::new();
+        loop {
+            let hosts = state.hosts.list();
+            followers.retain(|host_id, task| {
+                let keep = hosts.iter().any(|h| h.id() == host_id);
+                if !keep {
+                    task.abort();
+                }
+                keep
+            });
+            for host in hosts {
+                if followers.contains_key(host.id()) {
+                    continue;
+                }
+                followers.insert(
+                    host.id().to_string(),
+                    tokio::spawn(follow_host_events(state.clone(), host)),
+                );
+            }
+            tokio::time::sleep(EVENT_RECONNECT_DELAY).await;
+        }
+    });
+}
+
+async fn follow_host_events(state: Arc<AppState>, host: Arc<Host>) {
+    let mut reconnect = false;
+    loop {
+        match follow_events(state.as_ref(), &host, reconnect).await {
+            Ok(()) => tracing::warn!(host = host.id(), "docker event stream ended"),
+            Err(e) => tracing::warn!(host = host.id(), error = %e, "docker event stream failed"),
+        }
+        reconnect = true;
+        tokio::time::sleep(EVENT_RECONNECT_DELAY).await;
+    }
+}
+
+/// Re-syncs compose projects of `host` touched by container events until the stream closes.
+/// After a reconnect, a full scan of the host first catches up on events missed in between.
+pub(crate) async fn follow_events(
+    state: &AppState,
+    host: &Host,
+    reconnect: bool,
+) -> anyhow::Result<()> {
+    let mut rx = host
+        .docker
+        .container_events(&[PROJECT_LABEL.to_string()])
+        .await?;
+    if reconnect {
+        let _scan_guard = DISCOVERY_SCAN_LOCK.lock().await;
+        let now = now_rfc3339()?;
+        scan_host(state, host, &now).await?;
+    }
+
+    while let Some(event) = rx.recv().await {
+        let mut projects = BTreeSet::<String>::new();
+        projects.extend(event.attributes.get(PROJECT_LABEL).cloned());
+
+        let deadline = tokio::time::Instant::now() + EVENT_DEBOUNCE;
+        while let Ok(Some(event)) = tokio::time::timeout_at(deadline, rx.recv()).await {
+            projects.extend(event.attributes.get(PROJECT_LABEL).cloned());
+        }
+
+        if projects.is_empty() {
+            continue;
+        }
+        match sync_projects(state, host, &projects).await {
+            Ok(actions) => {
+                for action in actions {
+                    tracing::info!(
+                        host = %action.host_id,
+                        project = %action.project,
+                        action = ?action.action,
+                        reason = action.reason.as_deref().unwrap_or(""),
+                        "discovery event sync"
+                    );
+                }
+            }
+            Err(e) => tracing::warn!(host = host.id(), error = %e, "discovery event sync failed"),
+        }
+    }
+    Ok(())
+}
+
+/// Incremental form of [`run_scan`] for the given projects of `host`: running ones are
+/// reconciled, the others are marked missing.
+pub async fn sync_projects(
+    state: &AppState,
+    host: &Host,
+    projects: &BTreeSet<String>,
+) -> anyhow::Result<Vec<DiscoveryAction>> {
+    let _scan_guard = DISCOVERY_SCAN_LOCK.lock().await;
+    let now = now_rfc3339()?;
+
+    let mut actions = Vec::new();
+    for project in projects {
+        let running = list_compose_projects_from_docker(
+            host.docker.as_ref(),
+            &format!("{PROJECT_LABEL}={project}"),
+        )
+        .await?;
+        match running.get(project) {
+            Some(labels) => {
+                actions.push(reconcile_project(state, host, project, labels, &now).await?)
+            }
+            None => {
+                if state
+                    .db
+                    .mark_discovered_compose_project_missing(host.id(), project, &now)
+                    .await?
+                {
+                    actions.push(DiscoveryAction {
+                        host_id: host.id().to_string(),
+                        project: project.clone(),
+                        action: DiscoveryActionKind::MarkedMissing,
+                        stack_id: None,
+                        reason: None,
+                        details: None,
+                    });
+                }
+            }
+        }
+    }
+    Ok(actions)
+}
+
+/// Reconciles every running compose project of `host` and marks the others missing.
+async fn scan_host(
+    state: &AppState,
+    host: &Host,
+    now: &str,
+) -> anyhow::Result<Vec<DiscoveryAction>> {
+    let projects = list_compose_projects_from_docker(host.docker.as_ref(), PROJECT_LABEL).await?;
+
+    let mut actions = Vec::<DiscoveryAction>::new();
+    for (project, labels) in &projects {
+        actions.push(reconcile_project(state, host, project, labels, now).await?);
+    }
+
+    let seen_projects = projects.keys().cloned().collect::<Vec<_>>();
+    let newly_missing = state
+        .db
+        .mark_discovered_compose_projects_missing_except(host.id(), &seen_projects, now)
+        .await?;
+    for project in newly_missing {
+        actions.push(DiscoveryAction {
+            host_id: host.id().to_string(),
+            project,
+            action: DiscoveryActionKind::MarkedMissing,
+            stack_id: None,
+            reason: None,
+            details: None,
+        });
+    }
+    Ok(actions)
 }
 
 pub async fn run_scan(state: &AppState) -> anyhow::Result<TriggerDiscoveryScanResponse> {
@@ -240,10 +325,18 @@ pub async fn run_scan(state: &AppState) -> anyhow::Result<TriggerDiscoveryScanRe
     let start = std::time::Instant::now();
     let now = started_at.clone();
 
-    let projects = list_compose_projects_from_docker(state).await?;
-
-    let mut seen_projects = Vec::<String>::new();
     let mut actions = Vec::<DiscoveryAction>::new();
+    for host in state.hosts.list() {
+        match scan_host(state, &host, &now).await {
+            Ok(host_actions) => actions.extend(host_actions),
+            // An unreachable remote host must not block discovery of the others; its projects
+            // keep their last known state until it answers again.
+            Err(e) if !host.is_local() => {
+                tracing::warn!(host = host.id(), error = %e, "discovery scan of host failed");
+            }
+            Err(e) => return Err(e),
+        }
+    }
 
     let mut summary = DiscoveryScanSummary {
         projects_seen: 0,
@@ -253,315 +346,316 @@ pub async fn run_scan(state: &AppState) -> anyhow::Result<TriggerDiscoveryScanRe
         stacks_failed: 0,
         stacks_marked_missing: 0,
     };
-
-    for (project, labels) in &projects {
-        seen_projects.push(project.clone());
-        summary.projects_seen += 1;
-
-        let config_files_raw = match labels.config_files_raw.as_deref() {
-            None => {
-                summary.stacks_failed += 1;
-                state
-                    .db
-                    .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
-                        project: project.clone(),
-                        stack_id: None,
-                        status: "invalid".to_string(),
-                        last_seen_at: Some(now.clone()),
-                        last_scan_at: now.clone(),
-                        last_error: Some("config_files_missing".to_string()),
-                        last_config_files: None,
-                        unarchive_if_active: false,
-                    })
-                    .await?;
-                actions.push(DiscoveryAction {
-                    project: project.clone(),
-                    action: DiscoveryActionKind::Failed,
-                    stack_id: None,
-                    reason: Some("config_files_missing".to_string()),
-                    details: None,
-                });
-                continue;
-            }
-            Some("__CONFLICT__") => {
-                summary.stacks_failed += 1;
-                state
-                    .db
-                    .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
-                        project: project.clone(),
-                        stack_id: None,
-                        status: "invalid".to_string(),
-                        last_seen_at: Some(now.clone()),
-                        last_scan_at: now.clone(),
-                        last_error: Some("config_files_conflict".to_string()),
-                        last_config_files: None,
-                        unarchive_if_active: false,
-                    })
-                    .await?;
-                actions.push(DiscoveryAction {
-                    project: project.clone(),
-                    action: DiscoveryActionKind::Failed,
-                    stack_id: None,
-                    reason: Some("config_files_conflict".to_string()),
-                    details: None,
-                });
-                continue;
-            }
-            Some(v) => v,
-        };
-
-        let config_files = match normalize_config_files(config_files_raw) {
-            Ok(v) => v,
-            Err(NormalizeConfigFilesError::RelativePathRejected) => {
-                summary.stacks_failed += 1;
-                state
-                    .db
-                    .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
-                        project: project.clone(),
-                        stack_id: None,
-                        status: "invalid".to_string(),
-                        last_seen_at: Some(now.clone()),
-                        last_scan_at: now.clone(),
-                        last_error: Some("config_files_relative_path_rejected".to_string()),
-                        last_config_files: None,
-                        unarchive_if_active: false,
-                    })
-                    .await?;
-                actions.push(DiscoveryAction {
-                    project: project.clone(),
-                    action: DiscoveryActionKind::Failed,
-                    stack_id: None,
-                    reason: Some("config_files_relative_path_rejected".to_string()),
-                    details: None,
-                });
-                continue;
-            }
-            Err(NormalizeConfigFilesError::Empty) => {
-                summary.stacks_failed += 1;
-                state
-                    .db
-                    .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
-                        project: project.clone(),
-                        stack_id: None,
-                        status: "invalid".to_string(),
-                        last_seen_at: Some(now.clone()),
-                        last_scan_at: now.clone(),
-                        last_error: Some("config_files_empty".to_string()),
-                        last_config_files: None,
-                        unarchive_if_active: false,
-                    })
-                    .await?;
-                actions.push(DiscoveryAction {
-                    project: project.clone(),
-                    action: DiscoveryActionKind::Failed,
-                    stack_id: None,
-                    reason: Some("config_files_empty".to_string()),
-                    details: None,
-                });
+    for action in &actions {
+        match action.action {
+            DiscoveryActionKind::Created => summary.stacks_created += 1,
+            DiscoveryActionKind::Updated => summary.stacks_updated += 1,
+            DiscoveryActionKind::Skipped => summary.stacks_skipped += 1,
+            DiscoveryActionKind::Failed => summary.stacks_failed += 1,
+            DiscoveryActionKind::MarkedMissing => {
+                summary.stacks_marked_missing += 1;
                 continue;
             }
-        };
-
-        let mut merged: BTreeMap<String, compose::ServiceFromCompose> = BTreeMap::new();
-        let mut failure_reason: Option<String> = None;
-
-        for path in &config_files {
-            let contents = match tokio::fs::read_to_string(path).await {
-                Ok(v) => v,
-                Err(e) => {
-                    failure_reason = Some(format!(
-                        "compose_file_unreadable: {path} ({e}) (mount missing? ensure host path is mounted read-only at the same absolute path)"
-                    ));
-                    break;
-                }
-            };
-
-            match compose::parse_services(&contents) {
-                Ok(parsed) => {
-                    merged = compose::merge_services(merged, parsed);
-                }
-                Err(e) => {
-                    failure_reason = Some(format!("compose_file_invalid: {path} ({e})"));
-                    break;
-                }
-            }
         }
+        summary.projects_seen += 1;
+    }
 
-        if failure_reason.is_none() && merged.is_empty() {
-            failure_reason = Some("compose_no_services".to_string());
-        }
+    if actions.len() > state.config.discovery_max_actions as usize {
+        actions.truncate(state.config.discovery_max_actions as usize);
+    }
+
+    let duration_ms = start.elapsed().as_millis() as u64;
+    Ok(TriggerDiscoveryScanResponse {
+        started_at,
+        duration_ms,
+        summary,
+        actions,
+    })
+}
 
-        if let Some(msg) = failure_reason {
-            summary.stacks_failed += 1;
+/// Creates or syncs the stack of one running compose project and records the outcome.
+async fn reconcile_project(
+    state: &AppState,
+    host: &Host,
+    project: &str,
+    labels: &ProjectLabels,
+    now: &str,
+) -> anyhow::Result<DiscoveryAction> {
+    let host_id = host.id();
+    let config_files_raw = match labels.config_files_raw.as_deref() {
+        None => {
             state
                 .db
                 .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
-                    project: project.clone(),
+                    host_id: host_id.to_string(),
+                    project: project.to_string(),
                     stack_id: None,
                     status: "invalid".to_string(),
-                    last_seen_at: Some(now.clone()),
-                    last_scan_at: now.clone(),
-                    last_error: Some(msg.clone()),
-                    last_config_files: Some(config_files.clone()),
+                    last_seen_at: Some(now.to_string()),
+                    last_scan_at: now.to_string(),
+                    last_error: Some("config_files_missing".to_string()),
+                    last_config_files: None,
                     unarchive_if_active: false,
                 })
                 .await?;
-            actions.push(DiscoveryAction {
-                project: project.clone(),
+            return Ok(DiscoveryAction {
+                host_id: host_id.to_string(),
+                project: project.to_string(),
                 action: DiscoveryActionKind::Failed,
                 stack_id: None,
-                reason: Some(msg),
+                reason: Some("config_files_missing".to_string()),
                 details: None,
             });
-            continue;
         }
-
-        let svc_specs: Vec<ComposeServiceSpec> = merged
-            .values()
-            .map(|svc| ComposeServiceSpec {
-                name: svc.name.clone(),
-                image_ref: svc.image_ref.clone(),
-                image_tag: svc.image_tag.clone(),
-            })
-            .collect();
-
-        let existing = state.db.get_discovered_compose_project(project).await?;
-        let mut stack_id = existing.as_ref().and_then(|r| r.stack_id.clone());
-        let mut stack_exists = false;
-
-        if let Some(id) = stack_id.as_deref() {
-            stack_exists = state.db.get_stack(id).await?.is_some();
-        }
-
-        if stack_id.is_none() || !stack_exists {
-            let new_stack_id = ids::new_stack_id();
-            let stack = crate::api::types::StackRecord {
-                id: new_stack_id.clone(),
-                name: project.clone(),
-                archived: false,
-                compose: crate::api::types::ComposeConfig {
-                    kind: "path".to_string(),
-                    compose_files: config_files.clone(),
-                    env_file: None,
-                },
-                backup: crate::api::types::StackBackupConfig::default(),
-                services: Vec::new(),
-            };
-
-            let mut seeds = Vec::new();
-            for svc in merged.values() {
-                seeds.push(crate::api::types::ServiceSeed {
-                    id: ids::new_service_id(),
-                    name: svc.name.clone(),
-                    image_ref: svc.image_ref.clone(),
-                    image_tag: svc.image_tag.clone(),
-                    auto_rollback: true,
-                    backup_bind_paths: BTreeMap::new(),
-                    backup_volume_names: BTreeMap::new(),
-                });
-            }
-
-            state.db.insert_stack(&stack, &seeds, &now).await?;
-            stack_id = Some(new_stack_id.clone());
-            summary.stacks_created += 1;
+        Some("__CONFLICT__") => {
             state
                 .db
                 .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
-                    project: project.clone(),
-                    stack_id: stack_id.clone(),
-                    status: "active".to_string(),
-                    last_seen_at: Some(now.clone()),
-                    last_scan_at: now.clone(),
-                    last_error: None,
-                    last_config_files: Some(config_files.clone()),
-                    unarchive_if_active: true,
+                    host_id: host_id.to_string(),
+                    project: project.to_string(),
+                    stack_id: None,
+                    status: "invalid".to_string(),
+                    last_seen_at: Some(now.to_string()),
+                    last_scan_at: now.to_string(),
+                    last_error: Some("config_files_conflict".to_string()),
+                    last_config_files: None,
+                    unarchive_if_active: false,
                 })
                 .await?;
-            actions.push(DiscoveryAction {
-                project: project.clone(),
-                action: DiscoveryActionKind::Created,
-                stack_id: stack_id.clone(),
-                reason: None,
+            return Ok(DiscoveryAction {
+                host_id: host_id.to_string(),
+                project: project.to_string(),
+                action: DiscoveryActionKind::Failed,
+                stack_id: None,
+                reason: Some("config_files_conflict".to_string()),
                 details: None,
             });
-            continue;
         }
+        Some(v) => v,
+    };
 
-        let stack_id = stack_id.expect("stack id missing after create path");
-        let stack = state
-            .db
-            .get_stack(&stack_id)
-            .await?
-            .context("stack missing")?;
-        let needs_update = stack.compose.compose_files != config_files;
-        let needs_service_sync = !stack_services_match_specs(&stack, &svc_specs);
-        let needs_sync = needs_update || needs_service_sync;
-
-        if needs_sync {
+    let config_files = match normalize_config_files(config_files_raw) {
+        Ok(v) => v,
+        Err(NormalizeConfigFilesError::RelativePathRejected) => {
             state
                 .db
-                .sync_stack_from_compose(&stack_id, &config_files, &svc_specs, &now)
+                .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
+                    host_id: host_id.to_string(),
+                    project: project.to_string(),
+                    stack_id: None,
+                    status: "invalid".to_string(),
+                    last_seen_at: Some(now.to_string()),
+                    last_scan_at: now.to_string(),
+                    last_error: Some("config_files_relative_path_rejected".to_string()),
+                    last_config_files: None,
+                    unarchive_if_active: false,
+                })
                 .await?;
-            summary.stacks_updated += 1;
-            actions.push(DiscoveryAction {
-                project: project.clone(),
-                action: DiscoveryActionKind::Updated,
-                stack_id: Some(stack_id.clone()),
-                reason: None,
+            return Ok(DiscoveryAction {
+                host_id: host_id.to_string(),
+                project: project.to_string(),
+                action: DiscoveryActionKind::Failed,
+                stack_id: None,
+                reason: Some("config_files_relative_path_rejected".to_string()),
                 details: None,
             });
-        } else {
-            summary.stacks_skipped += 1;
-            actions.push(DiscoveryAction {
-                project: project.clone(),
-                action: DiscoveryActionKind::Skipped,
-                stack_id: Some(stack_id.clone()),
-                reason: None,
+        }
+        Err(NormalizeConfigFilesError::Empty) => {
+            state
+                .db
+                .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
+                    host_id: host_id.to_string(),
+                    project: project.to_string(),
+                    stack_id: None,
+                    status: "invalid".to_string(),
+                    last_seen_at: Some(now.to_string()),
+                    last_scan_at: now.to_string(),
+                    last_error: Some("config_files_empty".to_string()),
+                    last_config_files: None,
+                    unarchive_if_active: false,
+                })
+                .await?;
+            return Ok(DiscoveryAction {
+                host_id: host_id.to_string(),
+                project: project.to_string(),
+                action: DiscoveryActionKind::Failed,
+                stack_id: None,
+                reason: Some("config_files_empty".to_string()),
                 details: None,
             });
         }
+    };
+
+    let mut merged: BTreeMap<String, compose::ServiceFromCompose> = BTreeMap::new();
+    let mut failure_reason: Option<String> = None;
+
+    for path in &config_files {
+        let contents = match host.read_file(path).await {
+            Ok(v) => v,
+            Err(e) => {
+                failure_reason = Some(format!(
+                    "compose_file_unreadable: {path} ({e}) (mount missing? ensure host path is mounted read-only at the same absolute path)"
+                ));
+                break;
+            }
+        };
+
+        match compose::parse_services(&contents) {
+            Ok(parsed) => {
+                merged = compose::merge_services(merged, parsed);
+            }
+            Err(e) => {
+                failure_reason = Some(format!("compose_file_invalid: {path} ({e})"));
+                break;
+            }
+        }
+    }
+
+    if failure_reason.is_none() && merged.is_empty() {
+        failure_reason = Some("compose_no_services".to_string());
+    }
 
+    if let Some(msg) = failure_reason {
         state
             .db
             .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
-                project: project.clone(),
-                stack_id: Some(stack_id.clone()),
-                status: "active".to_string(),
-                last_seen_at: Some(now.clone()),
-                last_scan_at: now.clone(),
-                last_error: None,
-                last_config_files: Some(config_files),
-                unarchive_if_active: true,
+                host_id: host_id.to_string(),
+                project: project.to_string(),
+                stack_id: None,
+                status: "invalid".to_string(),
+                last_seen_at: Some(now.to_string()),
+                last_scan_at: now.to_string(),
+                last_error: Some(msg.clone()),
+                last_config_files: Some(config_files.clone()),
+                unarchive_if_active: false,
             })
             .await?;
+        return Ok(DiscoveryAction {
+            host_id: host_id.to_string(),
+            project: project.to_string(),
+            action: DiscoveryActionKind::Failed,
+            stack_id: None,
+            reason: Some(msg),
+            details: None,
+        });
     }
 
-    let newly_missing = state
+    let svc_specs: Vec<ComposeServiceSpec> = merged
+        .values()
+        .map(|svc| ComposeServiceSpec {
+            name: svc.name.clone(),
+            image_ref: svc.image_ref.clone(),
+            image_tag: svc.image_tag.clone(),
+        })
+        .collect();
+
+    let existing = state
         .db
-        .mark_discovered_compose_projects_missing_except(&seen_projects, &now)
+        .get_discovered_compose_project(host_id, project)
         .await?;
-    summary.stacks_marked_missing = newly_missing.len() as u32;
+    let mut stack_id = existing.as_ref().and_then(|r| r.stack_id.clone());
+    let mut stack_exists = false;
 
-    for project in newly_missing {
-        actions.push(DiscoveryAction {
-            project,
-            action: DiscoveryActionKind::MarkedMissing,
-            stack_id: None,
+    if let Some(id) = stack_id.as_deref() {
+        stack_exists = state.db.get_stack(id).await?.is_some();
+    }
+
+    if stack_id.is_none() || !stack_exists {
+        let new_stack_id = ids::new_stack_id();
+        let stack = crate::api::types::StackRecord {
+            id: new_stack_id.clone(),
+            name: project.to_string(),
+            host_id: host_id.to_string(),
+            archived: false,
+            compose: crate::api::types::ComposeConfig {
+                kind: "path".to_string(),
+                compose_files: config_files.clone(),
+                env_file: None,
+            },
+            backup: crate::api::types::StackBackupConfig::default(),
+            services: Vec::new(),
+        };
+
+        let mut seeds = Vec::new();
+        for svc in merged.values() {
+            seeds.push(crate::api::types::ServiceSeed {
+                id: ids::new_service_id(),
+                name: svc.name.clone(),
+                image_ref: svc.image_ref.clone(),
+                image_tag: svc.image_tag.clone(),
+                auto_rollback: true,
+                backup_bind_paths: BTreeMap::new(),
+                backup_volume_names: BTreeMap::new(),
+            });
+        }
+
+        state.db.insert_stack(&stack, &seeds, now).await?;
+        stack_id = Some(new_stack_id.clone());
+        state
+            .db
+            .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
+                host_id: host_id.to_string(),
+                project: project.to_string(),
+                stack_id: stack_id.clone(),
+                status: "active".to_string(),
+                last_seen_at: Some(now.to_string()),
+                last_scan_at: now.to_string(),
+                last_error: None,
+                last_config_files: Some(config_files.clone()),
+                unarchive_if_active: true,
+            })
+            .await?;
+        return Ok(DiscoveryAction {
+            host_id: host_id.to_string(),
+            project: project.to_string(),
+            action: DiscoveryActionKind::Created,
+            stack_id: stack_id.clone(),
             reason: None,
             details: None,
         });
     }
 
-    if actions.len() > state.config.discovery_max_actions as usize {
-        actions.truncate(state.config.discovery_max_actions as usize);
-    }
+    let stack_id = stack_id.expect("stack id missing after create path");
+    let stack = state
+        .db
+        .get_stack(&stack_id)
+        .await?
+        .context("stack missing")?;
+    let needs_update = stack.compose.compose_files != config_files;
+    let needs_service_sync = !stack_services_match_specs(&stack, &svc_specs);
+    let needs_sync = needs_update || needs_service_sync;
+
+    let action = if needs_sync {
+        state
+            .db
+            .sync_stack_from_compose(&stack_id, &config_files, &svc_specs, now)
+            .await?;
+        DiscoveryActionKind::Updated
+    } else {
+        DiscoveryActionKind::Skipped
+    };
 
-    let duration_ms = start.elapsed().as_millis() as u64;
-    Ok(TriggerDiscoveryScanResponse {
-        started_at,
-        duration_ms,
-        summary,
-        actions,
+    state
+        .db
+        .upsert_discovered_compose_project(DiscoveredComposeProjectUpsert {
+            host_id: host_id.to_string(),
+            project: project.to_string(),
+            stack_id: Some(stack_id.clone()),
+            status: "active".to_string(),
+            last_seen_at: Some(now.to_string()),
+            last_scan_at: now.to_string(),
+            last_error: None,
+            last_config_files: Some(config_files),
+            unarchive_if_active: true,
+        })
+        .await?;
+    Ok(DiscoveryAction {
+        host_id: host_id.to_string(),
+        project: project.to_string(),
+        action,
+        stack_id: Some(stack_id),
+        reason: None,
+        details: None,
     })
 }
 
@@ -569,30 +663,12 @@ pub async fn run_scan(state: &AppState) -> anyhow::Result<TriggerDiscoveryScanRe
 mod tests {
     use super::*;
 
-    #[test]
-    fn parse_labels_json_line_null_is_empty() {
-        let out = parse_labels_json_line("null").unwrap();
-        assert!(out.is_empty());
-    }
-
-    #[test]
-    fn parse_labels_json_line_non_object_is_empty() {
-        let out = parse_labels_json_line("[]").unwrap();
-        assert!(out.is_empty());
-    }
-
-    #[test]
-    fn parse_labels_json_line_object_extracts_strings() {
-        let out = parse_labels_json_line(r#"{"a":"b","n":123}"#).unwrap();
-        assert_eq!(out.get("a").map(String::as_str), Some("b"));
-        assert_eq!(out.get("n"), None);
-    }
-
     #[test]
     fn stack_services_match_specs_detects_changes() {
         let stack = crate::api::types::StackRecord {
             id: "stk_1".to_string(),
             name: "demo".to_string(),
+            host_id: crate::hosts::LOCAL_HOST_ID.to_string(),
             archived: false,
             compose: crate::api::types::ComposeConfig {
                 kind: "path".to_string(),
@@ -606,6 +682,7 @@ mod tests {
                 image: crate::api::types::ComposeRef {
                     reference: "ghcr.io/acme/web:1.0".to_string(),
                     tag: "1.0".to_string(),
+                    variant: None,
                     digest: None,
                     resolved_tag: None,
                     resolved_tags: None,
@@ -618,6 +695,9 @@ mod tests {
                         bind_paths: BTreeMap::new(),
                         volume_names: BTreeMap::new(),
                     },
+                    auto_update: Default::default(),
+                    version_strategy: Default::default(),
+                    prerelease_policy: None,
                 },
                 archived: None,
             }],
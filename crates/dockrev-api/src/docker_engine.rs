@@ -0,0 +1,833 @@
+use std::{collections::BTreeMap, path::Path, sync::Arc, time::Duration};
+
+use anyhow::Context as _;
+use serde::{Deserialize, de::DeserializeOwned};
+use tokio::sync::mpsc;
+
+use crate::{
+    docker_runner::{self, DockerRunnerConfig},
+    registry::{DEFAULT_TAG, ImageRef},
+    runner::{CommandRunner, CommandSpec, OutputLine},
+};
+
+const DEFAULT_DOCKER_HOST: &str = "unix:///var/run/docker.sock";
+const INSPECT_CHUNK: usize = 64;
+/// Container lifecycle events that can change what discovery sees.
+pub const CONTAINER_EVENT_ACTIONS: [&str; 4] = ["create", "start", "die", "destroy"];
+/// `docker events` never exits on its own; the caller reconnects after this.
+const CLI_EVENTS_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The part of the Docker Engine that Dockrev reads or changes directly (compose operations
+/// still go through the compose CLI).
+#[async_trait::async_trait]
+pub trait DockerEngine: Send + Sync {
+    /// Running containers matching every label filter (`key` or `key=value`).
+    async fn list_containers(
+        &self,
+        label_filters: &[String],
+    ) -> anyhow::Result<Vec<ContainerSummary>>;
+
+    async fn inspect_container(&self, container_id: &str) -> anyhow::Result<ContainerDetails>;
+
+    async fn inspect_image(&self, image_id: &str) -> anyhow::Result<ImageDetails>;
+
+    async fn tag_image(&self, image_id: &str, image_ref: &str) -> anyhow::Result<()>;
+
+    /// Subscribes to [`CONTAINER_EVENT_ACTIONS`] of containers matching every label filter. The
+    /// receiver closes when the underlying stream ends or fails.
+    async fn container_events(
+        &self,
+        label_filters: &[String],
+    ) -> anyhow::Result<mpsc::Receiver<ContainerEvent>>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContainerSummary {
+    pub id: String,
+    pub image_id: String,
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContainerDetails {
+    pub id: String,
+    pub image_id: String,
+    pub labels: BTreeMap<String, String>,
+    /// `State.Health.Status`; `None` when the container has no healthcheck.
+    pub health: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageDetails {
+    pub id: String,
+    pub repo_digests: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContainerEvent {
+    pub action: String,
+    pub container_id: String,
+    /// Container labels (plus a few engine attributes such as `name` and `image`).
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerListJson {
+    id: String,
+    #[serde(rename = "ImageID")]
+    image_id: String,
+    labels: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerInspectJson {
+    id: String,
+    image: String,
+    config: Option<ContainerConfigJson>,
+    state: Option<ContainerStateJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerConfigJson {
+    labels: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerStateJson {
+    health: Option<ContainerHealthJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerHealthJson {
+    status: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ImageInspectJson {
+    id: String,
+    repo_digests: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct EventJson {
+    #[serde(rename = "Type")]
+    kind: Option<String>,
+    #[serde(rename = "Action")]
+    action: Option<String>,
+    #[serde(rename = "Actor")]
+    actor: Option<EventActorJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct EventActorJson {
+    #[serde(rename = "ID")]
+    id: String,
+    attributes: Option<BTreeMap<String, String>>,
+}
+
+/// Parses one line of the Engine API / `docker events --format '{{json .}}'` stream.
+fn parse_event_line(line: &str) -> Option<ContainerEvent> {
+    let v = serde_json::from_str::<EventJson>(line.trim()).ok()?;
+    if v.kind.as_deref().is_some_and(|k| k != "container") {
+        return None;
+    }
+    let actor = v.actor?;
+    Some(ContainerEvent {
+        action: v.action?,
+        container_id: actor.id,
+        attributes: actor.attributes.unwrap_or_default(),
+    })
+}
+
+#[derive(Deserialize)]
+struct EngineErrorJson {
+    message: String,
+}
+
+impl From<ContainerInspectJson> for ContainerDetails {
+    fn from(v: ContainerInspectJson) -> Self {
+        Self {
+            id: v.id,
+            image_id: v.image,
+            labels: v.config.and_then(|c| c.labels).unwrap_or_default(),
+            health: v
+                .state
+                .and_then(|s| s.health)
+                .map(|h| h.status)
+                .filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+impl From<ImageInspectJson> for ImageDetails {
+    fn from(v: ImageInspectJson) -> Self {
+        Self {
+            id: v.id,
+            repo_digests: v.repo_digests.unwrap_or_default(),
+        }
+    }
+}
+
+/// Talks to the Engine API over a unix socket or plain TCP (`DOCKER_HOST`).
+pub struct HttpDockerEngine {
+    client: reqwest::Client,
+    /// Same transport without the request timeout, for the long-lived events stream.
+    stream_client: reqwest::Client,
+    base_url: String,
+}
+
+impl HttpDockerEngine {
+    /// `docker_host` accepts `unix:///path`, `tcp://host:port` and `http://host:port`.
+    pub fn new(docker_host: &str) -> anyhow::Result<Self> {
+        Self::build(docker_host, None)
+    }
+
+    /// TLS-protected `tcp://` endpoint; `cert_dir` holds `ca.pem`, `cert.pem` and `key.pem` like
+    /// `DOCKER_CERT_PATH`.
+    pub fn with_tls(docker_host: &str, cert_dir: &Path) -> anyhow::Result<Self> {
+        let read = |name: &str| {
+            let path = cert_dir.join(name);
+            std::fs::read(&path).with_context(|| format!("read {}", path.display()))
+        };
+        let ca = reqwest::Certificate::from_pem(&read("ca.pem")?).context("parse ca.pem")?;
+        let mut identity_pem = read("cert.pem")?;
+        identity_pem.push(b'\n');
+        identity_pem.extend(read("key.pem")?);
+        let identity =
+            reqwest::Identity::from_pem(&identity_pem).context("parse cert.pem / key.pem")?;
+        Self::build(docker_host, Some((ca, identity)))
+    }
+
+    fn build(
+        docker_host: &str,
+        tls: Option<(reqwest::Certificate, reqwest::Identity)>,
+    ) -> anyhow::Result<Self> {
+        let (socket, base_url) = if let Some(path) = docker_host.strip_prefix("unix://") {
+            if path.is_empty() {
+                return Err(anyhow::anyhow!("invalid DOCKER_HOST: {docker_host}"));
+            }
+            (Some(path), "http://localhost".to_string())
+        } else if let Some(addr) = docker_host
+            .strip_prefix("tcp://")
+            .or_else(|| docker_host.strip_prefix("http://"))
+        {
+            let addr = addr.trim_end_matches('/');
+            if addr.is_empty() {
+                return Err(anyhow::anyhow!("invalid DOCKER_HOST: {docker_host}"));
+            }
+            let scheme = if tls.is_some() { "https" } else { "http" };
+            (None, format!("{scheme}://{addr}"))
+        } else {
+            return Err(anyhow::anyhow!(
+                "unsupported DOCKER_HOST (expected unix:// or tcp://): {docker_host}"
+            ));
+        };
+
+        let build = |timeout: Option<Duration>| -> anyhow::Result<reqwest::Client> {
+            let mut builder = reqwest::Client::builder().connect_timeout(Duration::from_secs(3));
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(timeout);
+            }
+            if let Some(path) = socket {
+                builder = unix_socket(builder, path)?;
+            }
+            if let Some((ca, identity)) = &tls {
+                builder = builder
+                    .add_root_certificate(ca.clone())
+                    .identity(identity.clone());
+            }
+            builder.build().context("build docker engine client")
+        };
+
+        Ok(Self {
+            client: build(Some(Duration::from_secs(30)))?,
+            stream_client: build(None)?,
+            base_url,
+        })
+    }
+
+    pub async fn ping(&self) -> anyhow::Result<()> {
+        let resp = self
+            .client
+            .get(format!("{}/_ping", self.base_url))
+            .send()
+            .await
+            .context("docker engine ping")?;
+        check_status(resp, "GET /_ping").await?;
+        Ok(())
+    }
+
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> anyhow::Result<T> {
+        let resp = self
+            .client
+            .get(format!("{}{path}", self.base_url))
+            .query(query)
+            .send()
+            .await
+            .with_context(|| format!("docker engine GET {path}"))?;
+        let resp = check_status(resp, &format!("GET {path}")).await?;
+        resp.json::<T>()
+            .await
+            .with_context(|| format!("parse docker engine GET {path}"))
+    }
+}
+
+#[cfg(unix)]
+fn unix_socket(
+    builder: reqwest::ClientBuilder,
+    path: &str,
+) -> anyhow::Result<reqwest::ClientBuilder> {
+    Ok(builder.unix_socket(path))
+}
+
+#[cfg(not(unix))]
+fn unix_socket(
+    _builder: reqwest::ClientBuilder,
+    path: &str,
+) -> anyhow::Result<reqwest::ClientBuilder> {
+    Err(anyhow::anyhow!("unix sockets are not supported: {path}"))
+}
+
+async fn check_status(resp: reqwest::Response, what: &str) -> anyhow::Result<reqwest::Response> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body = resp.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<EngineErrorJson>(&body)
+        .map(|e| e.message)
+        .unwrap_or(body);
+    Err(anyhow::anyhow!(
+        "docker engine {what} failed status={} message={}",
+        status.as_u16(),
+        message.trim()
+    ))
+}
+
+#[async_trait::async_trait]
+impl DockerEngine for HttpDockerEngine {
+    async fn list_containers(
+        &self,
+        label_filters: &[String],
+    ) -> anyhow::Result<Vec<ContainerSummary>> {
+        let filters = serde_json::json!({ "label": label_filters }).to_string();
+        let list = self
+            .get_json::<Vec<ContainerListJson>>("/containers/json", &[("filters", &filters)])
+            .await?;
+        Ok(list
+            .into_iter()
+            .map(|c| ContainerSummary {
+                id: c.id,
+                image_id: c.image_id,
+                labels: c.labels.unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    async fn inspect_container(&self, container_id: &str) -> anyhow::Result<ContainerDetails> {
+        let v = self
+            .get_json::<ContainerInspectJson>(&format!("/containers/{container_id}/json"), &[])
+            .await?;
+        Ok(v.into())
+    }
+
+    async fn inspect_image(&self, image_id: &str) -> anyhow::Result<ImageDetails> {
+        let v = self
+            .get_json::<ImageInspectJson>(&format!("/images/{image_id}/json"), &[])
+            .await?;
+        Ok(v.into())
+    }
+
+    async fn tag_image(&self, image_id: &str, image_ref: &str) -> anyhow::Result<()> {
+        let (repo, tag) = split_repo_tag(image_ref)?;
+        let path = format!("/images/{image_id}/tag");
+        let resp = self
+            .client
+            .post(format!("{}{path}", self.base_url))
+            .query(&[("repo", &repo), ("tag", &tag)])
+            .send()
+            .await
+            .with_context(|| format!("docker engine POST {path}"))?;
+        check_status(resp, &format!("POST {path}")).await?;
+        Ok(())
+    }
+
+    async fn container_events(
+        &self,
+        label_filters: &[String],
+    ) -> anyhow::Result<mpsc::Receiver<ContainerEvent>> {
+        let filters = serde_json::json!({
+            "type": ["container"],
+            "event": CONTAINER_EVENT_ACTIONS,
+            "label": label_filters,
+        })
+        .to_string();
+        let resp = self
+            .stream_client
+            .get(format!("{}/events", self.base_url))
+            .query(&[("filters", &filters)])
+            .send()
+            .await
+            .context("docker engine GET /events")?;
+        let mut resp = check_status(resp, "GET /events").await?;
+
+        let (tx, rx) = mpsc::channel(256);
+        tokio::spawn(async move {
+            let mut buf = Vec::<u8>::new();
+            loop {
+                let chunk = match resp.chunk().await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => return,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "docker engine event stream failed");
+                        return;
+                    }
+                };
+                buf.extend_from_slice(&chunk);
+                while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+                    let line = buf.drain(..=pos).collect::<Vec<_>>();
+                    let Some(event) = parse_event_line(&String::from_utf8_lossy(&line)) else {
+                        continue;
+                    };
+                    if tx.send(event).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(rx)
+    }
+}
+
+/// Splits `repo[:tag][@digest]` into repo and tag (`latest` when missing).
+fn split_repo_tag(image_ref: &str) -> anyhow::Result<(String, String)> {
+    let img = ImageRef::parse(image_ref)?;
+    let tag = img.tag.clone().unwrap_or_else(|| DEFAULT_TAG.to_string());
+    Ok((img.familiar_name(), tag))
+}
+
+/// Fallback that shells out to the `docker` CLI through a [`CommandRunner`].
+pub struct CliDockerEngine {
+    runner: Arc<dyn CommandRunner>,
+    cfg: DockerRunnerConfig,
+}
+
+impl CliDockerEngine {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            runner,
+            cfg: DockerRunnerConfig::default(),
+        }
+    }
+
+    async fn run_stdout(&self, spec: CommandSpec, timeout: Duration) -> anyhow::Result<String> {
+        let what = format!("docker {}", spec.args.first().cloned().unwrap_or_default());
+        let out = self
+            .runner
+            .run(spec, timeout)
+            .await
+            .with_context(|| what.clone())?;
+        if out.status != 0 {
+            return Err(anyhow::anyhow!(
+                "{what} failed status={} stderr={}",
+                out.status,
+                out.stderr.trim()
+            ));
+        }
+        Ok(out.stdout)
+    }
+
+    async fn inspect_many(&self, ids: &[String]) -> anyhow::Result<Vec<ContainerDetails>> {
+        let mut out = Vec::new();
+        for chunk in ids.chunks(INSPECT_CHUNK) {
+            let stdout = self
+                .run_stdout(
+                    docker_runner::inspect_containers(&self.cfg, chunk),
+                    Duration::from_secs(12),
+                )
+                .await?;
+            let parsed = serde_json::from_str::<Vec<ContainerInspectJson>>(stdout.trim())
+                .context("parse docker inspect json")?;
+            out.extend(parsed.into_iter().map(ContainerDetails::from));
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait::async_trait]
+impl DockerEngine for CliDockerEngine {
+    async fn list_containers(
+        &self,
+        label_filters: &[String],
+    ) -> anyhow::Result<Vec<ContainerSummary>> {
+        let stdout = self
+            .run_stdout(
+                docker_runner::ps_ids(&self.cfg, label_filters),
+                Duration::from_secs(8),
+            )
+            .await?;
+        let ids = stdout
+            .lines()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(self
+            .inspect_many(&ids)
+            .await?
+            .into_iter()
+            .map(|c| ContainerSummary {
+                id: c.id,
+                image_id: c.image_id,
+                labels: c.labels,
+            })
+            .collect())
+    }
+
+    async fn inspect_container(&self, container_id: &str) -> anyhow::Result<ContainerDetails> {
+        self.inspect_many(&[container_id.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("docker inspect returned nothing for {container_id}"))
+    }
+
+    async fn inspect_image(&self, image_id: &str) -> anyhow::Result<ImageDetails> {
+        let stdout = self
+            .run_stdout(
+                docker_runner::inspect_image(&self.cfg, image_id),
+                Duration::from_secs(10),
+            )
+            .await?;
+        serde_json::from_str::<Vec<ImageInspectJson>>(stdout.trim())
+            .context("parse docker image inspect json")?
+            .into_iter()
+            .next()
+            .map(ImageDetails::from)
+            .ok_or_else(|| anyhow::anyhow!("docker image inspect returned nothing for {image_id}"))
+    }
+
+    async fn tag_image(&self, image_id: &str, image_ref: &str) -> anyhow::Result<()> {
+        self.run_stdout(
+            docker_runner::tag_image(&self.cfg, image_id, image_ref),
+            Duration::from_secs(30),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn container_events(
+        &self,
+        label_filters: &[String],
+    ) -> anyhow::Result<mpsc::Receiver<ContainerEvent>> {
+        let spec = docker_runner::events(&self.cfg, &CONTAINER_EVENT_ACTIONS, label_filters);
+        let (lines_tx, mut lines_rx) = mpsc::unbounded_channel::<OutputLine>();
+        let (tx, rx) = mpsc::channel(256);
+
+        let runner = self.runner.clone();
+        tokio::spawn(async move {
+            match runner
+                .run_streaming(spec, CLI_EVENTS_TIMEOUT, lines_tx)
+                .await
+            {
+                Ok(out) if out.status != 0 => tracing::warn!(
+                    status = out.status,
+                    stderr = %out.stderr.trim(),
+                    "docker events exited"
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::warn!(error = %e, "docker events failed"),
+            }
+        });
+        tokio::spawn(async move {
+            while let Some(line) = lines_rx.recv().await {
+                let Some(event) = parse_event_line(&line.line) else {
+                    continue;
+                };
+                if tx.send(event).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(rx)
+    }
+}
+
+/// Picks the engine backend: `api` (Engine API only), `cli` (docker CLI only) or `auto` (the
+/// Engine API when it answers `/_ping`, otherwise the CLI).
+pub async fn connect(
+    mode: &str,
+    docker_host: Option<&str>,
+    runner: Arc<dyn CommandRunner>,
+) -> anyhow::Result<Arc<dyn DockerEngine>> {
+    let host = docker_host.unwrap_or(DEFAULT_DOCKER_HOST);
+    match mode {
+        "cli" => Ok(Arc::new(CliDockerEngine::new(runner))),
+        "api" => Ok(Arc::new(HttpDockerEngine::new(host)?)),
+        _ => {
+            let probe = match HttpDockerEngine::new(host) {
+                Ok(engine) => engine.ping().await.map(|()| engine),
+                Err(e) => Err(e),
+            };
+            match probe {
+                Ok(engine) => {
+                    tracing::info!(docker_host = %host, "using docker engine api");
+                    Ok(Arc::new(engine))
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        docker_host = %host,
+                        error = %e,
+                        "docker engine api unavailable; falling back to docker cli"
+                    );
+                    Ok(Arc::new(CliDockerEngine::new(runner)))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::CommandOutput;
+
+    #[test]
+    fn splits_repo_and_tag() {
+        let split = |r: &str| split_repo_tag(r).unwrap();
+        assert_eq!(split("nginx:1.25"), ("nginx".into(), "1.25".into()));
+        assert_eq!(
+            split("registry:5000/acme/web"),
+            ("registry:5000/acme/web".into(), "latest".into())
+        );
+        assert_eq!(
+            split("registry:5000/acme/web:2@sha256:abc"),
+            ("registry:5000/acme/web".into(), "2".into())
+        );
+        assert!(split_repo_tag("acme/Web:2").is_err());
+    }
+
+    #[test]
+    fn parses_container_event_lines() {
+        let event = parse_event_line(
+            r#"{"status":"start","id":"c1","Type":"container","Action":"start","Actor":{"ID":"c1","Attributes":{"com.docker.compose.project":"demo","name":"demo-web-1"}},"time":1}"#,
+        )
+        .unwrap();
+        assert_eq!(event.action, "start");
+        assert_eq!(event.container_id, "c1");
+        assert_eq!(
+            event
+                .attributes
+                .get("com.docker.compose.project")
+                .map(|s| s.as_str()),
+            Some("demo")
+        );
+
+        assert_eq!(
+            parse_event_line(r#"{"Type":"network","Action":"connect","Actor":{"ID":"n1"}}"#),
+            None
+        );
+        assert_eq!(parse_event_line("not json"), None);
+    }
+
+    #[test]
+    fn inspect_json_tolerates_null_labels_and_missing_health() {
+        let v = serde_json::from_str::<ContainerInspectJson>(
+            r#"{"Id":"c1","Image":"sha256:i1","Config":{"Labels":null},"State":{"Status":"running"}}"#,
+        )
+        .unwrap();
+        let details = ContainerDetails::from(v);
+        assert!(details.labels.is_empty());
+        assert_eq!(details.health, None);
+    }
+
+    struct InspectRunner;
+
+    #[async_trait::async_trait]
+    impl CommandRunner for InspectRunner {
+        async fn run(
+            &self,
+            spec: CommandSpec,
+            _timeout: Duration,
+        ) -> anyhow::Result<CommandOutput> {
+            let stdout = match spec.args.first().map(|s| s.as_str()) {
+                Some("ps") => {
+                    assert!(
+                        spec.args
+                            .contains(&"label=com.docker.compose.project".to_string())
+                    );
+                    "c1\nc2\n".to_string()
+                }
+                Some("inspect") => serde_json::json!([
+                    {
+                        "Id": "c1",
+                        "Image": "sha256:i1",
+                        "Config": { "Labels": { "com.docker.compose.project": "demo" } },
+                        "State": { "Health": { "Status": "healthy" } }
+                    },
+                    { "Id": "c2", "Image": "sha256:i2", "Config": { "Labels": null }, "State": {} }
+                ])
+                .to_string(),
+                _ => String::new(),
+            };
+            Ok(CommandOutput {
+                status: 0,
+                stdout,
+                stderr: String::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn cli_engine_lists_containers_via_inspect() {
+        let engine = CliDockerEngine::new(Arc::new(InspectRunner));
+        let list = engine
+            .list_containers(&["com.docker.compose.project".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].image_id, "sha256:i1");
+        assert_eq!(
+            list[0]
+                .labels
+                .get("com.docker.compose.project")
+                .map(|s| s.as_str()),
+            Some("demo")
+        );
+        assert!(list[1].labels.is_empty());
+
+        let details = engine.inspect_container("c1").await.unwrap();
+        assert_eq!(details.health.as_deref(), Some("healthy"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn http_engine_talks_to_unix_socket() {
+        use axum::{
+            Json, Router,
+            extract::{Path, Query},
+            routing::{get, post},
+        };
+
+        let dir = std::env::temp_dir().join(format!("dockrev-engine-{}", crate::ids::new_job_id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("docker.sock");
+
+        let app = Router::new()
+            .route("/_ping", get(|| async { "OK" }))
+            .route(
+                "/containers/json",
+                get(|Query(q): Query<BTreeMap<String, String>>| async move {
+                    assert_eq!(
+                        q.get("filters").map(|s| s.as_str()),
+                        Some(r#"{"label":["com.docker.compose.project=demo"]}"#)
+                    );
+                    Json(serde_json::json!([
+                        { "Id": "c1", "ImageID": "sha256:i1", "Labels": { "a": "b" } }
+                    ]))
+                }),
+            )
+            .route(
+                "/containers/{id}/json",
+                get(|Path(id): Path<String>| async move {
+                    Json(serde_json::json!({
+                        "Id": id,
+                        "Image": "sha256:i1",
+                        "Config": { "Labels": {} },
+                        "State": { "Health": { "Status": "starting" } }
+                    }))
+                }),
+            )
+            .route(
+                "/images/{id}/json",
+                get(|Path(id): Path<String>| async move {
+                    if id == "missing" {
+                        return (
+                            axum::http::StatusCode::NOT_FOUND,
+                            Json(serde_json::json!({ "message": "No such image: missing" })),
+                        );
+                    }
+                    (
+                        axum::http::StatusCode::OK,
+                        Json(serde_json::json!({
+                            "Id": id,
+                            "RepoDigests": ["ghcr.io/acme/web@sha256:abc"]
+                        })),
+                    )
+                }),
+            )
+            .route(
+                "/events",
+                get(|Query(q): Query<BTreeMap<String, String>>| async move {
+                    assert!(q.get("filters").is_some_and(|f| f.contains(r#""event":["create","start","die","destroy"]"#)));
+                    concat!(
+                        r#"{"Type":"container","Action":"start","Actor":{"ID":"c1","Attributes":{"com.docker.compose.project":"demo"}}}"#,
+                        "\n",
+                        r#"{"Type":"container","Action":"die","Actor":{"ID":"c1","Attributes":{}}}"#,
+                        "\n",
+                    )
+                }),
+            )
+            .route(
+                "/images/{id}/tag",
+                post(|Query(q): Query<BTreeMap<String, String>>| async move {
+                    assert_eq!(q.get("repo").map(|s| s.as_str()), Some("ghcr.io/acme/web"));
+                    assert_eq!(q.get("tag").map(|s| s.as_str()), Some("1.2.3"));
+                    axum::http::StatusCode::CREATED
+                }),
+            );
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let engine = HttpDockerEngine::new(&format!("unix://{}", socket.display())).unwrap();
+        engine.ping().await.unwrap();
+
+        let list = engine
+            .list_containers(&["com.docker.compose.project=demo".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].labels.get("a").map(|s| s.as_str()), Some("b"));
+
+        let details = engine.inspect_container("c1").await.unwrap();
+        assert_eq!(details.id, "c1");
+        assert_eq!(details.health.as_deref(), Some("starting"));
+
+        let image = engine.inspect_image("sha256:i1").await.unwrap();
+        assert_eq!(image.repo_digests, vec!["ghcr.io/acme/web@sha256:abc"]);
+
+        let err = engine.inspect_image("missing").await.unwrap_err();
+        assert!(err.to_string().contains("No such image"), "{err}");
+
+        engine
+            .tag_image("sha256:i1", "ghcr.io/acme/web:1.2.3")
+            .await
+            .unwrap();
+
+        let mut events = engine
+            .container_events(&["com.docker.compose.project".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(events.recv().await.unwrap().action, "start");
+        assert_eq!(events.recv().await.unwrap().action, "die");
+        assert_eq!(events.recv().await, None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -13,42 +13,73 @@ impl Default for DockerRunnerConfig {
     }
 }
 
-pub fn inspect_health_status(cfg: &DockerRunnerConfig, container_id: &str) -> CommandSpec {
+/// Full ids of running containers matching every `label` filter (`key` or `key=value`).
+pub fn ps_ids(cfg: &DockerRunnerConfig, label_filters: &[String]) -> CommandSpec {
+    let mut args = vec!["ps".to_string(), "-q".to_string(), "--no-trunc".to_string()];
+    for label in label_filters {
+        args.push("--filter".to_string());
+        args.push(format!("label={label}"));
+    }
     CommandSpec {
         program: cfg.docker_bin.clone(),
-        args: vec![
-            "inspect".to_string(),
-            "--format".to_string(),
-            "{{.State.Health.Status}}".to_string(),
-            container_id.to_string(),
-        ],
+        args,
         env: Vec::new(),
+        stdin: None,
+        files: Vec::new(),
     }
 }
 
-pub fn inspect_has_healthcheck(cfg: &DockerRunnerConfig, container_id: &str) -> CommandSpec {
+/// Prints a JSON array with the Engine API inspect document of each container.
+pub fn inspect_containers(cfg: &DockerRunnerConfig, container_ids: &[String]) -> CommandSpec {
+    let mut args = vec!["inspect".to_string()];
+    args.extend(container_ids.iter().cloned());
     CommandSpec {
         program: cfg.docker_bin.clone(),
-        args: vec![
-            "inspect".to_string(),
-            "--format".to_string(),
-            "{{if .State.Health}}1{{else}}0{{end}}".to_string(),
-            container_id.to_string(),
-        ],
+        args,
         env: Vec::new(),
+        stdin: None,
+        files: Vec::new(),
     }
 }
 
-pub fn inspect_image_id(cfg: &DockerRunnerConfig, container_id: &str) -> CommandSpec {
+/// Prints a JSON array with the Engine API inspect document of the image.
+pub fn inspect_image(cfg: &DockerRunnerConfig, image_id: &str) -> CommandSpec {
     CommandSpec {
         program: cfg.docker_bin.clone(),
         args: vec![
+            "image".to_string(),
             "inspect".to_string(),
-            "--format".to_string(),
-            "{{.Image}}".to_string(),
-            container_id.to_string(),
+            image_id.to_string(),
         ],
         env: Vec::new(),
+        stdin: None,
+        files: Vec::new(),
+    }
+}
+
+/// Streams container events as JSON lines until killed.
+pub fn events(cfg: &DockerRunnerConfig, actions: &[&str], label_filters: &[String]) -> CommandSpec {
+    let mut args = vec![
+        "events".to_string(),
+        "--format".to_string(),
+        "{{json .}}".to_string(),
+        "--filter".to_string(),
+        "type=container".to_string(),
+    ];
+    for action in actions {
+        args.push("--filter".to_string());
+        args.push(format!("event={action}"));
+    }
+    for label in label_filters {
+        args.push("--filter".to_string());
+        args.push(format!("label={label}"));
+    }
+    CommandSpec {
+        program: cfg.docker_bin.clone(),
+        args,
+        env: Vec::new(),
+        stdin: None,
+        files: Vec::new(),
     }
 }
 
@@ -62,5 +93,7 @@ pub fn tag_image(cfg: &DockerRunnerConfig, image_id: &str, image_ref: &str) -> C
             image_ref.to_string(),
         ],
         env: Vec::new(),
+        stdin: None,
+        files: Vec::new(),
     }
 }
@@ -0,0 +1,162 @@
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::{api::types::JobLogLine, db::Db};
+
+const BUS_CAPACITY: usize = 1024;
+
+/// Job statuses after which a job never changes again.
+pub const TERMINAL_JOB_STATUSES: [&str; 5] = [
+    "success",
+    "failed",
+    "rolled_back",
+    "cancelled",
+    "interrupted",
+];
+
+/// Change notification published by [`Db`] writes.
+#[derive(Clone, Debug, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum Event {
+    JobLog {
+        job_id: String,
+        id: i64,
+        line: JobLogLine,
+    },
+    JobStatus {
+        job_id: String,
+        status: String,
+    },
+    Stack {
+        stack_id: String,
+    },
+    Service {
+        service_id: String,
+    },
+}
+
+impl Event {
+    /// SSE `event:` name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::JobLog { .. } => "log",
+            Self::JobStatus { .. } => "status",
+            Self::Stack { .. } => "stack",
+            Self::Service { .. } => "service",
+        }
+    }
+}
+
+/// In-process fan-out of [`Event`]s; slow subscribers lag instead of blocking writers.
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            tx: broadcast::Sender::new(BUS_CAPACITY),
+        }
+    }
+}
+
+impl EventBus {
+    pub fn publish(&self, event: Event) {
+        // No subscribers is the common case.
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.tx.subscribe()
+    }
+}
+
+/// Streams the log lines of `job_id` with an id above `after_id`, followed by live log lines and
+/// status transitions. The stream ends after the job reaches a terminal status.
+pub fn follow_job(db: Db, job_id: String, after_id: i64) -> mpsc::Receiver<Event> {
+    let (tx, rx) = mpsc::channel(64);
+    tokio::spawn(async move {
+        if let Err(e) = forward_job_events(&db, &job_id, after_id, &tx).await {
+            tracing::warn!(job = %job_id, error = %e, "job event stream failed");
+        }
+    });
+    rx
+}
+
+async fn forward_job_events(
+    db: &Db,
+    job_id: &str,
+    mut last_id: i64,
+    tx: &mpsc::Sender<Event>,
+) -> anyhow::Result<()> {
+    // Subscribe before reading the backlog so nothing is missed in between; ids dedupe the overlap.
+    let mut rx = db.subscribe();
+    let mut last_status = None::<String>;
+
+    loop {
+        for (id, line) in db.list_job_logs_after(job_id, last_id).await? {
+            last_id = id;
+            let event = Event::JobLog {
+                job_id: job_id.to_string(),
+                id,
+                line,
+            };
+            if tx.send(event).await.is_err() {
+                return Ok(());
+            }
+        }
+        let Some(job) = db.get_job(job_id).await? else {
+            return Ok(());
+        };
+        if last_status.as_deref() != Some(job.status.as_str()) {
+            last_status = Some(job.status.clone());
+            let event = Event::JobStatus {
+                job_id: job_id.to_string(),
+                status: job.status.clone(),
+            };
+            if tx.send(event).await.is_err() {
+                return Ok(());
+            }
+        }
+        if TERMINAL_JOB_STATUSES.contains(&job.status.as_str()) {
+            return Ok(());
+        }
+
+        // Follow live events until we fall behind, then resync from the database.
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => break,
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            };
+            let terminal = match &event {
+                Event::JobLog { job_id: id, .. } | Event::JobStatus { job_id: id, .. }
+                    if id != job_id =>
+                {
+                    continue;
+                }
+                Event::JobLog { id, .. } if *id <= last_id => continue,
+                Event::JobLog { id, .. } => {
+                    last_id = *id;
+                    false
+                }
+                Event::JobStatus { status, .. } => {
+                    if last_status.as_deref() == Some(status.as_str()) {
+                        continue;
+                    }
+                    last_status = Some(status.clone());
+                    TERMINAL_JOB_STATUSES.contains(&status.as_str())
+                }
+                Event::Stack { .. } | Event::Service { .. } => continue,
+            };
+            if tx.send(event).await.is_err() || terminal {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use tokio::sync::mpsc;
+
+use crate::{
+    agent::{AgentCommandRunner, AgentHub},
+    api::types::HostRecord,
+    docker_engine::{CliDockerEngine, DockerEngine, HttpDockerEngine},
+    runner::{CommandOutput, CommandRunner, CommandSpec, OutputLine},
+    state::AppState,
+};
+
+/// Id of the Docker daemon configured through `DOCKER_HOST` / `DOCKREV_DOCKER_ENGINE`.
+pub const LOCAL_HOST_ID: &str = "local";
+const DEFAULT_PLATFORM: &str = "linux/amd64";
+/// Endpoint scheme of hosts reached through an outbound `dockrev-agent` connection.
+pub const AGENT_SCHEME: &str = "agent://";
+
+/// A Docker daemon managed by this instance, with the runner and engine that target it.
+pub struct Host {
+    pub record: HostRecord,
+    /// Platform used for manifest selection and arch checks.
+    pub platform: String,
+    /// Runs `docker` / compose commands against this daemon.
+    pub runner: Arc<dyn CommandRunner>,
+    pub docker: Arc<dyn DockerEngine>,
+    /// Set for `agent://` hosts, whose compose files live on the agent's machine.
+    agent: Option<Arc<AgentCommandRunner>>,
+}
+
+impl Host {
+    pub fn id(&self) -> &str {
+        &self.record.id
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.record.id == LOCAL_HOST_ID
+    }
+
+    pub fn is_agent(&self) -> bool {
+        self.agent.is_some()
+    }
+
+    /// Reads a compose file of this host: through the agent for agent hosts, from the local
+    /// filesystem otherwise.
+    pub async fn read_file(&self, path: &str) -> anyhow::Result<String> {
+        match self.agent.as_ref() {
+            Some(agent) => agent.read_file(path).await,
+            None => Ok(tokio::fs::read_to_string(path).await?),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct HostRegistry {
+    hosts: RwLock<BTreeMap<String, Arc<Host>>>,
+}
+
+impl HostRegistry {
+    pub fn get(&self, host_id: &str) -> Option<Arc<Host>> {
+        self.hosts
+            .read()
+            .expect("host registry lock poisoned")
+            .get(host_id)
+            .cloned()
+    }
+
+    /// All hosts, local first.
+    pub fn list(&self) -> Vec<Arc<Host>> {
+        let hosts = self.hosts.read().expect("host registry lock poisoned");
+        let mut out = hosts.values().cloned().collect::<Vec<_>>();
+        out.sort_by_key(|h| (!h.is_local(), h.record.name.clone()));
+        out
+    }
+
+    pub fn insert(&self, host: Arc<Host>) {
+        self.hosts
+            .write()
+            .expect("host registry lock poisoned")
+            .insert(host.record.id.clone(), host);
+    }
+
+    pub fn remove(&self, host_id: &str) -> bool {
+        self.hosts
+            .write()
+            .expect("host registry lock poisoned")
+            .remove(host_id)
+            .is_some()
+    }
+}
+
+/// Adds the `DOCKER_*` variables of a host to every command, so the docker and compose CLIs
+/// talk to that daemon.
+pub struct HostCommandRunner {
+    inner: Arc<dyn CommandRunner>,
+    env: Vec<(String, String)>,
+}
+
+impl HostCommandRunner {
+    pub fn new(inner: Arc<dyn CommandRunner>, env: Vec<(String, String)>) -> Self {
+        Self { inner, env }
+    }
+
+    fn with_env(&self, mut spec: CommandSpec) -> CommandSpec {
+        spec.env.extend(self.env.iter().cloned());
+        spec
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandRunner for HostCommandRunner {
+    async fn run(&self, spec: CommandSpec, timeout: Duration) -> anyhow::Result<CommandOutput> {
+        self.inner.run(self.with_env(spec), timeout).await
+    }
+
+    async fn run_streaming(
+        &self,
+        spec: CommandSpec,
+        timeout: Duration,
+        lines: mpsc::UnboundedSender<OutputLine>,
+    ) -> anyhow::Result<CommandOutput> {
+        self.inner
+            .run_streaming(self.with_env(spec), timeout, lines)
+            .await
+    }
+}
+
+/// Checks a registration before it is stored; `unix://` is reserved for the local host.
+pub fn validate(endpoint: &str, tls_cert_path: Option<&str>) -> Result<(), String> {
+    if endpoint.starts_with(AGENT_SCHEME) {
+        if tls_cert_path.is_some() {
+            return Err("tlsCertPath only applies to tcp:// endpoints".to_string());
+        }
+        return Ok(());
+    }
+    let rest = if let Some(rest) = endpoint.strip_prefix("tcp://") {
+        rest
+    } else if let Some(rest) = endpoint.strip_prefix("ssh://") {
+        if tls_cert_path.is_some() {
+            return Err("tlsCertPath only applies to tcp:// endpoints".to_string());
+        }
+        rest
+    } else {
+        return Err("endpoint must start with tcp://, ssh:// or agent://".to_string());
+    };
+    if rest.trim_end_matches('/').is_empty() {
+        return Err("endpoint host is missing".to_string());
+    }
+    if let Some(path) = tls_cert_path
+        && !path.starts_with('/')
+    {
+        return Err("tlsCertPath must be an absolute path".to_string());
+    }
+    Ok(())
+}
+
+/// `DOCKER_*` environment that points the docker/compose CLIs at `record`.
+fn cli_env(record: &HostRecord) -> Vec<(String, String)> {
+    let mut env = vec![("DOCKER_HOST".to_string(), record.endpoint.clone())];
+    if let Some(path) = record.tls_cert_path.as_deref() {
+        env.push(("DOCKER_TLS_VERIFY".to_string(), "1".to_string()));
+        env.push(("DOCKER_CERT_PATH".to_string(), path.to_string()));
+    }
+    env
+}
+
+/// Builds a registered (remote) host. TCP endpoints use the Engine API, SSH endpoints the docker
+/// CLI; compose commands always go through the CLI with `DOCKER_HOST` set. Agent hosts run
+/// everything, including `docker inspect` / `docker events`, through their `dockrev-agent`.
+pub fn connect(
+    record: HostRecord,
+    runner: Arc<dyn CommandRunner>,
+    agents: &Arc<AgentHub>,
+) -> anyhow::Result<Host> {
+    if record.endpoint.starts_with(AGENT_SCHEME) {
+        let agent = Arc::new(AgentCommandRunner::new(agents.clone(), record.id.clone()));
+        return Ok(Host {
+            platform: platform_of(&record),
+            record,
+            runner: agent.clone(),
+            docker: Arc::new(CliDockerEngine::new(agent.clone())),
+            agent: Some(agent),
+        });
+    }
+
+    let runner: Arc<dyn CommandRunner> = Arc::new(HostCommandRunner::new(runner, cli_env(&record)));
+    let docker: Arc<dyn DockerEngine> = if record.endpoint.starts_with("ssh://") {
+        Arc::new(CliDockerEngine::new(runner.clone()))
+    } else if let Some(path) = record.tls_cert_path.as_deref() {
+        Arc::new(HttpDockerEngine::with_tls(
+            &record.endpoint,
+            Path::new(path),
+        )?)
+    } else {
+        Arc::new(HttpDockerEngine::new(&record.endpoint)?)
+    };
+    Ok(Host {
+        platform: platform_of(&record),
+        record,
+        runner,
+        docker,
+        agent: None,
+    })
+}
+
+fn platform_of(record: &HostRecord) -> String {
+    record
+        .platform
+        .clone()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PLATFORM.to_string())
+}
+
+/// Registers the hosts stored in SQLite; one that cannot be set up (e.g. unreadable TLS files)
+/// is skipped with a warning.
+pub async fn load(state: &AppState) -> anyhow::Result<()> {
+    for record in state.db.list_hosts().await? {
+        let host_id = record.id.clone();
+        match connect(record, state.runner.clone(), &state.agents) {
+            Ok(host) => state.hosts.insert(Arc::new(host)),
+            Err(e) => tracing::warn!(host = %host_id, error = %e, "skip docker host"),
+        }
+    }
+    Ok(())
+}
+
+/// The daemon Dockrev was configured with at startup.
+pub fn local(
+    endpoint: Option<&str>,
+    platform: Option<&str>,
+    runner: Arc<dyn CommandRunner>,
+    docker: Arc<dyn DockerEngine>,
+) -> Host {
+    Host {
+        record: HostRecord {
+            id: LOCAL_HOST_ID.to_string(),
+            name: LOCAL_HOST_ID.to_string(),
+            endpoint: endpoint
+                .unwrap_or("unix:///var/run/docker.sock")
+                .to_string(),
+            platform: platform.map(|p| p.to_string()),
+            tls_cert_path: None,
+            created_at: None,
+            agent_connected: None,
+        },
+        platform: crate::registry::host_platform_override(platform)
+            .unwrap_or_else(|| DEFAULT_PLATFORM.to_string()),
+        runner,
+        docker,
+        agent: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn validates_endpoints() {
+        assert!(validate("tcp://10.0.0.2:2376", Some("/certs/h2")).is_ok());
+        assert!(validate("ssh://deploy@10.0.0.3", None).is_ok());
+        assert!(validate("agent://edge", None).is_ok());
+        assert!(validate("agent://edge", Some("/certs")).is_err());
+        assert!(validate("unix:///var/run/docker.sock", None).is_err());
+        assert!(validate("tcp://", None).is_err());
+        assert!(validate("ssh://deploy@h", Some("/certs")).is_err());
+        assert!(validate("tcp://h:2376", Some("certs")).is_err());
+    }
+
+    #[derive(Default)]
+    struct RecordingRunner {
+        env: Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl CommandRunner for RecordingRunner {
+        async fn run(
+            &self,
+            spec: CommandSpec,
+            _timeout: Duration,
+        ) -> anyhow::Result<CommandOutput> {
+            *self.env.lock().unwrap() = spec.env;
+            Ok(CommandOutput {
+                status: 0,
+                stdout: String::new(),
+                stderr: String::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn host_runner_targets_the_host_daemon() {
+        let inner = Arc::new(RecordingRunner::default());
+        let host = connect(
+            HostRecord {
+                id: "host_1".to_string(),
+                name: "edge".to_string(),
+                endpoint: "ssh://deploy@edge".to_string(),
+                platform: Some("linux/arm64".to_string()),
+                tls_cert_path: None,
+                created_at: None,
+                agent_connected: None,
+            },
+            inner.clone(),
+            &Arc::default(),
+        )
+        .unwrap();
+        assert_eq!(host.platform, "linux/arm64");
+
+        host.runner
+            .run(
+                CommandSpec {
+                    program: "docker-compose".to_string(),
+                    args: vec!["ps".to_string()],
+                    env: vec![("COMPOSE_PROJECT_NAME".to_string(), "demo".to_string())],
+                    stdin: None,
+                    files: Vec::new(),
+                },
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            *inner.env.lock().unwrap(),
+            vec![
+                ("COMPOSE_PROJECT_NAME".to_string(), "demo".to_string()),
+                ("DOCKER_HOST".to_string(), "ssh://deploy@edge".to_string()),
+            ]
+        );
+    }
+}
@@ -27,3 +27,11 @@ pub fn new_discovery_id() -> String {
 pub fn new_backup_id() -> String {
     format!("bkp_{}", Ulid::new())
 }
+
+pub fn new_maintenance_window_id() -> String {
+    format!("mtw_{}", Ulid::new())
+}
+
+pub fn new_host_id() -> String {
+    format!("hst_{}", Ulid::new())
+}
@@ -1,6 +1,20 @@
+use std::sync::LazyLock;
+
 use regex::Regex;
 use semver::{Version, VersionReq};
 
+/// `<version>-<variant>`, where the version may carry a prerelease (`1.2.3-rc.1-alpine`).
+static VARIANT_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(v?\d+(?:\.\d+){0,2}(?:-(?i:alpha|beta|rc|pre|preview|dev)(?:[.-]?\d+)*)?)-([A-Za-z][A-Za-z0-9._-]*)$",
+    )
+    .expect("valid regex")
+});
+
+static PRERELEASE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?i:alpha|beta|rc|pre|preview|dev)(?:[.-]?\d+)*$").expect("valid regex")
+});
+
 #[derive(Clone, Debug)]
 pub enum IgnoreKind {
     Exact,
@@ -35,7 +49,7 @@ impl IgnoreRuleMatcher {
                 .ok()
                 .is_some_and(|re| re.is_match(tag)),
             IgnoreKind::Semver => {
-                let Some(tag_ver) = parse_version(tag) else {
+                let Some(tag_ver) = parse_version(split_variant(tag).0) else {
                     return false;
                 };
                 let Ok(req) = VersionReq::parse(&self.value) else {
@@ -66,6 +80,24 @@ pub fn parse_version(tag: &str) -> Option<Version> {
     Version::parse(&coerced).ok()
 }
 
+/// Splits the variant suffix off a tag: `16.2-alpine` is `("16.2", Some("alpine"))`,
+/// `1.25.3-fpm-alpine` is `("1.25.3", Some("fpm-alpine"))`. Prereleases such as `1.2.3-rc.1`
+/// are part of the version, not a variant.
+pub fn split_variant(tag: &str) -> (&str, Option<&str>) {
+    match VARIANT_TAG.captures(tag) {
+        Some(caps) if !PRERELEASE.is_match(&caps[2]) => (
+            caps.get(1).map_or(tag, |m| m.as_str()),
+            caps.get(2).map(|m| m.as_str()),
+        ),
+        _ => (tag, None),
+    }
+}
+
+/// Whether the version of `tag` (variant aside) is a semver prerelease, e.g. `2.0.0-rc.1`.
+pub fn is_prerelease(tag: &str) -> bool {
+    parse_version(split_variant(tag).0).is_some_and(|v| !v.pre.is_empty())
+}
+
 pub fn is_strict_semver(tag: &str) -> bool {
     let trimmed = tag.trim().strip_prefix('v').unwrap_or(tag.trim());
     if trimmed.is_empty() {
@@ -114,4 +146,42 @@ mod tests {
         assert!(m.matches("5.3.1"));
         assert!(!m.matches("5.4.0"));
     }
+
+    #[test]
+    fn splits_variant_suffixes() {
+        let cases = [
+            ("16.2-alpine", ("16.2", Some("alpine"))),
+            ("8.0-bookworm", ("8.0", Some("bookworm"))),
+            ("1.25.3-fpm-alpine", ("1.25.3", Some("fpm-alpine"))),
+            ("v2-slim", ("v2", Some("slim"))),
+            ("1.2.3-rc.1-alpine", ("1.2.3-rc.1", Some("alpine"))),
+            ("1.2.3-rc.1", ("1.2.3-rc.1", None)),
+            ("1.2.3-beta", ("1.2.3-beta", None)),
+            ("16.2", ("16.2", None)),
+            ("latest", ("latest", None)),
+            ("alpine", ("alpine", None)),
+        ];
+        for (tag, expected) in cases {
+            assert_eq!(split_variant(tag), expected, "{tag}");
+        }
+    }
+
+    #[test]
+    fn semver_req_matches_the_version_of_a_variant() {
+        let m = IgnoreRuleMatcher {
+            kind: IgnoreKind::Semver,
+            value: ">=17".to_string(),
+        };
+        assert!(m.matches("17.0-alpine"));
+        assert!(!m.matches("16.4-alpine"));
+    }
+
+    #[test]
+    fn detects_prereleases() {
+        assert!(is_prerelease("2.0.0-rc.1"));
+        assert!(is_prerelease("v1.2.3-beta-alpine"));
+        assert!(!is_prerelease("1.2.3-alpine"));
+        assert!(!is_prerelease("2.0"));
+        assert!(!is_prerelease("latest"));
+    }
 }
@@ -1,20 +1,33 @@
 #![forbid(unsafe_code)]
 
+mod agent;
 mod api;
 mod backup;
 mod candidates;
+mod cloud_auth;
 mod compose;
 mod compose_runner;
 mod config;
+mod credentials;
+mod cron;
 mod db;
 mod discovery;
+mod docker_engine;
 mod docker_runner;
 mod error;
+mod events;
+mod hosts;
 mod ids;
 mod ignore;
+mod maintenance;
 mod notify;
+mod queue;
 mod registry;
+mod release_notes;
 mod runner;
+mod scanner;
+mod scheduler;
+mod signatures;
 mod state;
 mod ui;
 mod updater;
@@ -34,13 +47,44 @@ async fn main() -> anyhow::Result<()> {
     let config = config::Config::from_env()?;
     let bind = config.http_addr.clone();
     let db = db::Db::open(&config.db_path).await?;
-    let registry = std::sync::Arc::new(registry::HttpRegistryClient::new(
-        config.docker_config_path.as_deref(),
-    )?);
     let runner = std::sync::Arc::new(runner::TokioCommandRunner);
-    let state = state::AppState::new(config, db, registry, runner);
+    let mut registry = registry::HttpRegistryClient::new(config.docker_config_path.as_deref())?;
+    if !config.registry_token_providers.is_empty() {
+        registry = registry.with_token_providers(std::sync::Arc::new(
+            cloud_auth::TokenProviders::from_env(&config.registry_token_providers)?,
+        ));
+    }
+    let registry = std::sync::Arc::new(
+        registry
+            .with_tag_limits(config.registry_tags_page_size, config.registry_max_tags)
+            .with_rate_limit_reserve(config.registry_rate_limit_reserve)
+            .with_endpoints(registry::EndpointSettings {
+                mirrors: config.registry_mirrors.clone(),
+                overrides: config.registry_endpoints.clone(),
+                insecure: config.registry_insecure.clone(),
+                ca_bundle: config.registry_ca_bundle.clone(),
+                certs_dir: config.registry_certs_dir.clone(),
+            })?
+            .with_credential_store(db.clone(), runner.clone())
+            .with_cache(
+                db.clone(),
+                std::time::Duration::from_secs(config.registry_cache_ttl_seconds),
+            ),
+    );
+    let docker = docker_engine::connect(
+        &config.docker_engine,
+        config.docker_host.as_deref(),
+        runner.clone(),
+    )
+    .await?;
+    let state = state::AppState::new(config, db, registry, runner, docker);
+    hosts::load(&state).await?;
+    queue::recover(&state).await?;
+    queue::spawn_task(state.clone());
     backup::spawn_cleanup_task(state.clone());
     discovery::spawn_task(state.clone());
+    scheduler::spawn_task(state.clone());
+    maintenance::spawn_task(state.clone());
     let app = api::router(state.clone());
 
     let listener = tokio::net::TcpListener::bind(&bind).await?;
@@ -0,0 +1,398 @@
+use std::{sync::Arc, time::Duration};
+
+use time::{OffsetDateTime, Time, UtcOffset, Weekday};
+use time_tz::{OffsetDateTimeExt as _, Tz};
+
+use crate::{
+    api::{
+        self,
+        types::{JobLogLine, JobScope, MaintenanceWindow, TriggerUpdateRequest},
+    },
+    state::AppState,
+};
+
+const TICK_SECONDS: u64 = 30;
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("mon", Weekday::Monday),
+    ("tue", Weekday::Tuesday),
+    ("wed", Weekday::Wednesday),
+    ("thu", Weekday::Thursday),
+    ("fri", Weekday::Friday),
+    ("sat", Weekday::Saturday),
+    ("sun", Weekday::Sunday),
+];
+
+/// Validated form of [`MaintenanceWindow`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Window {
+    weekdays: Vec<Weekday>,
+    start: Time,
+    end: Time,
+    zone: Zone,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Zone {
+    Fixed(UtcOffset),
+    Named(&'static Tz),
+}
+
+impl Window {
+    pub fn parse(window: &MaintenanceWindow) -> anyhow::Result<Self> {
+        let weekdays = window
+            .weekdays
+            .iter()
+            .map(|d| parse_weekday(d))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            weekdays,
+            start: parse_time(&window.start)?,
+            end: parse_time(&window.end)?,
+            zone: parse_timezone(&window.timezone)?,
+        })
+    }
+
+    /// Whether `now` falls inside the window.
+    ///
+    /// Weekdays refer to the day the window starts on, so `sat 22:00-02:00` is still open early
+    /// on Sunday. `start == end` means the whole day.
+    pub fn is_open(&self, now: OffsetDateTime) -> bool {
+        let local = match self.zone {
+            Zone::Fixed(offset) => now.to_offset(offset),
+            Zone::Named(tz) => now.to_timezone(tz),
+        };
+        let t = local.time();
+        let today = local.weekday();
+
+        if self.start == self.end {
+            return self.day_allowed(today);
+        }
+        if self.start < self.end {
+            return self.day_allowed(today) && t >= self.start && t < self.end;
+        }
+        (t >= self.start && self.day_allowed(today))
+            || (t < self.end && self.day_allowed(today.previous()))
+    }
+
+    /// First minute at or after `after` where the window is open, searching up to 8 days ahead.
+    pub fn next_open_at(&self, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        let mut t = after.replace_second(0).ok()?.replace_nanosecond(0).ok()?;
+        if t < after {
+            t += time::Duration::minutes(1);
+        }
+        let limit = after + time::Duration::days(8);
+        while t <= limit {
+            if self.is_open(t) {
+                return Some(t);
+            }
+            t += time::Duration::minutes(1);
+        }
+        None
+    }
+
+    fn day_allowed(&self, day: Weekday) -> bool {
+        self.weekdays.is_empty() || self.weekdays.contains(&day)
+    }
+}
+
+fn parse_weekday(input: &str) -> anyhow::Result<Weekday> {
+    let lower = input.trim().to_ascii_lowercase();
+    WEEKDAYS
+        .iter()
+        .find(|(name, day)| lower == *name || lower == day.to_string().to_ascii_lowercase())
+        .map(|(_, d)| *d)
+        .ok_or_else(|| anyhow::anyhow!("invalid weekday: {input}"))
+}
+
+fn parse_time(input: &str) -> anyhow::Result<Time> {
+    let (h, m) = input
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("invalid time (expected HH:MM): {input}"))?;
+    let h = h
+        .parse::<u8>()
+        .map_err(|_| anyhow::anyhow!("invalid time (expected HH:MM): {input}"))?;
+    let m = m
+        .parse::<u8>()
+        .map_err(|_| anyhow::anyhow!("invalid time (expected HH:MM): {input}"))?;
+    Time::from_hms(h, m, 0).map_err(|_| anyhow::anyhow!("invalid time (expected HH:MM): {input}"))
+}
+
+fn parse_timezone(input: &str) -> anyhow::Result<Zone> {
+    let input = input.trim();
+    if input.is_empty() || input.eq_ignore_ascii_case("utc") || input == "Z" {
+        return Ok(Zone::Fixed(UtcOffset::UTC));
+    }
+
+    let (sign, rest) = match input.as_bytes()[0] {
+        b'+' => (1i8, &input[1..]),
+        b'-' => (-1i8, &input[1..]),
+        _ => {
+            return time_tz::timezones::get_by_name(input)
+                .map(Zone::Named)
+                .ok_or_else(|| anyhow::anyhow!("unknown timezone: {input}"));
+        }
+    };
+    let invalid = || anyhow::anyhow!("invalid timezone (expected UTC or +HH:MM): {input}");
+    // Digits only: `u8::from_str` would also take a second sign.
+    let field = |s: &str, max: u8| {
+        if s.is_empty() || s.len() > 2 || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        s.parse::<u8>().ok().filter(|v| *v <= max)
+    };
+    let (h, m) = rest.split_once(':').unwrap_or((rest, "0"));
+    let (Some(h), Some(m)) = (field(h, 23), field(m, 59)) else {
+        return Err(invalid());
+    };
+    UtcOffset::from_hms(sign * h as i8, sign * m as i8, 0)
+        .map(Zone::Fixed)
+        .map_err(|_| invalid())
+}
+
+/// Windows that gate updates for `stack_ids`: a stack's own windows replace the global ones, and
+/// multi-stack (`scope=all`) updates only follow the global windows. Empty means "always open".
+pub async fn effective_windows(
+    state: &AppState,
+    scope: &JobScope,
+    stack_ids: &[String],
+) -> anyhow::Result<Vec<Vec<Window>>> {
+    let all = state.db.list_maintenance_windows().await?;
+    let parse = |stack_id: Option<&str>| {
+        all.iter()
+            .filter(|w| w.stack_id.as_deref() == stack_id)
+            .filter_map(|w| match Window::parse(&w.window) {
+                Ok(parsed) => Some(parsed),
+                Err(e) => {
+                    tracing::warn!(window = %w.id, error = %e, "invalid maintenance window");
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let global = parse(None);
+    if matches!(scope, JobScope::All) {
+        return Ok(vec![global]);
+    }
+    Ok(stack_ids
+        .iter()
+        .map(|id| {
+            let own = parse(Some(id));
+            if own.is_empty() { global.clone() } else { own }
+        })
+        .collect())
+}
+
+/// Returns `None` when every stack has an open window at `now`, otherwise the earliest time all
+/// of them could start (best effort; `Some(None)` when no opening was found in the next days).
+pub fn deferral(windows: &[Vec<Window>], now: OffsetDateTime) -> Option<Option<OffsetDateTime>> {
+    let open = |set: &[Window], at| set.is_empty() || set.iter().any(|w| w.is_open(at));
+    if windows.iter().all(|set| open(set, now)) {
+        return None;
+    }
+
+    let next = windows
+        .iter()
+        .filter(|set| !open(set, now))
+        .map(|set| set.iter().filter_map(|w| w.next_open_at(now)).min())
+        .collect::<Option<Vec<_>>>()
+        .and_then(|v| v.into_iter().max());
+    Some(next)
+}
+
+pub fn spawn_task(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(TICK_SECONDS));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = start_due_pending(&state, OffsetDateTime::now_utc()).await {
+                tracing::warn!(error = %e, "pending update scan failed");
+            }
+        }
+    });
+}
+
+/// Queues every pending update job whose maintenance windows are open at `now`. A job whose
+/// request can no longer be read is marked failed; other errors skip the job until the next pass.
+pub async fn start_due_pending(
+    state: &Arc<AppState>,
+    now: OffsetDateTime,
+) -> anyhow::Result<Vec<String>> {
+    let mut started = Vec::new();
+    for job in state.db.list_jobs_by_status("pending").await? {
+        let req = match serde_json::from_value::<TriggerUpdateRequest>(job.request_json) {
+            Ok(req) => req,
+            Err(e) => {
+                tracing::warn!(job = %job.id, error = %e, "pending job has invalid request");
+                if let Err(e) = fail_pending(state, &job.id, &e.to_string()).await {
+                    tracing::warn!(job = %job.id, error = %e, "failed to fail pending job");
+                }
+                continue;
+            }
+        };
+        match release_if_due(state, &job.id, &req, now).await {
+            Ok(true) => started.push(job.id),
+            Ok(false) => {}
+            Err(e) => tracing::warn!(job = %job.id, error = %e, "pending job check failed"),
+        }
+    }
+    Ok(started)
+}
+
+async fn release_if_due(
+    state: &Arc<AppState>,
+    job_id: &str,
+    req: &TriggerUpdateRequest,
+    now: OffsetDateTime,
+) -> anyhow::Result<bool> {
+    let stack_ids = api::resolve_stack_ids_for_update(state, req).await?;
+    let windows = effective_windows(state, &req.scope, &stack_ids).await?;
+    if deferral(&windows, now).is_some() {
+        return Ok(false);
+    }
+    api::release_pending_update_job(state, job_id).await
+}
+
+async fn fail_pending(state: &AppState, job_id: &str, error: &str) -> anyhow::Result<()> {
+    let now = OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339)?;
+    state
+        .db
+        .insert_job_log(
+            job_id,
+            &JobLogLine {
+                ts: now.clone(),
+                level: "error".to_string(),
+                msg: format!("invalid pending request: {error}"),
+            },
+        )
+        .await?;
+    state
+        .db
+        .finish_job(
+            job_id,
+            "failed",
+            &now,
+            &serde_json::json!({ "error": error }),
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::format_description::well_known::Rfc3339;
+
+    fn window(weekdays: &[&str], start: &str, end: &str, timezone: &str) -> Window {
+        Window::parse(&MaintenanceWindow {
+            weekdays: weekdays.iter().map(|d| d.to_string()).collect(),
+            start: start.to_string(),
+            end: end.to_string(),
+            timezone: timezone.to_string(),
+        })
+        .unwrap()
+    }
+
+    fn at(s: &str) -> OffsetDateTime {
+        OffsetDateTime::parse(s, &Rfc3339).unwrap()
+    }
+
+    #[test]
+    fn same_day_range() {
+        let w = window(&[], "02:00", "04:00", "UTC");
+        assert!(w.is_open(at("2026-01-10T02:00:00Z")));
+        assert!(w.is_open(at("2026-01-10T03:59:00Z")));
+        assert!(!w.is_open(at("2026-01-10T04:00:00Z")));
+        assert!(!w.is_open(at("2026-01-10T01:59:00Z")));
+    }
+
+    #[test]
+    fn wraps_midnight_and_keeps_start_weekday() {
+        // 2026-01-10 is a Saturday.
+        let w = window(&["sat"], "22:00", "02:00", "UTC");
+        assert!(w.is_open(at("2026-01-10T23:00:00Z")));
+        assert!(w.is_open(at("2026-01-11T01:00:00Z")));
+        assert!(!w.is_open(at("2026-01-11T23:00:00Z")));
+        assert!(!w.is_open(at("2026-01-10T01:00:00Z")));
+    }
+
+    #[test]
+    fn applies_fixed_offset() {
+        let w = window(&[], "02:00", "04:00", "+08:00");
+        assert!(w.is_open(at("2026-01-09T18:30:00Z")));
+        assert!(!w.is_open(at("2026-01-10T02:30:00Z")));
+    }
+
+    #[test]
+    fn applies_named_timezone_with_dst() {
+        let w = window(&[], "02:00", "04:00", "Europe/Berlin");
+        // CET (+01:00) in winter, CEST (+02:00) in summer.
+        assert!(w.is_open(at("2026-01-10T01:30:00Z")));
+        assert!(w.is_open(at("2026-07-10T00:30:00Z")));
+        assert!(!w.is_open(at("2026-07-10T02:30:00Z")));
+    }
+
+    #[test]
+    fn parses_fixed_offsets_strictly() {
+        let offset = |tz| match parse_timezone(tz) {
+            Ok(Zone::Fixed(o)) => Some(o.whole_seconds()),
+            _ => None,
+        };
+        assert_eq!(offset("+05:30"), Some(5 * 3600 + 30 * 60));
+        assert_eq!(offset("-05:00"), Some(-5 * 3600));
+        assert_eq!(offset("+8"), Some(8 * 3600));
+        for tz in [
+            "--128", "+-05:00", "-+05:00", "++05", "+24:00", "+05:60", "+05:", "+",
+        ] {
+            assert!(parse_timezone(tz).is_err(), "{tz}");
+        }
+    }
+
+    #[test]
+    fn next_open_and_deferral() {
+        let w = window(&["sun"], "03:00", "04:00", "UTC");
+        // 2026-01-10 is a Saturday.
+        let now = at("2026-01-10T12:00:30Z");
+        assert_eq!(w.next_open_at(now), Some(at("2026-01-11T03:00:00Z")));
+
+        assert_eq!(deferral(&[vec![]], now), None);
+        assert_eq!(
+            deferral(&[vec![], vec![w.clone()]], now),
+            Some(Some(at("2026-01-11T03:00:00Z")))
+        );
+        assert_eq!(deferral(&[vec![w]], at("2026-01-11T03:30:00Z")), None);
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let base = MaintenanceWindow {
+            weekdays: Vec::new(),
+            start: "02:00".to_string(),
+            end: "04:00".to_string(),
+            timezone: "UTC".to_string(),
+        };
+        assert!(
+            Window::parse(&MaintenanceWindow {
+                start: "25:00".to_string(),
+                ..base.clone()
+            })
+            .is_err()
+        );
+        assert!(
+            Window::parse(&MaintenanceWindow {
+                weekdays: vec!["someday".to_string()],
+                ..base.clone()
+            })
+            .is_err()
+        );
+        assert!(
+            Window::parse(&MaintenanceWindow {
+                timezone: "Mars/Base".to_string(),
+                ..base
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    api::{
+        self,
+        types::{JobLogLine, JobScope, TriggerUpdateRequest},
+    },
+    runner::CancelToken,
+    state::AppState,
+};
+
+const TICK_SECONDS: u64 = 5;
+
+/// In-process side of the update job queue; the queue itself is the `jobs` table (`status = 'queued'`).
+#[derive(Default)]
+pub struct JobQueue {
+    dispatching: tokio::sync::Mutex<()>,
+    running: Mutex<BTreeMap<String, StackLock>>,
+    cancels: Mutex<BTreeMap<String, CancelToken>>,
+}
+
+impl JobQueue {
+    /// Registers an in-flight job (update or check) so that it can be cancelled.
+    pub fn register(&self, job_id: &str) -> CancelToken {
+        let token = CancelToken::default();
+        self.cancels
+            .lock()
+            .expect("job queue lock poisoned")
+            .insert(job_id.to_string(), token.clone());
+        token
+    }
+
+    pub fn unregister(&self, job_id: &str) {
+        self.cancels
+            .lock()
+            .expect("job queue lock poisoned")
+            .remove(job_id);
+    }
+
+    /// Signals an in-flight job to stop; returns false if the job is not running in this process.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        match self
+            .cancels
+            .lock()
+            .expect("job queue lock poisoned")
+            .get(job_id)
+        {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum StackLock {
+    All,
+    Stacks(BTreeSet<String>),
+}
+
+impl StackLock {
+    fn conflicts(&self, other: &StackLock) -> bool {
+        match (self, other) {
+            (Self::All, _) | (_, Self::All) => true,
+            (Self::Stacks(a), Self::Stacks(b)) => !a.is_disjoint(b),
+        }
+    }
+}
+
+pub fn spawn_task(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(TICK_SECONDS));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = dispatch(&state).await {
+                tracing::warn!(error = %e, "job dispatch failed");
+            }
+        }
+    });
+}
+
+/// Marks jobs left `running` by a previous process as `interrupted`, then resumes the queue.
+pub async fn recover(state: &Arc<AppState>) -> anyhow::Result<()> {
+    let now = now_rfc3339()?;
+    let interrupted = state.db.interrupt_running_jobs(&now).await?;
+    for job_id in &interrupted {
+        tracing::warn!(job = %job_id, "job interrupted by restart");
+    }
+    dispatch(state).await?;
+    Ok(())
+}
+
+/// Starts queued update jobs in FIFO order while respecting the global concurrency cap and
+/// per-stack mutual exclusion. Returns the ids of jobs started by this pass.
+pub async fn dispatch(state: &Arc<AppState>) -> anyhow::Result<Vec<String>> {
+    let _guard = state.queue.dispatching.lock().await;
+
+    let mut held = state
+        .queue
+        .running
+        .lock()
+        .expect("job queue lock poisoned")
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut started = Vec::new();
+
+    for job in state.db.list_jobs_by_status("queued").await? {
+        if held_running(state) >= state.config.job_concurrency {
+            break;
+        }
+
+        let req = match serde_json::from_value::<TriggerUpdateRequest>(job.request_json) {
+            Ok(req) => req,
+            Err(e) => {
+                let now = now_rfc3339()?;
+                state
+                    .db
+                    .insert_job_log(
+                        &job.id,
+                        &JobLogLine {
+                            ts: now.clone(),
+                            level: "error".to_string(),
+                            msg: format!("invalid queued request: {e}"),
+                        },
+                    )
+                    .await?;
+                state
+                    .db
+                    .finish_job(
+                        &job.id,
+                        "failed",
+                        &now,
+                        &serde_json::json!({ "error": e.to_string() }),
+                    )
+                    .await?;
+                continue;
+            }
+        };
+
+        let lock = match req.scope {
+            JobScope::All => StackLock::All,
+            _ => StackLock::Stacks(
+                api::resolve_stack_ids_for_update(state, &req)
+                    .await?
+                    .into_iter()
+                    .collect(),
+            ),
+        };
+        let blocked = held.iter().any(|h| h.conflicts(&lock));
+        // Later jobs touching the same stacks wait behind this one, even if it is blocked.
+        held.push(lock.clone());
+        if blocked {
+            continue;
+        }
+
+        let now = now_rfc3339()?;
+        // Registered before the claim so a cancel racing with dispatch always finds the job.
+        let cancel = state.queue.register(&job.id);
+        if !state
+            .db
+            .claim_job(&job.id, "queued", "running", Some(&now))
+            .await?
+        {
+            state.queue.unregister(&job.id);
+            continue;
+        }
+        state
+            .queue
+            .running
+            .lock()
+            .expect("job queue lock poisoned")
+            .insert(job.id.clone(), lock);
+        state
+            .db
+            .insert_job_log(
+                &job.id,
+                &JobLogLine {
+                    ts: now,
+                    level: "info".to_string(),
+                    msg: "update started".to_string(),
+                },
+            )
+            .await?;
+
+        spawn_job(state.clone(), job.id.clone(), req, cancel);
+        started.push(job.id);
+    }
+
+    Ok(started)
+}
+
+fn held_running(state: &AppState) -> usize {
+    state
+        .queue
+        .running
+        .lock()
+        .expect("job queue lock poisoned")
+        .len()
+}
+
+fn spawn_job(state: Arc<AppState>, job_id: String, req: TriggerUpdateRequest, cancel: CancelToken) {
+    tokio::spawn(async move {
+        // Run in its own task so that a panic still releases the slot and the stack lock below.
+        let job = tokio::spawn(api::run_update_job(
+            state.clone(),
+            job_id.clone(),
+            req,
+            cancel,
+        ));
+        let error = match job.await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(e) => Some(format!("update job aborted: {e}")),
+        };
+        if let Some(error) = error {
+            tracing::warn!(job = %job_id, error = %error, "update job failed");
+            if let Err(e) = fail_running(&state, &job_id, &error).await {
+                tracing::warn!(job = %job_id, error = %e, "failed to mark job failed");
+            }
+        }
+        state.queue.unregister(&job_id);
+        state
+            .queue
+            .running
+            .lock()
+            .expect("job queue lock poisoned")
+            .remove(&job_id);
+        if let Err(e) = dispatch(&state).await {
+            tracing::warn!(error = %e, "job dispatch failed");
+        }
+    });
+}
+
+/// Marks a job that ended without recording its own outcome as failed.
+async fn fail_running(state: &AppState, job_id: &str, error: &str) -> anyhow::Result<()> {
+    if !state
+        .db
+        .claim_job(job_id, "running", "failed", None)
+        .await?
+    {
+        return Ok(());
+    }
+    let now = now_rfc3339()?;
+    state
+        .db
+        .insert_job_log(
+            job_id,
+            &JobLogLine {
+                ts: now.clone(),
+                level: "error".to_string(),
+                msg: format!("update failed: {error}"),
+            },
+        )
+        .await?;
+    state
+        .db
+        .finish_job(
+            job_id,
+            "failed",
+            &now,
+            &serde_json::json!({ "error": error }),
+        )
+        .await
+}
+
+fn now_rfc3339() -> anyhow::Result<String> {
+    Ok(time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stacks(ids: &[&str]) -> StackLock {
+        StackLock::Stacks(ids.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn stack_locks_conflict_on_overlap_and_all() {
+        assert!(stacks(&["a", "b"]).conflicts(&stacks(&["b"])));
+        assert!(!stacks(&["a"]).conflicts(&stacks(&["b"])));
+        assert!(StackLock::All.conflicts(&stacks(&["b"])));
+        assert!(stacks(&[]).conflicts(&StackLock::All));
+    }
+}
@@ -1,74 +1,91 @@
 use std::{
-    collections::HashMap,
-    path::Path,
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use anyhow::Context as _;
 use async_trait::async_trait;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use reqwest::header::{ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE};
-use serde::Deserialize;
-
-use crate::api::types::ArchMatch;
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct ImageRef {
-    pub registry: String,
-    pub name: String,
-    pub reference: String,
-}
-
-impl ImageRef {
-    pub fn parse(input: &str) -> anyhow::Result<Self> {
-        // Very small parser: registry host is the first segment if it contains '.' or ':'.
-        // Otherwise default to docker.io. Name is the rest. Reference is required.
-        let (without_digest, _) = input.split_once('@').unwrap_or((input, ""));
-
-        let (name_with_registry, reference) = without_digest
-            .rsplit_once(':')
-            .ok_or_else(|| anyhow::anyhow!("image ref missing tag (expected repo/name:tag)"))?;
+use reqwest::header::{
+    ACCEPT, AUTHORIZATION, ETAG, HeaderMap, HeaderName, IF_NONE_MATCH, LINK, RETRY_AFTER,
+    WWW_AUTHENTICATE,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use time::{
+    OffsetDateTime,
+    format_description::well_known::{Rfc2822, Rfc3339},
+};
 
-        if reference.is_empty() || reference.contains('/') {
-            return Err(anyhow::anyhow!(
-                "invalid tag in image ref (expected repo/name:tag)"
-            ));
-        }
+use crate::{
+    api::types::{ArchMatch, RegistryQuota},
+    cloud_auth::TokenProviders,
+    credentials::CredentialStore,
+    db::{Db, RegistryManifestCacheEntry, RegistryTagsCacheEntry},
+    runner::CommandRunner,
+};
 
-        let mut parts = name_with_registry.split('/').collect::<Vec<_>>();
-        if parts.is_empty() {
-            return Err(anyhow::anyhow!("invalid image ref"));
-        }
+pub use dockrev_image_ref::{DEFAULT_TAG, ImageRef};
 
-        let (registry, name) = if parts[0].contains('.') || parts[0].contains(':') {
-            let reg = parts.remove(0).to_string();
-            (reg, parts.join("/"))
-        } else {
-            ("docker.io".to_string(), name_with_registry.to_string())
-        };
+const DOCKER_CONTENT_DIGEST: &str = "Docker-Content-Digest";
 
-        let name = normalize_dockerhub_name(&registry, &name);
+#[derive(Clone, Debug)]
+pub struct ManifestInfo {
+    pub digest: Option<String>,
+    pub arch: Vec<String>,
+    /// Digest of the manifest document the reference resolves to: the image index of a
+    /// multi-arch tag, which is what `cosign sign` and `notation sign` sign.
+    pub content_digest: Option<String>,
+}
 
-        Ok(Self {
-            registry,
-            name,
-            reference: reference.to_string(),
-        })
-    }
+/// Metadata of one platform image: its manifest's annotations and its config blob.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageConfig {
+    /// Annotations of the platform manifest, on top of those of the index it was picked from.
+    pub annotations: BTreeMap<String, String>,
+    /// `config.Labels` of the config blob.
+    pub labels: BTreeMap<String, String>,
+    /// `created` of the config blob (RFC 3339).
+    pub created: Option<String>,
+    /// `config.ExposedPorts`, e.g. `8080/tcp`.
+    pub exposed_ports: Vec<String>,
+    /// Names of the `config.Env` variables; values are left out as they may hold secrets.
+    pub env: Vec<String>,
+    pub entrypoint: Option<Vec<String>>,
+    pub cmd: Option<Vec<String>>,
+    /// Sum of the compressed layer sizes of the platform manifest.
+    pub compressed_size: Option<u64>,
 }
 
-fn normalize_dockerhub_name(registry: &str, name: &str) -> String {
-    if registry == "docker.io" && !name.contains('/') {
-        format!("library/{name}")
-    } else {
-        name.to_string()
+impl ImageConfig {
+    /// A non-empty annotation, falling back to the label of the same key (`docker build` only
+    /// sets labels).
+    pub fn annotation(&self, key: &str) -> Option<&str> {
+        [self.annotations.get(key), self.labels.get(key)]
+            .into_iter()
+            .flatten()
+            .map(|v| v.trim())
+            .find(|v| !v.is_empty())
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct ManifestInfo {
-    pub digest: Option<String>,
-    pub arch: Vec<String>,
+/// Annotation of a cosign signature layer holding the base64 signature of the layer blob.
+const COSIGN_SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+/// Artifact type of cosign signatures stored as OCI referrers.
+const COSIGN_ARTIFACT_TYPE: &str = "application/vnd.dev.cosign.artifact.sig.v1+json";
+const NOTATION_ARTIFACT_TYPE: &str = "application/vnd.cncf.notary.signature";
+const JWS_MEDIA_TYPE: &str = "application/jose+json";
+const REFERRERS_ACCEPT: &str = "application/vnd.oci.image.index.v1+json";
+
+/// A signature found for a digest, as stored in the registry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignatureArtifact {
+    /// Cosign simple signing payload and the base64 signature annotated on its layer.
+    Cosign { payload: Vec<u8>, signature: String },
+    /// Notation JWS envelope (`application/jose+json`); COSE envelopes are not collected.
+    NotationJws { envelope: Vec<u8> },
 }
 
 #[async_trait]
@@ -80,114 +97,1090 @@ pub trait RegistryClient: Send + Sync {
         reference: &str,
         host_platform: &str,
     ) -> anyhow::Result<ManifestInfo>;
+
+    /// Image config of the `host_platform` image behind `reference` (a tag or digest).
+    async fn get_image_config(
+        &self,
+        _image: &ImageRef,
+        _reference: &str,
+        _host_platform: &str,
+    ) -> anyhow::Result<ImageConfig> {
+        Ok(ImageConfig::default())
+    }
+
+    /// Signatures attached to `digest`: the cosign `sha256-<hex>.sig` tag and OCI referrers
+    /// with a cosign or Notation artifact type. Not verified.
+    async fn get_signatures(
+        &self,
+        _image: &ImageRef,
+        _digest: &str,
+    ) -> anyhow::Result<Vec<SignatureArtifact>> {
+        Ok(Vec::new())
+    }
+
+    /// Set while requests to `registry` are held back: after a 429 until its `Retry-After`, or
+    /// while the remaining pull quota is at or below the configured reserve.
+    fn rate_limited(&self, _registry: &str) -> Option<RateLimited> {
+        None
+    }
+
+    /// Last known pull quota of every registry that reported one.
+    fn quotas(&self) -> Vec<RegistryQuota> {
+        Vec::new()
+    }
 }
 
+/// Error returned instead of sending a request while a registry is rate limited.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RateLimited {
+    pub registry: String,
+    pub until: Option<OffsetDateTime>,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "registry {} is rate limited", self.registry)?;
+        if let Some(until) = self.until.and_then(|t| t.format(&Rfc3339).ok()) {
+            write!(f, " until {until}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Tokens are refreshed this long before the `expires_in` the token server reported.
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(10);
+/// Token lifetime assumed when the token server omits `expires_in` (distribution spec).
+const DEFAULT_TOKEN_EXPIRES_IN: u64 = 60;
+/// A 429 with a `Retry-After` up to this long is waited out inline (at most
+/// `MAX_INLINE_RETRIES` times); longer ones block the registry until then.
+const MAX_INLINE_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_INLINE_RETRIES: u32 = 2;
+/// Backoff after a 429 without a usable `Retry-After`.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+/// Without a `RateLimit-Reset`, an exhausted quota is trusted for this long before requests are
+/// tried again (Docker Hub only reports a rolling window).
+const QUOTA_STALE_AFTER: Duration = Duration::from_secs(15 * 60);
+const MANIFEST_ACCEPT: &str = "application/vnd.oci.image.index.v1+json, application/vnd.docker.distribution.manifest.list.v2+json, application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
 #[derive(Clone)]
 pub struct HttpRegistryClient {
     http: reqwest::Client,
-    docker: Option<DockerConfig>,
-    token_cache: Arc<Mutex<HashMap<String, String>>>,
+    credentials: CredentialStore,
+    /// Bearer tokens keyed by `registry|scope`.
+    token_cache: Arc<Mutex<HashMap<String, CachedToken>>>,
+    /// `n=` sent with every tags/list request.
+    tags_page_size: u32,
+    /// Pagination stops once this many tags were collected.
+    max_tags: usize,
+    cache: Option<MetadataCache>,
+    quotas: Arc<Mutex<HashMap<String, QuotaState>>>,
+    /// Checks are deferred once a registry reports this few remaining pulls.
+    rate_limit_reserve: u64,
+    endpoints: Arc<Endpoints>,
+}
+
+/// Where registries are reached, as configured by `DOCKREV_REGISTRY_*`; see
+/// [`HttpRegistryClient::with_endpoints`].
+#[derive(Clone, Debug, Default)]
+pub struct EndpointSettings {
+    /// Pull-through caches tried in order before the registry itself, e.g. `docker.io` ->
+    /// `https://harbor.example.com/dockerhub-proxy` (the path is a repository prefix).
+    pub mirrors: HashMap<String, Vec<String>>,
+    /// Endpoint used instead of the registry's own one (after its mirrors).
+    pub overrides: HashMap<String, String>,
+    /// Registries and endpoint hosts that may be reached over plain HTTP; `https://` endpoints
+    /// among them are used without certificate verification.
+    pub insecure: Vec<String>,
+    /// PEM bundle trusted in addition to the system roots for every registry.
+    pub ca_bundle: Option<PathBuf>,
+    /// Docker `certs.d` layout: `<dir>/<host[:port]>/*.crt` is trusted for that host only.
+    pub certs_dir: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct Endpoints {
+    mirrors: HashMap<String, Vec<reqwest::Url>>,
+    overrides: HashMap<String, reqwest::Url>,
+    insecure: Vec<String>,
+    /// Client without certificate verification, for insecure `https://` endpoints.
+    insecure_http: Option<reqwest::Client>,
+    /// Clients trusting a `certs.d` CA, keyed by `host[:port]`.
+    tls: HashMap<String, reqwest::Client>,
+}
+
+/// One place a registry's API is reached at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Endpoint {
+    /// Keys credentials, bearer tokens and quota state: the registry name for the registry's
+    /// own (or overridden) endpoint, the mirror's `host[:port]` otherwise.
+    key: String,
+    /// `scheme://host[:port]`.
+    base_url: String,
+    /// `host[:port]` the requests go to.
+    authority: String,
+    /// Repository namespace of pull-through caches such as Harbor proxy projects.
+    prefix: Option<String>,
+    insecure: bool,
+}
+
+impl Endpoint {
+    fn from_url(key: &str, url: &reqwest::Url, insecure: &[String]) -> Self {
+        let authority = url_authority(url);
+        let prefix = url.path().trim_matches('/');
+        Self {
+            insecure: insecure.iter().any(|i| i == key || *i == authority),
+            key: key.to_string(),
+            base_url: format!("{}://{authority}", url.scheme()),
+            authority,
+            prefix: (!prefix.is_empty()).then(|| prefix.to_string()),
+        }
+    }
+
+    fn repository(&self, name: &str) -> String {
+        match self.prefix.as_deref() {
+            Some(prefix) => format!("{prefix}/{name}"),
+            None => name.to_string(),
+        }
+    }
+
+    fn url(&self, name: &str, path: &str) -> String {
+        format!("{}/v2/{}/{path}", self.base_url, self.repository(name))
+    }
+
+    fn scope(&self, name: &str) -> String {
+        format!("repository:{}:pull", self.repository(name))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct QuotaState {
+    limit: Option<u64>,
+    remaining: Option<u64>,
+    window_seconds: Option<u64>,
+    reset_at: Option<OffsetDateTime>,
+    blocked_until: Option<OffsetDateTime>,
+    updated_at: OffsetDateTime,
+}
+
+impl QuotaState {
+    fn new(now: OffsetDateTime) -> Self {
+        Self {
+            limit: None,
+            remaining: None,
+            window_seconds: None,
+            reset_at: None,
+            blocked_until: None,
+            updated_at: now,
+        }
+    }
+
+    fn limited_until(&self, reserve: u64, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        if let Some(until) = self.blocked_until
+            && until > now
+        {
+            return Some(until);
+        }
+        if self.remaining.is_some_and(|r| r <= reserve) {
+            let until = self.reset_at.unwrap_or(self.updated_at + QUOTA_STALE_AFTER);
+            if until > now {
+                return Some(until);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CachedToken {
+    token: String,
+    expires_at: Instant,
+}
+
+/// Tag lists and manifest lookups persisted in SQLite. Entries younger than `ttl` are used as
+/// is; older ones are revalidated with `If-None-Match` (tags) or a `HEAD` request (manifests),
+/// neither of which counts against Docker Hub's pull limit.
+#[derive(Clone)]
+struct MetadataCache {
+    db: Db,
+    ttl: Duration,
+}
+
+impl MetadataCache {
+    fn is_fresh(&self, fetched_at_unix: i64, now: i64) -> bool {
+        now.saturating_sub(fetched_at_unix) < self.ttl.as_secs() as i64
+    }
+}
+
+enum TagListing {
+    NotModified,
+    Tags {
+        tags: Vec<String>,
+        etag: Option<String>,
+    },
 }
 
 impl HttpRegistryClient {
     pub fn new(docker_config_path: Option<&Path>) -> anyhow::Result<Self> {
         let http = reqwest::Client::builder().build()?;
-        let docker = docker_config_path.and_then(|p| DockerConfig::load(p).ok());
         Ok(Self {
             http,
-            docker,
+            credentials: CredentialStore::load(docker_config_path),
             token_cache: Arc::new(Mutex::new(HashMap::new())),
+            tags_page_size: 1000,
+            max_tags: 10_000,
+            cache: None,
+            quotas: Arc::new(Mutex::new(HashMap::new())),
+            rate_limit_reserve: 0,
+            endpoints: Arc::new(Endpoints::default()),
         })
     }
+
+    /// Applies mirrors, endpoint overrides and TLS settings. Plain `http://` endpoints must be
+    /// loopback or listed in `insecure`.
+    pub fn with_endpoints(mut self, settings: EndpointSettings) -> anyhow::Result<Self> {
+        let parse = |registry: &str, raw: &str| -> anyhow::Result<reqwest::Url> {
+            let url = reqwest::Url::parse(raw)
+                .with_context(|| format!("invalid endpoint for {registry}: {raw}"))?;
+            let authority = url_authority(&url);
+            match url.scheme() {
+                "https" => {}
+                "http"
+                    if is_loopback(&authority)
+                        || settings
+                            .insecure
+                            .iter()
+                            .any(|i| *i == authority || i == registry) => {}
+                "http" => {
+                    return Err(anyhow::anyhow!(
+                        "plain HTTP endpoint {raw} for {registry} must be listed as insecure"
+                    ));
+                }
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "endpoint for {registry} must be http:// or https://: {raw}"
+                    ));
+                }
+            }
+            Ok(url)
+        };
+        let mut mirrors = HashMap::new();
+        for (registry, urls) in &settings.mirrors {
+            let urls = urls
+                .iter()
+                .map(|u| parse(registry, u))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            mirrors.insert(registry.clone(), urls);
+        }
+        let mut overrides = HashMap::new();
+        for (registry, url) in &settings.overrides {
+            overrides.insert(registry.clone(), parse(registry, url)?);
+        }
+
+        let mut roots = Vec::new();
+        if let Some(path) = settings.ca_bundle.as_deref() {
+            roots.extend(read_pem_bundle(path)?);
+        }
+        let build = |extra: &[reqwest::Certificate], insecure: bool| {
+            let mut builder = reqwest::Client::builder().danger_accept_invalid_certs(insecure);
+            for cert in roots.iter().chain(extra) {
+                builder = builder.add_root_certificate(cert.clone());
+            }
+            builder.build().context("build registry client")
+        };
+
+        let mut tls = HashMap::new();
+        if let Some(dir) = settings.certs_dir.as_deref() {
+            let entries =
+                std::fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))?;
+            for entry in entries {
+                let host_dir = entry?.path();
+                let Some(authority) = host_dir.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if !host_dir.is_dir() {
+                    continue;
+                }
+                let mut certs = Vec::new();
+                for file in std::fs::read_dir(&host_dir)? {
+                    let file = file?.path();
+                    if file.extension().is_some_and(|e| e == "crt") {
+                        certs.extend(read_pem_bundle(&file)?);
+                    }
+                }
+                if !certs.is_empty() {
+                    tls.insert(authority.to_string(), build(&certs, false)?);
+                }
+            }
+        }
+
+        self.http = build(&[], false)?;
+        self.endpoints = Arc::new(Endpoints {
+            mirrors,
+            overrides,
+            insecure_http: if settings.insecure.is_empty() {
+                None
+            } else {
+                Some(build(&[], true)?)
+            },
+            insecure: settings.insecure,
+            tls,
+        });
+        Ok(self)
+    }
+
+    pub fn with_tag_limits(mut self, page_size: u32, max_tags: usize) -> Self {
+        self.tags_page_size = page_size.max(1);
+        self.max_tags = max_tags.max(1);
+        self
+    }
+
+    pub fn with_rate_limit_reserve(mut self, reserve: u64) -> Self {
+        self.rate_limit_reserve = reserve;
+        self
+    }
+
+    /// Looks up credentials in Dockrev's database and runs Docker credential helpers.
+    pub fn with_credential_store(mut self, db: Db, runner: Arc<dyn CommandRunner>) -> Self {
+        self.credentials = self.credentials.with_db(db).with_runner(runner);
+        self
+    }
+
+    pub fn with_token_providers(mut self, providers: Arc<TokenProviders>) -> Self {
+        self.credentials = self.credentials.with_token_providers(providers);
+        self
+    }
+
+    pub fn with_cache(mut self, db: Db, ttl: Duration) -> Self {
+        self.cache = Some(MetadataCache { db, ttl });
+        self
+    }
 }
 
 #[async_trait]
 impl RegistryClient for HttpRegistryClient {
     async fn list_tags(&self, image: &ImageRef) -> anyhow::Result<Vec<String>> {
-        let scope = format!("repository:{}:pull", image.name);
-        let url = format!(
-            "https://{}/v2/{}/tags/list",
-            registry_api_host(&image.registry),
-            image.name
-        );
-        let resp = self
-            .get_with_auth(&image.registry, &scope, url, None)
-            .await?;
+        let Some(cache) = self.cache.as_ref() else {
+            return match self.fetch_tags(image, None).await? {
+                TagListing::Tags { tags, .. } => Ok(tags),
+                TagListing::NotModified => Err(anyhow::anyhow!("unexpected 304 for tags list")),
+            };
+        };
+
+        let now = unix_now();
+        let cached = cache
+            .db
+            .get_registry_tags_cache(&image.registry, &image.name)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "read registry tags cache failed");
+                None
+            });
+        if let Some(entry) = cached.as_ref()
+            && cache.is_fresh(entry.fetched_at_unix, now)
+        {
+            return Ok(entry.tags.clone());
+        }
+
+        let etag = cached.as_ref().and_then(|c| c.etag.as_deref());
+        let entry = match (self.fetch_tags(image, etag).await?, cached.clone()) {
+            (TagListing::NotModified, Some(cached)) => RegistryTagsCacheEntry {
+                fetched_at_unix: now,
+                ..cached
+            },
+            (TagListing::NotModified, None) => {
+                return Err(anyhow::anyhow!("unexpected 304 for tags list"));
+            }
+            (TagListing::Tags { tags, etag }, _) => RegistryTagsCacheEntry {
+                tags,
+                etag,
+                fetched_at_unix: now,
+            },
+        };
+        if let Err(e) = cache
+            .db
+            .put_registry_tags_cache(&image.registry, &image.name, &entry)
+            .await
+        {
+            tracing::warn!(error = %e, "write registry tags cache failed");
+        }
+        Ok(entry.tags)
+    }
+
+    async fn get_manifest(
+        &self,
+        image: &ImageRef,
+        reference: &str,
+        host_platform: &str,
+    ) -> anyhow::Result<ManifestInfo> {
+        let Some(cache) = self.cache.as_ref() else {
+            return Ok(self
+                .fetch_manifest(image, reference, host_platform)
+                .await?
+                .0);
+        };
+
+        let now = unix_now();
+        let cached = cache
+            .db
+            .get_registry_manifest_cache(&image.registry, &image.name, reference, host_platform)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "read registry manifest cache failed");
+                None
+            });
+        if let Some(entry) = cached {
+            // Digest references are immutable.
+            if reference.contains(':') || cache.is_fresh(entry.fetched_at_unix, now) {
+                return Ok(manifest_info(&entry));
+            }
+            if let Some(content_digest) = entry.content_digest.as_deref() {
+                match self.head_manifest_digest(image, reference).await {
+                    Ok(Some(current)) if current == content_digest => {
+                        let entry = RegistryManifestCacheEntry {
+                            fetched_at_unix: now,
+                            ..entry
+                        };
+                        self.store_manifest(cache, image, reference, host_platform, &entry)
+                            .await;
+                        return Ok(manifest_info(&entry));
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!(error = %e, "manifest HEAD failed; fetching"),
+                }
+            }
+        }
+
+        let (info, content_digest) = self.fetch_manifest(image, reference, host_platform).await?;
+        let entry = RegistryManifestCacheEntry {
+            content_digest: Some(content_digest),
+            digest: info.digest.clone(),
+            arch: info.arch.clone(),
+            fetched_at_unix: now,
+        };
+        self.store_manifest(cache, image, reference, host_platform, &entry)
+            .await;
+        Ok(info)
+    }
+
+    async fn get_image_config(
+        &self,
+        image: &ImageRef,
+        reference: &str,
+        host_platform: &str,
+    ) -> anyhow::Result<ImageConfig> {
+        // Digest references are immutable.
+        if let Some(cache) = self.cache.as_ref()
+            && reference.contains(':')
+        {
+            let cached = cache
+                .db
+                .get_registry_image_config_cache(
+                    &image.registry,
+                    &image.name,
+                    reference,
+                    host_platform,
+                )
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!(error = %e, "read registry image config cache failed");
+                    None
+                });
+            if let Some(config) = cached.and_then(|json| serde_json::from_str(&json).ok()) {
+                return Ok(config);
+            }
+        }
+
+        let mut body = self.fetch_manifest_body(image, reference).await?;
+        let mut manifest: serde_json::Value =
+            serde_json::from_str(&body).context("parse manifest json")?;
+        let mut annotations = BTreeMap::new();
+        if manifest.get("manifests").is_some() {
+            annotations = string_map(manifest.get("annotations"));
+            let platform_digest = parse_manifest_json(&body, None, host_platform)?
+                .digest
+                .ok_or_else(|| anyhow::anyhow!("no {host_platform} image in index {reference}"))?;
+            body = self.fetch_manifest_body(image, &platform_digest).await?;
+            manifest = serde_json::from_str(&body).context("parse manifest json")?;
+        }
+        annotations.extend(string_map(manifest.get("annotations")));
+
+        let config_digest = manifest
+            .pointer("/config/digest")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("manifest {reference} has no config"))?;
+        let config = self.fetch_blob(image, config_digest).await?;
+        let config: serde_json::Value =
+            serde_json::from_slice(&config).context("parse image config json")?;
+        let out = image_config(annotations, &manifest, &config);
+
+        if let Some(cache) = self.cache.as_ref()
+            && reference.contains(':')
+        {
+            let stored = match serde_json::to_string(&out) {
+                Ok(json) => {
+                    cache
+                        .db
+                        .put_registry_image_config_cache(
+                            &image.registry,
+                            &image.name,
+                            reference,
+                            host_platform,
+                            &json,
+                        )
+                        .await
+                }
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = stored {
+                tracing::warn!(error = %e, "write registry image config cache failed");
+            }
+        }
+        Ok(out)
+    }
+
+    async fn get_signatures(
+        &self,
+        image: &ImageRef,
+        digest: &str,
+    ) -> anyhow::Result<Vec<SignatureArtifact>> {
+        let mut out = Vec::new();
+        let sig_tag = format!("{}.sig", digest.replacen(':', "-", 1));
+        if let Some(body) = not_found_as_none(self.fetch_manifest_body(image, &sig_tag).await)? {
+            out.extend(self.cosign_signatures(image, &body).await?);
+        }
+
+        let referrers = not_found_as_none(
+            self.try_endpoints(&image.registry, |endpoint| async move {
+                self.send_with_auth(
+                    reqwest::Method::GET,
+                    &endpoint,
+                    &endpoint.scope(&image.name),
+                    &endpoint.url(&image.name, &format!("referrers/{digest}")),
+                    &[(ACCEPT, REFERRERS_ACCEPT)],
+                )
+                .await
+            })
+            .await,
+        )?;
+        let Some(referrers) = referrers else {
+            return Ok(out);
+        };
+        let index: serde_json::Value =
+            serde_json::from_str(&referrers.text().await?).context("parse referrers index")?;
+        for referrer in index
+            .get("manifests")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let artifact_type = referrer.get("artifactType").and_then(|v| v.as_str());
+            let Some(referrer_digest) = referrer.get("digest").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            match artifact_type {
+                Some(COSIGN_ARTIFACT_TYPE) => {
+                    let body = self.fetch_manifest_body(image, referrer_digest).await?;
+                    out.extend(self.cosign_signatures(image, &body).await?);
+                }
+                Some(NOTATION_ARTIFACT_TYPE) => {
+                    let body = self.fetch_manifest_body(image, referrer_digest).await?;
+                    let manifest: serde_json::Value =
+                        serde_json::from_str(&body).context("parse notation manifest")?;
+                    for layer in manifest_layers(&manifest) {
+                        if layer.get("mediaType").and_then(|v| v.as_str()) != Some(JWS_MEDIA_TYPE) {
+                            continue;
+                        }
+                        if let Some(blob) = layer.get("digest").and_then(|v| v.as_str()) {
+                            out.push(SignatureArtifact::NotationJws {
+                                envelope: self.fetch_blob(image, blob).await?,
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+
+    fn rate_limited(&self, registry: &str) -> Option<RateLimited> {
+        // Held back only while no endpoint (mirror or the registry itself) is usable.
+        let mut limited = None;
+        for endpoint in self.endpoints_for(registry) {
+            limited = Some(self.endpoint_limited(&endpoint.key)?);
+        }
+        limited.map(|l| RateLimited {
+            registry: registry.to_string(),
+            ..l
+        })
+    }
+
+    fn quotas(&self) -> Vec<RegistryQuota> {
+        let now = OffsetDateTime::now_utc();
+        let Ok(quotas) = self.quotas.lock() else {
+            return Vec::new();
+        };
+        let mut out = quotas
+            .iter()
+            .map(|(registry, q)| RegistryQuota {
+                registry: registry.clone(),
+                limit: q.limit,
+                remaining: q.remaining,
+                window_seconds: q.window_seconds,
+                reset_at: q.reset_at.and_then(|t| t.format(&Rfc3339).ok()),
+                blocked_until: q
+                    .blocked_until
+                    .filter(|t| *t > now)
+                    .and_then(|t| t.format(&Rfc3339).ok()),
+                deferred: q.limited_until(self.rate_limit_reserve, now).is_some(),
+                updated_at: q.updated_at.format(&Rfc3339).unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        out.sort_by(|a, b| a.registry.cmp(&b.registry));
+        out
+    }
+}
+
+impl HttpRegistryClient {
+    /// Lists every tag, following `Link` pagination. `etag` makes the first page conditional;
+    /// it is only returned (and only meaningful) for listings that fit on one page.
+    async fn fetch_tags(&self, image: &ImageRef, etag: Option<&str>) -> anyhow::Result<TagListing> {
+        self.try_endpoints(&image.registry, |endpoint| async move {
+            self.fetch_tags_from(&endpoint, image, etag).await
+        })
+        .await
+    }
+
+    async fn fetch_tags_from(
+        &self,
+        endpoint: &Endpoint,
+        image: &ImageRef,
+        etag: Option<&str>,
+    ) -> anyhow::Result<TagListing> {
+        let scope = endpoint.scope(&image.name);
+        let mut url = reqwest::Url::parse(&endpoint.url(&image.name, "tags/list"))?;
+        url.query_pairs_mut()
+            .append_pair("n", &self.tags_page_size.to_string());
 
         #[derive(Deserialize)]
         struct TagsResponse {
             tags: Option<Vec<String>>,
         }
 
-        let body: TagsResponse = resp.json().await?;
-        Ok(body.tags.unwrap_or_default())
+        let mut tags = Vec::new();
+        let mut first_page_etag = None;
+        let mut pages = 0;
+        loop {
+            let headers = match (pages, etag) {
+                (0, Some(etag)) => vec![(IF_NONE_MATCH, etag)],
+                _ => Vec::new(),
+            };
+            let resp = self
+                .send_with_auth(
+                    reqwest::Method::GET,
+                    endpoint,
+                    &scope,
+                    url.as_str(),
+                    &headers,
+                )
+                .await?;
+            if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+                return Ok(TagListing::NotModified);
+            }
+            pages += 1;
+            if pages == 1 {
+                first_page_etag = header_string(&resp, ETAG);
+            }
+            let next = resp
+                .headers()
+                .get(LINK)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| parse_link_next(v, &url));
+
+            let body: TagsResponse = resp.json().await?;
+            tags.extend(body.tags.unwrap_or_default());
+            if tags.len() >= self.max_tags {
+                if next.is_some() || tags.len() > self.max_tags {
+                    tracing::warn!(
+                        image = %format!("{}/{}", image.registry, image.name),
+                        max_tags = self.max_tags,
+                        "tag list truncated"
+                    );
+                }
+                tags.truncate(self.max_tags);
+                break;
+            }
+
+            match next {
+                // A registry repeating the same page would otherwise loop forever.
+                Some(next) if next != url => url = next,
+                _ => break,
+            }
+        }
+        Ok(TagListing::Tags {
+            tags,
+            etag: if pages == 1 { first_page_etag } else { None },
+        })
     }
 
-    async fn get_manifest(
+    /// Fetches and parses a manifest; also returns the digest of the manifest document, which
+    /// later `HEAD` requests are compared against.
+    async fn fetch_manifest(
         &self,
         image: &ImageRef,
         reference: &str,
         host_platform: &str,
-    ) -> anyhow::Result<ManifestInfo> {
-        let scope = format!("repository:{}:pull", image.name);
-        let url = format!(
-            "https://{}/v2/{}/manifests/{}",
-            registry_api_host(&image.registry),
-            image.name,
-            reference
-        );
+    ) -> anyhow::Result<(ManifestInfo, String)> {
+        let resp = self
+            .try_endpoints(&image.registry, |endpoint| async move {
+                self.send_with_auth(
+                    reqwest::Method::GET,
+                    &endpoint,
+                    &endpoint.scope(&image.name),
+                    &endpoint.url(&image.name, &format!("manifests/{reference}")),
+                    &[(ACCEPT, MANIFEST_ACCEPT)],
+                )
+                .await
+            })
+            .await?;
+        let digest = header_string(&resp, DOCKER_CONTENT_DIGEST);
+        let body = resp.text().await?;
+        let content_digest = digest
+            .clone()
+            .unwrap_or_else(|| format!("sha256:{:x}", Sha256::digest(body.as_bytes())));
+        let info = ManifestInfo {
+            content_digest: Some(content_digest.clone()),
+            ..parse_manifest_json(&body, digest, host_platform)?
+        };
+        Ok((info, content_digest))
+    }
+
+    async fn head_manifest_digest(
+        &self,
+        image: &ImageRef,
+        reference: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let resp = self
+            .try_endpoints(&image.registry, |endpoint| async move {
+                self.send_with_auth(
+                    reqwest::Method::HEAD,
+                    &endpoint,
+                    &endpoint.scope(&image.name),
+                    &endpoint.url(&image.name, &format!("manifests/{reference}")),
+                    &[(ACCEPT, MANIFEST_ACCEPT)],
+                )
+                .await
+            })
+            .await?;
+        Ok(header_string(&resp, DOCKER_CONTENT_DIGEST))
+    }
+
+    async fn fetch_manifest_body(
+        &self,
+        image: &ImageRef,
+        reference: &str,
+    ) -> anyhow::Result<String> {
+        let resp = self
+            .try_endpoints(&image.registry, |endpoint| async move {
+                self.send_with_auth(
+                    reqwest::Method::GET,
+                    &endpoint,
+                    &endpoint.scope(&image.name),
+                    &endpoint.url(&image.name, &format!("manifests/{reference}")),
+                    &[(ACCEPT, MANIFEST_ACCEPT)],
+                )
+                .await
+            })
+            .await?;
+        Ok(resp.text().await?)
+    }
+
+    /// Payloads of the layers of a cosign signature manifest that carry a signature.
+    async fn cosign_signatures(
+        &self,
+        image: &ImageRef,
+        body: &str,
+    ) -> anyhow::Result<Vec<SignatureArtifact>> {
+        let manifest: serde_json::Value =
+            serde_json::from_str(body).context("parse cosign signature manifest")?;
+        let mut out = Vec::new();
+        for layer in manifest_layers(&manifest) {
+            let signature = layer
+                .pointer("/annotations")
+                .and_then(|a| a.get(COSIGN_SIGNATURE_ANNOTATION))
+                .and_then(|v| v.as_str());
+            let (Some(signature), Some(blob)) =
+                (signature, layer.get("digest").and_then(|v| v.as_str()))
+            else {
+                continue;
+            };
+            out.push(SignatureArtifact::Cosign {
+                payload: self.fetch_blob(image, blob).await?,
+                signature: signature.to_string(),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Downloads a blob and checks it against its `sha256` digest.
+    async fn fetch_blob(&self, image: &ImageRef, digest: &str) -> anyhow::Result<Vec<u8>> {
+        let resp = self
+            .try_endpoints(&image.registry, |endpoint| async move {
+                self.send_with_auth(
+                    reqwest::Method::GET,
+                    &endpoint,
+                    &endpoint.scope(&image.name),
+                    &endpoint.url(&image.name, &format!("blobs/{digest}")),
+                    &[],
+                )
+                .await
+            })
+            .await?;
+        let bytes = resp.bytes().await?.to_vec();
+        if let Some(expected) = digest.strip_prefix("sha256:") {
+            let actual = format!("{:x}", Sha256::digest(&bytes));
+            if actual != expected {
+                return Err(anyhow::anyhow!(
+                    "blob {digest} does not match its digest (got sha256:{actual})"
+                ));
+            }
+        }
+        Ok(bytes)
+    }
+
+    async fn store_manifest(
+        &self,
+        cache: &MetadataCache,
+        image: &ImageRef,
+        reference: &str,
+        host_platform: &str,
+        entry: &RegistryManifestCacheEntry,
+    ) {
+        if let Err(e) = cache
+            .db
+            .put_registry_manifest_cache(
+                &image.registry,
+                &image.name,
+                reference,
+                host_platform,
+                entry,
+            )
+            .await
+        {
+            tracing::warn!(error = %e, "write registry manifest cache failed");
+        }
+    }
+
+    /// Sends an authenticated request while honoring the registry's rate limit: nothing is sent
+    /// while it is limited, and a 429 is retried after a short `Retry-After` or blocks the
+    /// registry for a longer one. `304 Not Modified` counts as success.
+    async fn send_with_auth(
+        &self,
+        method: reqwest::Method,
+        endpoint: &Endpoint,
+        scope: &str,
+        url: &str,
+        headers: &[(HeaderName, &str)],
+    ) -> anyhow::Result<reqwest::Response> {
+        let registry_host = endpoint.key.as_str();
+        let mut retries = 0;
+        loop {
+            if let Some(limited) = self.endpoint_limited(registry_host) {
+                return Err(limited.into());
+            }
+            let resp = self
+                .send_authenticated(method.clone(), endpoint, scope, url, headers)
+                .await?;
+            let now = OffsetDateTime::now_utc();
+            self.record_quota(registry_host, resp.headers(), now);
+            if resp.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                return check_status(resp);
+            }
+
+            let retry_after = resp
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| parse_retry_after(v, now))
+                .unwrap_or(DEFAULT_RETRY_AFTER);
+            if retry_after <= MAX_INLINE_BACKOFF && retries < MAX_INLINE_RETRIES {
+                retries += 1;
+                tracing::info!(registry = %registry_host, retry_after = ?retry_after, "registry returned 429; backing off");
+                tokio::time::sleep(retry_after).await;
+                continue;
+            }
+
+            let until = now + retry_after;
+            if let Ok(mut quotas) = self.quotas.lock() {
+                quotas
+                    .entry(registry_host.to_string())
+                    .or_insert_with(|| QuotaState::new(now))
+                    .blocked_until = Some(until);
+            }
+            tracing::warn!(registry = %registry_host, retry_after = ?retry_after, "registry rate limit hit");
+            return Err(RateLimited {
+                registry: registry_host.to_string(),
+                until: Some(until),
+            }
+            .into());
+        }
+    }
+
+    /// The registry's endpoints in the order they are tried: its mirrors, then the registry
+    /// itself (or its override).
+    fn endpoints_for(&self, registry: &str) -> Vec<Endpoint> {
+        let insecure = &self.endpoints.insecure;
+        let mut out = self
+            .endpoints
+            .mirrors
+            .get(registry)
+            .into_iter()
+            .flatten()
+            .map(|url| Endpoint::from_url(&url_authority(url), url, insecure))
+            .collect::<Vec<_>>();
+        if let Some(url) = self.endpoints.overrides.get(registry) {
+            out.push(Endpoint::from_url(registry, url, insecure));
+            return out;
+        }
+        let plain_http = insecure.iter().any(|i| i == registry);
+        let own = reqwest::Url::parse(&registry_base_url(registry, plain_http))
+            .map(|url| Endpoint::from_url(registry, &url, insecure));
+        match own {
+            Ok(own) => out.push(own),
+            Err(e) => tracing::warn!(registry = %registry, error = %e, "invalid registry host"),
+        }
+        out
+    }
+
+    /// Runs `op` against each endpoint of `registry` until one succeeds; a failing mirror falls
+    /// through to the next endpoint.
+    async fn try_endpoints<T, F>(
+        &self,
+        registry: &str,
+        op: impl Fn(Endpoint) -> F,
+    ) -> anyhow::Result<T>
+    where
+        F: Future<Output = anyhow::Result<T>>,
+    {
+        let endpoints = self.endpoints_for(registry);
+        let mut last_err = anyhow::anyhow!("no endpoint for registry {registry}");
+        for (i, endpoint) in endpoints.iter().enumerate() {
+            match op(endpoint.clone()).await {
+                Ok(v) => return Ok(v),
+                Err(e) => {
+                    if i + 1 < endpoints.len() {
+                        tracing::warn!(
+                            registry = %registry,
+                            endpoint = %endpoint.base_url,
+                            error = %e,
+                            "registry mirror failed; trying next endpoint"
+                        );
+                    }
+                    last_err = e;
+                }
+            }
+        }
+        Err(last_err)
+    }
 
-        let accept = Some(
-            "application/vnd.oci.image.index.v1+json, application/vnd.docker.distribution.manifest.list.v2+json, application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json",
-        );
-        let resp = self
-            .get_with_auth(&image.registry, &scope, url, accept)
-            .await?;
+    fn client_for(&self, authority: &str, insecure: bool) -> &reqwest::Client {
+        let endpoints = &self.endpoints;
+        match (insecure, endpoints.insecure_http.as_ref()) {
+            (true, Some(client)) => client,
+            _ => endpoints.tls.get(authority).unwrap_or(&self.http),
+        }
+    }
 
-        let digest = resp
-            .headers()
-            .get("Docker-Content-Digest")
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.to_string());
+    fn endpoint_limited(&self, key: &str) -> Option<RateLimited> {
+        let quotas = self.quotas.lock().ok()?;
+        let until = quotas
+            .get(key)?
+            .limited_until(self.rate_limit_reserve, OffsetDateTime::now_utc())?;
+        Some(RateLimited {
+            registry: key.to_string(),
+            until: Some(until),
+        })
+    }
 
-        let body = resp.text().await?;
-        parse_manifest_json(&body, digest, host_platform)
+    /// Records `RateLimit-Limit` / `RateLimit-Remaining` / `RateLimit-Reset` of a response.
+    fn record_quota(&self, registry_host: &str, headers: &HeaderMap, now: OffsetDateTime) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_ratelimit_value)
+        };
+        let limit = header("ratelimit-limit");
+        let remaining = header("ratelimit-remaining");
+        if limit.is_none() && remaining.is_none() {
+            return;
+        }
+        let reset_at = header("ratelimit-reset").map(|(v, _)| {
+            // Delta seconds per the IETF draft; some registries send an epoch timestamp.
+            if v > 1_000_000_000 {
+                OffsetDateTime::from_unix_timestamp(v as i64).unwrap_or(now)
+            } else {
+                now + Duration::from_secs(v)
+            }
+        });
+        let Ok(mut quotas) = self.quotas.lock() else {
+            return;
+        };
+        let q = quotas
+            .entry(registry_host.to_string())
+            .or_insert_with(|| QuotaState::new(now));
+        q.limit = limit.map(|(v, _)| v);
+        q.remaining = remaining.map(|(v, _)| v);
+        q.window_seconds = limit.or(remaining).and_then(|(_, w)| w);
+        q.reset_at = reset_at;
+        q.updated_at = now;
     }
-}
 
-impl HttpRegistryClient {
-    async fn get_with_auth(
+    /// Sends a request with a cached bearer token (or docker config basic auth) and answers a
+    /// bearer challenge once.
+    async fn send_authenticated(
         &self,
-        registry_host: &str,
+        method: reqwest::Method,
+        endpoint: &Endpoint,
         scope: &str,
-        url: String,
-        accept: Option<&str>,
+        url: &str,
+        headers: &[(HeaderName, &str)],
     ) -> anyhow::Result<reqwest::Response> {
-        let mut builder = self.http.get(url.clone());
-        if let Some(accept) = accept {
-            builder = builder.header(ACCEPT, accept);
-        }
-        if let Some((user, pass)) = self
-            .docker
-            .as_ref()
-            .and_then(|d| d.basic_auth(registry_host))
-        {
-            builder = builder.header(
-                AUTHORIZATION,
-                format!("Basic {}", BASE64.encode(format!("{user}:{pass}"))),
-            );
-        }
+        let registry_host = endpoint.key.as_str();
+        let token_key = format!("{registry_host}|{scope}");
+        let http = self.client_for(&endpoint.authority, endpoint.insecure);
+        let request = |authorization: Option<String>| {
+            let mut builder = http.request(method.clone(), url);
+            for (name, value) in headers {
+                builder = builder.header(name, *value);
+            }
+            if let Some(authorization) = authorization {
+                builder = builder.header(AUTHORIZATION, authorization);
+            }
+            builder
+        };
+
+        let cached_token = self.cached_token(&token_key);
+        let authorization = match cached_token.as_deref() {
+            Some(token) => Some(format!("Bearer {token}")),
+            None => self.credentials.get(registry_host).await.map(|c| {
+                format!(
+                    "Basic {}",
+                    BASE64.encode(format!("{}:{}", c.username, c.password))
+                )
+            }),
+        };
 
-        let resp = builder.send().await?;
+        let resp = request(authorization).send().await?;
         if resp.status() != reqwest::StatusCode::UNAUTHORIZED {
-            if !resp.status().is_success() {
-                return Err(anyhow::anyhow!(
-                    "registry request failed: {}",
-                    resp.status()
-                ));
-            }
             return Ok(resp);
         }
+        if cached_token.is_some() {
+            // Revoked before its expiry; go through the challenge again.
+            self.forget_token(&token_key);
+        }
 
         let www = resp
             .headers()
@@ -200,22 +1193,23 @@ impl HttpRegistryClient {
             return Err(anyhow::anyhow!("unauthorized"));
         };
 
-        let token = self.get_bearer_token(registry_host, &bearer, scope).await?;
+        let token = self
+            .get_bearer_token(registry_host, &bearer, scope, &token_key)
+            .await?;
+        Ok(request(Some(format!("Bearer {token}"))).send().await?)
+    }
 
-        let mut builder2 = self.http.get(url);
-        if let Some(accept) = accept {
-            builder2 = builder2.header(ACCEPT, accept);
-        }
-        builder2 = builder2.header(AUTHORIZATION, format!("Bearer {token}"));
+    fn cached_token(&self, token_key: &str) -> Option<String> {
+        let m = self.token_cache.lock().ok()?;
+        m.get(token_key)
+            .filter(|t| t.expires_at > Instant::now())
+            .map(|t| t.token.clone())
+    }
 
-        let resp2 = builder2.send().await?;
-        if !resp2.status().is_success() {
-            return Err(anyhow::anyhow!(
-                "registry request failed: {}",
-                resp2.status()
-            ));
+    fn forget_token(&self, token_key: &str) {
+        if let Ok(mut m) = self.token_cache.lock() {
+            m.remove(token_key);
         }
-        Ok(resp2)
     }
 
     async fn get_bearer_token(
@@ -223,19 +1217,8 @@ impl HttpRegistryClient {
         registry_host: &str,
         bearer: &BearerAuth,
         scope: &str,
+        token_key: &str,
     ) -> anyhow::Result<String> {
-        let cache_key = format!(
-            "{}|{}|{}",
-            bearer.realm,
-            bearer.service.as_deref().unwrap_or_default(),
-            scope
-        );
-        if let Ok(m) = self.token_cache.lock()
-            && let Some(t) = m.get(&cache_key)
-        {
-            return Ok(t.clone());
-        }
-
         let mut url = reqwest::Url::parse(&bearer.realm)?;
         {
             let mut qp = url.query_pairs_mut();
@@ -245,19 +1228,17 @@ impl HttpRegistryClient {
             qp.append_pair("scope", scope);
         }
 
-        let mut req = self.http.get(url);
-        if let Some((user, pass)) = self
-            .docker
-            .as_ref()
-            .and_then(|d| d.basic_auth(registry_host))
-        {
-            req = req.basic_auth(user, Some(pass));
+        let insecure = self.endpoints.insecure.contains(&url_authority(&url));
+        let mut req = self.client_for(&url_authority(&url), insecure).get(url);
+        if let Some(c) = self.credentials.get(registry_host).await {
+            req = req.basic_auth(c.username, Some(c.password));
         }
 
         #[derive(Deserialize)]
         struct TokenResponse {
             token: Option<String>,
             access_token: Option<String>,
+            expires_in: Option<u64>,
         }
 
         let resp = req.send().await?;
@@ -270,14 +1251,173 @@ impl HttpRegistryClient {
             .or(tr.access_token)
             .ok_or_else(|| anyhow::anyhow!("token response missing token"))?;
 
+        let lifetime = Duration::from_secs(tr.expires_in.unwrap_or(DEFAULT_TOKEN_EXPIRES_IN))
+            .saturating_sub(TOKEN_EXPIRY_MARGIN);
         if let Ok(mut m) = self.token_cache.lock() {
-            m.insert(cache_key, token.clone());
+            m.insert(
+                token_key.to_string(),
+                CachedToken {
+                    token: token.clone(),
+                    expires_at: Instant::now() + lifetime,
+                },
+            );
         }
 
         Ok(token)
     }
 }
 
+/// Unsuccessful registry response.
+#[derive(Debug)]
+struct StatusError(reqwest::StatusCode);
+
+impl std::fmt::Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "registry request failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+fn check_status(resp: reqwest::Response) -> anyhow::Result<reqwest::Response> {
+    if resp.status().is_success() || resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        Ok(resp)
+    } else {
+        Err(StatusError(resp.status()).into())
+    }
+}
+
+/// Maps a 404 to `None`, for documents that may legitimately not exist.
+fn not_found_as_none<T>(result: anyhow::Result<T>) -> anyhow::Result<Option<T>> {
+    match result {
+        Ok(v) => Ok(Some(v)),
+        Err(e)
+            if e.downcast_ref::<StatusError>()
+                .is_some_and(|s| s.0 == reqwest::StatusCode::NOT_FOUND) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn header_string(
+    resp: &reqwest::Response,
+    name: impl reqwest::header::AsHeaderName,
+) -> Option<String> {
+    resp.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+/// `100;w=21600` -> (100, Some(21600)).
+fn parse_ratelimit_value(value: &str) -> Option<(u64, Option<u64>)> {
+    let mut parts = value.split(';');
+    let n = parts.next()?.trim().parse::<u64>().ok()?;
+    let window = parts.find_map(|p| {
+        p.trim()
+            .strip_prefix("w=")
+            .and_then(|w| w.trim().parse::<u64>().ok())
+    });
+    Some((n, window))
+}
+
+/// `Retry-After` as delta seconds or HTTP date.
+fn parse_retry_after(value: &str, now: OffsetDateTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+    Some(Duration::try_from(at - now).unwrap_or(Duration::ZERO))
+}
+
+/// String values of a JSON object such as `annotations` or `Labels` (which may be `null`).
+/// What the candidates view shows of an image: the config blob's `config` section and the
+/// compressed size of the platform manifest's layers.
+fn image_config(
+    annotations: BTreeMap<String, String>,
+    manifest: &serde_json::Value,
+    config: &serde_json::Value,
+) -> ImageConfig {
+    let strings = |pointer: &str| {
+        config.pointer(pointer).and_then(|v| v.as_array()).map(|v| {
+            v.iter()
+                .filter_map(|s| Some(s.as_str()?.to_string()))
+                .collect::<Vec<_>>()
+        })
+    };
+    let mut exposed_ports = config
+        .pointer("/config/ExposedPorts")
+        .and_then(|v| v.as_object())
+        .map(|m| m.keys().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    exposed_ports.sort();
+    let env = strings("/config/Env")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|kv| {
+            kv.split_once('=')
+                .map_or(kv.as_str(), |(k, _)| k)
+                .to_string()
+        })
+        .collect();
+    let compressed_size = manifest
+        .get("layers")
+        .and_then(|v| v.as_array())
+        .and_then(|layers| {
+            layers
+                .iter()
+                .map(|l| l.get("size").and_then(|v| v.as_u64()))
+                .sum::<Option<u64>>()
+        });
+    ImageConfig {
+        annotations,
+        labels: string_map(config.pointer("/config/Labels")),
+        created: config
+            .get("created")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        exposed_ports,
+        env,
+        entrypoint: strings("/config/Entrypoint"),
+        cmd: strings("/config/Cmd"),
+        compressed_size,
+    }
+}
+
+fn manifest_layers(manifest: &serde_json::Value) -> impl Iterator<Item = &serde_json::Value> {
+    manifest
+        .get("layers")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+}
+
+fn string_map(value: Option<&serde_json::Value>) -> BTreeMap<String, String> {
+    value
+        .and_then(|v| v.as_object())
+        .map(|m| {
+            m.iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn manifest_info(entry: &RegistryManifestCacheEntry) -> ManifestInfo {
+    ManifestInfo {
+        digest: entry.digest.clone(),
+        arch: entry.arch.clone(),
+        content_digest: entry.content_digest.clone(),
+    }
+}
+
+fn unix_now() -> i64 {
+    time::OffsetDateTime::now_utc().unix_timestamp()
+}
+
 fn registry_api_host(registry: &str) -> &str {
     if registry == "docker.io" {
         "registry-1.docker.io"
@@ -286,6 +1426,66 @@ fn registry_api_host(registry: &str) -> &str {
     }
 }
 
+/// Like the Docker daemon, loopback and insecure registries are spoken to over plain HTTP.
+pub fn registry_base_url(registry: &str, insecure: bool) -> String {
+    let host = registry_api_host(registry);
+    let scheme = if insecure || is_loopback(host) {
+        "http"
+    } else {
+        "https"
+    };
+    format!("{scheme}://{host}")
+}
+
+fn is_loopback(authority: &str) -> bool {
+    let hostname = match authority.rsplit_once(':') {
+        Some((h, port)) if !port.contains(']') => h,
+        _ => authority,
+    };
+    hostname == "localhost"
+        || hostname == "[::1]"
+        || hostname
+            .parse::<std::net::Ipv4Addr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+pub fn url_authority(url: &reqwest::Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    }
+}
+
+fn read_pem_bundle(path: &Path) -> anyhow::Result<Vec<reqwest::Certificate>> {
+    let pem = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    reqwest::Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("parse certificates in {}", path.display()))
+}
+
+/// Target of the `rel="next"` entry of an RFC 8288 `Link` header, resolved against the
+/// request URL (registries usually send a path like `</v2/x/tags/list?n=100&last=b>`).
+fn parse_link_next(header_value: &str, base: &reqwest::Url) -> Option<reqwest::Url> {
+    header_value.split(',').find_map(|link| {
+        let (target, params) = link.trim().split_once(';')?;
+        let target = target.trim().strip_prefix('<')?.strip_suffix('>')?;
+        let is_next = params.split(';').any(|p| {
+            p.trim().split_once('=').is_some_and(|(k, v)| {
+                k.trim().eq_ignore_ascii_case("rel")
+                    && v.trim()
+                        .trim_matches('"')
+                        .split_ascii_whitespace()
+                        .any(|r| r.eq_ignore_ascii_case("next"))
+            })
+        });
+        if is_next {
+            base.join(target).ok()
+        } else {
+            None
+        }
+    })
+}
+
 #[derive(Clone, Debug)]
 struct BearerAuth {
     realm: String,
@@ -319,72 +1519,6 @@ fn parse_www_authenticate_bearer(header_value: &str) -> Option<BearerAuth> {
     })
 }
 
-#[derive(Clone, Debug)]
-struct DockerConfig {
-    auths: HashMap<String, DockerAuthEntry>,
-}
-
-#[derive(Clone, Debug, Deserialize)]
-struct DockerAuthEntry {
-    auth: Option<String>,
-    #[serde(rename = "identitytoken")]
-    identity_token: Option<String>,
-}
-
-impl DockerConfig {
-    fn load(path: &Path) -> anyhow::Result<Self> {
-        let text = std::fs::read_to_string(path)
-            .with_context(|| format!("read docker config {path:?}"))?;
-        #[derive(Deserialize)]
-        struct Root {
-            auths: Option<HashMap<String, DockerAuthEntry>>,
-        }
-        let root: Root = serde_json::from_str(&text).context("parse docker config json")?;
-        let mut auths = HashMap::new();
-        for (k, v) in root.auths.unwrap_or_default() {
-            auths.insert(normalize_auth_key(&k), v);
-        }
-        Ok(Self { auths })
-    }
-
-    fn basic_auth(&self, registry_host: &str) -> Option<(String, String)> {
-        let key = normalize_auth_key(registry_host);
-        let entry = self.auths.get(&key)?;
-
-        if let Some(token) = entry.identity_token.as_deref() {
-            return Some(("oauth2".to_string(), token.to_string()));
-        }
-
-        let auth = entry.auth.as_deref()?;
-        let decoded = BASE64.decode(auth).ok()?;
-        let decoded = String::from_utf8(decoded).ok()?;
-        let (user, pass) = decoded.split_once(':')?;
-        Some((user.to_string(), pass.to_string()))
-    }
-}
-
-fn normalize_auth_key(input: &str) -> String {
-    if let Ok(url) = reqwest::Url::parse(input)
-        && let Some(host) = url.host_str()
-    {
-        return normalize_auth_key(host);
-    }
-
-    let host = input
-        .trim()
-        .trim_end_matches('/')
-        .trim_end_matches("/v1/")
-        .trim_end_matches("/v2/")
-        .trim_end_matches("/v1")
-        .trim_end_matches("/v2")
-        .to_string();
-
-    match host.as_str() {
-        "index.docker.io" | "registry-1.docker.io" => "docker.io".to_string(),
-        _ => host,
-    }
-}
-
 pub fn parse_manifest_json(
     body: &str,
     digest: Option<String>,
@@ -457,7 +1591,7 @@ pub fn parse_manifest_json(
     arch.sort();
     arch.dedup();
 
-    let digest = if host_platform_digest_exact.is_some() {
+    let host_digest = if host_platform_digest_exact.is_some() {
         host_platform_digest_exact
     } else {
         host_platform_digest_base_matches.sort();
@@ -468,8 +1602,12 @@ pub fn parse_manifest_json(
             None
         }
     }
-    .or(digest);
-    Ok(ManifestInfo { digest, arch })
+    .or(digest.clone());
+    Ok(ManifestInfo {
+        digest: host_digest,
+        arch,
+        content_digest: digest,
+    })
 }
 
 fn platform_matches(
@@ -529,22 +1667,6 @@ pub fn compute_arch_match(host_platform: &str, arch: &[String]) -> ArchMatch {
 mod tests {
     use super::*;
 
-    #[test]
-    fn parse_image_ref_with_registry() {
-        let img = ImageRef::parse("ghcr.io/org/app:5.2").unwrap();
-        assert_eq!(img.registry, "ghcr.io");
-        assert_eq!(img.name, "org/app");
-        assert_eq!(img.reference, "5.2");
-    }
-
-    #[test]
-    fn parse_image_ref_dockerhub() {
-        let img = ImageRef::parse("postgres:16").unwrap();
-        assert_eq!(img.registry, "docker.io");
-        assert_eq!(img.name, "library/postgres");
-        assert_eq!(img.reference, "16");
-    }
-
     #[test]
     fn parse_manifest_list_arch() {
         let json = r#"{
@@ -614,4 +1736,859 @@ mod tests {
             ArchMatch::Mismatch
         ));
     }
+
+    #[test]
+    fn link_header_next() {
+        let base = reqwest::Url::parse("https://ghcr.io/v2/org/app/tags/list?n=2").unwrap();
+        let next =
+            parse_link_next(r#"</v2/org/app/tags/list?n=2&last=b>; rel="next""#, &base).unwrap();
+        assert_eq!(
+            next.as_str(),
+            "https://ghcr.io/v2/org/app/tags/list?n=2&last=b"
+        );
+        assert!(parse_link_next(r#"</v2/x>; rel="prev""#, &base).is_none());
+        assert_eq!(
+            parse_link_next(r#"<https://a/x>; rel=prev, <https://b/y>; rel=next"#, &base)
+                .unwrap()
+                .as_str(),
+            "https://b/y"
+        );
+        assert_eq!(
+            registry_base_url("docker.io", false),
+            "https://registry-1.docker.io"
+        );
+        assert_eq!(
+            registry_base_url("127.0.0.1:5000", false),
+            "http://127.0.0.1:5000"
+        );
+        assert_eq!(
+            registry_base_url("localhost:5000", false),
+            "http://localhost:5000"
+        );
+        assert_eq!(registry_base_url("[::1]:5000", false), "http://[::1]:5000");
+    }
+
+    /// Serves `tags` in pages of `n` (capped at 10 like real registries), linking to the next
+    /// page with a relative `Link` header.
+    async fn stub_registry(tags: Vec<String>) -> (String, Arc<Mutex<Vec<String>>>) {
+        use axum::{
+            extract::{Query, State},
+            http::HeaderMap,
+        };
+
+        type Seen = Arc<Mutex<Vec<String>>>;
+
+        async fn tags_list(
+            State((tags, seen)): State<(Arc<Vec<String>>, Seen)>,
+            Query(q): Query<HashMap<String, String>>,
+        ) -> (HeaderMap, axum::Json<serde_json::Value>) {
+            seen.lock().unwrap().push(format!(
+                "n={} last={}",
+                q.get("n").map(String::as_str).unwrap_or("-"),
+                q.get("last").map(String::as_str).unwrap_or("-")
+            ));
+            let n = q
+                .get("n")
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(10)
+                .min(10);
+            let start = q
+                .get("last")
+                .and_then(|last| tags.iter().position(|t| t == last))
+                .map(|i| i + 1)
+                .unwrap_or(0);
+            let page = tags.iter().skip(start).take(n).cloned().collect::<Vec<_>>();
+            let mut headers = HeaderMap::new();
+            if start + page.len() < tags.len() {
+                headers.insert(
+                    LINK,
+                    format!(
+                        r#"</v2/acme/web/tags/list?n={n}&last={}>; rel="next""#,
+                        page.last().unwrap()
+                    )
+                    .parse()
+                    .unwrap(),
+                );
+            }
+            (
+                headers,
+                axum::Json(serde_json::json!({"name": "acme/web", "tags": page})),
+            )
+        }
+
+        let seen: Seen = Arc::default();
+        let app = axum::Router::new()
+            .route("/v2/acme/web/tags/list", axum::routing::get(tags_list))
+            .with_state((Arc::new(tags), seen.clone()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (addr.to_string(), seen)
+    }
+
+    #[tokio::test]
+    async fn list_tags_follows_link_pagination() {
+        let all = (1..=25).map(|i| format!("1.0.{i}")).collect::<Vec<_>>();
+        let (registry, seen) = stub_registry(all.clone()).await;
+        let image = ImageRef::parse(&format!("{registry}/acme/web:1.0.1")).unwrap();
+
+        let client = HttpRegistryClient::new(None)
+            .unwrap()
+            .with_tag_limits(100, 1000);
+        assert_eq!(client.list_tags(&image).await.unwrap(), all);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            ["n=100 last=-", "n=10 last=1.0.10", "n=10 last=1.0.20"]
+        );
+
+        seen.lock().unwrap().clear();
+        let capped = HttpRegistryClient::new(None)
+            .unwrap()
+            .with_tag_limits(5, 12);
+        assert_eq!(capped.list_tags(&image).await.unwrap(), all[..12]);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            ["n=5 last=-", "n=5 last=1.0.5", "n=5 last=1.0.10"]
+        );
+    }
+
+    #[tokio::test]
+    async fn image_config_follows_the_index_to_the_platform_config() {
+        let digest = |body: &str| format!("sha256:{:x}", Sha256::digest(body.as_bytes()));
+        let config = r#"{"created":"2026-01-02T03:04:05Z","config":{"Labels":{"org.opencontainers.image.version":"1.0.0","maintainer":"acme"},"ExposedPorts":{"8080/tcp":{},"443/tcp":{}},"Env":["PATH=/usr/bin","API_KEY=secret"],"Entrypoint":["/entrypoint.sh"],"Cmd":null}}"#;
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "config": {"digest": digest(config)},
+            "layers": [{"size": 100}, {"size": 23}],
+            "annotations": {"org.opencontainers.image.revision": "abc123"}
+        })
+        .to_string();
+        let corrupt = serde_json::json!({"config": {"digest": digest("other")}}).to_string();
+        let index = serde_json::json!({
+            "manifests": [
+                {"digest": digest(&manifest), "platform": {"os": "linux", "architecture": "amd64"}},
+                {"digest": digest(&corrupt), "platform": {"os": "linux", "architecture": "arm64"}}
+            ],
+            "annotations": {
+                "org.opencontainers.image.source": "https://github.com/acme/web",
+                "org.opencontainers.image.revision": "index"
+            }
+        })
+        .to_string();
+        let docs = HashMap::from([
+            ("manifests/1.0".to_string(), index),
+            (format!("manifests/{}", digest(&manifest)), manifest),
+            (format!("manifests/{}", digest(&corrupt)), corrupt),
+            (format!("blobs/{}", digest(config)), config.to_string()),
+            (format!("blobs/{}", digest("other")), config.to_string()),
+        ]);
+
+        async fn serve(
+            axum::extract::State(docs): axum::extract::State<Arc<HashMap<String, String>>>,
+            uri: axum::http::Uri,
+        ) -> axum::response::Response {
+            use axum::response::IntoResponse as _;
+            let path = uri.path().trim_start_matches("/v2/acme/web/");
+            match docs.get(path) {
+                Some(body) => body.clone().into_response(),
+                None => axum::http::StatusCode::NOT_FOUND.into_response(),
+            }
+        }
+        let app = axum::Router::new()
+            .fallback(serve)
+            .with_state(Arc::new(docs));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = HttpRegistryClient::new(None).unwrap();
+        let image = ImageRef::parse(&format!("{addr}/acme/web:1.0")).unwrap();
+        let config = client
+            .get_image_config(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        assert_eq!(
+            config.annotation("org.opencontainers.image.source"),
+            Some("https://github.com/acme/web")
+        );
+        assert_eq!(
+            config.annotation("org.opencontainers.image.revision"),
+            Some("abc123"),
+            "platform manifest annotations win over the index"
+        );
+        assert_eq!(
+            config.annotation("org.opencontainers.image.version"),
+            Some("1.0.0"),
+            "labels back annotations"
+        );
+        assert_eq!(config.labels["maintainer"], "acme");
+        assert_eq!(config.created.as_deref(), Some("2026-01-02T03:04:05Z"));
+        assert_eq!(config.exposed_ports, ["443/tcp", "8080/tcp"]);
+        assert_eq!(config.env, ["PATH", "API_KEY"], "values are dropped");
+        assert_eq!(config.entrypoint, Some(vec!["/entrypoint.sh".to_string()]));
+        assert_eq!(config.cmd, None);
+        assert_eq!(config.compressed_size, Some(123));
+
+        let err = client
+            .get_image_config(&image, "1.0", "linux/arm64")
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("does not match its digest"),
+            "{err}"
+        );
+        assert!(
+            client
+                .get_image_config(&image, "1.0", "linux/s390x")
+                .await
+                .is_err()
+        );
+    }
+
+    /// Anonymous registry serving `acme/web` documents by path (`manifests/1.0`, `blobs/...`).
+    async fn serve_docs(docs: HashMap<String, String>) -> std::net::SocketAddr {
+        async fn serve(
+            axum::extract::State(docs): axum::extract::State<Arc<HashMap<String, String>>>,
+            uri: axum::http::Uri,
+        ) -> axum::response::Response {
+            use axum::response::IntoResponse as _;
+            let path = uri.path().trim_start_matches("/v2/acme/web/");
+            match docs.get(path) {
+                Some(body) => body.clone().into_response(),
+                None => axum::http::StatusCode::NOT_FOUND.into_response(),
+            }
+        }
+        let app = axum::Router::new()
+            .fallback(serve)
+            .with_state(Arc::new(docs));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    #[tokio::test]
+    async fn signatures_come_from_the_sig_tag_and_referrers() {
+        let digest = |body: &str| format!("sha256:{:x}", Sha256::digest(body.as_bytes()));
+        let image_digest = format!("sha256:{}", "a".repeat(64));
+        let tag_payload = r#"{"critical":{"image":{"docker-manifest-digest":"tag"}}}"#;
+        let referrer_payload = r#"{"critical":{"image":{"docker-manifest-digest":"referrer"}}}"#;
+        let envelope = r#"{"payload":"e30","protected":"e30","signature":"c2ln"}"#;
+        let cosign_manifest = |payload: &str, sig: &str| {
+            serde_json::json!({"layers": [
+                {"digest": digest(payload), "annotations": {COSIGN_SIGNATURE_ANNOTATION: sig}},
+                {"digest": digest("unsigned")}
+            ]})
+            .to_string()
+        };
+        let tag_manifest = cosign_manifest(tag_payload, "c2lnMQ==");
+        let referrer_manifest = cosign_manifest(referrer_payload, "c2lnMg==");
+        let notation_manifest = serde_json::json!({"layers": [
+            {"mediaType": JWS_MEDIA_TYPE, "digest": digest(envelope)}
+        ]})
+        .to_string();
+        let referrers = serde_json::json!({"manifests": [
+            {"artifactType": COSIGN_ARTIFACT_TYPE, "digest": digest(&referrer_manifest)},
+            {"artifactType": NOTATION_ARTIFACT_TYPE, "digest": digest(&notation_manifest)},
+            {"artifactType": "application/spdx+json", "digest": "sha256:sbom"}
+        ]})
+        .to_string();
+        let docs = HashMap::from([
+            (
+                format!("manifests/sha256-{}.sig", "a".repeat(64)),
+                tag_manifest,
+            ),
+            (format!("referrers/{image_digest}"), referrers),
+            (
+                format!("manifests/{}", digest(&referrer_manifest)),
+                referrer_manifest,
+            ),
+            (
+                format!("manifests/{}", digest(&notation_manifest)),
+                notation_manifest,
+            ),
+            (
+                format!("blobs/{}", digest(tag_payload)),
+                tag_payload.to_string(),
+            ),
+            (
+                format!("blobs/{}", digest(referrer_payload)),
+                referrer_payload.to_string(),
+            ),
+            (format!("blobs/{}", digest(envelope)), envelope.to_string()),
+        ]);
+        let addr = serve_docs(docs).await;
+
+        let client = HttpRegistryClient::new(None).unwrap();
+        let image = ImageRef::parse(&format!("{addr}/acme/web:1.0")).unwrap();
+        let signatures = client.get_signatures(&image, &image_digest).await.unwrap();
+        assert_eq!(
+            signatures,
+            vec![
+                SignatureArtifact::Cosign {
+                    payload: tag_payload.as_bytes().to_vec(),
+                    signature: "c2lnMQ==".to_string(),
+                },
+                SignatureArtifact::Cosign {
+                    payload: referrer_payload.as_bytes().to_vec(),
+                    signature: "c2lnMg==".to_string(),
+                },
+                SignatureArtifact::NotationJws {
+                    envelope: envelope.as_bytes().to_vec(),
+                },
+            ]
+        );
+
+        // Neither a signature tag nor a referrers API: unsigned, not an error.
+        let other = format!("sha256:{}", "b".repeat(64));
+        assert!(
+            client
+                .get_signatures(&image, &other)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn signed_indexes_verify_against_the_index_digest() {
+        use crate::{api::types::SignatureStatus, signatures::SignaturePolicy};
+
+        let digest = |body: &str| format!("sha256:{:x}", Sha256::digest(body.as_bytes()));
+        let amd64 = format!("sha256:{}", "a".repeat(64));
+        let index = serde_json::json!({"manifests": [
+            {"digest": amd64, "platform": {"os": "linux", "architecture": "amd64"}},
+            {"digest": format!("sha256:{}", "b".repeat(64)), "platform": {"os": "linux", "architecture": "arm64"}}
+        ]})
+        .to_string();
+        let index_digest = digest(&index);
+
+        // `cosign sign ghcr.io/acme/web:1.0` signs the index, not the platform manifests.
+        let signer = crate::signatures::tests::TestSigner::new();
+        let SignatureArtifact::Cosign { payload, signature } = signer.cosign(&index_digest) else {
+            unreachable!()
+        };
+        let payload = String::from_utf8(payload).unwrap();
+        let sig_manifest = serde_json::json!({"layers": [
+            {"digest": digest(&payload), "annotations": {COSIGN_SIGNATURE_ANNOTATION: signature}}
+        ]})
+        .to_string();
+        let addr = serve_docs(HashMap::from([
+            ("manifests/1.0".to_string(), index),
+            (
+                format!("manifests/{}.sig", index_digest.replacen(':', "-", 1)),
+                sig_manifest,
+            ),
+            (format!("blobs/{}", digest(&payload)), payload),
+        ]))
+        .await;
+
+        let client = HttpRegistryClient::new(None).unwrap();
+        let image = ImageRef::parse(&format!("{addr}/acme/web:1.0")).unwrap();
+        let manifest = client
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        assert_eq!(manifest.digest.as_deref(), Some(amd64.as_str()));
+        assert_eq!(
+            manifest.content_digest.as_deref(),
+            Some(index_digest.as_str())
+        );
+
+        let policy = SignaturePolicy::default()
+            .with_key(&format!("{addr}/acme/*"), signer.public_key("acme"))
+            .unwrap();
+        assert_eq!(
+            policy.verify(&client, &image, &amd64).await.status,
+            SignatureStatus::Unsigned
+        );
+        let (verified, verdict) = policy
+            .verify_resolved(&client, &image, manifest.content_digest.as_deref(), &amd64)
+            .await;
+        assert_eq!(verdict.status, SignatureStatus::Verified);
+        assert_eq!(verified, index_digest);
+
+        // A platform digest signed on its own still verifies when the index is unsigned.
+        let (verified, verdict) = policy
+            .verify_resolved(&client, &image, Some("sha256:unsigned"), &index_digest)
+            .await;
+        assert_eq!(verdict.status, SignatureStatus::Verified);
+        assert_eq!(verified, index_digest);
+    }
+
+    #[derive(Default)]
+    struct AuthStub {
+        /// `expires_in` handed out with tokens.
+        expires_in: u64,
+        /// Docker-Content-Digest of the served manifest.
+        digest: Mutex<String>,
+        tokens: Mutex<u32>,
+        log: Mutex<Vec<String>>,
+    }
+
+    /// Token-protected registry serving one manifest and a single-page tag list with an ETag.
+    async fn stub_auth_registry(expires_in: u64) -> (String, Arc<AuthStub>) {
+        use axum::{
+            extract::State,
+            http::{HeaderMap, Method, StatusCode},
+            response::IntoResponse,
+        };
+
+        async fn token(State((stub, _)): State<(Arc<AuthStub>, String)>) -> impl IntoResponse {
+            let mut n = stub.tokens.lock().unwrap();
+            *n += 1;
+            axum::Json(serde_json::json!({"token": format!("t{n}"), "expires_in": stub.expires_in}))
+        }
+
+        fn challenge(addr: &str) -> axum::response::Response {
+            let mut resp = StatusCode::UNAUTHORIZED.into_response();
+            resp.headers_mut().insert(
+                WWW_AUTHENTICATE,
+                format!(r#"Bearer realm="http://{addr}/token",service="stub""#)
+                    .parse()
+                    .unwrap(),
+            );
+            resp
+        }
+
+        async fn manifest(
+            State((stub, addr)): State<(Arc<AuthStub>, String)>,
+            method: Method,
+            headers: HeaderMap,
+        ) -> axum::response::Response {
+            let auth = headers
+                .get(AUTHORIZATION)
+                .map(|v| v.to_str().unwrap().to_string());
+            stub.log.lock().unwrap().push(format!(
+                "{method} manifest {}",
+                auth.as_deref().unwrap_or("-")
+            ));
+            if auth.is_none() {
+                return challenge(&addr);
+            }
+            let digest = stub.digest.lock().unwrap().clone();
+            (
+                [(DOCKER_CONTENT_DIGEST, digest)],
+                r#"{"schemaVersion":2,"os":"linux","architecture":"amd64"}"#,
+            )
+                .into_response()
+        }
+
+        async fn tags(
+            State((stub, addr)): State<(Arc<AuthStub>, String)>,
+            headers: HeaderMap,
+        ) -> axum::response::Response {
+            let inm = headers
+                .get(IF_NONE_MATCH)
+                .map(|v| v.to_str().unwrap().to_string());
+            stub.log
+                .lock()
+                .unwrap()
+                .push(format!("GET tags {}", inm.as_deref().unwrap_or("-")));
+            if headers.get(AUTHORIZATION).is_none() {
+                return challenge(&addr);
+            }
+            if inm.as_deref() == Some("\"v1\"") {
+                return StatusCode::NOT_MODIFIED.into_response();
+            }
+            (
+                [(ETAG, "\"v1\"")],
+                axum::Json(serde_json::json!({"name": "acme/web", "tags": ["1.0", "1.1"]})),
+            )
+                .into_response()
+        }
+
+        let stub = Arc::new(AuthStub {
+            expires_in,
+            digest: Mutex::new("sha256:aaa".to_string()),
+            ..Default::default()
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let app = axum::Router::new()
+            .route("/token", axum::routing::get(token))
+            .route("/v2/acme/web/manifests/1.0", axum::routing::get(manifest))
+            .route("/v2/acme/web/tags/list", axum::routing::get(tags))
+            .with_state((stub.clone(), addr.clone()));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (addr, stub)
+    }
+
+    async fn cache_db() -> Db {
+        Db::open(Path::new(":memory:")).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn tokens_are_reused_until_they_expire() {
+        let (registry, stub) = stub_auth_registry(300).await;
+        let image = ImageRef::parse(&format!("{registry}/acme/web:1.0")).unwrap();
+        let client = HttpRegistryClient::new(None).unwrap();
+        client
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        client
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        assert_eq!(*stub.tokens.lock().unwrap(), 1);
+        assert_eq!(
+            *stub.log.lock().unwrap(),
+            [
+                "GET manifest -",
+                "GET manifest Bearer t1",
+                "GET manifest Bearer t1"
+            ]
+        );
+
+        // Shorter than the refresh margin: never reused.
+        let (registry, stub) = stub_auth_registry(5).await;
+        let image = ImageRef::parse(&format!("{registry}/acme/web:1.0")).unwrap();
+        let client = HttpRegistryClient::new(None).unwrap();
+        client
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        client
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        assert_eq!(*stub.tokens.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn cached_manifests_are_revalidated_with_head() {
+        let (registry, stub) = stub_auth_registry(300).await;
+        let image = ImageRef::parse(&format!("{registry}/acme/web:1.0")).unwrap();
+        let db = cache_db().await;
+
+        let fresh = HttpRegistryClient::new(None)
+            .unwrap()
+            .with_cache(db.clone(), Duration::from_secs(3600));
+        let info = fresh
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        assert_eq!(info.digest.as_deref(), Some("sha256:aaa"));
+        assert_eq!(info.arch, ["linux/amd64"]);
+        stub.log.lock().unwrap().clear();
+        fresh
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        assert!(stub.log.lock().unwrap().is_empty(), "served from cache");
+
+        let stale = HttpRegistryClient::new(None)
+            .unwrap()
+            .with_cache(db.clone(), Duration::ZERO);
+        let info = stale
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        assert_eq!(info.digest.as_deref(), Some("sha256:aaa"));
+        assert_eq!(
+            *stub.log.lock().unwrap(),
+            ["HEAD manifest -", "HEAD manifest Bearer t2"]
+        );
+
+        *stub.digest.lock().unwrap() = "sha256:bbb".to_string();
+        stub.log.lock().unwrap().clear();
+        let info = stale
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        assert_eq!(info.digest.as_deref(), Some("sha256:bbb"));
+        assert_eq!(
+            *stub.log.lock().unwrap(),
+            ["HEAD manifest Bearer t2", "GET manifest Bearer t2"]
+        );
+        let entry = db
+            .get_registry_manifest_cache(&image.registry, &image.name, "1.0", "linux/amd64")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.content_digest.as_deref(), Some("sha256:bbb"));
+    }
+
+    #[tokio::test]
+    async fn cached_tags_are_revalidated_with_etag() {
+        let (registry, stub) = stub_auth_registry(300).await;
+        let image = ImageRef::parse(&format!("{registry}/acme/web:1.0")).unwrap();
+        let client = HttpRegistryClient::new(None)
+            .unwrap()
+            .with_cache(cache_db().await, Duration::ZERO);
+
+        assert_eq!(client.list_tags(&image).await.unwrap(), ["1.0", "1.1"]);
+        assert_eq!(client.list_tags(&image).await.unwrap(), ["1.0", "1.1"]);
+        assert_eq!(
+            *stub.log.lock().unwrap(),
+            ["GET tags -", "GET tags -", "GET tags \"v1\""]
+        );
+    }
+
+    #[test]
+    fn parses_rate_limit_headers() {
+        assert_eq!(
+            parse_ratelimit_value("100;w=21600"),
+            Some((100, Some(21600)))
+        );
+        assert_eq!(parse_ratelimit_value("76"), Some((76, None)));
+        assert_eq!(parse_ratelimit_value("n/a"), None);
+
+        let now = OffsetDateTime::from_unix_timestamp(1_445_412_470).unwrap();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    /// Answers with the scripted `(status, headers)` responses in order, then 200s.
+    async fn stub_limited_registry(
+        script: Vec<(u16, Vec<(&'static str, &'static str)>)>,
+    ) -> (String, Arc<Mutex<u32>>) {
+        use axum::{extract::State, http::StatusCode, response::IntoResponse};
+
+        type Script =
+            Arc<Mutex<std::collections::VecDeque<(u16, Vec<(&'static str, &'static str)>)>>>;
+
+        async fn manifest(
+            State((script, hits)): State<(Script, Arc<Mutex<u32>>)>,
+        ) -> axum::response::Response {
+            *hits.lock().unwrap() += 1;
+            let (status, headers) = script
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or((200, Vec::new()));
+            let mut resp = (
+                StatusCode::from_u16(status).unwrap(),
+                r#"{"schemaVersion":2,"os":"linux","architecture":"amd64"}"#,
+            )
+                .into_response();
+            for (k, v) in headers {
+                resp.headers_mut().insert(k, v.parse().unwrap());
+            }
+            resp
+        }
+
+        let hits = Arc::new(Mutex::new(0));
+        let app = axum::Router::new()
+            .route("/v2/acme/web/manifests/1.0", axum::routing::get(manifest))
+            .with_state((Arc::new(Mutex::new(script.into())), hits.clone()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (addr, hits)
+    }
+
+    #[tokio::test]
+    async fn backs_off_on_429_and_tracks_quota() {
+        // A short Retry-After is waited out inline.
+        let (registry, hits) = stub_limited_registry(vec![(429, vec![("retry-after", "0")])]).await;
+        let image = ImageRef::parse(&format!("{registry}/acme/web:1.0")).unwrap();
+        let client = HttpRegistryClient::new(None).unwrap();
+        client
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        assert_eq!(*hits.lock().unwrap(), 2);
+        assert!(client.rate_limited(&registry).is_none());
+
+        // A long one blocks the registry without further requests.
+        let (registry, hits) = stub_limited_registry(vec![(
+            429,
+            vec![
+                ("retry-after", "120"),
+                ("ratelimit-limit", "100;w=21600"),
+                ("ratelimit-remaining", "0;w=21600"),
+            ],
+        )])
+        .await;
+        let image = ImageRef::parse(&format!("{registry}/acme/web:1.0")).unwrap();
+        let client = HttpRegistryClient::new(None).unwrap();
+        let err = client
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RateLimited>().unwrap().registry,
+            registry
+        );
+        assert!(
+            client
+                .get_manifest(&image, "1.0", "linux/amd64")
+                .await
+                .is_err()
+        );
+        assert_eq!(*hits.lock().unwrap(), 1);
+
+        let quotas = client.quotas();
+        assert_eq!(quotas.len(), 1);
+        assert_eq!(quotas[0].limit, Some(100));
+        assert_eq!(quotas[0].remaining, Some(0));
+        assert_eq!(quotas[0].window_seconds, Some(21600));
+        assert!(quotas[0].blocked_until.is_some());
+        assert!(quotas[0].deferred);
+
+        // Remaining quota at the reserve defers checks until the reported reset.
+        let (registry, _) = stub_limited_registry(vec![(
+            200,
+            vec![("ratelimit-remaining", "3"), ("ratelimit-reset", "600")],
+        )])
+        .await;
+        let image = ImageRef::parse(&format!("{registry}/acme/web:1.0")).unwrap();
+        let client = HttpRegistryClient::new(None)
+            .unwrap()
+            .with_rate_limit_reserve(5);
+        client
+            .get_manifest(&image, "1.0", "linux/amd64")
+            .await
+            .unwrap();
+        let limited = client.rate_limited(&registry).unwrap();
+        assert!(limited.until.unwrap() > OffsetDateTime::now_utc() + Duration::from_secs(590));
+    }
+
+    fn endpoint_settings(
+        mirrors: &[(&str, &str)],
+        overrides: &[(&str, &str)],
+        insecure: &[&str],
+    ) -> EndpointSettings {
+        let mut settings = EndpointSettings {
+            overrides: overrides
+                .iter()
+                .map(|(r, u)| (r.to_string(), u.to_string()))
+                .collect(),
+            insecure: insecure.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        for (registry, url) in mirrors {
+            settings
+                .mirrors
+                .entry(registry.to_string())
+                .or_default()
+                .push(url.to_string());
+        }
+        settings
+    }
+
+    #[test]
+    fn endpoints_resolve_mirrors_overrides_and_insecure_registries() {
+        let client = HttpRegistryClient::new(None)
+            .unwrap()
+            .with_endpoints(endpoint_settings(
+                &[
+                    ("docker.io", "https://harbor.example.com/dockerhub-proxy/"),
+                    ("docker.io", "http://cache.internal:5000"),
+                ],
+                &[("registry.internal", "https://10.0.0.5:5443")],
+                &[
+                    "cache.internal:5000",
+                    "registry.internal",
+                    "plain.internal:5000",
+                ],
+            ))
+            .unwrap();
+
+        let hub = client.endpoints_for("docker.io");
+        let urls = hub
+            .iter()
+            .map(|e| e.url("library/nginx", "tags/list"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            [
+                "https://harbor.example.com/v2/dockerhub-proxy/library/nginx/tags/list",
+                "http://cache.internal:5000/v2/library/nginx/tags/list",
+                "https://registry-1.docker.io/v2/library/nginx/tags/list",
+            ]
+        );
+        assert_eq!(
+            hub[0].scope("library/nginx"),
+            "repository:dockerhub-proxy/library/nginx:pull"
+        );
+        assert_eq!(
+            hub.iter().map(|e| e.key.as_str()).collect::<Vec<_>>(),
+            ["harbor.example.com", "cache.internal:5000", "docker.io"]
+        );
+        assert_eq!(
+            hub.iter().map(|e| e.insecure).collect::<Vec<_>>(),
+            [false, true, false]
+        );
+
+        let internal = client.endpoints_for("registry.internal");
+        assert_eq!(internal.len(), 1);
+        assert_eq!(internal[0].base_url, "https://10.0.0.5:5443");
+        assert_eq!(internal[0].key, "registry.internal");
+        assert!(internal[0].insecure);
+
+        let plain = client.endpoints_for("plain.internal:5000");
+        assert_eq!(plain[0].base_url, "http://plain.internal:5000");
+        assert_eq!(
+            client.endpoints_for("ghcr.io")[0].base_url,
+            "https://ghcr.io"
+        );
+
+        let err = HttpRegistryClient::new(None)
+            .unwrap()
+            .with_endpoints(endpoint_settings(
+                &[("docker.io", "http://cache.internal:5000")],
+                &[],
+                &[],
+            ))
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string().contains("must be listed as insecure"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_mirrors_fall_back_to_the_registry() {
+        let (upstream, upstream_seen) = stub_registry(vec!["1.0".to_string()]).await;
+        let mirror = {
+            let app = axum::Router::new().route(
+                "/v2/proxy/acme/web/tags/list",
+                axum::routing::get(|| async {
+                    axum::Json(serde_json::json!({ "tags": ["1.0", "1.1"] }))
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            addr
+        };
+        let image = ImageRef::parse("registry.example/acme/web:1.0").unwrap();
+        let upstream_url = format!("http://{upstream}");
+
+        let mirrored = HttpRegistryClient::new(None)
+            .unwrap()
+            .with_endpoints(endpoint_settings(
+                &[
+                    ("registry.example", "http://127.0.0.1:9"),
+                    ("registry.example", &format!("http://{mirror}/proxy")),
+                ],
+                &[("registry.example", &upstream_url)],
+                &[],
+            ))
+            .unwrap();
+        assert_eq!(mirrored.list_tags(&image).await.unwrap(), ["1.0", "1.1"]);
+        assert!(upstream_seen.lock().unwrap().is_empty());
+
+        let fallback = HttpRegistryClient::new(None)
+            .unwrap()
+            .with_endpoints(endpoint_settings(
+                &[("registry.example", "http://127.0.0.1:9")],
+                &[("registry.example", &upstream_url)],
+                &[],
+            ))
+            .unwrap();
+        assert_eq!(fallback.list_tags(&image).await.unwrap(), ["1.0"]);
+        assert_eq!(upstream_seen.lock().unwrap().len(), 1);
+    }
 }
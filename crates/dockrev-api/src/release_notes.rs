@@ -0,0 +1,549 @@
+//! Release notes of candidates. The source repository comes from the candidate's
+//! `org.opencontainers.image.*` annotations (or labels), the releases between the current and the
+//! candidate version from the GitHub, GitLab or Gitea releases API of that repository.
+
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Context as _;
+use reqwest::header::{ACCEPT, AUTHORIZATION, HeaderName, USER_AGENT};
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    api::types::{Release, ReleaseNotes},
+    ignore::parse_version,
+    registry::ImageConfig,
+};
+
+pub const SOURCE_ANNOTATION: &str = "org.opencontainers.image.source";
+pub const REVISION_ANNOTATION: &str = "org.opencontainers.image.revision";
+pub const VERSION_ANNOTATION: &str = "org.opencontainers.image.version";
+
+/// Looked-up notes (including "no source") are reused this long per candidate digest, so that
+/// releases published after the image still show up.
+pub const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_RELEASES: usize = 10;
+const MAX_BODY_BYTES: usize = 8 * 1024;
+/// Releases requested per repository; older ones are not considered.
+const RELEASES_PAGE_SIZE: u32 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+impl ForgeKind {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "github" => Some(Self::GitHub),
+            "gitlab" => Some(Self::GitLab),
+            "gitea" | "forgejo" => Some(Self::Gitea),
+            _ => None,
+        }
+    }
+
+    /// Public forges recognized without configuration.
+    fn well_known(host: &str) -> Option<Self> {
+        match host {
+            "github.com" => Some(Self::GitHub),
+            "gitlab.com" => Some(Self::GitLab),
+            "codeberg.org" | "gitea.com" => Some(Self::Gitea),
+            _ => None,
+        }
+    }
+}
+
+/// Source repository of an image, from its annotations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceInfo {
+    pub url: Url,
+    pub revision: Option<String>,
+    pub version: Option<String>,
+}
+
+impl SourceInfo {
+    pub fn from_config(config: &ImageConfig) -> Option<Self> {
+        let url = normalize_source_url(config.annotation(SOURCE_ANNOTATION)?)?;
+        Some(Self {
+            url,
+            revision: config.annotation(REVISION_ANNOTATION).map(str::to_string),
+            version: config.annotation(VERSION_ANNOTATION).map(str::to_string),
+        })
+    }
+}
+
+/// `https://host/path` of a source annotation; `git+https://…`, `…/repo.git` and scp-like
+/// `git@host:owner/repo` forms are accepted.
+fn normalize_source_url(raw: &str) -> Option<Url> {
+    let raw = raw.trim();
+    let raw = raw.strip_prefix("git+").unwrap_or(raw);
+    let mut url = match Url::parse(raw) {
+        Ok(url) => url,
+        Err(_) => {
+            let (user_host, path) = raw.split_once(':')?;
+            let host = user_host.rsplit_once('@').map_or(user_host, |(_, h)| h);
+            Url::parse(&format!("https://{host}/{}", path.trim_start_matches('/'))).ok()?
+        }
+    };
+    match url.scheme() {
+        "http" | "https" => {}
+        // The port of these is not the web UI's.
+        "ssh" | "git" => {
+            url = Url::parse(&format!("https://{}{}", url.host_str()?, url.path())).ok()?
+        }
+        _ => return None,
+    }
+    url.set_username("").ok()?;
+    url.set_password(None).ok()?;
+    url.set_query(None);
+    url.set_fragment(None);
+    let path = url.path().trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path).to_string();
+    url.set_path(&path);
+    Some(url)
+}
+
+pub struct ReleaseNotesClient {
+    http: reqwest::Client,
+    /// Forge kind per host (`host[:port]`) on top of the well-known ones.
+    forges: HashMap<String, ForgeKind>,
+    /// API token per host.
+    tokens: HashMap<String, String>,
+}
+
+impl ReleaseNotesClient {
+    pub fn new(
+        forges: HashMap<String, ForgeKind>,
+        tokens: HashMap<String, String>,
+    ) -> anyhow::Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .context("build release notes http client")?;
+        Ok(Self {
+            http,
+            forges,
+            tokens,
+        })
+    }
+
+    /// Releases of `source` after `current_tag` up to the candidate, whose version is its
+    /// `org.opencontainers.image.version` annotation when that parses, else `candidate_tag`.
+    pub async fn fetch(
+        &self,
+        source: &SourceInfo,
+        current_tag: &str,
+        candidate_tag: &str,
+    ) -> anyhow::Result<ReleaseNotes> {
+        let host = authority(&source.url);
+        let kind = self
+            .forges
+            .get(&host)
+            .copied()
+            .or_else(|| ForgeKind::well_known(&host))
+            .ok_or_else(|| anyhow::anyhow!("no release API known for {host}"))?;
+        let releases = self.list_releases(kind, &source.url, &host).await?;
+        let candidate_version = source
+            .version
+            .as_deref()
+            .filter(|v| parse_version(v).is_some())
+            .unwrap_or(candidate_tag);
+        Ok(ReleaseNotes {
+            source: source.url.to_string(),
+            revision: source.revision.clone(),
+            version: source.version.clone(),
+            releases: releases_in_range(releases, current_tag, candidate_version),
+        })
+    }
+
+    async fn list_releases(
+        &self,
+        kind: ForgeKind,
+        source: &Url,
+        host: &str,
+    ) -> anyhow::Result<Vec<Release>> {
+        let base = format!("{}://{host}", source.scheme());
+        let project = source.path().trim_matches('/');
+        let token = self.tokens.get(host);
+        let (url, auth): (String, Option<(HeaderName, String)>) = match kind {
+            ForgeKind::GitHub => {
+                let api = if host == "github.com" {
+                    "https://api.github.com".to_string()
+                } else {
+                    format!("{base}/api/v3")
+                };
+                (
+                    format!(
+                        "{api}/repos/{}/releases?per_page={RELEASES_PAGE_SIZE}",
+                        owner_repo(project)?
+                    ),
+                    token.map(|t| (AUTHORIZATION, format!("Bearer {t}"))),
+                )
+            }
+            ForgeKind::GitLab => {
+                // Group paths may be nested; `/-/` starts GitLab's own routes.
+                let project = project.split("/-/").next().unwrap_or(project);
+                (
+                    format!(
+                        "{base}/api/v4/projects/{}/releases?per_page={RELEASES_PAGE_SIZE}",
+                        project.replace('/', "%2F")
+                    ),
+                    token.map(|t| (HeaderName::from_static("private-token"), t.clone())),
+                )
+            }
+            ForgeKind::Gitea => (
+                format!(
+                    "{base}/api/v1/repos/{}/releases?limit={RELEASES_PAGE_SIZE}",
+                    owner_repo(project)?
+                ),
+                token.map(|t| (AUTHORIZATION, format!("token {t}"))),
+            ),
+        };
+
+        let mut req = self
+            .http
+            .get(&url)
+            .header(USER_AGENT, "dockrev")
+            .header(ACCEPT, "application/json");
+        if let Some((name, value)) = auth {
+            req = req.header(name, value);
+        }
+        let resp = req.send().await.with_context(|| format!("GET {url}"))?;
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!("GET {url}: {}", resp.status()));
+        }
+        let releases: Vec<ApiRelease> = resp.json().await.context("parse releases json")?;
+        Ok(releases
+            .into_iter()
+            .filter(|r| !r.draft)
+            .map(ApiRelease::into_release)
+            .collect())
+    }
+}
+
+/// GitHub, GitLab and Gitea release objects (GitLab names some fields differently).
+#[derive(Deserialize)]
+struct ApiRelease {
+    tag_name: String,
+    name: Option<String>,
+    html_url: Option<String>,
+    published_at: Option<String>,
+    released_at: Option<String>,
+    body: Option<String>,
+    description: Option<String>,
+    #[serde(rename = "_links")]
+    links: Option<ApiReleaseLinks>,
+    #[serde(default)]
+    draft: bool,
+}
+
+#[derive(Deserialize)]
+struct ApiReleaseLinks {
+    #[serde(rename = "self")]
+    self_url: Option<String>,
+}
+
+impl ApiRelease {
+    fn into_release(self) -> Release {
+        Release {
+            name: self
+                .name
+                .filter(|n| !n.trim().is_empty() && *n != self.tag_name),
+            tag: self.tag_name,
+            url: self.html_url.or(self.links.and_then(|l| l.self_url)),
+            published_at: self.published_at.or(self.released_at),
+            body: truncate_body(self.body.or(self.description).unwrap_or_default()),
+        }
+    }
+}
+
+/// Releases with a version after `current` up to `candidate`, newest first. Without a parsable
+/// current version only the candidate's own release is returned.
+pub fn releases_in_range(releases: Vec<Release>, current: &str, candidate: &str) -> Vec<Release> {
+    let Some(to) = parse_version(candidate) else {
+        return releases
+            .into_iter()
+            .filter(|r| r.tag.strip_prefix('v').unwrap_or(&r.tag) == candidate)
+            .take(1)
+            .collect();
+    };
+    let from = parse_version(current);
+    let mut selected = releases
+        .into_iter()
+        .filter_map(|r| Some((parse_version(&r.tag)?, r)))
+        .filter(|(v, _)| match from.as_ref() {
+            Some(from) => v > from && v <= &to,
+            None => v == &to,
+        })
+        .collect::<Vec<_>>();
+    selected.sort_by(|a, b| b.0.cmp(&a.0));
+    selected
+        .into_iter()
+        .map(|(_, r)| r)
+        .take(MAX_RELEASES)
+        .collect()
+}
+
+fn owner_repo(project: &str) -> anyhow::Result<String> {
+    let mut parts = project.split('/').filter(|p| !p.is_empty());
+    match (parts.next(), parts.next()) {
+        (Some(owner), Some(repo)) => Ok(format!("{owner}/{repo}")),
+        _ => Err(anyhow::anyhow!(
+            "source {project:?} is not an owner/repo path"
+        )),
+    }
+}
+
+fn authority(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    }
+}
+
+fn truncate_body(mut body: String) -> String {
+    if body.len() <= MAX_BODY_BYTES {
+        return body;
+    }
+    let mut end = MAX_BODY_BYTES;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    body.truncate(end);
+    body.push('…');
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{Json, Router, extract::State, http::Uri, response::IntoResponse};
+
+    use super::*;
+
+    fn config(annotations: &[(&str, &str)], labels: &[(&str, &str)]) -> ImageConfig {
+        let map = |kv: &[(&str, &str)]| {
+            kv.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        ImageConfig {
+            annotations: map(annotations),
+            labels: map(labels),
+            ..Default::default()
+        }
+    }
+
+    fn release(tag: &str) -> Release {
+        Release {
+            tag: tag.to_string(),
+            name: None,
+            url: None,
+            published_at: None,
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn resolves_sources_from_annotations_and_labels() {
+        let cases = [
+            ("https://github.com/acme/web", "https://github.com/acme/web"),
+            (
+                "https://github.com/acme/web.git/",
+                "https://github.com/acme/web",
+            ),
+            (
+                "git+https://gitlab.com/g/sub/app.git",
+                "https://gitlab.com/g/sub/app",
+            ),
+            (
+                "git@codeberg.org:acme/web.git",
+                "https://codeberg.org/acme/web",
+            ),
+            (
+                "ssh://git@git.example.com:2222/acme/web",
+                "https://git.example.com/acme/web",
+            ),
+        ];
+        for (raw, expected) in cases {
+            let source = SourceInfo::from_config(&config(&[(SOURCE_ANNOTATION, raw)], &[]))
+                .unwrap_or_else(|| panic!("{raw}"));
+            assert_eq!(source.url.as_str(), expected, "{raw}");
+        }
+
+        let source = SourceInfo::from_config(&config(
+            &[(SOURCE_ANNOTATION, " "), (VERSION_ANNOTATION, "1.4.0")],
+            &[
+                (SOURCE_ANNOTATION, "https://github.com/acme/web"),
+                (REVISION_ANNOTATION, "abc123"),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(source.url.as_str(), "https://github.com/acme/web");
+        assert_eq!(source.revision.as_deref(), Some("abc123"));
+        assert_eq!(source.version.as_deref(), Some("1.4.0"));
+
+        assert!(SourceInfo::from_config(&config(&[], &[])).is_none());
+        assert!(
+            SourceInfo::from_config(&config(&[(SOURCE_ANNOTATION, "file:///src")], &[])).is_none()
+        );
+    }
+
+    #[test]
+    fn selects_releases_between_current_and_candidate() {
+        let releases = ["v1.5.0", "v1.4.1", "v1.4.0", "1.3.0", "nightly", "v1.2.0"]
+            .map(release)
+            .to_vec();
+        let tags = |r: Vec<Release>| r.into_iter().map(|r| r.tag).collect::<Vec<_>>();
+
+        assert_eq!(
+            tags(releases_in_range(releases.clone(), "1.2", "1.4.1")),
+            ["v1.4.1", "v1.4.0", "1.3.0"]
+        );
+        assert_eq!(
+            tags(releases_in_range(releases.clone(), "latest", "1.4")),
+            ["v1.4.0"]
+        );
+        assert_eq!(
+            tags(releases_in_range(releases.clone(), "1.2", "nightly")),
+            ["nightly"]
+        );
+        assert!(releases_in_range(releases, "1.5.0", "1.6.0").is_empty());
+
+        let many = (0..20).map(|i| release(&format!("v1.{i}.0"))).collect();
+        assert_eq!(releases_in_range(many, "0.1", "2.0").len(), MAX_RELEASES);
+    }
+
+    #[test]
+    fn truncates_long_bodies_on_char_boundaries() {
+        let body = truncate_body("é".repeat(MAX_BODY_BYTES));
+        assert!(body.len() <= MAX_BODY_BYTES + '…'.len_utf8());
+        assert!(body.ends_with('…'));
+        assert_eq!(truncate_body("short".to_string()), "short");
+    }
+
+    type Seen = Arc<Mutex<Vec<(String, Option<String>, Option<String>)>>>;
+
+    async fn stub_forge() -> (String, Seen) {
+        async fn releases(
+            State(seen): State<Seen>,
+            headers: axum::http::HeaderMap,
+            uri: Uri,
+        ) -> impl IntoResponse {
+            let header = |name: &str| {
+                headers
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string)
+            };
+            seen.lock().unwrap().push((
+                uri.to_string(),
+                header("authorization"),
+                header("private-token"),
+            ));
+            if uri.path().starts_with("/api/v4/") {
+                Json(serde_json::json!([
+                    {"tag_name": "v2.1.0", "name": "v2.1.0", "description": "fixes",
+                     "released_at": "2026-02-01T00:00:00Z",
+                     "_links": {"self": "https://forge.test/g/sub/app/-/releases/v2.1.0"}},
+                    {"tag_name": "v2.0.0", "description": "big"}
+                ]))
+            } else {
+                Json(serde_json::json!([
+                    {"tag_name": "v2.1.0", "name": "Spring", "body": "fixes",
+                     "html_url": "https://forge.test/acme/web/releases/v2.1.0",
+                     "published_at": "2026-02-01T00:00:00Z"},
+                    {"tag_name": "v2.2.0", "draft": true, "body": "unreleased"},
+                    {"tag_name": "v2.0.0", "body": "big"}
+                ]))
+            }
+        }
+
+        let seen = Seen::default();
+        let app = Router::new().fallback(releases).with_state(seen.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (addr, seen)
+    }
+
+    #[tokio::test]
+    async fn fetches_releases_from_each_forge_api() {
+        let (addr, seen) = stub_forge().await;
+        let source = |path: &str, version: Option<&str>| SourceInfo {
+            url: Url::parse(&format!("http://{addr}{path}")).unwrap(),
+            revision: None,
+            version: version.map(str::to_string),
+        };
+
+        for (kind, path, version, expected_uri, expected_auth) in [
+            (
+                ForgeKind::Gitea,
+                "/acme/web",
+                None,
+                "/api/v1/repos/acme/web/releases?limit=50",
+                (Some("token t0k"), None),
+            ),
+            (
+                ForgeKind::GitHub,
+                "/acme/web/tree/main",
+                Some("2.1.0"),
+                "/api/v3/repos/acme/web/releases?per_page=50",
+                (Some("Bearer t0k"), None),
+            ),
+            (
+                ForgeKind::GitLab,
+                "/g/sub/app/-/tree/main",
+                None,
+                "/api/v4/projects/g%2Fsub%2Fapp/releases?per_page=50",
+                (None, Some("t0k")),
+            ),
+        ] {
+            let client = ReleaseNotesClient::new(
+                HashMap::from([(addr.clone(), kind)]),
+                HashMap::from([(addr.clone(), "t0k".to_string())]),
+            )
+            .unwrap();
+            let notes = client
+                .fetch(&source(path, version), "1.9", "2.1")
+                .await
+                .unwrap();
+            let tags = notes
+                .releases
+                .iter()
+                .map(|r| r.tag.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(tags, ["v2.1.0", "v2.0.0"], "{kind:?}");
+            assert_eq!(notes.releases[0].body, "fixes");
+            assert_eq!(
+                notes.releases[0].published_at.as_deref(),
+                Some("2026-02-01T00:00:00Z")
+            );
+            assert!(notes.releases[0].url.is_some(), "{kind:?}");
+
+            let (uri, authorization, private_token) = seen.lock().unwrap().pop().unwrap();
+            assert_eq!(uri, expected_uri);
+            assert_eq!(
+                (authorization.as_deref(), private_token.as_deref()),
+                expected_auth
+            );
+        }
+
+        let client = ReleaseNotesClient::new(HashMap::new(), HashMap::new()).unwrap();
+        let err = client
+            .fetch(&source("/acme/web", None), "1.9", "2.1")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no release API known"));
+    }
+}
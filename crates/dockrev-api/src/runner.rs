@@ -1,13 +1,23 @@
-use std::time::Duration;
+use std::{process::Stdio, sync::Arc, time::Duration};
 
+use anyhow::Context as _;
 use async_trait::async_trait;
-use tokio::process::Command;
+use tokio::{
+    io::{AsyncBufReadExt as _, AsyncRead, AsyncWriteExt as _, BufReader},
+    process::Command,
+    sync::{mpsc, watch},
+};
 
 #[derive(Clone, Debug)]
 pub struct CommandSpec {
     pub program: String,
     pub args: Vec<String>,
     pub env: Vec<(String, String)>,
+    /// Written to the child's stdin (closed afterwards); stdin is empty otherwise.
+    pub stdin: Option<String>,
+    /// Local files generated for this command (see `updater::build_override_file`); runners on
+    /// another machine ship them along with the command.
+    pub files: Vec<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -17,9 +27,59 @@ pub struct CommandOutput {
     pub stderr: String,
 }
 
+/// One line of command output (stdout or stderr), without the trailing newline.
+#[derive(Clone, Debug)]
+pub struct OutputLine {
+    pub line: String,
+}
+
+/// Cooperative cancellation signal shared by a job and the commands it runs.
+#[derive(Clone, Debug)]
+pub struct CancelToken(Arc<watch::Sender<bool>>);
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self(Arc::new(watch::Sender::new(false)))
+    }
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once [`CancelToken::cancel`] has been called.
+    pub async fn cancelled(&self) {
+        let mut rx = self.0.subscribe();
+        let _ = rx.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
 #[async_trait]
 pub trait CommandRunner: Send + Sync {
     async fn run(&self, spec: CommandSpec, timeout: Duration) -> anyhow::Result<CommandOutput>;
+
+    /// Like [`CommandRunner::run`], but also sends every output line to `lines` as it is produced.
+    ///
+    /// The default implementation replays the output once the command has exited.
+    async fn run_streaming(
+        &self,
+        spec: CommandSpec,
+        timeout: Duration,
+        lines: mpsc::UnboundedSender<OutputLine>,
+    ) -> anyhow::Result<CommandOutput> {
+        let out = self.run(spec, timeout).await?;
+        for line in out.stdout.lines().chain(out.stderr.lines()) {
+            let _ = lines.send(OutputLine {
+                line: line.to_string(),
+            });
+        }
+        Ok(out)
+    }
 }
 
 #[derive(Clone, Default)]
@@ -28,17 +88,148 @@ pub struct TokioCommandRunner;
 #[async_trait]
 impl CommandRunner for TokioCommandRunner {
     async fn run(&self, spec: CommandSpec, timeout: Duration) -> anyhow::Result<CommandOutput> {
-        let mut cmd = Command::new(&spec.program);
-        cmd.args(&spec.args);
-        for (k, v) in &spec.env {
-            cmd.env(k, v);
-        }
-
-        let output = tokio::time::timeout(timeout, cmd.output()).await??;
+        let mut cmd = command(&spec);
+        let output = match spec.stdin {
+            None => tokio::time::timeout(timeout, cmd.output()).await??,
+            Some(input) => {
+                cmd.stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped());
+                let mut child = cmd.spawn()?;
+                let mut stdin = child.stdin.take().context("child stdin not captured")?;
+                let run = async {
+                    stdin.write_all(input.as_bytes()).await?;
+                    drop(stdin);
+                    child.wait_with_output().await
+                };
+                tokio::time::timeout(timeout, run).await??
+            }
+        };
         Ok(CommandOutput {
             status: output.status.code().unwrap_or(-1),
             stdout: String::from_utf8_lossy(&output.stdout).to_string(),
             stderr: String::from_utf8_lossy(&output.stderr).to_string(),
         })
     }
+
+    async fn run_streaming(
+        &self,
+        spec: CommandSpec,
+        timeout: Duration,
+        lines: mpsc::UnboundedSender<OutputLine>,
+    ) -> anyhow::Result<CommandOutput> {
+        let mut cmd = command(&spec);
+        cmd.stdin(if spec.stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+        let mut child = cmd.spawn()?;
+        let stdout = child.stdout.take().context("child stdout not captured")?;
+        let stderr = child.stderr.take().context("child stderr not captured")?;
+        let stdin = child.stdin.take();
+
+        let run = async {
+            if let (Some(mut stdin), Some(input)) = (stdin, spec.stdin.as_deref()) {
+                stdin.write_all(input.as_bytes()).await?;
+            }
+            tokio::try_join!(
+                read_lines(stdout, &lines),
+                read_lines(stderr, &lines),
+                child.wait(),
+            )
+        };
+        let (stdout, stderr, status) = tokio::time::timeout(timeout, run).await??;
+        Ok(CommandOutput {
+            status: status.code().unwrap_or(-1),
+            stdout,
+            stderr,
+        })
+    }
+}
+
+fn command(spec: &CommandSpec) -> Command {
+    let mut cmd = Command::new(&spec.program);
+    cmd.args(&spec.args);
+    for (k, v) in &spec.env {
+        cmd.env(k, v);
+    }
+    // Dropping the future (timeout or job cancellation) must not leave the child running.
+    cmd.kill_on_drop(true);
+    cmd
+}
+
+async fn read_lines(
+    reader: impl AsyncRead + Unpin,
+    lines: &mpsc::UnboundedSender<OutputLine>,
+) -> std::io::Result<String> {
+    let mut reader = BufReader::new(reader);
+    let mut out = String::new();
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf).await? == 0 {
+            return Ok(out);
+        }
+        let text = String::from_utf8_lossy(&buf);
+        out.push_str(&text);
+        let _ = lines.send(OutputLine {
+            line: text.trim_end_matches(['\r', '\n']).to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn streams_lines_from_both_pipes() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let spec = CommandSpec {
+            program: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                "echo pulling; echo progress >&2; echo done".to_string(),
+            ],
+            env: Vec::new(),
+            stdin: None,
+            files: Vec::new(),
+        };
+        let out = TokioCommandRunner
+            .run_streaming(spec, Duration::from_secs(10), tx)
+            .await
+            .unwrap();
+        assert_eq!(out.status, 0);
+        assert_eq!(out.stdout, "pulling\ndone\n");
+        assert_eq!(out.stderr, "progress\n");
+
+        let mut lines = Vec::new();
+        while let Some(line) = rx.recv().await {
+            lines.push(line.line);
+        }
+        lines.sort();
+        assert_eq!(lines, ["done", "progress", "pulling"]);
+    }
+
+    #[tokio::test]
+    async fn writes_stdin() {
+        let spec = CommandSpec {
+            program: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                "read server; echo \"got $server\"".to_string(),
+            ],
+            env: Vec::new(),
+            stdin: Some("ghcr.io\n".to_string()),
+            files: Vec::new(),
+        };
+        let out = TokioCommandRunner
+            .run(spec, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(out.stdout, "got ghcr.io\n");
+    }
 }
@@ -0,0 +1,320 @@
+//! Vulnerability scans of image digests: Trivy or Grype run through the local
+//! [`CommandRunner`], or an HTTP scanner service. Only the number of findings per severity is
+//! kept.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::{
+    api::types::{VulnerabilityCounts, VulnerabilityDiff},
+    runner::{CommandRunner, CommandSpec},
+};
+
+/// `DOCKREV_SCANNER`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScannerKind {
+    Trivy,
+    Grype,
+    /// `POST`s `{"image": "<registry>/<name>@<digest>"}` and reads severity counts, or a Trivy
+    /// or Grype JSON report, from the response.
+    Http(String),
+}
+
+impl ScannerKind {
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if input.starts_with("http://") || input.starts_with("https://") {
+            return Some(Self::Http(input.to_string()));
+        }
+        match input.to_ascii_lowercase().as_str() {
+            "trivy" => Some(Self::Trivy),
+            "grype" => Some(Self::Grype),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+pub trait VulnerabilityScanner: Send + Sync {
+    /// Recorded with every scan.
+    fn name(&self) -> &str;
+
+    /// Scans `image`, a `registry/name@digest` reference.
+    async fn scan(&self, image: &str) -> anyhow::Result<VulnerabilityCounts>;
+}
+
+pub fn build(
+    kind: &ScannerKind,
+    program: Option<&str>,
+    timeout: Duration,
+    runner: Arc<dyn CommandRunner>,
+) -> anyhow::Result<Arc<dyn VulnerabilityScanner>> {
+    Ok(match kind {
+        ScannerKind::Trivy | ScannerKind::Grype => Arc::new(CommandScanner {
+            trivy: *kind == ScannerKind::Trivy,
+            program: program.map(str::to_string).unwrap_or_else(|| {
+                if *kind == ScannerKind::Trivy {
+                    "trivy"
+                } else {
+                    "grype"
+                }
+                .to_string()
+            }),
+            timeout,
+            runner,
+        }),
+        ScannerKind::Http(url) => Arc::new(HttpScanner {
+            url: url.clone(),
+            http: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .context("build reqwest client")?,
+        }),
+    })
+}
+
+struct CommandScanner {
+    trivy: bool,
+    program: String,
+    timeout: Duration,
+    runner: Arc<dyn CommandRunner>,
+}
+
+#[async_trait]
+impl VulnerabilityScanner for CommandScanner {
+    fn name(&self) -> &str {
+        if self.trivy { "trivy" } else { "grype" }
+    }
+
+    async fn scan(&self, image: &str) -> anyhow::Result<VulnerabilityCounts> {
+        let args = if self.trivy {
+            vec![
+                "image",
+                "--quiet",
+                "--format",
+                "json",
+                "--scanners",
+                "vuln",
+                image,
+            ]
+        } else {
+            vec![image, "--output", "json", "--quiet"]
+        };
+        let out = self
+            .runner
+            .run(
+                CommandSpec {
+                    program: self.program.clone(),
+                    args: args.into_iter().map(str::to_string).collect(),
+                    env: Vec::new(),
+                    stdin: None,
+                    files: Vec::new(),
+                },
+                self.timeout,
+            )
+            .await?;
+        if out.status != 0 {
+            return Err(anyhow::anyhow!(
+                "{} exited with {}: {}",
+                self.name(),
+                out.status,
+                out.stderr.trim()
+            ));
+        }
+        let report: serde_json::Value = serde_json::from_str(&out.stdout)
+            .with_context(|| format!("parse {} report", self.name()))?;
+        Ok(counts_from_report(&report))
+    }
+}
+
+struct HttpScanner {
+    url: String,
+    http: reqwest::Client,
+}
+
+#[async_trait]
+impl VulnerabilityScanner for HttpScanner {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    async fn scan(&self, image: &str) -> anyhow::Result<VulnerabilityCounts> {
+        let report: serde_json::Value = self
+            .http
+            .post(&self.url)
+            .json(&json!({ "image": image }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("parse scanner response")?;
+        if report.get("Results").is_some() || report.get("matches").is_some() {
+            return Ok(counts_from_report(&report));
+        }
+        serde_json::from_value(report).context("parse scanner response")
+    }
+}
+
+/// Counts the findings of a Trivy (`Results[].Vulnerabilities[]`) or Grype (`matches[]`) report.
+fn counts_from_report(report: &serde_json::Value) -> VulnerabilityCounts {
+    let array = |v: Option<&serde_json::Value>| v.and_then(|v| v.as_array()).cloned();
+    let trivy = array(report.get("Results"))
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|r| array(r.get("Vulnerabilities")).unwrap_or_default())
+        .map(|v| v.get("Severity").cloned());
+    let grype = array(report.get("matches"))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| m.pointer("/vulnerability/severity").cloned());
+
+    let mut counts = VulnerabilityCounts::default();
+    for severity in trivy.chain(grype) {
+        let severity = severity
+            .as_ref()
+            .and_then(|s| s.as_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match severity.as_str() {
+            "critical" => counts.critical += 1,
+            "high" => counts.high += 1,
+            "medium" => counts.medium += 1,
+            "low" | "negligible" => counts.low += 1,
+            _ => counts.unknown += 1,
+        }
+    }
+    counts
+}
+
+/// `candidate` minus `current`. A candidate fixes vulnerabilities when it has fewer findings,
+/// compared from the most severe level down (unknown severities are not weighed).
+pub fn diff(current: &VulnerabilityCounts, candidate: &VulnerabilityCounts) -> VulnerabilityDiff {
+    let levels = |c: &VulnerabilityCounts| (c.critical, c.high, c.medium, c.low);
+    let delta = |a: u32, b: u32| i64::from(b) - i64::from(a);
+    VulnerabilityDiff {
+        critical: delta(current.critical, candidate.critical),
+        high: delta(current.high, candidate.high),
+        medium: delta(current.medium, candidate.medium),
+        low: delta(current.low, candidate.low),
+        unknown: delta(current.unknown, candidate.unknown),
+        fixes: levels(candidate) < levels(current),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::CommandOutput;
+
+    struct ReportRunner(&'static str);
+
+    #[async_trait]
+    impl CommandRunner for ReportRunner {
+        async fn run(
+            &self,
+            spec: CommandSpec,
+            _timeout: Duration,
+        ) -> anyhow::Result<CommandOutput> {
+            assert_eq!(spec.args.last().map(String::as_str), Some("--quiet"));
+            assert_eq!(spec.args[0], "ghcr.io/acme/web@sha256:abc");
+            Ok(CommandOutput {
+                status: 0,
+                stdout: self.0.to_string(),
+                stderr: String::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn counts_trivy_and_grype_findings() {
+        let trivy = json!({"Results": [
+            {"Target": "os", "Vulnerabilities": [
+                {"VulnerabilityID": "CVE-1", "Severity": "CRITICAL"},
+                {"VulnerabilityID": "CVE-2", "Severity": "HIGH"},
+                {"VulnerabilityID": "CVE-3", "Severity": "HIGH"}
+            ]},
+            {"Target": "app"},
+            {"Target": "jar", "Vulnerabilities": [{"VulnerabilityID": "CVE-4", "Severity": "UNKNOWN"}]}
+        ]});
+        assert_eq!(
+            counts_from_report(&trivy),
+            VulnerabilityCounts {
+                critical: 1,
+                high: 2,
+                medium: 0,
+                low: 0,
+                unknown: 1,
+            }
+        );
+
+        let grype = json!({"matches": [
+            {"vulnerability": {"id": "CVE-1", "severity": "Medium"}},
+            {"vulnerability": {"id": "CVE-2", "severity": "Negligible"}},
+            {"vulnerability": {"id": "CVE-3", "severity": "Low"}}
+        ]});
+        assert_eq!(
+            counts_from_report(&grype),
+            VulnerabilityCounts {
+                medium: 1,
+                low: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn grype_runs_through_the_command_runner() {
+        let scanner = build(
+            &ScannerKind::Grype,
+            None,
+            Duration::from_secs(1),
+            Arc::new(ReportRunner(
+                r#"{"matches": [{"vulnerability": {"severity": "Critical"}}]}"#,
+            )),
+        )
+        .unwrap();
+        assert_eq!(scanner.name(), "grype");
+        let counts = scanner.scan("ghcr.io/acme/web@sha256:abc").await.unwrap();
+        assert_eq!(counts.critical, 1);
+    }
+
+    #[test]
+    fn diffs_weigh_severities_from_the_top() {
+        let counts = |critical, high, medium| VulnerabilityCounts {
+            critical,
+            high,
+            medium,
+            ..Default::default()
+        };
+        let cases = [
+            (counts(1, 0, 0), counts(0, 3, 0), true),
+            (counts(0, 2, 0), counts(0, 1, 9), true),
+            (counts(0, 2, 0), counts(0, 2, 0), false),
+            (counts(0, 0, 1), counts(1, 0, 0), false),
+        ];
+        for (current, candidate, fixes) in cases {
+            assert_eq!(
+                diff(&current, &candidate).fixes,
+                fixes,
+                "{current:?} -> {candidate:?}"
+            );
+        }
+        let d = diff(&counts(1, 2, 3), &counts(0, 4, 3));
+        assert_eq!((d.critical, d.high, d.medium), (-1, 2, 0));
+    }
+
+    #[test]
+    fn parses_scanner_kinds() {
+        assert_eq!(ScannerKind::parse("Trivy"), Some(ScannerKind::Trivy));
+        assert_eq!(ScannerKind::parse("grype"), Some(ScannerKind::Grype));
+        assert_eq!(
+            ScannerKind::parse("http://scanner:8080/scan"),
+            Some(ScannerKind::Http("http://scanner:8080/scan".to_string()))
+        );
+        assert_eq!(ScannerKind::parse("clair"), None);
+    }
+}
@@ -0,0 +1,178 @@
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+use crate::{
+    api::{
+        self,
+        types::{CheckReason, JobScope},
+    },
+    cron::CronSchedule,
+    db::{ArchivedFilter, CheckScheduleRecord, GLOBAL_CHECK_SCHEDULE_ID},
+    state::AppState,
+};
+
+const TICK_SECONDS: u64 = 30;
+/// Cached registry metadata is kept this many TTLs (but at least a day) past its fetch, so that
+/// it can still be revalidated.
+const REGISTRY_CACHE_RETENTION_TTLS: u64 = 10;
+const REGISTRY_CACHE_MIN_RETENTION_SECONDS: u64 = 24 * 60 * 60;
+
+pub fn spawn_task(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(TICK_SECONDS));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let now = OffsetDateTime::now_utc();
+            if let Err(e) = run_due(&state, now).await {
+                tracing::warn!(error = %e, "scheduler tick failed");
+            }
+            if let Err(e) = prune_registry_cache(&state, now).await {
+                tracing::warn!(error = %e, "registry cache pruning failed");
+            }
+        }
+    });
+}
+
+/// Drops registry cache rows that are past their retention.
+pub async fn prune_registry_cache(state: &AppState, now: OffsetDateTime) -> anyhow::Result<usize> {
+    let retention = state
+        .config
+        .registry_cache_ttl_seconds
+        .saturating_mul(REGISTRY_CACHE_RETENTION_TTLS)
+        .max(REGISTRY_CACHE_MIN_RETENTION_SECONDS);
+    let before = now
+        .unix_timestamp()
+        .saturating_sub(i64::try_from(retention).unwrap_or(i64::MAX));
+    state.db.prune_registry_cache(before).await
+}
+
+/// Computes the next run time (RFC3339, UTC) of `cron` strictly after `after`.
+pub fn next_run_at(cron: &str, after: OffsetDateTime) -> anyhow::Result<Option<String>> {
+    let schedule = CronSchedule::parse(cron)?;
+    schedule
+        .next_after(after)
+        .map(|t| t.format(&Rfc3339))
+        .transpose()
+        .map_err(Into::into)
+}
+
+/// Fires every enabled schedule whose `next_run_at` has passed and returns the created job ids.
+///
+/// A missed run (e.g. while Dockrev was down) fires once on the next tick; the following run is
+/// computed from `now`, so restarts never replay a backlog of runs.
+pub async fn run_due(state: &Arc<AppState>, now: OffsetDateTime) -> anyhow::Result<Vec<String>> {
+    let schedules = state.db.list_check_schedules().await?;
+    let now_str = now.format(&Rfc3339)?;
+
+    let overridden = schedules
+        .iter()
+        .filter_map(|s| s.stack_id.clone())
+        .collect::<BTreeSet<_>>();
+
+    let mut job_ids = Vec::new();
+    for schedule in &schedules {
+        if !schedule.enabled {
+            continue;
+        }
+
+        let cron = match CronSchedule::parse(&schedule.cron) {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!(schedule = %schedule.id, error = %e, "invalid cron expression");
+                continue;
+            }
+        };
+        let next = cron
+            .next_after(now)
+            .map(|t| t.format(&Rfc3339))
+            .transpose()?;
+
+        let due = match schedule.next_run_at.as_deref() {
+            None => false,
+            Some(at) => OffsetDateTime::parse(at, &Rfc3339).map_or(true, |at| at <= now),
+        };
+
+        if !due {
+            if schedule.next_run_at.is_none() {
+                state
+                    .db
+                    .claim_check_schedule(&schedule.id, None, next.as_deref(), None)
+                    .await?;
+            }
+            continue;
+        }
+
+        let claimed = state
+            .db
+            .claim_check_schedule(
+                &schedule.id,
+                schedule.next_run_at.as_deref(),
+                next.as_deref(),
+                Some(&now_str),
+            )
+            .await?;
+        if !claimed {
+            continue;
+        }
+
+        match fire(state, schedule, &overridden).await {
+            Ok(Some(job_id)) => {
+                state
+                    .db
+                    .set_check_schedule_last_job(&schedule.id, &job_id)
+                    .await?;
+                job_ids.push(job_id);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(schedule = %schedule.id, error = %e, "scheduled check failed");
+            }
+        }
+    }
+
+    Ok(job_ids)
+}
+
+async fn fire(
+    state: &Arc<AppState>,
+    schedule: &CheckScheduleRecord,
+    overridden: &BTreeSet<String>,
+) -> anyhow::Result<Option<String>> {
+    let (scope, stack_id, stack_ids) = if schedule.id == GLOBAL_CHECK_SCHEDULE_ID {
+        let stack_ids = state
+            .db
+            .list_stacks(ArchivedFilter::Exclude, None)
+            .await?
+            .into_iter()
+            .map(|s| s.id)
+            .filter(|id| !overridden.contains(id))
+            .collect::<Vec<_>>();
+        (JobScope::All, None, stack_ids)
+    } else {
+        let Some(stack_id) = schedule.stack_id.clone() else {
+            return Ok(None);
+        };
+        if state.db.is_stack_archived(&stack_id).await? != Some(false) {
+            return Ok(None);
+        }
+        (JobScope::Stack, Some(stack_id.clone()), vec![stack_id])
+    };
+
+    if stack_ids.is_empty() {
+        return Ok(None);
+    }
+
+    let job_id = api::run_check_job(
+        state,
+        "scheduler",
+        CheckReason::Schedule,
+        scope,
+        stack_id,
+        stack_ids,
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("{}", e.message()))?;
+    Ok(Some(job_id))
+}
@@ -0,0 +1,700 @@
+//! Signature verification of candidate digests. Cosign signatures (the `sha256-<hex>.sig` tag or
+//! OCI referrers) and Notation JWS envelopes are checked against the public keys configured per
+//! image pattern; certificate chains, Rekor and Fulcio are not consulted.
+
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use base64::{
+    Engine as _,
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64_URL},
+};
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use serde::Deserialize;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+use crate::{
+    api::types::{SignatureFormat, SignatureStatus, SignatureVerdict},
+    registry::{ImageRef, RegistryClient, SignatureArtifact},
+};
+
+const NOTATION_PAYLOAD_TYPE: &str = "application/vnd.cncf.notary.payload.v1+json";
+const NOTATION_EXPIRY_HEADER: &str = "io.cncf.notary.expiry";
+
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_SECP384R1: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyKind {
+    EcdsaP256,
+    EcdsaP384,
+    Rsa,
+    Ed25519,
+}
+
+/// A PEM `PUBLIC KEY` (SubjectPublicKeyInfo), as written by `cosign generate-key-pair`.
+#[derive(Clone, Debug)]
+pub struct PublicKey {
+    name: String,
+    kind: KeyKind,
+    /// Contents of the SPKI bit string, which is what `ring` takes for every key kind.
+    key: Vec<u8>,
+}
+
+impl PublicKey {
+    pub fn from_pem(name: &str, pem: &str) -> anyhow::Result<Self> {
+        let body = pem
+            .lines()
+            .map(str::trim)
+            .skip_while(|l| *l != "-----BEGIN PUBLIC KEY-----")
+            .skip(1)
+            .take_while(|l| *l != "-----END PUBLIC KEY-----")
+            .collect::<String>();
+        if body.is_empty() {
+            return Err(anyhow::anyhow!("no PUBLIC KEY block"));
+        }
+        let der = BASE64.decode(body).context("decode PEM body")?;
+        let (kind, key) = parse_spki(&der).ok_or_else(|| {
+            anyhow::anyhow!("unsupported public key (expected ECDSA P-256/P-384, RSA or Ed25519)")
+        })?;
+        Ok(Self {
+            name: name.to_string(),
+            kind,
+            key: key.to_vec(),
+        })
+    }
+
+    /// Cosign signs the payload with SHA-256 (SHA-384 for P-384); ECDSA signatures are DER.
+    fn verify_cosign(&self, payload: &[u8], sig: &[u8]) -> bool {
+        let alg: &dyn VerificationAlgorithm = match self.kind {
+            KeyKind::EcdsaP256 => &signature::ECDSA_P256_SHA256_ASN1,
+            KeyKind::EcdsaP384 => &signature::ECDSA_P384_SHA384_ASN1,
+            KeyKind::Rsa => &signature::RSA_PKCS1_2048_8192_SHA256,
+            KeyKind::Ed25519 => &signature::ED25519,
+        };
+        UnparsedPublicKey::new(alg, &self.key)
+            .verify(payload, sig)
+            .is_ok()
+    }
+
+    /// JWS `alg` values supported for this key; ECDSA signatures are `r || s`.
+    fn verify_jws(&self, alg: &str, message: &[u8], sig: &[u8]) -> bool {
+        let alg: &dyn VerificationAlgorithm = match (self.kind, alg) {
+            (KeyKind::EcdsaP256, "ES256") => &signature::ECDSA_P256_SHA256_FIXED,
+            (KeyKind::EcdsaP384, "ES384") => &signature::ECDSA_P384_SHA384_FIXED,
+            (KeyKind::Rsa, "PS256") => &signature::RSA_PSS_2048_8192_SHA256,
+            (KeyKind::Rsa, "PS384") => &signature::RSA_PSS_2048_8192_SHA384,
+            (KeyKind::Rsa, "PS512") => &signature::RSA_PSS_2048_8192_SHA512,
+            _ => return false,
+        };
+        UnparsedPublicKey::new(alg, &self.key)
+            .verify(message, sig)
+            .is_ok()
+    }
+}
+
+/// Glob over `registry/name` (e.g. `ghcr.io/acme/*`, `docker.io/library/nginx`); `*` matches any
+/// run of characters, including `/`.
+#[derive(Clone, Debug)]
+struct ImagePattern(regex::Regex);
+
+impl ImagePattern {
+    fn new(glob: &str) -> anyhow::Result<Self> {
+        if glob.is_empty() {
+            return Err(anyhow::anyhow!("empty image pattern"));
+        }
+        let body = glob
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join(".*");
+        let re = regex::Regex::new(&format!("^{body}$"))
+            .with_context(|| format!("invalid image pattern {glob:?}"))?;
+        Ok(Self(re))
+    }
+
+    fn matches(&self, image: &ImageRef) -> bool {
+        self.0
+            .is_match(&format!("{}/{}", image.registry, image.name))
+    }
+}
+
+/// Keys per image pattern (`DOCKREV_SIGNATURE_KEYS`) and the patterns whose updates need a
+/// verified signature (`DOCKREV_SIGNATURE_REQUIRED`).
+#[derive(Clone, Debug, Default)]
+pub struct SignaturePolicy {
+    keys: Vec<(ImagePattern, PublicKey)>,
+    required: Vec<ImagePattern>,
+}
+
+impl SignaturePolicy {
+    /// Reads the key files; a key may be listed for several patterns.
+    pub fn load(keys: &[(String, PathBuf)], required: &[String]) -> anyhow::Result<Self> {
+        let keys = keys
+            .iter()
+            .map(|(pattern, path)| {
+                let pem = std::fs::read_to_string(path)
+                    .with_context(|| format!("read public key {}", path.display()))?;
+                let name = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let key = PublicKey::from_pem(&name, &pem)
+                    .with_context(|| format!("parse public key {}", path.display()))?;
+                Ok((ImagePattern::new(pattern)?, key))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let required = required
+            .iter()
+            .map(|p| ImagePattern::new(p))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { keys, required })
+    }
+
+    #[cfg(test)]
+    pub fn with_key(mut self, pattern: &str, key: PublicKey) -> anyhow::Result<Self> {
+        self.keys.push((ImagePattern::new(pattern)?, key));
+        Ok(self)
+    }
+
+    #[cfg(test)]
+    pub fn with_required(mut self, pattern: &str) -> anyhow::Result<Self> {
+        self.required.push(ImagePattern::new(pattern)?);
+        Ok(self)
+    }
+
+    pub fn requires(&self, image: &ImageRef) -> bool {
+        self.required.iter().any(|p| p.matches(image))
+    }
+
+    /// Whether digests of `image` are verified at all.
+    pub fn covers(&self, image: &ImageRef) -> bool {
+        self.requires(image) || self.keys.iter().any(|(p, _)| p.matches(image))
+    }
+
+    pub async fn verify(
+        &self,
+        registry: &dyn RegistryClient,
+        image: &ImageRef,
+        digest: &str,
+    ) -> SignatureVerdict {
+        let now = OffsetDateTime::now_utc();
+        let keys = self
+            .keys
+            .iter()
+            .filter(|(p, _)| p.matches(image))
+            .map(|(_, k)| k)
+            .collect::<Vec<_>>();
+        let outcome = if keys.is_empty() {
+            Outcome::error(format!("no public key configured for {image}"))
+        } else {
+            match registry.get_signatures(image, digest).await {
+                Ok(artifacts) => verify_artifacts(&keys, digest, &artifacts, now),
+                Err(e) => Outcome::error(format!("fetch signatures: {e:#}")),
+            }
+        };
+        SignatureVerdict {
+            status: outcome.status,
+            required: self.requires(image),
+            format: outcome.format,
+            key: outcome.key,
+            message: outcome.message,
+            checked_at: now.format(&Rfc3339).unwrap_or_default(),
+        }
+    }
+
+    /// Verifies the content digest of a tag (its image index, which is what signers sign) and
+    /// falls back to its platform `digest`. Returns the digest that verified; otherwise the
+    /// verdict of the index unless no signatures were found for it.
+    pub async fn verify_resolved(
+        &self,
+        registry: &dyn RegistryClient,
+        image: &ImageRef,
+        content_digest: Option<&str>,
+        digest: &str,
+    ) -> (String, SignatureVerdict) {
+        let index = match content_digest.filter(|d| *d != digest) {
+            Some(index) => {
+                let verdict = self.verify(registry, image, index).await;
+                if verdict.status == SignatureStatus::Verified {
+                    return (index.to_string(), verdict);
+                }
+                Some((index.to_string(), verdict))
+            }
+            None => None,
+        };
+        let verdict = self.verify(registry, image, digest).await;
+        match index {
+            Some(index)
+                if verdict.status != SignatureStatus::Verified
+                    && index.1.status != SignatureStatus::Unsigned =>
+            {
+                index
+            }
+            _ => (digest.to_string(), verdict),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Outcome {
+    status: SignatureStatus,
+    format: Option<SignatureFormat>,
+    key: Option<String>,
+    message: Option<String>,
+}
+
+impl Outcome {
+    fn error(message: String) -> Self {
+        Self {
+            status: SignatureStatus::Error,
+            format: None,
+            key: None,
+            message: Some(message),
+        }
+    }
+}
+
+/// The first signature that verifies wins; otherwise the last reason a signature was rejected.
+fn verify_artifacts(
+    keys: &[&PublicKey],
+    digest: &str,
+    artifacts: &[SignatureArtifact],
+    now: OffsetDateTime,
+) -> Outcome {
+    if artifacts.is_empty() {
+        return Outcome {
+            status: SignatureStatus::Unsigned,
+            format: None,
+            key: None,
+            message: Some("no signatures found".to_string()),
+        };
+    }
+    let mut reason = String::new();
+    for artifact in artifacts {
+        let (format, result) = match artifact {
+            SignatureArtifact::Cosign { payload, signature } => (
+                SignatureFormat::Cosign,
+                verify_cosign(keys, digest, payload, signature),
+            ),
+            SignatureArtifact::NotationJws { envelope } => (
+                SignatureFormat::Notation,
+                verify_notation(keys, digest, envelope, now),
+            ),
+        };
+        match result {
+            Ok(key) => {
+                return Outcome {
+                    status: SignatureStatus::Verified,
+                    format: Some(format),
+                    key: Some(key.to_string()),
+                    message: None,
+                };
+            }
+            Err(e) => reason = e,
+        }
+    }
+    Outcome {
+        status: SignatureStatus::Invalid,
+        format: None,
+        key: None,
+        message: Some(reason),
+    }
+}
+
+#[derive(Deserialize)]
+struct SimpleSigning {
+    critical: SimpleSigningCritical,
+}
+
+#[derive(Deserialize)]
+struct SimpleSigningCritical {
+    image: SimpleSigningImage,
+}
+
+#[derive(Deserialize)]
+struct SimpleSigningImage {
+    #[serde(rename = "docker-manifest-digest")]
+    docker_manifest_digest: String,
+}
+
+/// Name of the key that signed `payload`, which must name `digest`.
+fn verify_cosign<'a>(
+    keys: &[&'a PublicKey],
+    digest: &str,
+    payload: &[u8],
+    signature: &str,
+) -> Result<&'a str, String> {
+    let sig = BASE64
+        .decode(signature.trim())
+        .map_err(|e| format!("cosign signature is not base64: {e}"))?;
+    let key = keys
+        .iter()
+        .find(|k| k.verify_cosign(payload, &sig))
+        .ok_or_else(|| "cosign signature does not match any configured key".to_string())?;
+    let payload: SimpleSigning =
+        serde_json::from_slice(payload).map_err(|e| format!("invalid cosign payload: {e}"))?;
+    if payload.critical.image.docker_manifest_digest != digest {
+        return Err(format!(
+            "cosign signature is for {}",
+            payload.critical.image.docker_manifest_digest
+        ));
+    }
+    Ok(&key.name)
+}
+
+#[derive(Deserialize)]
+struct JwsEnvelope {
+    payload: String,
+    protected: String,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct NotationPayload {
+    #[serde(rename = "targetArtifact")]
+    target_artifact: NotationTarget,
+}
+
+#[derive(Deserialize)]
+struct NotationTarget {
+    digest: String,
+}
+
+/// Name of the key that signed the JWS envelope, whose payload must target `digest`.
+fn verify_notation<'a>(
+    keys: &[&'a PublicKey],
+    digest: &str,
+    envelope: &[u8],
+    now: OffsetDateTime,
+) -> Result<&'a str, String> {
+    let envelope: JwsEnvelope =
+        serde_json::from_slice(envelope).map_err(|e| format!("invalid notation envelope: {e}"))?;
+    let decode = |part: &str, what: &str| {
+        BASE64_URL
+            .decode(part)
+            .map_err(|e| format!("notation {what} is not base64url: {e}"))
+    };
+    let header: serde_json::Value = serde_json::from_slice(&decode(&envelope.protected, "header")?)
+        .map_err(|e| format!("invalid notation header: {e}"))?;
+    let alg = header.get("alg").and_then(|v| v.as_str()).unwrap_or("");
+    if header.get("cty").and_then(|v| v.as_str()) != Some(NOTATION_PAYLOAD_TYPE) {
+        return Err("notation envelope has an unexpected content type".to_string());
+    }
+    let sig = decode(&envelope.signature, "signature")?;
+    let message = format!("{}.{}", envelope.protected, envelope.payload);
+    let key = keys
+        .iter()
+        .find(|k| k.verify_jws(alg, message.as_bytes(), &sig))
+        .ok_or_else(|| format!("notation signature ({alg}) does not match any configured key"))?;
+
+    if let Some(expiry) = header.get(NOTATION_EXPIRY_HEADER).and_then(|v| v.as_str()) {
+        let expiry = OffsetDateTime::parse(expiry, &Rfc3339)
+            .map_err(|e| format!("invalid notation expiry: {e}"))?;
+        if expiry <= now {
+            return Err(format!(
+                "notation signature expired at {}",
+                header[NOTATION_EXPIRY_HEADER]
+            ));
+        }
+    }
+    let payload: NotationPayload = serde_json::from_slice(&decode(&envelope.payload, "payload")?)
+        .map_err(|e| format!("invalid notation payload: {e}"))?;
+    if payload.target_artifact.digest != digest {
+        return Err(format!(
+            "notation signature is for {}",
+            payload.target_artifact.digest
+        ));
+    }
+    Ok(&key.name)
+}
+
+/// Algorithm and bit string of a DER SubjectPublicKeyInfo.
+fn parse_spki(der: &[u8]) -> Option<(KeyKind, &[u8])> {
+    let (0x30, spki, _) = der_element(der)? else {
+        return None;
+    };
+    let (0x30, algorithm, rest) = der_element(spki)? else {
+        return None;
+    };
+    let (0x03, bits, _) = der_element(rest)? else {
+        return None;
+    };
+    let (0x06, oid, params) = der_element(algorithm)? else {
+        return None;
+    };
+    let kind = match oid {
+        OID_EC_PUBLIC_KEY => match der_element(params)? {
+            (0x06, OID_PRIME256V1, _) => KeyKind::EcdsaP256,
+            (0x06, OID_SECP384R1, _) => KeyKind::EcdsaP384,
+            _ => return None,
+        },
+        OID_RSA_ENCRYPTION => KeyKind::Rsa,
+        OID_ED25519 => KeyKind::Ed25519,
+        _ => return None,
+    };
+    // No unused bits in any supported key.
+    match bits.split_first()? {
+        (0, key) => Some((kind, key)),
+        _ => None,
+    }
+}
+
+/// Tag, contents and the remaining input of the first DER element.
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, &rest[n..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ring::{
+        rand::SystemRandom,
+        signature::{
+            ECDSA_P256_SHA256_ASN1_SIGNING, ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair,
+            KeyPair as _,
+        },
+    };
+
+    use super::*;
+
+    /// DER prefix of a P-256 SubjectPublicKeyInfo, followed by the 65-byte point.
+    const P256_SPKI_PREFIX: &[u8] = &[
+        0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08,
+        0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+    ];
+
+    pub struct TestSigner {
+        asn1: EcdsaKeyPair,
+        fixed: EcdsaKeyPair,
+    }
+
+    impl TestSigner {
+        pub fn new() -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+            Self {
+                asn1: EcdsaKeyPair::from_pkcs8(
+                    &ECDSA_P256_SHA256_ASN1_SIGNING,
+                    pkcs8.as_ref(),
+                    &rng,
+                )
+                .unwrap(),
+                fixed: EcdsaKeyPair::from_pkcs8(
+                    &ECDSA_P256_SHA256_FIXED_SIGNING,
+                    pkcs8.as_ref(),
+                    &rng,
+                )
+                .unwrap(),
+            }
+        }
+
+        pub fn public_key_pem(&self) -> String {
+            let mut der = P256_SPKI_PREFIX.to_vec();
+            der.extend_from_slice(self.asn1.public_key().as_ref());
+            format!(
+                "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+                BASE64.encode(der)
+            )
+        }
+
+        pub fn public_key(&self, name: &str) -> PublicKey {
+            PublicKey::from_pem(name, &self.public_key_pem()).unwrap()
+        }
+
+        /// A cosign signature layer for `digest`.
+        pub fn cosign(&self, digest: &str) -> SignatureArtifact {
+            let payload = serde_json::json!({
+                "critical": {
+                    "identity": {"docker-reference": "ghcr.io/acme/web"},
+                    "image": {"docker-manifest-digest": digest},
+                    "type": "cosign container image signature"
+                },
+                "optional": null
+            })
+            .to_string()
+            .into_bytes();
+            let sig = self.asn1.sign(&SystemRandom::new(), &payload).unwrap();
+            SignatureArtifact::Cosign {
+                payload,
+                signature: BASE64.encode(sig.as_ref()),
+            }
+        }
+
+        pub fn notation(&self, digest: &str, expiry: Option<&str>) -> SignatureArtifact {
+            let mut header = serde_json::json!({
+                "alg": "ES256",
+                "cty": NOTATION_PAYLOAD_TYPE,
+                "io.cncf.notary.signingScheme": "notary.x509",
+            });
+            if let Some(expiry) = expiry {
+                header[NOTATION_EXPIRY_HEADER] = serde_json::json!(expiry);
+            }
+            let protected = BASE64_URL.encode(header.to_string());
+            let payload = BASE64_URL.encode(
+                serde_json::json!({"targetArtifact": {
+                    "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                    "digest": digest,
+                    "size": 1234
+                }})
+                .to_string(),
+            );
+            let sig = self
+                .fixed
+                .sign(
+                    &SystemRandom::new(),
+                    format!("{protected}.{payload}").as_bytes(),
+                )
+                .unwrap();
+            SignatureArtifact::NotationJws {
+                envelope: serde_json::json!({
+                    "payload": payload,
+                    "protected": protected,
+                    "header": {"x5c": []},
+                    "signature": BASE64_URL.encode(sig.as_ref()),
+                })
+                .to_string()
+                .into_bytes(),
+            }
+        }
+    }
+
+    fn status(outcome: &Outcome) -> (SignatureStatus, Option<SignatureFormat>, Option<&str>) {
+        (outcome.status, outcome.format, outcome.key.as_deref())
+    }
+
+    #[test]
+    fn verifies_cosign_and_notation_signatures() {
+        let signer = TestSigner::new();
+        let other = TestSigner::new();
+        let key = signer.public_key("release");
+        let other_key = other.public_key("other");
+        let now = OffsetDateTime::now_utc();
+
+        let outcome = verify_artifacts(
+            &[&other_key, &key],
+            "sha256:abc",
+            &[signer.cosign("sha256:abc")],
+            now,
+        );
+        assert_eq!(
+            status(&outcome),
+            (
+                SignatureStatus::Verified,
+                Some(SignatureFormat::Cosign),
+                Some("release")
+            )
+        );
+
+        let outcome = verify_artifacts(
+            &[&key],
+            "sha256:abc",
+            &[signer.notation("sha256:abc", Some("2999-01-01T00:00:00Z"))],
+            now,
+        );
+        assert_eq!(
+            status(&outcome),
+            (
+                SignatureStatus::Verified,
+                Some(SignatureFormat::Notation),
+                Some("release")
+            )
+        );
+
+        // One valid signature among others is enough.
+        let outcome = verify_artifacts(
+            &[&key],
+            "sha256:abc",
+            &[other.cosign("sha256:abc"), signer.cosign("sha256:abc")],
+            now,
+        );
+        assert_eq!(outcome.status, SignatureStatus::Verified);
+    }
+
+    #[test]
+    fn rejects_foreign_mismatched_and_expired_signatures() {
+        let signer = TestSigner::new();
+        let key = signer.public_key("release");
+        let now = OffsetDateTime::now_utc();
+        let cases = [
+            (
+                TestSigner::new().cosign("sha256:abc"),
+                "does not match any configured key",
+            ),
+            (signer.cosign("sha256:other"), "is for sha256:other"),
+            (signer.notation("sha256:other", None), "is for sha256:other"),
+            (
+                signer.notation("sha256:abc", Some("2001-01-01T00:00:00Z")),
+                "expired",
+            ),
+            (
+                SignatureArtifact::Cosign {
+                    payload: b"{}".to_vec(),
+                    signature: "not base64!".to_string(),
+                },
+                "not base64",
+            ),
+        ];
+        for (artifact, reason) in cases {
+            let outcome = verify_artifacts(&[&key], "sha256:abc", &[artifact], now);
+            assert_eq!(outcome.status, SignatureStatus::Invalid);
+            let message = outcome.message.unwrap();
+            assert!(message.contains(reason), "{message}");
+        }
+
+        let outcome = verify_artifacts(&[&key], "sha256:abc", &[], now);
+        assert_eq!(outcome.status, SignatureStatus::Unsigned);
+    }
+
+    #[test]
+    fn parses_public_keys_and_image_patterns() {
+        let signer = TestSigner::new();
+        assert_eq!(signer.public_key("k").kind, KeyKind::EcdsaP256);
+        // `openssl genpkey -algorithm ed25519 | openssl pkey -pubout`
+        let ed25519 = "-----BEGIN PUBLIC KEY-----\nMCowBQYDK2VwAyEAGb9ECWmEzf6FQbrBZ9w7lshQhqowtrbLDFw4rXAxZuE=\n-----END PUBLIC KEY-----\n";
+        assert_eq!(
+            PublicKey::from_pem("ed", ed25519).unwrap().kind,
+            KeyKind::Ed25519
+        );
+        assert!(PublicKey::from_pem("x", "not a key").is_err());
+        assert!(
+            PublicKey::from_pem(
+                "x",
+                "-----BEGIN PUBLIC KEY-----\nMAUGAytlcA==\n-----END PUBLIC KEY-----"
+            )
+            .is_err()
+        );
+
+        let image = |s: &str| ImageRef::parse(s).unwrap();
+        let policy = SignaturePolicy::default()
+            .with_key("ghcr.io/acme/*", signer.public_key("acme"))
+            .unwrap()
+            .with_required("docker.io/library/nginx")
+            .unwrap();
+        assert!(policy.covers(&image("ghcr.io/acme/web:1.0")));
+        assert!(policy.covers(&image("ghcr.io/acme/team/api")));
+        assert!(!policy.requires(&image("ghcr.io/acme/web:1.0")));
+        assert!(policy.requires(&image("nginx:1.27")));
+        assert!(!policy.covers(&image("ghcr.io/other/web")));
+    }
+}
@@ -1,13 +1,32 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use crate::{config::Config, db::Db, registry::RegistryClient, runner::CommandRunner};
+use crate::{
+    agent::AgentHub,
+    config::Config,
+    db::Db,
+    docker_engine::DockerEngine,
+    hosts::{self, Host, HostRegistry, LOCAL_HOST_ID},
+    queue::JobQueue,
+    registry::RegistryClient,
+    release_notes::ReleaseNotesClient,
+    runner::CommandRunner,
+    scanner::{self, VulnerabilityScanner},
+};
 
-#[derive(Clone)]
 pub struct AppState {
     pub config: Config,
     pub db: Db,
     pub registry: Arc<dyn RegistryClient>,
+    /// Runner of the local host, without any `DOCKER_*` overrides.
     pub runner: Arc<dyn CommandRunner>,
+    pub hosts: HostRegistry,
+    /// Command queues of `agent://` hosts.
+    pub agents: Arc<AgentHub>,
+    pub queue: JobQueue,
+    /// Set unless release notes are disabled.
+    pub release_notes: Option<ReleaseNotesClient>,
+    /// Set when `DOCKREV_SCANNER` is.
+    pub scanner: Option<Arc<dyn VulnerabilityScanner>>,
 }
 
 impl AppState {
@@ -16,12 +35,76 @@ impl AppState {
         db: Db,
         registry: Arc<dyn RegistryClient>,
         runner: Arc<dyn CommandRunner>,
+        docker: Arc<dyn DockerEngine>,
     ) -> Arc<Self> {
+        let hosts = HostRegistry::default();
+        hosts.insert(Arc::new(hosts::local(
+            config.docker_host.as_deref(),
+            config.host_platform.as_deref(),
+            runner.clone(),
+            docker,
+        )));
+        let release_notes = config
+            .release_notes_enabled
+            .then(|| {
+                ReleaseNotesClient::new(
+                    config.release_notes_forges.clone(),
+                    config.release_notes_tokens.clone(),
+                )
+            })
+            .transpose()
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "release notes disabled");
+                None
+            });
+        let scanner = config
+            .scanner
+            .as_ref()
+            .map(|kind| {
+                scanner::build(
+                    kind,
+                    config.scanner_bin.as_deref(),
+                    Duration::from_secs(config.scanner_timeout_seconds),
+                    runner.clone(),
+                )
+            })
+            .transpose()
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "vulnerability scans disabled");
+                None
+            });
         Arc::new(Self {
             config,
             db,
             registry,
             runner,
+            hosts,
+            agents: Arc::default(),
+            queue: JobQueue::default(),
+            release_notes,
+            scanner,
         })
     }
+
+    /// Host of a stack; unknown ids (a host removed while a job was queued) are an error.
+    pub fn host(&self, host_id: &str) -> anyhow::Result<Arc<Host>> {
+        self.hosts
+            .get(host_id)
+            .ok_or_else(|| anyhow::anyhow!("unknown host: {host_id}"))
+    }
+
+    /// Platform used for manifest selection and arch checks on `host_id`.
+    pub fn host_platform(&self, host_id: &str) -> String {
+        self.hosts
+            .get(host_id)
+            .unwrap_or_else(|| self.local_host())
+            .platform
+            .clone()
+    }
+
+    pub fn local_host(&self) -> Arc<Host> {
+        self.hosts
+            .get(LOCAL_HOST_ID)
+            .expect("local host is always registered")
+    }
 }
@@ -1,12 +1,13 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
 use serde_json::json;
 
 use crate::{
     api::types::{JobScope, StackRecord},
     compose_runner::{ComposeRunnerConfig, ComposeStack},
-    docker_runner,
-    runner::{CommandRunner, CommandSpec},
+    docker_engine::DockerEngine,
+    registry::ImageRef,
+    runner::{CancelToken, CommandRunner, CommandSpec},
 };
 
 #[derive(Clone, Debug)]
@@ -24,9 +25,12 @@ pub struct UpdateOutcome {
     pub summary_json: serde_json::Value,
 }
 
+/// `verified_digests` (service id to digest) are the digests the signature gate verified; they
+/// are pinned instead of the target tag or the candidate.
 #[allow(clippy::too_many_arguments)]
 pub async fn run_update_job(
     runner: &dyn CommandRunner,
+    docker: &dyn DockerEngine,
     compose_bin: &str,
     stack: &StackRecord,
     scope: &JobScope,
@@ -34,7 +38,9 @@ pub async fn run_update_job(
     mode: &str,
     target_tag: Option<&str>,
     target_digest: Option<&str>,
+    verified_digests: &BTreeMap<String, String>,
     allow_arch_mismatch: bool,
+    cancel: &CancelToken,
 ) -> anyhow::Result<UpdateOutcome> {
     let compose_cfg = ComposeRunnerConfig {
         compose_bin: compose_bin.to_string(),
@@ -42,38 +48,10 @@ pub async fn run_update_job(
     let compose_stack = ComposeStack {
         project_name: sanitize_project_name(&stack.name),
         compose: stack.compose.clone(),
+        override_file: None,
     };
 
-    let mut services = match scope {
-        JobScope::All => stack.services.iter().collect::<Vec<_>>(),
-        JobScope::Stack => stack.services.iter().collect::<Vec<_>>(),
-        JobScope::Service => stack
-            .services
-            .iter()
-            .filter(|s| service_id.is_some_and(|id| id == s.id))
-            .collect::<Vec<_>>(),
-    };
-
-    // For stack/all updates, only apply to actionable candidates (UI shows others as skipped).
-    if !matches!(scope, JobScope::Service) {
-        services.retain(|svc| {
-            if svc.archived.unwrap_or(false) {
-                return false;
-            }
-            if svc.ignore.as_ref().is_some_and(|i| i.matched) {
-                return false;
-            }
-            let Some(candidate) = svc.candidate.as_ref() else {
-                return false;
-            };
-            if !allow_arch_mismatch
-                && matches!(candidate.arch_match, crate::api::types::ArchMatch::Mismatch)
-            {
-                return false;
-            }
-            true
-        });
-    }
+    let services = services_to_update(stack, scope, service_id, allow_arch_mismatch);
 
     if mode == "dry-run" {
         return Ok(UpdateOutcome {
@@ -85,119 +63,119 @@ pub async fn run_update_job(
         });
     }
 
-    let override_path = build_override_file(stack, &services, target_tag, target_digest)?;
+    let override_path = build_override_file(
+        stack,
+        &services,
+        target_tag,
+        target_digest,
+        verified_digests,
+    )?;
     let _override_cleanup = override_path.as_ref().map(|p| TempFileCleanup(p.clone()));
     let override_stack = override_path.as_ref().map(|p| ComposeStack {
-        project_name: compose_stack.project_name.clone(),
-        compose: {
-            let mut c = stack.compose.clone();
-            c.compose_files.push(p.to_string_lossy().to_string());
-            c
-        },
+        override_file: Some(p.to_string_lossy().to_string()),
+        ..compose_stack.clone()
     });
 
-    let docker_cfg = docker_runner::DockerRunnerConfig::default();
-
     let mut changed = 0u32;
     let mut old_images = serde_json::Map::new();
     let mut new_images = serde_json::Map::new();
 
     let compose_for_update = override_stack.as_ref().unwrap_or(&compose_stack);
 
-    for svc in services {
-        let container_id = run_to_string(
-            runner,
-            compose_for_update.ps_q_service(&compose_cfg, &svc.name),
-            Duration::from_secs(30),
-        )
-        .await?;
-        let container_id = container_id.trim().to_string();
-        if container_id.is_empty() {
-            continue;
-        }
+    // Cancellation stops before the next service; a command aborted mid-service surfaces as an
+    // error, which is reported as cancelled so the services updated so far stay in the summary.
+    let result: anyhow::Result<Option<UpdateOutcome>> = async {
+        for svc in services {
+            if cancel.is_cancelled() {
+                return Ok(None);
+            }
 
-        let old_image_id = run_to_string(
-            runner,
-            docker_runner::inspect_image_id(&docker_cfg, &container_id),
-            Duration::from_secs(10),
-        )
-        .await?;
-        let old_image_id = old_image_id.trim().to_string();
-        old_images.insert(svc.id.clone(), json!(old_image_id));
-
-        run_checked(
-            runner,
-            compose_for_update.pull_service(&compose_cfg, &svc.name),
-            Duration::from_secs(300),
-        )
-        .await?;
-        run_checked(
-            runner,
-            compose_for_update.up_service(&compose_cfg, &svc.name),
-            Duration::from_secs(300),
-        )
-        .await?;
+            let container_id = run_to_string(
+                runner,
+                compose_for_update.ps_q_service(&compose_cfg, &svc.name),
+                Duration::from_secs(30),
+            )
+            .await?;
+            let container_id = container_id.trim().to_string();
+            if container_id.is_empty() {
+                continue;
+            }
 
-        let has_health = run_to_string(
-            runner,
-            docker_runner::inspect_has_healthcheck(&docker_cfg, &container_id),
-            Duration::from_secs(10),
-        )
-        .await?;
-
-        let has_health = has_health.trim() == "1";
-        let mut rolled_back = false;
-        if has_health {
-            let ok =
-                wait_healthy(runner, &docker_cfg, &container_id, Duration::from_secs(90)).await?;
-            if !ok {
-                run_checked(
-                    runner,
-                    docker_runner::tag_image(&docker_cfg, &old_image_id, &svc.image.reference),
-                    Duration::from_secs(30),
-                )
-                .await?;
-                run_checked(
-                    runner,
-                    compose_stack.up_service_no_pull(&compose_cfg, &svc.name),
-                    Duration::from_secs(300),
-                )
-                .await?;
-                let ok2 = wait_healthy(runner, &docker_cfg, &container_id, Duration::from_secs(90))
+            let old_image_id = docker.inspect_container(&container_id).await?.image_id;
+            old_images.insert(svc.id.clone(), json!(old_image_id));
+
+            run_checked_streaming(
+                runner,
+                compose_for_update.pull_service(&compose_cfg, &svc.name),
+                Duration::from_secs(300),
+            )
+            .await?;
+            run_checked_streaming(
+                runner,
+                compose_for_update.up_service(&compose_cfg, &svc.name),
+                Duration::from_secs(300),
+            )
+            .await?;
+
+            let has_health = docker
+                .inspect_container(&container_id)
+                .await?
+                .health
+                .is_some();
+            let mut rolled_back = false;
+            if has_health {
+                let ok =
+                    wait_healthy(docker, &container_id, Duration::from_secs(90), cancel).await?;
+                if !ok {
+                    docker
+                        .tag_image(&old_image_id, &svc.image.reference)
+                        .await?;
+                    run_checked_streaming(
+                        runner,
+                        compose_stack.up_service_no_pull(&compose_cfg, &svc.name),
+                        Duration::from_secs(300),
+                    )
                     .await?;
-                if !ok2 {
-                    return Ok(UpdateOutcome {
-                        status: "failed".to_string(),
-                        summary_json: json!({"reason":"rollback_failed"}),
-                    });
+                    let ok2 = wait_healthy(docker, &container_id, Duration::from_secs(90), cancel)
+                        .await?;
+                    if !ok2 {
+                        return Ok(Some(UpdateOutcome {
+                            status: "failed".to_string(),
+                            summary_json: json!({"reason":"rollback_failed"}),
+                        }));
+                    }
+                    rolled_back = true;
                 }
-                rolled_back = true;
             }
-        }
 
-        let new_image_id = run_to_string(
-            runner,
-            docker_runner::inspect_image_id(&docker_cfg, &container_id),
-            Duration::from_secs(10),
-        )
-        .await?;
-        new_images.insert(svc.id.clone(), json!(new_image_id.trim()));
-        changed += 1;
-
-        if rolled_back {
-            return Ok(UpdateOutcome {
-                status: "rolled_back".to_string(),
-                summary_json: json!({
-                    "changedServices": changed,
-                    "oldDigests": old_images,
-                    "newDigests": new_images,
-                }),
-            });
+            let new_image_id = docker.inspect_container(&container_id).await?.image_id;
+            new_images.insert(svc.id.clone(), json!(new_image_id));
+            changed += 1;
+
+            if rolled_back {
+                return Ok(Some(UpdateOutcome {
+                    status: "rolled_back".to_string(),
+                    summary_json: json!({
+                        "changedServices": changed,
+                        "oldDigests": old_images,
+                        "newDigests": new_images,
+                    }),
+                }));
+            }
         }
+        Ok(None)
     }
+    .await;
+
+    let status = match result {
+        Ok(Some(outcome)) => return Ok(outcome),
+        Err(e) if !cancel.is_cancelled() => return Err(e),
+        Ok(None) if !cancel.is_cancelled() => "success",
+        _ => "cancelled",
+    };
 
     Ok(UpdateOutcome {
-        status: "success".to_string(),
+        status: status.to_string(),
         summary_json: json!({
             "changedServices": changed,
             "oldDigests": old_images,
@@ -206,11 +184,53 @@ pub async fn run_update_job(
     })
 }
 
+/// Services an update job touches: the scoped service, or the actionable candidates of a
+/// stack/all update.
+pub fn services_to_update<'a>(
+    stack: &'a StackRecord,
+    scope: &JobScope,
+    service_id: Option<&str>,
+    allow_arch_mismatch: bool,
+) -> Vec<&'a crate::api::types::Service> {
+    let mut services = match scope {
+        JobScope::All => stack.services.iter().collect::<Vec<_>>(),
+        JobScope::Stack => stack.services.iter().collect::<Vec<_>>(),
+        JobScope::Service => stack
+            .services
+            .iter()
+            .filter(|s| service_id.is_some_and(|id| id == s.id))
+            .collect::<Vec<_>>(),
+    };
+
+    // For stack/all updates, only apply to actionable candidates (UI shows others as skipped).
+    if !matches!(scope, JobScope::Service) {
+        services.retain(|svc| {
+            if svc.archived.unwrap_or(false) {
+                return false;
+            }
+            if svc.ignore.as_ref().is_some_and(|i| i.matched) {
+                return false;
+            }
+            let Some(candidate) = svc.candidate.as_ref() else {
+                return false;
+            };
+            if !allow_arch_mismatch
+                && matches!(candidate.arch_match, crate::api::types::ArchMatch::Mismatch)
+            {
+                return false;
+            }
+            true
+        });
+    }
+    services
+}
+
 fn build_override_file(
     stack: &StackRecord,
     services: &[&crate::api::types::Service],
     target_tag: Option<&str>,
     target_digest: Option<&str>,
+    verified_digests: &BTreeMap<String, String>,
 ) -> anyhow::Result<Option<std::path::PathBuf>> {
     if services.is_empty() {
         return Ok(None);
@@ -223,7 +243,11 @@ fn build_override_file(
 
     let mut any = false;
     for svc in services {
-        let override_image = if has_explicit_target {
+        let override_image = if let Some(digest) = verified_digests.get(&svc.id) {
+            let base = strip_tag_and_digest(&svc.image.reference)
+                .unwrap_or_else(|| svc.image.reference.clone());
+            format!("{base}@{}", normalize_digest(digest))
+        } else if has_explicit_target {
             let base = strip_tag_and_digest(&svc.image.reference)
                 .unwrap_or_else(|| svc.image.reference.clone());
             if let Some(d) = target_digest {
@@ -271,64 +295,64 @@ fn normalize_digest(input: &str) -> String {
     format!("sha256:{t}")
 }
 
+/// Repository of the reference, without tag and digest.
 fn strip_tag_and_digest(image_ref: &str) -> Option<String> {
-    let (without_digest, _) = image_ref.split_once('@').unwrap_or((image_ref, ""));
-    let Some((left, right)) = without_digest.rsplit_once(':') else {
-        return Some(without_digest.to_string());
-    };
-    if right.is_empty() || right.contains('/') || left.is_empty() {
-        return Some(without_digest.to_string());
-    }
-    Some(left.to_string())
+    ImageRef::parse(image_ref)
+        .ok()
+        .map(|img| img.familiar_name())
 }
 
+/// The reference with `tag`; a pinned digest is dropped since it belongs to the old tag.
 fn replace_tag(image_ref: &str, tag: &str) -> Option<String> {
-    let (without_digest, digest) = image_ref.split_once('@').unwrap_or((image_ref, ""));
-    let (left, right) = without_digest.rsplit_once(':')?;
-    if right.is_empty() || right.contains('/') || left.is_empty() {
-        return None;
-    }
-    if digest.is_empty() {
-        Some(format!("{left}:{tag}"))
-    } else {
-        Some(format!("{left}:{tag}@{digest}"))
-    }
+    let img = ImageRef::parse(image_ref).ok()?;
+    Some(
+        ImageRef {
+            tag: Some(tag.to_string()),
+            digest: None,
+            ..img
+        }
+        .to_string(),
+    )
 }
 
 async fn wait_healthy(
-    runner: &dyn CommandRunner,
-    docker_cfg: &docker_runner::DockerRunnerConfig,
+    docker: &dyn DockerEngine,
     container_id: &str,
     timeout: Duration,
+    cancel: &CancelToken,
 ) -> anyhow::Result<bool> {
     let deadline = tokio::time::Instant::now() + timeout;
     loop {
-        let status = run_to_string(
-            runner,
-            docker_runner::inspect_health_status(docker_cfg, container_id),
-            Duration::from_secs(10),
-        )
-        .await?;
+        if cancel.is_cancelled() {
+            return Err(anyhow::anyhow!("job cancelled"));
+        }
+        let status = docker.inspect_container(container_id).await?.health;
 
-        match status.trim() {
-            "healthy" => return Ok(true),
-            "unhealthy" => return Ok(false),
+        match status.as_deref() {
+            Some("healthy") => return Ok(true),
+            Some("unhealthy") => return Ok(false),
             _ => {}
         }
 
         if tokio::time::Instant::now() >= deadline {
             return Ok(false);
         }
-        tokio::time::sleep(Duration::from_secs(2)).await;
+        tokio::select! {
+            _ = cancel.cancelled() => return Err(anyhow::anyhow!("job cancelled")),
+            _ = tokio::time::sleep(Duration::from_secs(2)) => {}
+        }
     }
 }
 
-async fn run_checked(
+/// Runs a long-running compose command and fails on a non-zero exit; the runner may forward its
+/// output while it runs (e.g. into the job log).
+async fn run_checked_streaming(
     runner: &dyn CommandRunner,
     spec: CommandSpec,
     timeout: Duration,
 ) -> anyhow::Result<()> {
-    let out = runner.run(spec, timeout).await?;
+    let (lines, _) = tokio::sync::mpsc::unbounded_channel();
+    let out = runner.run_streaming(spec, timeout, lines).await?;
     if out.status != 0 {
         return Err(anyhow::anyhow!(
             "command failed: status={} stderr={}",
@@ -378,9 +402,13 @@ mod tests {
     use super::*;
     use crate::{
         api::types::{BackupTargetOverrides, ComposeRef, Service, ServiceSettings, TernaryChoice},
+        docker_engine::CliDockerEngine,
         runner::{CommandOutput, CommandRunner},
     };
-    use std::{collections::BTreeMap, sync::Mutex};
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
 
     #[derive(Default)]
     struct FakeRunner {
@@ -411,6 +439,7 @@ mod tests {
         let stack = StackRecord {
             id: "stk_1".to_string(),
             name: "App".to_string(),
+            host_id: crate::hosts::LOCAL_HOST_ID.to_string(),
             archived: false,
             compose: crate::api::types::ComposeConfig {
                 kind: "path".to_string(),
@@ -424,6 +453,7 @@ mod tests {
                 image: ComposeRef {
                     reference: "ghcr.io/org/web:1.0".to_string(),
                     tag: "1.0".to_string(),
+                    variant: None,
                     digest: None,
                     resolved_tag: None,
                     resolved_tags: None,
@@ -436,14 +466,19 @@ mod tests {
                         bind_paths: BTreeMap::<String, TernaryChoice>::new(),
                         volume_names: BTreeMap::<String, TernaryChoice>::new(),
                     },
+                    auto_update: Default::default(),
+                    version_strategy: Default::default(),
+                    prerelease_policy: None,
                 },
                 archived: None,
             }],
         };
 
-        let runner = FakeRunner::default();
+        let runner = Arc::new(FakeRunner::default());
+        let docker = CliDockerEngine::new(runner.clone());
         let outcome = run_update_job(
-            &runner,
+            &*runner,
+            &docker,
             "docker-compose",
             &stack,
             &JobScope::Stack,
@@ -451,7 +486,9 @@ mod tests {
             "dry-run",
             None,
             None,
+            &BTreeMap::new(),
             false,
+            &CancelToken::default(),
         )
         .await
         .unwrap();
@@ -469,5 +506,26 @@ mod tests {
             strip_tag_and_digest("ghcr.io/org/web@sha256:deadbeef"),
             Some("ghcr.io/org/web".to_string())
         );
+        assert_eq!(
+            strip_tag_and_digest("[::1]:5000/web:1@sha256:deadbeef"),
+            Some("[::1]:5000/web".to_string())
+        );
+    }
+
+    #[test]
+    fn replace_tag_handles_untagged_and_pinned_refs() {
+        let cases = [
+            ("alpine", Some("alpine:3.20")),
+            ("alpine:3.19", Some("alpine:3.20")),
+            ("localhost:5000/web", Some("localhost:5000/web:3.20")),
+            (
+                "ghcr.io/org/web:1@sha256:deadbeef",
+                Some("ghcr.io/org/web:3.20"),
+            ),
+            ("Web:1", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(replace_tag(input, "3.20").as_deref(), expected, "{input}");
+        }
     }
 }
@@ -0,0 +1,410 @@
+//! Image references as written in compose files and `docker ps` output, resolved the way the
+//! Docker CLI does. Shared by `dockrev-api` and `dockrev-supervisor`.
+
+/// Tag of references that name neither a tag nor a digest.
+pub const DEFAULT_TAG: &str = "latest";
+const DOCKER_HUB: &str = "docker.io";
+/// Longest `registry/name` the distribution spec allows.
+const MAX_NAME_LEN: usize = 255;
+const MAX_TAG_LEN: usize = 128;
+
+/// An image reference `[registry[:port]/]name[:tag][@digest]`, with Docker Hub names
+/// normalized to `docker.io/library/<name>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageRef {
+    pub registry: String,
+    pub name: String,
+    pub tag: Option<String>,
+    pub digest: Option<String>,
+}
+
+impl ImageRef {
+    /// Parses a reference per the distribution reference grammar (digests per the OCI image
+    /// spec). The first path component is the registry when it contains `.` or `:`, is
+    /// `localhost` or has upper-case letters; IPv6 registries are written `[::1]:5000`.
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        if input.is_empty() {
+            return Err(anyhow::anyhow!("empty image ref"));
+        }
+
+        let (rest, digest) = match input.split_once('@') {
+            Some((rest, digest)) => {
+                validate_digest(digest)?;
+                (rest, Some(digest.to_string()))
+            }
+            None => (input, None),
+        };
+
+        // A ':' after the last '/' starts the tag; earlier ones belong to the registry.
+        let (written_name, tag) = match rest.rfind(':') {
+            Some(i) if !rest[i + 1..].contains('/') => (&rest[..i], Some(&rest[i + 1..])),
+            _ => (rest, None),
+        };
+        if let Some(tag) = tag {
+            validate_tag(tag)?;
+        }
+        if written_name.len() > MAX_NAME_LEN {
+            return Err(anyhow::anyhow!(
+                "image name longer than {MAX_NAME_LEN} characters"
+            ));
+        }
+
+        let (registry, path) = match written_name.split_once('/') {
+            Some((first, path))
+                if first.contains(['.', ':'])
+                    || first == "localhost"
+                    || first.chars().any(|c| c.is_ascii_uppercase()) =>
+            {
+                validate_registry(first)?;
+                (first, path)
+            }
+            _ => (DOCKER_HUB, written_name),
+        };
+        validate_path(path)?;
+
+        let registry = if registry == "index.docker.io" {
+            DOCKER_HUB
+        } else {
+            registry
+        };
+        let name = if registry == DOCKER_HUB && !path.contains('/') {
+            format!("library/{path}")
+        } else {
+            path.to_string()
+        };
+
+        Ok(Self {
+            registry: registry.to_string(),
+            name,
+            tag: tag.map(str::to_string),
+            digest,
+        })
+    }
+
+    /// Shortest name Docker resolves to the same repository: `nginx` for
+    /// `docker.io/library/nginx`, `acme/web` for `docker.io/acme/web`.
+    pub fn familiar_name(&self) -> String {
+        if self.registry != DOCKER_HUB {
+            return format!("{}/{}", self.registry, self.name);
+        }
+        match self.name.strip_prefix("library/") {
+            Some(short) if !short.contains('/') => short.to_string(),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for ImageRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.familiar_name())?;
+        if let Some(tag) = self.tag.as_deref() {
+            write!(f, ":{tag}")?;
+        }
+        if let Some(digest) = self.digest.as_deref() {
+            write!(f, "@{digest}")?;
+        }
+        Ok(())
+    }
+}
+
+/// `host[:port]` where host is a DNS name, an IPv4 address or a bracketed IPv6 address.
+fn validate_registry(registry: &str) -> anyhow::Result<()> {
+    let invalid = || anyhow::anyhow!("invalid registry {registry:?} in image ref");
+    let (host, port) = if let Some(bracketed) = registry.strip_prefix('[') {
+        let (ip, after) = bracketed.split_once(']').ok_or_else(invalid)?;
+        ip.parse::<std::net::Ipv6Addr>().map_err(|_| invalid())?;
+        match after {
+            "" => (None, None),
+            _ => (None, Some(after.strip_prefix(':').ok_or_else(invalid)?)),
+        }
+    } else {
+        match registry.split_once(':') {
+            Some((host, port)) => (Some(host), Some(port)),
+            None => (Some(registry), None),
+        }
+    };
+    if let Some(host) = host {
+        let label_ok = |l: &str| {
+            !l.is_empty()
+                && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && !l.starts_with('-')
+                && !l.ends_with('-')
+        };
+        if !host.split('.').all(label_ok) {
+            return Err(invalid());
+        }
+    }
+    if let Some(port) = port
+        && (port.is_empty() || !port.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// `/`-separated components of lower-case alphanumerics joined by `.`, `_`, `__` or dashes.
+fn validate_path(path: &str) -> anyhow::Result<()> {
+    let component_ok = |c: &str| {
+        let mut prev_sep: Option<char> = None;
+        let mut sep_len = 0;
+        for (i, ch) in c.chars().enumerate() {
+            if ch.is_ascii_lowercase() || ch.is_ascii_digit() {
+                prev_sep = None;
+                sep_len = 0;
+                continue;
+            }
+            if !matches!(ch, '.' | '_' | '-') || i == 0 {
+                return false;
+            }
+            sep_len += 1;
+            let ok = match (prev_sep, ch) {
+                (None, _) => true,
+                (Some('_'), '_') => sep_len == 2,
+                (Some('-'), '-') => true,
+                _ => false,
+            };
+            if !ok {
+                return false;
+            }
+            prev_sep = Some(ch);
+        }
+        !c.is_empty() && prev_sep.is_none()
+    };
+    if path.split('/').all(component_ok) {
+        Ok(())
+    } else if path.chars().any(|c| c.is_ascii_uppercase()) {
+        Err(anyhow::anyhow!(
+            "repository name {path:?} must be lowercase"
+        ))
+    } else {
+        Err(anyhow::anyhow!("invalid repository name {path:?}"))
+    }
+}
+
+fn validate_tag(tag: &str) -> anyhow::Result<()> {
+    let word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let ok = tag.len() <= MAX_TAG_LEN
+        && tag.chars().next().is_some_and(word)
+        && tag.chars().all(|c| word(c) || c == '.' || c == '-');
+    if ok {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("invalid tag {tag:?} in image ref"))
+    }
+}
+
+/// `algorithm:encoded`, e.g. `sha256:<hex>`.
+fn validate_digest(digest: &str) -> anyhow::Result<()> {
+    let invalid = || anyhow::anyhow!("invalid digest {digest:?} in image ref");
+    let (algorithm, encoded) = digest.split_once(':').ok_or_else(invalid)?;
+    let algorithm_ok = algorithm.split(['+', '.', '_', '-']).all(|c| {
+        !c.is_empty()
+            && c.chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    });
+    let encoded_ok = !encoded.is_empty()
+        && encoded
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '=' | '_' | '-'));
+    if algorithm_ok && encoded_ok {
+        Ok(())
+    } else {
+        Err(invalid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_image_ref_with_registry() {
+        let img = ImageRef::parse("ghcr.io/org/app:5.2").unwrap();
+        assert_eq!(img.registry, "ghcr.io");
+        assert_eq!(img.name, "org/app");
+        assert_eq!(img.tag.as_deref(), Some("5.2"));
+    }
+
+    #[test]
+    fn parse_image_ref_dockerhub() {
+        let img = ImageRef::parse("postgres:16").unwrap();
+        assert_eq!(img.registry, "docker.io");
+        assert_eq!(img.name, "library/postgres");
+        assert_eq!(img.tag.as_deref(), Some("16"));
+    }
+
+    #[test]
+    fn parses_image_refs() {
+        const D: &str = "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        // (input, registry, name, tag, digest)
+        type Case<'a> = (&'a str, &'a str, &'a str, Option<&'a str>, Option<&'a str>);
+        let cases: &[Case] = &[
+            ("nginx", "docker.io", "library/nginx", None, None),
+            (
+                "nginx:1.25",
+                "docker.io",
+                "library/nginx",
+                Some("1.25"),
+                None,
+            ),
+            ("acme/web:2", "docker.io", "acme/web", Some("2"), None),
+            ("docker.io/nginx", "docker.io", "library/nginx", None, None),
+            (
+                "index.docker.io/acme/web",
+                "docker.io",
+                "acme/web",
+                None,
+                None,
+            ),
+            (
+                "docker.io/library/a/b:1",
+                "docker.io",
+                "library/a/b",
+                Some("1"),
+                None,
+            ),
+            (
+                "ghcr.io/org/app:5.2",
+                "ghcr.io",
+                "org/app",
+                Some("5.2"),
+                None,
+            ),
+            ("localhost/app", "localhost", "app", None, None),
+            (
+                "localhost:5000/app:1",
+                "localhost:5000",
+                "app",
+                Some("1"),
+                None,
+            ),
+            (
+                "localhost:5000",
+                "docker.io",
+                "library/localhost",
+                Some("5000"),
+                None,
+            ),
+            (
+                "registry:5000/acme/web",
+                "registry:5000",
+                "acme/web",
+                None,
+                None,
+            ),
+            ("Registry/web", "Registry", "web", None, None),
+            (
+                "10.0.0.2:5000/web:v1",
+                "10.0.0.2:5000",
+                "web",
+                Some("v1"),
+                None,
+            ),
+            ("[::1]/web", "[::1]", "web", None, None),
+            (
+                "[2001:db8::1]:5000/org/web:3",
+                "[2001:db8::1]:5000",
+                "org/web",
+                Some("3"),
+                None,
+            ),
+            (
+                "alpine@sha256:deadbeef",
+                "docker.io",
+                "library/alpine",
+                None,
+                Some("sha256:deadbeef"),
+            ),
+            (
+                &format!("ghcr.io/org/web:1.2@{D}"),
+                "ghcr.io",
+                "org/web",
+                Some("1.2"),
+                Some(D),
+            ),
+            (
+                &format!("[::1]:5000/web@{D}"),
+                "[::1]:5000",
+                "web",
+                None,
+                Some(D),
+            ),
+            (
+                "a/b.c_d__e-f---g:_x.Y-1",
+                "docker.io",
+                "a/b.c_d__e-f---g",
+                Some("_x.Y-1"),
+                None,
+            ),
+        ];
+        for (input, registry, name, tag, digest) in cases {
+            let img = ImageRef::parse(input).unwrap_or_else(|e| panic!("{input}: {e}"));
+            assert_eq!(img.registry, *registry, "{input}");
+            assert_eq!(img.name, *name, "{input}");
+            assert_eq!(img.tag.as_deref(), *tag, "{input}");
+            assert_eq!(img.digest.as_deref(), *digest, "{input}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_image_refs() {
+        let long_tag = format!("web:{}", "a".repeat(129));
+        let long_name = format!("ghcr.io/{}", "a".repeat(250));
+        let cases = [
+            "",
+            ":1",
+            "web:",
+            "Web",
+            "acme/Web:1",
+            "web:-1",
+            "web:1@",
+            "web@sha256",
+            "web@sha256:",
+            "web@SHA256:abc",
+            "web@sha256:a/b",
+            "a//b",
+            "a/b/",
+            "-a/b",
+            "a_/b",
+            "a/b..c",
+            "a/b___c",
+            "ex_ample.com/web",
+            "-example.com/web",
+            "example.com:/web",
+            "example.com:5x/web",
+            "[::1/web",
+            "[::g]/web",
+            "[::1]5000/web",
+            long_tag.as_str(),
+            long_name.as_str(),
+        ];
+        for input in cases {
+            assert!(
+                ImageRef::parse(input).is_err(),
+                "{input:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn image_refs_format_familiar_names() {
+        let cases = [
+            ("nginx", "nginx"),
+            ("docker.io/library/nginx:1", "nginx:1"),
+            ("index.docker.io/acme/web:2", "acme/web:2"),
+            ("docker.io/library/a/b", "library/a/b"),
+            (
+                "localhost:5000/web:1@sha256:ab",
+                "localhost:5000/web:1@sha256:ab",
+            ),
+            ("[::1]:5000/web@sha256:ab", "[::1]:5000/web@sha256:ab"),
+        ];
+        for (input, display) in cases {
+            assert_eq!(
+                ImageRef::parse(input).unwrap().to_string(),
+                display,
+                "{input}"
+            );
+        }
+    }
+}
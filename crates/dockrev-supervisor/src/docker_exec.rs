@@ -1,5 +1,6 @@
 use std::{collections::HashMap, path::Path, time::Duration};
 
+use dockrev_image_ref::ImageRef;
 use serde::Deserialize;
 
 use crate::{config::Config, state_store::now_rfc3339};
@@ -408,10 +409,13 @@ fn parse_dockrev_http_port_from_env(env: &[String]) -> Option<u16> {
     parse_port_from_http_addr(http_addr)
 }
 
+/// Whether `image_ref` names `repo` (either may carry a tag or digest); Docker Hub short names
+/// match their `docker.io/library/...` form. Invalid references match nothing.
 fn image_ref_matches_repo(image_ref: &str, repo: &str) -> bool {
-    image_ref == repo
-        || image_ref.starts_with(&format!("{repo}:"))
-        || image_ref.starts_with(&format!("{repo}@"))
+    match (ImageRef::parse(image_ref), ImageRef::parse(repo)) {
+        (Ok(a), Ok(b)) => a.registry == b.registry && a.name == b.name,
+        _ => false,
+    }
 }
 
 fn parse_port_from_http_addr(addr: &str) -> Option<u16> {
@@ -560,4 +564,26 @@ mod tests {
             "ghcr.io/ivanli-cn/dockrev"
         ));
     }
+
+    #[test]
+    fn image_ref_matches_repo_normalizes_references() {
+        let cases = [
+            ("nginx", "docker.io/library/nginx", true),
+            ("docker.io/library/nginx:1.25", "nginx", true),
+            ("index.docker.io/acme/web@sha256:abc", "acme/web", true),
+            ("acme/web:1", "docker.io/acme/web:2", true),
+            ("localhost:5000/web:1", "localhost:5000/web", true),
+            ("[::1]:5000/web:1@sha256:abc", "[::1]:5000/web", true),
+            ("localhost:5000/web", "localhost/web", false),
+            ("ghcr.io/acme/web:1", "acme/web", false),
+            ("nginx", "library/nginx-extras", false),
+        ];
+        for (image_ref, repo, expected) in cases {
+            assert_eq!(
+                image_ref_matches_repo(image_ref, repo),
+                expected,
+                "{image_ref} vs {repo}"
+            );
+        }
+    }
 }